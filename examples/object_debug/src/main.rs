@@ -14,7 +14,10 @@ fn print_error(mut error: &dyn std::error::Error) {
     }
 }
 
-fn inspect_object<P: AsRef<Path>>(path: P) -> Result<(), Box<dyn std::error::Error>> {
+fn inspect_object<P: AsRef<Path>>(
+    path: P,
+    show_sections: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
     let path = path.as_ref();
     println!("Inspecting {}", path.display());
 
@@ -40,6 +43,34 @@ fn inspect_object<P: AsRef<Path>>(path: P) -> Result<(), Box<dyn std::error::Err
                 println!("   debug info:   {}", object.has_debug_info());
                 println!("   unwind info:  {}", object.has_unwind_info());
                 println!("   is malformed: {}", object.is_malformed());
+
+                if show_sections {
+                    println!("   sections:");
+                    for section in object.sections() {
+                        let name = match section.segment() {
+                            Some(segment) => format!("{},{}", segment, section.name()),
+                            None => section.name().to_owned(),
+                        };
+
+                        let offset = match section.file_offset {
+                            Some(offset) => format!("{:#x}", offset),
+                            None => "-".to_owned(),
+                        };
+
+                        println!(
+                            "    {:<32} addr {:#010x}  offset {:>10}  size {:#010x}{}",
+                            name,
+                            section.address,
+                            offset,
+                            section.size,
+                            if section.compressed {
+                                "  (compressed)"
+                            } else {
+                                ""
+                            },
+                        );
+                    }
+                }
             }
             Err(e) => {
                 print!(" - ");
@@ -53,8 +84,10 @@ fn inspect_object<P: AsRef<Path>>(path: P) -> Result<(), Box<dyn std::error::Err
 }
 
 fn execute(matches: &ArgMatches) {
+    let show_sections = *matches.get_one::<bool>("sections").unwrap();
+
     for path in matches.get_many::<PathBuf>("paths").unwrap_or_default() {
-        if let Err(e) = inspect_object(path) {
+        if let Err(e) = inspect_object(path, show_sections) {
             print_error(e.as_ref())
         }
 
@@ -75,6 +108,12 @@ fn main() {
                 .number_of_values(1)
                 .index(1),
         )
+        .arg(
+            Arg::new("sections")
+                .long("sections")
+                .action(ArgAction::SetTrue)
+                .help("List the sections of every object"),
+        )
         .get_matches();
 
     execute(&matches);
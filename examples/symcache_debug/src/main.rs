@@ -107,6 +107,13 @@ fn execute(matches: &ArgMatches) -> Result<()> {
             File::create(&filename)?.write_all(&buffer)?;
             println!("Cache file written to {}", filename.display());
         }
+    } else if let Some(file_path) = matches.get_one::<PathBuf>("text_file_path") {
+        let text = std::fs::read_to_string(file_path)?;
+        let mut writer = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+        writer.process_text(&text)?;
+
+        buffer = ByteView::from_vec(writer.finish()?.into_inner());
+        symcache = SymCache::parse(&buffer)?;
     } else if let Some(file_path) = matches.get_one::<PathBuf>("symcache_file_path") {
         buffer = ByteView::open(file_path)?;
         symcache = SymCache::parse(&buffer)?;
@@ -120,6 +127,12 @@ fn execute(matches: &ArgMatches) -> Result<()> {
         println!("{:#?}", &symcache);
     }
 
+    // text mode
+    if *matches.get_one("print_text").unwrap() {
+        let symcache = symbolic::symcache::new::SymCache::parse(&buffer)?;
+        symcache.write_text(std::io::stdout().lock())?;
+    }
+
     // lookup mode
     if let Some(addr) = matches.get_one::<u64>("lookup_addr").copied() {
         let m = symcache.lookup(addr)?.collect::<Vec<_>>()?;
@@ -240,6 +253,20 @@ fn main() {
                 .value_parser(clap::value_parser!(PathBuf))
                 .help("Path to the symcache file"),
         )
+        .arg(
+            Arg::new("text_file_path")
+                .short('t')
+                .long("text-file")
+                .value_name("PATH")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("Path to a symcache in the text format to convert"),
+        )
+        .arg(
+            Arg::new("print_text")
+                .long("text")
+                .action(ArgAction::SetTrue)
+                .help("Print the contents of the symcache in the text format"),
+        )
         .arg(
             Arg::new("lookup_addr")
                 .long("lookup")
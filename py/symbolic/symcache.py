@@ -157,25 +157,46 @@ class SymCache(RustObject):
         addr = parse_addr(addr)
         rv = self._methodcall(lib.symbolic_symcache_lookup, addr)
         try:
-            matches = []
-            for idx in range(rv.len):
-                sym = rv.items[idx]
-                matches.append(
-                    LineInfo(
-                        sym_addr=sym.sym_addr,
-                        line_addr=sym.line_addr,
-                        instr_addr=sym.instr_addr,
-                        line=sym.line,
-                        lang=decode_str(sym.lang, free=False),
-                        symbol=decode_str(sym.symbol, free=False),
-                        filename=decode_str(sym.filename, free=False),
-                        base_dir=decode_str(sym.base_dir, free=False),
-                        comp_dir=decode_str(sym.comp_dir, free=False),
-                    )
-                )
+            return _decode_lookup_result(rv)
         finally:
             rustcall(lib.symbolic_lookup_result_free, ffi.addressof(rv))
-        return matches
+
+    def lookup_batch(self, addrs):
+        """Look up a batch of addresses at once.
+
+        Returns a list of matches for every address, in the same order as the
+        given addresses.
+        """
+        addrs = [parse_addr(addr) for addr in addrs]
+        rv = self._methodcall(
+            lib.symbolic_symcache_lookup_batch,
+            ffi.new("uint64_t[]", addrs),
+            len(addrs),
+        )
+        try:
+            return [_decode_lookup_result(rv.items[idx]) for idx in range(rv.len)]
+        finally:
+            rustcall(lib.symbolic_batch_lookup_result_free, ffi.addressof(rv))
+
+
+def _decode_lookup_result(rv):
+    matches = []
+    for idx in range(rv.len):
+        sym = rv.items[idx]
+        matches.append(
+            LineInfo(
+                sym_addr=sym.sym_addr,
+                line_addr=sym.line_addr,
+                instr_addr=sym.instr_addr,
+                line=sym.line,
+                lang=decode_str(sym.lang, free=False),
+                symbol=decode_str(sym.symbol, free=False),
+                filename=decode_str(sym.filename, free=False),
+                base_dir=decode_str(sym.base_dir, free=False),
+                comp_dir=decode_str(sym.comp_dir, free=False),
+            )
+        )
+    return matches
 
 
 def find_best_instruction(addr, arch, crashing_frame=False, signal=None, ip_reg=None):
@@ -47,6 +47,14 @@ def test_symbolicate_electron_darwin_dsym(res_path):
     assert symbol.base_dir == "../../atom/app"
     assert symbol.filename == "atom_main.cc"
 
+    # Batch lookups return the same matches as individual lookups
+    addrs = [0x107BB9F25 - 0x107BB9000, 0, 0x107BB9F25 - 0x107BB9000 - 1]
+    def frames(matches):
+        return [(m.symbol, m.filename, m.line, m.instr_addr) for m in matches]
+
+    batch = cache.lookup_batch(addrs)
+    assert [frames(m) for m in batch] == [frames(cache.lookup(a)) for a in addrs]
+
 
 def test_symbolicate_electron_darwin_sym(res_path):
     path = os.path.join(
@@ -69,6 +69,9 @@ enum SymbolicErrorCode {
   SYMBOLIC_ERROR_CODE_SYM_CACHE_ERROR_VALUE_TOO_LARGE = 6010,
   SYMBOLIC_ERROR_CODE_SYM_CACHE_ERROR_WRITE_FAILED = 6011,
   SYMBOLIC_ERROR_CODE_SYM_CACHE_ERROR_TOO_MANY_VALUES = 6012,
+  SYMBOLIC_ERROR_CODE_SYM_CACHE_ERROR_INCOMPATIBLE_OBJECT = 6013,
+  SYMBOLIC_ERROR_CODE_SYM_CACHE_ERROR_BAD_TEXT_FORMAT = 6014,
+  SYMBOLIC_ERROR_CODE_SYM_CACHE_ERROR_UNSUPPORTED_CFI = 6015,
   SYMBOLIC_ERROR_CODE_UNREAL4_ERROR_UNKNOWN = 7001,
   SYMBOLIC_ERROR_CODE_UNREAL4_ERROR_EMPTY = 7002,
   SYMBOLIC_ERROR_CODE_UNREAL4_ERROR_BAD_COMPRESSION = 7004,
@@ -327,6 +330,14 @@ typedef struct SymbolicLookupResult {
   uintptr_t len;
 } SymbolicLookupResult;
 
+/**
+ * Represents the lookup results of a batch of addresses.
+ */
+typedef struct SymbolicBatchLookupResult {
+  struct SymbolicLookupResult *items;
+  uintptr_t len;
+} SymbolicBatchLookupResult;
+
 /**
  * Represents an instruction info.
  */
@@ -803,11 +814,26 @@ uint32_t symbolic_symcache_get_version(const struct SymbolicSymCache *symcache);
 struct SymbolicLookupResult symbolic_symcache_lookup(const struct SymbolicSymCache *symcache,
                                                      uint64_t addr);
 
+/**
+ * Looks up the symbols at a batch of addresses.
+ *
+ * Returns one lookup result for every address, in the same order as `addrs`. The addresses
+ * do not need to be sorted. If `addrs` is a null pointer, the result is empty.
+ */
+struct SymbolicBatchLookupResult symbolic_symcache_lookup_batch(const struct SymbolicSymCache *symcache,
+                                                                const uint64_t *addrs,
+                                                                uintptr_t len);
+
 /**
  * Frees a lookup result.
  */
 void symbolic_lookup_result_free(struct SymbolicLookupResult *lookup_result);
 
+/**
+ * Frees a batch lookup result, including all of its lookup results.
+ */
+void symbolic_batch_lookup_result_free(struct SymbolicBatchLookupResult *lookup_result);
+
 /**
  * Return the best instruction for an isntruction info.
  */
@@ -203,6 +203,9 @@ pub enum SymbolicErrorCode {
     SymCacheErrorValueTooLarge = 6010,
     SymCacheErrorWriteFailed = 6011,
     SymCacheErrorTooManyValues = 6012,
+    SymCacheErrorIncompatibleObject = 6013,
+    SymCacheErrorBadTextFormat = 6014,
+    SymCacheErrorUnsupportedCfi = 6015,
 
     // symbolic::unreal
     Unreal4ErrorUnknown = 7001,
@@ -335,6 +338,15 @@ impl SymbolicErrorCode {
                     SymCacheErrorKind::TooManyValues(_) => {
                         SymbolicErrorCode::SymCacheErrorTooManyValues
                     }
+                    SymCacheErrorKind::IncompatibleObject => {
+                        SymbolicErrorCode::SymCacheErrorIncompatibleObject
+                    }
+                    SymCacheErrorKind::BadTextFormat => {
+                        SymbolicErrorCode::SymCacheErrorBadTextFormat
+                    }
+                    SymCacheErrorKind::UnsupportedCfi => {
+                        SymbolicErrorCode::SymCacheErrorUnsupportedCfi
+                    }
                     _ => SymbolicErrorCode::SymCacheErrorUnknown,
                 };
             }
@@ -5,7 +5,7 @@ use std::os::raw::c_char;
 use std::slice;
 
 use symbolic::common::{ByteView, InstructionInfo, SelfCell};
-use symbolic::symcache::{SymCache, SymCacheWriter, SYMCACHE_VERSION};
+use symbolic::symcache::{Lookup, SymCache, SymCacheError, SymCacheWriter, SYMCACHE_VERSION};
 
 use crate::core::SymbolicStr;
 use crate::debuginfo::SymbolicObject;
@@ -39,6 +39,13 @@ pub struct SymbolicLookupResult {
     pub len: usize,
 }
 
+/// Represents the lookup results of a batch of addresses.
+#[repr(C)]
+pub struct SymbolicBatchLookupResult {
+    pub items: *mut SymbolicLookupResult,
+    pub len: usize,
+}
+
 /// Represents an instruction info.
 #[repr(C)]
 pub struct SymbolicInstructionInfo {
@@ -153,31 +160,43 @@ ffi_fn! {
 
 ffi_fn! {
     /// Looks up a single symbol.
-    #[allow(deprecated)]
     unsafe fn symbolic_symcache_lookup(
         symcache: *const SymbolicSymCache,
         addr: u64,
     ) -> Result<SymbolicLookupResult> {
         let cache = SymbolicSymCache::as_rust(symcache).get();
+        let items = collect_line_infos(cache.lookup(addr)?)?;
+        Ok(into_lookup_result(items))
+    }
+}
 
-        let mut items = vec![];
-        for line_info in cache.lookup(addr)? {
-            let line_info = line_info?;
-            items.push(SymbolicLineInfo {
-                sym_addr: line_info.function_address(),
-                line_addr: line_info.line_address(),
-                instr_addr: line_info.instruction_address(),
-                line: line_info.line(),
-                lang: SymbolicStr::new(line_info.language().name()),
-                symbol: SymbolicStr::new(line_info.symbol()),
-                filename: SymbolicStr::new(line_info.filename()),
-                base_dir: SymbolicStr::new(line_info.base_dir()),
-                comp_dir: SymbolicStr::new(line_info.compilation_dir()),
-            });
-        }
+ffi_fn! {
+    /// Looks up the symbols at a batch of addresses.
+    ///
+    /// Returns one lookup result for every address, in the same order as `addrs`. The addresses
+    /// do not need to be sorted. If `addrs` is a null pointer, the result is empty.
+    unsafe fn symbolic_symcache_lookup_batch(
+        symcache: *const SymbolicSymCache,
+        addrs: *const u64,
+        len: usize,
+    ) -> Result<SymbolicBatchLookupResult> {
+        let cache = SymbolicSymCache::as_rust(symcache).get();
+        let addrs = if addrs.is_null() {
+            &[]
+        } else {
+            slice::from_raw_parts(addrs, len)
+        };
 
+        // collect all line infos first, so that nothing is leaked if a lookup fails
+        let lookups = cache
+            .lookup_batch(addrs)?
+            .into_iter()
+            .map(collect_line_infos)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut items: Vec<_> = lookups.into_iter().map(into_lookup_result).collect();
         items.shrink_to_fit();
-        let rv = SymbolicLookupResult {
+        let rv = SymbolicBatchLookupResult {
             items: items.as_mut_ptr(),
             len: items.len(),
         };
@@ -186,6 +205,38 @@ ffi_fn! {
     }
 }
 
+/// Converts all line infos of a lookup into their C representation.
+#[allow(deprecated)]
+fn collect_line_infos(lookup: Lookup<'_, '_>) -> Result<Vec<SymbolicLineInfo>, SymCacheError> {
+    let mut items = vec![];
+    for line_info in lookup {
+        let line_info = line_info?;
+        items.push(SymbolicLineInfo {
+            sym_addr: line_info.function_address(),
+            line_addr: line_info.line_address(),
+            instr_addr: line_info.instruction_address(),
+            line: line_info.line(),
+            lang: SymbolicStr::new(line_info.language().name()),
+            symbol: SymbolicStr::new(line_info.symbol()),
+            filename: SymbolicStr::new(line_info.filename()),
+            base_dir: SymbolicStr::new(line_info.base_dir()),
+            comp_dir: SymbolicStr::new(line_info.compilation_dir()),
+        });
+    }
+    Ok(items)
+}
+
+/// Hands ownership of the line infos to a `SymbolicLookupResult`.
+fn into_lookup_result(mut items: Vec<SymbolicLineInfo>) -> SymbolicLookupResult {
+    items.shrink_to_fit();
+    let rv = SymbolicLookupResult {
+        items: items.as_mut_ptr(),
+        len: items.len(),
+    };
+    mem::forget(items);
+    rv
+}
+
 ffi_fn! {
     /// Frees a lookup result.
     unsafe fn symbolic_lookup_result_free(lookup_result: *mut SymbolicLookupResult) {
@@ -196,6 +247,18 @@ ffi_fn! {
     }
 }
 
+ffi_fn! {
+    /// Frees a batch lookup result, including all of its lookup results.
+    unsafe fn symbolic_batch_lookup_result_free(lookup_result: *mut SymbolicBatchLookupResult) {
+        if !lookup_result.is_null() {
+            let result = &*lookup_result;
+            for item in Vec::from_raw_parts(result.items, result.len, result.len) {
+                Vec::from_raw_parts(item.items, item.len, item.len);
+            }
+        }
+    }
+}
+
 ffi_fn! {
     /// Return the best instruction for an isntruction info.
     unsafe fn symbolic_find_best_instruction(ii: *const SymbolicInstructionInfo) -> Result<u64> {
@@ -647,6 +647,7 @@ impl str::FromStr for Language {
             "objcpp" => Language::ObjCpp,
             "rust" => Language::Rust,
             "swift" => Language::Swift,
+            "csharp" => Language::CSharp,
             _ => return Err(UnknownLanguageError),
         })
     }
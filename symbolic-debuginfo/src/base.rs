@@ -425,6 +425,59 @@ impl<'d> FromIterator<Symbol<'d>> for SymbolMap<'d> {
     }
 }
 
+/// A section or custom section of an object file.
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct Section<'data> {
+    /// The name of the section.
+    pub name: Cow<'data, str>,
+
+    /// The name of the segment containing this section, if the format groups sections into
+    /// segments (such as Mach-O).
+    pub segment: Option<Cow<'data, str>>,
+
+    /// The address of this section relative to the load address of the object.
+    ///
+    /// This is `0` for sections that are not mapped into memory.
+    pub address: u64,
+
+    /// The offset of the section data in the file, or `None` if the section occupies no space in
+    /// the file (such as `.bss`).
+    pub file_offset: Option<u64>,
+
+    /// The size of the section in bytes.
+    ///
+    /// For compressed sections, this is the size of the compressed data in the file.
+    pub size: u64,
+
+    /// Whether the section data is compressed.
+    pub compressed: bool,
+}
+
+impl<'data> Section<'data> {
+    /// Returns the name of this section as string.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the name of the containing segment as string, if any.
+    pub fn segment(&self) -> Option<&str> {
+        self.segment.as_deref()
+    }
+}
+
+impl<'d> fmt::Debug for Section<'d> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Section")
+            .field("name", &self.name())
+            .field("segment", &self.segment())
+            .field("address", &format_args!("{:#x}", self.address))
+            .field("file_offset", &self.file_offset)
+            .field("size", &format_args!("{:#x}", self.size))
+            .field("compressed", &self.compressed)
+            .finish()
+    }
+}
+
 /// File information referred by [`LineInfo`](struct.LineInfo.html) comprising a directory and name.
 ///
 /// The file path is usually relative to a compilation directory. It might contain parent directory
@@ -526,6 +579,8 @@ pub struct LineInfo<'data> {
     pub file: FileInfo<'data>,
     /// Absolute line number starting at 1. Zero means no line number.
     pub line: u64,
+    /// Column number starting at 1. Zero means no column number.
+    pub column: u64,
 }
 
 impl fmt::Debug for LineInfo<'_> {
@@ -540,6 +595,7 @@ impl fmt::Debug for LineInfo<'_> {
 
         s.field("file", &self.file)
             .field("line", &self.line)
+            .field("column", &self.column)
             .finish()
     }
 }
@@ -589,6 +645,161 @@ impl fmt::Debug for Function<'_> {
     }
 }
 
+/// The role of a [`Variable`] within its function.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VariableKind {
+    /// A formal parameter of the function.
+    Parameter,
+    /// A local variable declared in the function body.
+    Local,
+    /// The debug information does not specify whether this is a parameter or a local.
+    Unknown,
+}
+
+/// Describes where the value of a [`Variable`] is stored.
+///
+/// Register numbers are specific to the debug information format: DWARF register numbers for ELF
+/// and MachO, and CodeView register numbers for PDB.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum VariableLocation<'data> {
+    /// The value is held in a register.
+    Register(u16),
+    /// The value is stored in memory at an offset relative to the value of a register.
+    RegisterRelative {
+        /// The base register.
+        register: u16,
+        /// The offset added to the register value.
+        offset: i64,
+    },
+    /// The value is stored in memory at an offset relative to the frame base of the function.
+    FrameBaseRelative {
+        /// The offset added to the frame base.
+        offset: i64,
+        /// A raw expression that computes the frame base at the queried address.
+        ///
+        /// This is the function's `DW_AT_frame_base` expression. It is empty if the function does
+        /// not declare a frame base. PDB resolves the frame pointer register of the function and
+        /// returns [`RegisterRelative`](Self::RegisterRelative) locations instead.
+        frame_base: &'data [u8],
+    },
+    /// A raw location expression that needs to be evaluated.
+    ///
+    /// For DWARF, this is a DWARF expression. PDB does not emit raw expressions.
+    Expression(&'data [u8]),
+    /// The variable is live, but its location is described in a way that is not supported.
+    ///
+    /// For PDB, this is the case for locations computed by DIA programs, for variables whose fields
+    /// are stored in different places, and for frame pointer relative locations in functions that
+    /// do not declare their frame pointer.
+    Unsupported,
+}
+
+impl fmt::Debug for VariableLocation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            VariableLocation::Register(register) => {
+                f.debug_tuple("Register").field(&register).finish()
+            }
+            VariableLocation::RegisterRelative { register, offset } => f
+                .debug_struct("RegisterRelative")
+                .field("register", &register)
+                .field("offset", &offset)
+                .finish(),
+            VariableLocation::FrameBaseRelative { offset, frame_base } => f
+                .debug_struct("FrameBaseRelative")
+                .field("offset", &offset)
+                .field("frame_base", &format_args!("{:02x?}", frame_base))
+                .finish(),
+            VariableLocation::Expression(expr) => f
+                .debug_tuple("Expression")
+                .field(&format_args!("{:02x?}", expr))
+                .finish(),
+            VariableLocation::Unsupported => f.write_str("Unsupported"),
+        }
+    }
+}
+
+/// A parameter or local variable that is live at an instruction address.
+#[derive(Clone, Debug)]
+pub struct Variable<'data> {
+    /// The name of the variable.
+    pub name: Cow<'data, str>,
+    /// The formatted name of the variable's type, if available.
+    pub type_name: Option<String>,
+    /// Whether this variable is a parameter or a local.
+    pub kind: VariableKind,
+    /// The location of the variable's value at the queried address.
+    pub location: VariableLocation<'data>,
+}
+
+/// The kind of a user-defined type described by [`TypeInfo`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TypeKind {
+    /// A structure declared with `struct`.
+    Struct,
+    /// A class declared with `class`.
+    Class,
+    /// A union.
+    Union,
+    /// An enumeration.
+    Enum,
+}
+
+/// A data member of a structure, class or union.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TypeMember<'data> {
+    /// The name of the member. Anonymous members have an empty name.
+    pub name: Cow<'data, str>,
+    /// The offset of the member in bytes from the start of the containing type.
+    pub offset: u64,
+    /// The formatted name of the member's type, if available.
+    pub type_name: Option<String>,
+    /// The number of elements in each dimension if the member is an array, outermost first.
+    ///
+    /// Dimensions with unknown bounds, such as flexible array members, have zero elements. This is
+    /// empty for members that are not arrays.
+    pub array_bounds: Vec<u64>,
+    /// The offset of the first bit of a bit field, relative to `offset`.
+    pub bit_offset: Option<u64>,
+    /// The number of bits of a bit field.
+    pub bit_size: Option<u64>,
+}
+
+/// A base class of a structure or class.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BaseClass {
+    /// The formatted name of the base class, if available.
+    pub type_name: Option<String>,
+    /// The offset of the base class in bytes, or `None` for virtual base classes.
+    pub offset: Option<u64>,
+}
+
+/// A named value of an enumeration.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EnumValue<'data> {
+    /// The name of the enumerator.
+    pub name: Cow<'data, str>,
+    /// The value of the enumerator.
+    pub value: i64,
+}
+
+/// Layout information of a user-defined type.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TypeInfo<'data> {
+    /// The fully qualified name of the type.
+    pub name: Cow<'data, str>,
+    /// The kind of this type.
+    pub kind: TypeKind,
+    /// The size of the type in bytes, if known.
+    pub size: Option<u64>,
+    /// Data members of structures, classes and unions in declaration order.
+    pub members: Vec<TypeMember<'data>>,
+    /// Base classes of structures and classes in declaration order.
+    pub base_classes: Vec<BaseClass>,
+    /// Values of enumerations in declaration order.
+    pub enum_values: Vec<EnumValue<'data>>,
+}
+
 /// A dynamically dispatched iterator over items with the given lifetime.
 pub type DynIterator<'a, T> = Box<dyn Iterator<Item = T> + 'a>;
 
@@ -635,6 +846,20 @@ pub trait DebugSession<'session> {
     /// caches and optimize resources while resolving function and line information.
     fn functions(&'session self) -> Self::FunctionIterator;
 
+    /// Returns an iterator over all functions in this debug file, reading compilation units in
+    /// parallel.
+    ///
+    /// The iterator yields the same functions and errors as [`functions`](Self::functions) in the
+    /// same order. Compilation units are read in batches, so that only the functions of one batch
+    /// are held in memory at a time. The default implementation reads `functions` on the current
+    /// thread.
+    #[cfg(feature = "rayon")]
+    fn functions_par(
+        &'session self,
+    ) -> DynIterator<'session, Result<Function<'session>, Self::Error>> {
+        Box::new(self.functions())
+    }
+
     /// Returns an iterator over all source files referenced by this debug file.
     fn files(&'session self) -> Self::FileIterator;
 
@@ -642,6 +867,29 @@ pub trait DebugSession<'session> {
     ///
     /// The given path must be canonicalized.
     fn source_by_path(&self, path: &str) -> Result<Option<Cow<'_, str>>, Self::Error>;
+
+    /// Returns the parameters and local variables that are live at the given address.
+    ///
+    /// The address is relative to the image base, like [`Function::address`]. Variables are
+    /// returned from the outermost scope to the innermost scope, which includes variables of
+    /// functions inlined at this address. The default implementation returns no variables.
+    fn variables_at(&'session self, address: u64) -> Result<Vec<Variable<'session>>, Self::Error> {
+        let _ = address;
+        Ok(Vec::new())
+    }
+
+    /// Returns the layouts of all named structures, classes, unions and enumerations.
+    ///
+    /// Forward declarations are skipped, and every type name is only returned once. The default
+    /// implementation returns no types.
+    fn types(&'session self) -> Result<Vec<TypeInfo<'session>>, Self::Error> {
+        Ok(Vec::new())
+    }
+
+    /// Looks up the layout of a type by its fully qualified name.
+    fn type_by_name(&'session self, name: &str) -> Result<Option<TypeInfo<'session>>, Self::Error> {
+        Ok(self.types()?.into_iter().find(|info| info.name == name))
+    }
 }
 
 /// An object containing debug information.
@@ -655,6 +903,9 @@ pub trait ObjectLike<'data, 'object> {
     /// The iterator over the symbols in the public symbol table.
     type SymbolIterator: Iterator<Item = Symbol<'data>>;
 
+    /// The iterator over the sections of this object.
+    type SectionIterator: Iterator<Item = Section<'data>>;
+
     /// The container format of this file.
     fn file_format(&self) -> FileFormat;
 
@@ -685,6 +936,11 @@ pub trait ObjectLike<'data, 'object> {
     /// Returns an ordered map of symbols in the symbol table.
     fn symbol_map(&self) -> SymbolMap<'data>;
 
+    /// Returns an iterator over the sections of this object.
+    ///
+    /// Formats without sections return an empty iterator.
+    fn sections(&'object self) -> Self::SectionIterator;
+
     /// Determines whether this object contains debug information.
     fn has_debug_info(&self) -> bool;
 
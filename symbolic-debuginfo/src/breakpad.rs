@@ -1099,6 +1099,7 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for BreakpadObject<'dat
     type Error = BreakpadError;
     type Session = BreakpadDebugSession<'data>;
     type SymbolIterator = BreakpadSymbolIterator<'data>;
+    type SectionIterator = BreakpadSectionIterator<'data>;
 
     fn file_format(&self) -> FileFormat {
         self.file_format()
@@ -1132,6 +1133,10 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for BreakpadObject<'dat
         self.symbols()
     }
 
+    fn sections(&self) -> Self::SectionIterator {
+        std::iter::empty()
+    }
+
     fn symbol_map(&self) -> SymbolMap<'data> {
         self.symbol_map()
     }
@@ -1157,6 +1162,9 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for BreakpadObject<'dat
     }
 }
 
+/// An iterator over sections in the Breakpad object, which has no sections.
+pub type BreakpadSectionIterator<'data> = std::iter::Empty<Section<'data>>;
+
 /// An iterator over symbols in the Breakpad object.
 ///
 /// Returned by [`BreakpadObject::symbols`](struct.BreakpadObject.html#method.symbols).
@@ -1259,6 +1267,7 @@ impl<'s> BreakpadFunctionIterator<'s> {
                 size: Some(line.size),
                 file: FileInfo::from_path(filename.as_bytes()),
                 line: line.line,
+                column: 0,
             });
         }
 
@@ -8,7 +8,7 @@
 //! [`MachObject`]: ../macho/struct.MachObject.html
 
 use std::borrow::Cow;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
 use std::fmt;
 use std::marker::PhantomData;
@@ -16,9 +16,8 @@ use std::ops::Deref;
 use std::sync::Arc;
 
 use fallible_iterator::FallibleIterator;
-use gimli::read::{AttributeValue, Error as GimliError, Range};
+use gimli::read::{AttributeValue, Error as GimliError, Operation, Range};
 use gimli::{constants, DwarfFileType, UnitSectionOffset};
-use lazycell::LazyCell;
 use thiserror::Error;
 
 use symbolic_common::{AsSelf, Language, Name, NameMangling, SelfCell};
@@ -26,7 +25,9 @@ use symbolic_common::{AsSelf, Language, Name, NameMangling, SelfCell};
 use crate::base::*;
 #[cfg(feature = "macho")]
 use crate::macho::BcSymbolMap;
-use crate::shared::FunctionStack;
+#[cfg(feature = "rayon")]
+use crate::shared::UnitBatches;
+use crate::shared::{FunctionStack, SyncLazyCell};
 
 /// This is a fake BcSymbolMap used when macho support is turned off since they are unfortunately
 /// part of the dwarf interface
@@ -49,11 +50,13 @@ pub use gimli::RunTimeEndian as Endian;
 
 type Slice<'a> = gimli::read::EndianSlice<'a, Endian>;
 type RangeLists<'a> = gimli::read::RangeLists<Slice<'a>>;
+type LocationLists<'a> = gimli::read::LocationLists<Slice<'a>>;
 type Unit<'a> = gimli::read::Unit<Slice<'a>>;
 type DwarfInner<'a> = gimli::read::Dwarf<Slice<'a>>;
 
 type Die<'d, 'u> = gimli::read::DebuggingInformationEntry<'u, 'u, Slice<'d>, usize>;
 type Attribute<'a> = gimli::read::Attribute<Slice<'a>>;
+type Expression<'a> = gimli::read::Expression<Slice<'a>>;
 type UnitOffset = gimli::read::UnitOffset<usize>;
 type DebugInfoOffset = gimli::DebugInfoOffset<usize>;
 
@@ -70,6 +73,12 @@ fn offset(addr: u64, offset: i64) -> u64 {
     (addr as i64).wrapping_sub(offset as i64) as u64
 }
 
+/// Name and type name of the abstract origin of a variable.
+type VariableOrigin<'d> = (Option<Cow<'d, str>>, Option<String>);
+
+/// Maximum nesting of type modifiers that is resolved when formatting type names.
+const MAX_TYPE_DEPTH: usize = 32;
+
 /// The error type for [`DwarfError`].
 #[non_exhaustive]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -223,6 +232,7 @@ struct DwarfRow {
     address: u64,
     file_index: u64,
     line: Option<u64>,
+    column: u64,
     size: Option<u64>,
 }
 
@@ -291,11 +301,16 @@ impl<'d, 'a> DwarfLineProgram<'d> {
             } else {
                 let file_index = program_row.file_index();
                 let line = program_row.line().map(|v| v.get());
+                let column = match program_row.column() {
+                    gimli::ColumnType::LeftEdge => 0,
+                    gimli::ColumnType::Column(column) => column.get(),
+                };
                 let mut duplicate = false;
                 if let Some(last_row) = sequence_rows.last_mut() {
                     if last_row.address == address {
                         last_row.file_index = file_index;
                         last_row.line = line;
+                        last_row.column = column;
                         duplicate = true;
                     }
                 }
@@ -304,6 +319,7 @@ impl<'d, 'a> DwarfLineProgram<'d> {
                         address,
                         file_index,
                         line,
+                        column,
                         size: None,
                     });
                 }
@@ -456,6 +472,350 @@ impl<'d, 'a> UnitRef<'d, 'a> {
 
         Ok(None)
     }
+
+    /// Resolves the name and type name of a variable's abstract origin.
+    fn resolve_variable_origin(
+        &self,
+        entry: &Die<'d, '_>,
+    ) -> Result<Option<VariableOrigin<'d>>, DwarfError> {
+        let name = match entry.attr_value(constants::DW_AT_name)? {
+            Some(value) => self.string_value(value),
+            None => None,
+        };
+
+        let type_name = match entry.attr(constants::DW_AT_type)? {
+            Some(attr) => self.resolve_type_name(attr, 0)?,
+            None => None,
+        };
+
+        Ok(Some((name, type_name)))
+    }
+
+    /// Formats the name of the type referenced by a `DW_AT_type` attribute.
+    fn resolve_type_name(
+        &self,
+        attr: Attribute<'d>,
+        depth: usize,
+    ) -> Result<Option<String>, DwarfError> {
+        if depth >= MAX_TYPE_DEPTH {
+            return Ok(None);
+        }
+
+        self.resolve_reference(attr, |unit, entry| unit.format_type(entry, depth + 1))
+    }
+
+    /// Formats the name of a type entry, including pointers, references and qualifiers.
+    fn format_type(&self, entry: &Die<'d, '_>, depth: usize) -> Result<Option<String>, DwarfError> {
+        let name = match entry.attr_value(constants::DW_AT_name)? {
+            Some(value) => self.string_value(value),
+            None => None,
+        };
+
+        let inner = match entry.attr(constants::DW_AT_type)? {
+            Some(attr) => self.resolve_type_name(attr, depth)?,
+            None => None,
+        };
+
+        // Modifiers without a type attribute apply to `void`.
+        let inner = inner.as_deref().unwrap_or("void");
+
+        let type_name = match entry.tag() {
+            constants::DW_TAG_pointer_type => format!("{}*", inner),
+            constants::DW_TAG_reference_type => format!("{}&", inner),
+            constants::DW_TAG_rvalue_reference_type => format!("{}&&", inner),
+            constants::DW_TAG_const_type => qualify_type(inner, "const"),
+            constants::DW_TAG_volatile_type => qualify_type(inner, "volatile"),
+            constants::DW_TAG_atomic_type => format!("_Atomic {}", inner),
+            constants::DW_TAG_restrict_type => format!("{} restrict", inner),
+            constants::DW_TAG_subroutine_type => format!("{} ()", inner),
+            constants::DW_TAG_array_type => {
+                format!("{}{}", inner, self.format_array_bounds(entry)?)
+            }
+            _ => return Ok(name.map(Cow::into_owned)),
+        };
+
+        Ok(Some(type_name))
+    }
+
+    /// Formats the dimensions of an array type, such as `[4][2]`.
+    fn format_array_bounds(&self, entry: &Die<'d, '_>) -> Result<String, DwarfError> {
+        let mut bounds = String::new();
+
+        for count in self.array_bounds(entry)? {
+            match count {
+                Some(count) => bounds.push_str(&format!("[{}]", count)),
+                None => bounds.push_str("[]"),
+            }
+        }
+
+        if bounds.is_empty() {
+            bounds.push_str("[]");
+        }
+
+        Ok(bounds)
+    }
+
+    /// Returns the number of elements in each dimension of an array type, outermost first.
+    fn array_bounds(&self, entry: &Die<'d, '_>) -> Result<Vec<Option<u64>>, DwarfError> {
+        let mut bounds = Vec::new();
+
+        let mut tree = self.unit.entries_tree(Some(entry.offset()))?;
+        let mut children = tree.root()?.children();
+        while let Some(child) = children.next()? {
+            let child = child.entry();
+            if child.tag() != constants::DW_TAG_subrange_type {
+                continue;
+            }
+
+            let count = match child.attr_value(constants::DW_AT_count)? {
+                Some(value) => value.udata_value(),
+                None => child
+                    .attr_value(constants::DW_AT_upper_bound)?
+                    .and_then(|value| value.udata_value())
+                    .map(|upper| upper + 1),
+            };
+
+            bounds.push(count);
+        }
+
+        Ok(bounds)
+    }
+
+    /// Resolves the array dimensions of the type referenced by a `DW_AT_type` attribute.
+    ///
+    /// Typedefs and qualifiers are followed to the array type. Returns an empty list if the type is
+    /// not an array.
+    fn resolve_array_bounds(
+        &self,
+        attr: Attribute<'d>,
+        depth: usize,
+    ) -> Result<Vec<Option<u64>>, DwarfError> {
+        if depth >= MAX_TYPE_DEPTH {
+            return Ok(Vec::new());
+        }
+
+        let bounds = self.resolve_reference(attr, |unit, entry| match entry.tag() {
+            constants::DW_TAG_array_type => unit.array_bounds(entry).map(Some),
+            constants::DW_TAG_typedef
+            | constants::DW_TAG_const_type
+            | constants::DW_TAG_volatile_type => match entry.attr(constants::DW_AT_type)? {
+                Some(attr) => unit.resolve_array_bounds(attr, depth + 1).map(Some),
+                None => Ok(None),
+            },
+            _ => Ok(None),
+        })?;
+
+        Ok(bounds.unwrap_or_default())
+    }
+
+    /// Resolves the byte offset of a member or base class.
+    ///
+    /// Returns `None` if the offset is computed at runtime, such as for virtual base classes.
+    fn member_offset(&self, entry: &Die<'d, '_>) -> Result<Option<u64>, DwarfError> {
+        Ok(
+            match entry.attr_value(constants::DW_AT_data_member_location)? {
+                // DWARF 2 encodes constant offsets as `DW_OP_plus_uconst` expression.
+                Some(AttributeValue::Exprloc(expression)) => {
+                    let mut operations = expression.operations(self.unit.encoding());
+                    match (operations.next(), operations.next()) {
+                        (Ok(Some(Operation::PlusConstant { value })), Ok(None)) => Some(value),
+                        _ => None,
+                    }
+                }
+                Some(value) => value.udata_value(),
+                None => None,
+            },
+        )
+    }
+
+    /// Resolves a data member of a structure, class or union.
+    ///
+    /// Returns `None` for static members.
+    fn type_member(&self, entry: &Die<'d, '_>) -> Result<Option<TypeMember<'d>>, DwarfError> {
+        if entry.attr_value(constants::DW_AT_declaration)?.is_some() {
+            return Ok(None);
+        }
+
+        let name = match entry.attr_value(constants::DW_AT_name)? {
+            Some(value) => self.string_value(value),
+            None => None,
+        };
+
+        let (type_name, array_bounds) = match entry.attr(constants::DW_AT_type)? {
+            Some(attr) => (
+                self.resolve_type_name(attr, 0)?,
+                self.resolve_array_bounds(attr, 0)?,
+            ),
+            None => (None, Vec::new()),
+        };
+
+        let bit_size = entry
+            .attr_value(constants::DW_AT_bit_size)?
+            .and_then(|value| value.udata_value());
+
+        // Bit fields in DWARF 4 and later specify their offset in bits from the start of the type.
+        let data_bit_offset = entry
+            .attr_value(constants::DW_AT_data_bit_offset)?
+            .and_then(|value| value.udata_value());
+
+        let (offset, bit_offset) = match data_bit_offset {
+            Some(bits) => (bits / 8, Some(bits % 8)),
+            // Members of unions do not declare an offset.
+            None => (self.member_offset(entry)?.unwrap_or(0), None),
+        };
+
+        Ok(Some(TypeMember {
+            name: name.unwrap_or_default(),
+            offset,
+            type_name,
+            array_bounds: array_bounds
+                .into_iter()
+                .map(|count| count.unwrap_or(0))
+                .collect(),
+            bit_offset,
+            bit_size,
+        }))
+    }
+
+    /// Resolves the layout of a structure, class, union or enumeration entry.
+    fn type_info(
+        &self,
+        entry: &Die<'d, '_>,
+        name: String,
+        kind: TypeKind,
+    ) -> Result<TypeInfo<'d>, DwarfError> {
+        let size = entry
+            .attr_value(constants::DW_AT_byte_size)?
+            .and_then(|value| value.udata_value());
+
+        let mut info = TypeInfo {
+            name: Cow::Owned(name),
+            kind,
+            size,
+            members: Vec::new(),
+            base_classes: Vec::new(),
+            enum_values: Vec::new(),
+        };
+
+        let mut tree = self.unit.entries_tree(Some(entry.offset()))?;
+        let mut children = tree.root()?.children();
+        while let Some(child) = children.next()? {
+            let child = child.entry();
+            match child.tag() {
+                constants::DW_TAG_member => {
+                    if let Some(member) = self.type_member(child)? {
+                        info.members.push(member);
+                    }
+                }
+                constants::DW_TAG_inheritance => {
+                    let type_name = match child.attr(constants::DW_AT_type)? {
+                        Some(attr) => self.resolve_type_name(attr, 0)?,
+                        None => None,
+                    };
+
+                    info.base_classes.push(BaseClass {
+                        type_name,
+                        offset: self.member_offset(child)?,
+                    });
+                }
+                constants::DW_TAG_enumerator => {
+                    let name = match child.attr_value(constants::DW_AT_name)? {
+                        Some(value) => self.string_value(value),
+                        None => None,
+                    };
+
+                    // Negative values are encoded as signed data. All other forms are unsigned.
+                    let value = match child.attr_value(constants::DW_AT_const_value)? {
+                        Some(AttributeValue::Sdata(value)) => Some(value),
+                        Some(value) => value.udata_value().map(|value| value as i64),
+                        None => None,
+                    };
+
+                    if let (Some(name), Some(value)) = (name, value) {
+                        info.enum_values.push(EnumValue { name, value });
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Ok(info)
+    }
+
+    /// Decodes a location expression into a [`VariableLocation`].
+    ///
+    /// Returns `None` for empty expressions, which indicate that the value has been optimized out.
+    fn location_from_expression(&self, expression: Expression<'d>) -> Option<VariableLocation<'d>> {
+        let bytes = expression.0.slice();
+        if bytes.is_empty() {
+            return None;
+        }
+
+        // Only expressions consisting of a single operation can be described directly.
+        let mut operations = expression.operations(self.unit.encoding());
+        let location = match operations.next() {
+            Ok(Some(Operation::Register { register })) => VariableLocation::Register(register.0),
+            Ok(Some(Operation::RegisterOffset {
+                register,
+                offset,
+                base_type,
+            })) if base_type.0 == 0 => VariableLocation::RegisterRelative {
+                register: register.0,
+                offset,
+            },
+            Ok(Some(Operation::FrameOffset { offset })) => VariableLocation::FrameBaseRelative {
+                offset,
+                frame_base: &[],
+            },
+            _ => return Some(VariableLocation::Expression(bytes)),
+        };
+
+        match operations.next() {
+            Ok(None) => Some(location),
+            _ => Some(VariableLocation::Expression(bytes)),
+        }
+    }
+
+    /// Resolves the location expression of an attribute that is valid at the given address.
+    ///
+    /// The address is the raw address in the debug information. Returns `None` if no expression
+    /// covers this address.
+    fn resolve_expression(
+        &self,
+        value: AttributeValue<Slice<'d>>,
+        address: u64,
+    ) -> Result<Option<Expression<'d>>, DwarfError> {
+        if let AttributeValue::Exprloc(expression) = value {
+            return Ok(Some(expression));
+        }
+
+        let mut locations = match self.info.attr_locations(self.unit, value)? {
+            Some(locations) => locations,
+            None => return Ok(None),
+        };
+
+        while let Some(location) = locations.next()? {
+            if location.range.begin <= address && address < location.range.end {
+                return Ok(Some(location.data));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Resolves the location of a variable at the given address.
+    ///
+    /// The address is the raw address in the debug information. Returns `None` if the variable is
+    /// not live at this address.
+    fn resolve_location(
+        &self,
+        value: AttributeValue<Slice<'d>>,
+        address: u64,
+    ) -> Result<Option<VariableLocation<'d>>, DwarfError> {
+        Ok(self
+            .resolve_expression(value, address)?
+            .and_then(|expression| self.location_from_expression(expression)))
+    }
 }
 
 /// Wrapper around a DWARF Unit.
@@ -533,8 +893,8 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
         &self,
         entry: &Die<'d, '_>,
         range_buf: &mut Vec<Range>,
-    ) -> Result<(Option<u64>, Option<u64>), DwarfError> {
-        let mut tuple = (None, None);
+    ) -> Result<(Option<u64>, Option<u64>, u64), DwarfError> {
+        let mut tuple = (None, None, 0);
         let mut low_pc = None;
         let mut high_pc = None;
         let mut high_pc_rel = None;
@@ -567,6 +927,10 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                     AttributeValue::FileIndex(file) => tuple.1 = Some(file),
                     _ => return Err(GimliError::UnsupportedAttributeForm.into()),
                 },
+                constants::DW_AT_call_column => match attr.value() {
+                    AttributeValue::Udata(column) => tuple.2 = column,
+                    _ => return Err(GimliError::UnsupportedAttributeForm.into()),
+                },
                 constants::DW_AT_ranges
                 | constants::DW_AT_rnglists_base
                 | constants::DW_AT_start_scope => {
@@ -676,6 +1040,7 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                     size: first.size.map(|s| s + first.address - range.begin),
                     file: self.resolve_file(first.file_index).unwrap_or_default(),
                     line: first.line.unwrap_or(0),
+                    column: first.column,
                 };
 
                 for row in rows {
@@ -700,6 +1065,7 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                         size: row.size,
                         file: self.resolve_file(row.file_index).unwrap_or_default(),
                         line,
+                        column: row.column,
                     };
                 }
 
@@ -798,7 +1164,7 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
             };
 
             range_buf.clear();
-            let (call_line, call_file) = self.parse_ranges(entry, range_buf)?;
+            let (call_line, call_file, call_column) = self.parse_ranges(entry, range_buf)?;
 
             // Ranges can be empty for two reasons: (1) the function is a no-op and does not
             // contain any code, or (2) the function did contain eliminated dead code. In the
@@ -888,6 +1254,7 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                                     size: Some(range_end.min(next.address) - range_begin),
                                     file: file.clone(),
                                     line,
+                                    column: call_column,
                                 };
 
                                 lines.insert(index, line_info);
@@ -923,6 +1290,7 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                                     size: Some(record_end - range_end),
                                     file: record.file.clone(),
                                     line: record.line,
+                                    column: record.column,
                                 })
                             } else {
                                 None
@@ -948,6 +1316,7 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                                     size: Some(size),
                                     file: file.clone(),
                                     line,
+                                    column: call_column,
                                 };
 
                                 lines.insert(index, line_info);
@@ -955,6 +1324,7 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                             } else {
                                 record.file = file.clone();
                                 record.line = line;
+                                record.column = call_column;
                             };
 
                             // Insert the split record after mutating the previous one to avoid
@@ -975,6 +1345,7 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                                     size: Some(range_end - record_end),
                                     file: file.clone(),
                                     line,
+                                    column: call_column,
                                 };
 
                                 lines.insert(index, line_info);
@@ -1003,6 +1374,278 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
 
         Ok(functions)
     }
+
+    /// Resolves a parameter or variable entry that is live at the given raw address.
+    fn resolve_variable(
+        &self,
+        entry: &Die<'d, '_>,
+        address: u64,
+    ) -> Result<Option<Variable<'d>>, DwarfError> {
+        let kind = match entry.tag() {
+            constants::DW_TAG_formal_parameter => VariableKind::Parameter,
+            _ => VariableKind::Local,
+        };
+
+        let mut name = None;
+        let mut type_attr = None;
+        let mut location = None;
+        let mut origin = None;
+
+        let mut attrs = entry.attrs();
+        while let Some(attr) = attrs.next()? {
+            match attr.name() {
+                constants::DW_AT_name => name = self.inner.string_value(attr.value()),
+                constants::DW_AT_type => type_attr = Some(attr),
+                constants::DW_AT_location => location = Some(attr.value()),
+                constants::DW_AT_abstract_origin => origin = Some(attr),
+                _ => {}
+            }
+        }
+
+        // Variables without a location have been optimized out or are constants.
+        let location = match location {
+            Some(value) => match self.inner.resolve_location(value, address)? {
+                Some(location) => location,
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+
+        let mut type_name = match type_attr {
+            Some(attr) => self.inner.resolve_type_name(attr, 0)?,
+            None => None,
+        };
+
+        // Concrete instances of inlined or out-of-line functions refer to the abstract instance for
+        // their name and type.
+        if let (Some(attr), true) = (origin, name.is_none() || type_name.is_none()) {
+            let resolved = self
+                .inner
+                .resolve_reference(attr, |unit, entry| unit.resolve_variable_origin(entry))?;
+
+            if let Some((origin_name, origin_type_name)) = resolved {
+                name = name.or(origin_name);
+                type_name = type_name.or(origin_type_name);
+            }
+        }
+
+        Ok(name.map(|name| Variable {
+            name,
+            type_name,
+            kind,
+            location,
+        }))
+    }
+
+    /// Collects all parameters and local variables that are live at the given raw address.
+    ///
+    /// Returns `None` if no function in this compilation unit covers the address.
+    fn variables_at(
+        &self,
+        address: u64,
+        range_buf: &mut Vec<Range>,
+    ) -> Result<Option<Vec<Variable<'d>>>, DwarfError> {
+        let mut depth = 0;
+        let mut skipped_depth = None;
+        let mut function_depth = None;
+        let mut frame_base: &[u8] = &[];
+        let mut variables = Vec::new();
+
+        let mut entries = self.inner.unit.entries();
+        while let Some((movement, entry)) = entries.next_dfs()? {
+            depth += movement;
+
+            match skipped_depth {
+                Some(skipped) if depth > skipped => continue,
+                _ => skipped_depth = None,
+            }
+
+            // Once we leave the function containing the address, there is nothing more to find.
+            match function_depth {
+                Some(function_depth) if depth <= function_depth => break,
+                _ => (),
+            }
+
+            let tag = entry.tag();
+            match tag {
+                constants::DW_TAG_compile_unit | constants::DW_TAG_partial_unit => {
+                    range_buf.clear();
+                    self.parse_ranges(entry, range_buf)?;
+
+                    // Units without ranges have to be searched entirely.
+                    if !range_buf.is_empty() && !ranges_contain(range_buf, address) {
+                        return Ok(None);
+                    }
+                }
+                constants::DW_TAG_subprogram
+                | constants::DW_TAG_inlined_subroutine
+                | constants::DW_TAG_lexical_block => {
+                    range_buf.clear();
+                    self.parse_ranges(entry, range_buf)?;
+
+                    // Lexical blocks without ranges cover the same code as their parent scope.
+                    let open_block = function_depth.is_some()
+                        && tag == constants::DW_TAG_lexical_block
+                        && range_buf.is_empty();
+
+                    if !open_block && !ranges_contain(range_buf, address) {
+                        skipped_depth = Some(depth);
+                    } else if function_depth.is_none() {
+                        function_depth = Some(depth);
+
+                        // Locations relative to the frame base refer to the outermost function.
+                        if let Some(value) = entry.attr_value(constants::DW_AT_frame_base)? {
+                            if let Some(expression) =
+                                self.inner.resolve_expression(value, address)?
+                            {
+                                frame_base = expression.0.slice();
+                            }
+                        }
+                    }
+                }
+                constants::DW_TAG_formal_parameter | constants::DW_TAG_variable
+                    if function_depth.is_some() =>
+                {
+                    if let Some(mut variable) = self.resolve_variable(entry, address)? {
+                        if let VariableLocation::FrameBaseRelative {
+                            frame_base: ref mut base,
+                            ..
+                        } = variable.location
+                        {
+                            *base = frame_base;
+                        }
+                        variables.push(variable);
+                    }
+                }
+                // Skip nested types and other entries within functions, which may declare static
+                // members or parameters of function types.
+                _ if function_depth.is_some() => skipped_depth = Some(depth),
+                _ => (),
+            }
+        }
+
+        Ok(function_depth.map(|_| variables))
+    }
+
+    /// Collects the layouts of all named types defined in this compilation unit.
+    ///
+    /// Types whose qualified name is already contained in `seen` are skipped.
+    fn types(
+        &self,
+        seen: &mut BTreeSet<String>,
+        types: &mut Vec<TypeInfo<'d>>,
+    ) -> Result<(), DwarfError> {
+        self.visit_types(|qualified_name, kind, entry| {
+            if seen.insert(qualified_name.clone()) {
+                types.push(self.inner.type_info(entry, qualified_name, kind)?);
+            }
+            Ok(())
+        })
+    }
+
+    /// Invokes `f` with the qualified name and kind of every named type defined in this unit.
+    ///
+    /// Declarations of types and types local to functions are skipped.
+    fn visit_types<F>(&self, mut f: F) -> Result<(), DwarfError>
+    where
+        F: FnMut(String, TypeKind, &Die<'d, '_>) -> Result<(), DwarfError>,
+    {
+        let mut depth = 0;
+        let mut skipped_depth = None;
+        let mut scopes: Vec<(isize, Cow<'d, str>)> = Vec::new();
+
+        let mut entries = self.inner.unit.entries();
+        while let Some((movement, entry)) = entries.next_dfs()? {
+            depth += movement;
+
+            match skipped_depth {
+                Some(skipped) if depth > skipped => continue,
+                _ => skipped_depth = None,
+            }
+
+            // Leave all namespaces and types that do not contain this entry.
+            while matches!(scopes.last(), Some(&(scope_depth, _)) if scope_depth >= depth) {
+                scopes.pop();
+            }
+
+            let kind = match entry.tag() {
+                constants::DW_TAG_namespace => {
+                    let name = match entry.attr_value(constants::DW_AT_name)? {
+                        Some(value) => self.inner.string_value(value),
+                        None => None,
+                    };
+
+                    let name = name.unwrap_or(Cow::Borrowed("(anonymous namespace)"));
+                    scopes.push((depth, name));
+                    continue;
+                }
+                constants::DW_TAG_structure_type => TypeKind::Struct,
+                constants::DW_TAG_class_type => TypeKind::Class,
+                constants::DW_TAG_union_type => TypeKind::Union,
+                constants::DW_TAG_enumeration_type => TypeKind::Enum,
+                // Types local to functions cannot be referenced by name.
+                constants::DW_TAG_subprogram => {
+                    skipped_depth = Some(depth);
+                    continue;
+                }
+                _ => continue,
+            };
+
+            let name = match entry.attr_value(constants::DW_AT_name)? {
+                Some(value) => self.inner.string_value(value),
+                None => None,
+            };
+
+            // Nested types of anonymous types cannot be named either.
+            let name = match name {
+                Some(name) => name,
+                None => {
+                    skipped_depth = Some(depth);
+                    continue;
+                }
+            };
+
+            let mut qualified_name = String::new();
+            for (_, scope) in &scopes {
+                qualified_name.push_str(scope);
+                qualified_name.push_str("::");
+            }
+            qualified_name.push_str(&name);
+            scopes.push((depth, name));
+
+            if entry.attr_value(constants::DW_AT_declaration)?.is_some() {
+                continue;
+            }
+
+            f(qualified_name, kind, entry)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Applies a cv-qualifier to a formatted type name.
+///
+/// Qualifiers of pointers are written after the type, such as `char* const`. Otherwise, the
+/// qualifier is written in front. References cannot be qualified, and qualifiers are not
+/// duplicated.
+fn qualify_type(type_name: &str, qualifier: &str) -> String {
+    if type_name.ends_with('&') {
+        type_name.to_owned()
+    } else if type_name.ends_with('*') {
+        format!("{} {}", type_name, qualifier)
+    } else if type_name.split(' ').any(|part| part == qualifier) {
+        type_name.to_owned()
+    } else {
+        format!("{} {}", qualifier, type_name)
+    }
+}
+
+/// Returns whether any of the given ranges contains the address.
+fn ranges_contain(ranges: &[Range], address: u64) -> bool {
+    ranges
+        .iter()
+        .any(|range| range.begin <= address && address < range.end)
 }
 
 /// Converts a DWARF language number into our `Language` type.
@@ -1087,6 +1730,8 @@ struct DwarfSections<'data> {
     debug_str_offsets: DwarfSectionData<'data, gimli::read::DebugStrOffsets<Slice<'data>>>,
     debug_ranges: DwarfSectionData<'data, gimli::read::DebugRanges<Slice<'data>>>,
     debug_rnglists: DwarfSectionData<'data, gimli::read::DebugRngLists<Slice<'data>>>,
+    debug_loc: DwarfSectionData<'data, gimli::read::DebugLoc<Slice<'data>>>,
+    debug_loclists: DwarfSectionData<'data, gimli::read::DebugLocLists<Slice<'data>>>,
 }
 
 impl<'data> DwarfSections<'data> {
@@ -1104,6 +1749,8 @@ impl<'data> DwarfSections<'data> {
             debug_str_offsets: DwarfSectionData::load(dwarf),
             debug_ranges: DwarfSectionData::load(dwarf),
             debug_rnglists: DwarfSectionData::load(dwarf),
+            debug_loc: DwarfSectionData::load(dwarf),
+            debug_loclists: DwarfSectionData::load(dwarf),
         }
     }
 }
@@ -1111,7 +1758,7 @@ impl<'data> DwarfSections<'data> {
 struct DwarfInfo<'data> {
     inner: DwarfInner<'data>,
     headers: Vec<UnitHeader<'data>>,
-    units: Vec<LazyCell<Option<Unit<'data>>>>,
+    units: Vec<SyncLazyCell<Option<Unit<'data>>>>,
     symbol_map: SymbolMap<'data>,
     address_offset: i64,
     kind: ObjectKind,
@@ -1143,7 +1790,10 @@ impl<'d> DwarfInfo<'d> {
             debug_str: sections.debug_str.to_gimli(),
             debug_str_offsets: sections.debug_str_offsets.to_gimli(),
             debug_types: Default::default(),
-            locations: Default::default(),
+            locations: LocationLists::new(
+                sections.debug_loc.to_gimli(),
+                sections.debug_loclists.to_gimli(),
+            ),
             ranges: RangeLists::new(
                 sections.debug_ranges.to_gimli(),
                 sections.debug_rnglists.to_gimli(),
@@ -1154,7 +1804,7 @@ impl<'d> DwarfInfo<'d> {
 
         // Prepare random access to unit headers.
         let headers = inner.units().collect::<Vec<_>>()?;
-        let units = headers.iter().map(|_| LazyCell::new()).collect();
+        let units = headers.iter().map(|_| SyncLazyCell::new()).collect();
 
         Ok(DwarfInfo {
             inner,
@@ -1277,10 +1927,22 @@ impl<'s> Iterator for DwarfUnitIterator<'s> {
 
 impl std::iter::FusedIterator for DwarfUnitIterator<'_> {}
 
+/// The location of a named type definition within the DWARF information.
+#[derive(Clone, Copy, Debug)]
+struct TypeLocation {
+    /// Index of the compilation unit declaring the type.
+    unit: usize,
+    /// Offset of the type's entry within its unit.
+    offset: UnitOffset,
+    /// The kind of the type.
+    kind: TypeKind,
+}
+
 /// A debugging session for DWARF debugging information.
 pub struct DwarfDebugSession<'data> {
     cell: SelfCell<Box<DwarfSections<'data>>, DwarfInfo<'data>>,
     bcsymbolmap: Option<Arc<BcSymbolMap<'data>>>,
+    type_names: SyncLazyCell<BTreeMap<String, TypeLocation>>,
 }
 
 impl<'data> DwarfDebugSession<'data> {
@@ -1302,6 +1964,7 @@ impl<'data> DwarfDebugSession<'data> {
         Ok(DwarfDebugSession {
             cell,
             bcsymbolmap: None,
+            type_names: SyncLazyCell::new(),
         })
     }
 
@@ -1334,12 +1997,122 @@ impl<'data> DwarfDebugSession<'data> {
         }
     }
 
+    /// Returns an iterator over all functions in this debug file, reading compilation units in
+    /// parallel.
+    ///
+    /// This yields the same functions as [`functions`](Self::functions) in the same order.
+    /// Compilation units are loaded in batches, so only the functions of one batch are held in
+    /// memory at a time.
+    #[cfg(feature = "rayon")]
+    pub fn functions_par(&self) -> DwarfParFunctionIterator<'_> {
+        let info = self.cell.get();
+        DwarfParFunctionIterator {
+            info,
+            bcsymbolmap: self.bcsymbolmap.as_deref(),
+            batches: UnitBatches::new(info.headers.len()),
+            seen_ranges: BTreeSet::new(),
+        }
+    }
+
     /// Looks up a file's source contents by its full canonicalized path.
     ///
     /// The given path must be canonicalized.
     pub fn source_by_path(&self, _path: &str) -> Result<Option<Cow<'_, str>>, DwarfError> {
         Ok(None)
     }
+
+    /// Returns the parameters and local variables that are live at the given address.
+    ///
+    /// The address is relative to the image base. Register numbers in the returned locations are
+    /// DWARF register numbers of the target architecture.
+    pub fn variables_at(&self, address: u64) -> Result<Vec<Variable<'_>>, DwarfError> {
+        let info = self.cell.get();
+        let address = address.wrapping_add(info.address_offset as u64);
+
+        let mut range_buf = Vec::new();
+        for unit in info.units(self.bcsymbolmap.as_deref()) {
+            if let Some(variables) = unit?.variables_at(address, &mut range_buf)? {
+                return Ok(variables);
+            }
+        }
+
+        Ok(Vec::new())
+    }
+
+    /// Returns the layouts of all named structures, classes, unions and enumerations.
+    ///
+    /// Type names are qualified with their namespaces and containing types. If multiple
+    /// compilation units define a type with the same name, only the first definition is returned.
+    pub fn types(&self) -> Result<Vec<TypeInfo<'_>>, DwarfError> {
+        let mut seen = BTreeSet::new();
+        let mut types = Vec::new();
+
+        for unit in self.cell.get().units(self.bcsymbolmap.as_deref()) {
+            unit?.types(&mut seen, &mut types)?;
+        }
+
+        Ok(types)
+    }
+
+    /// Looks up the layout of a type by its fully qualified name.
+    pub fn type_by_name(&self, name: &str) -> Result<Option<TypeInfo<'_>>, DwarfError> {
+        let location = match self.type_names()?.get(name) {
+            Some(&location) => location,
+            None => return Ok(None),
+        };
+
+        let info = self.cell.get();
+        let bcsymbolmap = self.bcsymbolmap.as_deref();
+        let unit = match info.get_unit(location.unit)? {
+            Some(unit) => unit,
+            None => return Ok(None),
+        };
+
+        let unit = match DwarfUnit::from_unit(unit, info, bcsymbolmap)? {
+            Some(unit) => unit,
+            None => return Ok(None),
+        };
+
+        let entry = unit.inner.unit.entry(location.offset)?;
+        let info = unit
+            .inner
+            .type_info(&entry, name.to_owned(), location.kind)?;
+        Ok(Some(info))
+    }
+
+    /// Returns the index of named type definitions, building it on first access.
+    fn type_names(&self) -> Result<&BTreeMap<String, TypeLocation>, DwarfError> {
+        self.type_names.try_borrow_with(|| {
+            let info = self.cell.get();
+            let bcsymbolmap = self.bcsymbolmap.as_deref();
+            let mut type_names = BTreeMap::new();
+
+            for index in 0..info.headers.len() {
+                let unit = match info.get_unit(index)? {
+                    Some(unit) => unit,
+                    None => continue,
+                };
+
+                let unit = match DwarfUnit::from_unit(unit, info, bcsymbolmap)? {
+                    Some(unit) => unit,
+                    None => continue,
+                };
+
+                unit.visit_types(|qualified_name, kind, entry| {
+                    type_names
+                        .entry(qualified_name)
+                        .or_insert_with(|| TypeLocation {
+                            unit: index,
+                            offset: entry.offset(),
+                            kind,
+                        });
+                    Ok(())
+                })?;
+            }
+
+            Ok(type_names)
+        })
+    }
 }
 
 impl<'data, 'session> DebugSession<'session> for DwarfDebugSession<'data> {
@@ -1351,6 +2124,13 @@ impl<'data, 'session> DebugSession<'session> for DwarfDebugSession<'data> {
         self.functions()
     }
 
+    #[cfg(feature = "rayon")]
+    fn functions_par(
+        &'session self,
+    ) -> DynIterator<'session, Result<Function<'session>, Self::Error>> {
+        Box::new(self.functions_par())
+    }
+
     fn files(&'session self) -> Self::FileIterator {
         self.files()
     }
@@ -1358,6 +2138,18 @@ impl<'data, 'session> DebugSession<'session> for DwarfDebugSession<'data> {
     fn source_by_path(&self, path: &str) -> Result<Option<Cow<'_, str>>, Self::Error> {
         self.source_by_path(path)
     }
+
+    fn variables_at(&'session self, address: u64) -> Result<Vec<Variable<'session>>, Self::Error> {
+        self.variables_at(address)
+    }
+
+    fn types(&'session self) -> Result<Vec<TypeInfo<'session>>, Self::Error> {
+        self.types()
+    }
+
+    fn type_by_name(&'session self, name: &str) -> Result<Option<TypeInfo<'session>>, Self::Error> {
+        self.type_by_name(name)
+    }
 }
 
 #[derive(Debug, Default)]
@@ -1474,3 +2266,58 @@ impl<'s> Iterator for DwarfFunctionIterator<'s> {
 }
 
 impl std::iter::FusedIterator for DwarfFunctionIterator<'_> {}
+
+/// An iterator over functions in a DWARF file that reads compilation units in parallel.
+#[cfg(feature = "rayon")]
+pub struct DwarfParFunctionIterator<'s> {
+    info: &'s DwarfInfo<'s>,
+    bcsymbolmap: Option<&'s BcSymbolMap<'s>>,
+    batches: UnitBatches<Function<'s>, DwarfError>,
+    seen_ranges: BTreeSet<(u64, u64)>,
+}
+
+#[cfg(feature = "rayon")]
+impl<'s> Iterator for DwarfParFunctionIterator<'s> {
+    type Item = Result<Function<'s>, DwarfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        use rayon::prelude::*;
+
+        let info = self.info;
+        let bcsymbolmap = self.bcsymbolmap;
+
+        // Units are loaded lazily into thread-safe cells, so workers can resolve references into
+        // other units concurrently.
+        let load = |units: std::ops::Range<usize>| {
+            units
+                .into_par_iter()
+                .map(|index| {
+                    let unit = match info.get_unit(index)? {
+                        Some(unit) => unit,
+                        None => return Ok(Vec::new()),
+                    };
+
+                    match DwarfUnit::from_unit(unit, info, bcsymbolmap)? {
+                        Some(unit) => unit.functions(&mut Vec::new(), &mut BTreeSet::new()),
+                        None => Ok(Vec::new()),
+                    }
+                })
+                .collect()
+        };
+
+        // Units only deduplicate functions locally. Apply the deduplication across units in the
+        // same order as `DwarfFunctionIterator`. Only top-level functions are returned, and all of
+        // them are subject to deduplication.
+        loop {
+            match self.batches.next(load)? {
+                Ok(function) if !self.seen_ranges.insert((function.address, function.size)) => {
+                    continue
+                }
+                result => return Some(result),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl std::iter::FusedIterator for DwarfParFunctionIterator<'_> {}
@@ -510,6 +510,18 @@ impl<'data> ElfObject<'data> {
         self.symbols().collect()
     }
 
+    /// Returns an iterator over all sections in the section header table.
+    ///
+    /// Sections are considered compressed if they have the `SHF_COMPRESSED` flag set or use the
+    /// legacy `.zdebug` prefix.
+    pub fn sections(&self) -> ElfSectionIterator<'data, '_> {
+        ElfSectionIterator {
+            headers: self.elf.section_headers.iter(),
+            strtab: &self.elf.shdr_strtab,
+            load_addr: self.load_address(),
+        }
+    }
+
     /// Determines whether this object contains debug information.
     pub fn has_debug_info(&self) -> bool {
         self.has_section("debug_info")
@@ -741,6 +753,7 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for ElfObject<'data> {
     type Error = DwarfError;
     type Session = DwarfDebugSession<'data>;
     type SymbolIterator = ElfSymbolIterator<'data, 'object>;
+    type SectionIterator = ElfSectionIterator<'data, 'object>;
 
     fn file_format(&self) -> FileFormat {
         self.file_format()
@@ -778,6 +791,10 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for ElfObject<'data> {
         self.symbol_map()
     }
 
+    fn sections(&'object self) -> Self::SectionIterator {
+        self.sections()
+    }
+
     fn has_debug_info(&self) -> bool {
         self.has_debug_info()
     }
@@ -898,6 +915,44 @@ impl<'data, 'object> Iterator for ElfSymbolIterator<'data, 'object> {
     }
 }
 
+/// An iterator over sections in the ELF file.
+///
+/// Returned by [`ElfObject::sections`](struct.ElfObject.html#method.sections).
+pub struct ElfSectionIterator<'data, 'object> {
+    headers: std::slice::Iter<'object, elf::SectionHeader>,
+    strtab: &'object strtab::Strtab<'data>,
+    load_addr: u64,
+}
+
+impl<'data, 'object> Iterator for ElfSectionIterator<'data, 'object> {
+    type Item = Section<'data>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for header in &mut self.headers {
+            if header.sh_type == elf::section_header::SHT_NULL {
+                continue;
+            }
+
+            let name = self.strtab.get_at(header.sh_name).unwrap_or("");
+            let file_offset = match header.sh_type {
+                elf::section_header::SHT_NOBITS => None,
+                _ => Some(header.sh_offset),
+            };
+
+            return Some(Section {
+                name: Cow::Borrowed(name),
+                segment: None,
+                address: header.sh_addr.saturating_sub(self.load_addr),
+                file_offset,
+                size: header.sh_size,
+                compressed: name.starts_with(".zdebug") || header.sh_flags & SHF_COMPRESSED != 0,
+            });
+        }
+
+        None
+    }
+}
+
 /// Parsed debug link section.
 #[derive(Debug)]
 pub struct DebugLink<'data> {
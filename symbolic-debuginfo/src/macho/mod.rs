@@ -6,6 +6,7 @@ use std::fmt;
 use std::sync::Arc;
 
 use goblin::mach;
+use goblin::mach::constants::{SECTION_TYPE, S_GB_ZEROFILL, S_THREAD_LOCAL_ZEROFILL, S_ZEROFILL};
 use smallvec::SmallVec;
 use thiserror::Error;
 
@@ -297,6 +298,18 @@ impl<'d> MachObject<'d> {
         self.symbols().collect()
     }
 
+    /// Returns an iterator over the sections of all segments.
+    ///
+    /// Each section carries the name of its containing segment. Iteration stops at the first
+    /// section header that cannot be parsed.
+    pub fn sections(&self) -> MachOSectionIterator<'d, '_> {
+        MachOSectionIterator {
+            segments: self.macho.segments.iter(),
+            current: None,
+            load_addr: self.load_address(),
+        }
+    }
+
     /// Determines whether this object contains debug information.
     pub fn has_debug_info(&self) -> bool {
         self.has_section("debug_info")
@@ -395,6 +408,7 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for MachObject<'data> {
     type Error = DwarfError;
     type Session = DwarfDebugSession<'data>;
     type SymbolIterator = MachOSymbolIterator<'data>;
+    type SectionIterator = MachOSectionIterator<'data, 'object>;
 
     fn file_format(&self) -> FileFormat {
         self.file_format()
@@ -432,6 +446,10 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for MachObject<'data> {
         self.symbol_map()
     }
 
+    fn sections(&'object self) -> Self::SectionIterator {
+        self.sections()
+    }
+
     fn has_debug_info(&self) -> bool {
         self.has_debug_info()
     }
@@ -548,6 +566,70 @@ impl<'data> Iterator for MachOSymbolIterator<'data> {
     }
 }
 
+/// An iterator over the sections of all segments in the MachO file.
+///
+/// Returned by [`MachObject::sections`](struct.MachObject.html#method.sections).
+pub struct MachOSectionIterator<'data, 'object> {
+    segments: std::slice::Iter<'object, mach::segment::Segment<'data>>,
+    /// The name and remaining sections of the current segment.
+    current: Option<(
+        Option<Cow<'data, str>>,
+        mach::segment::SectionIterator<'data>,
+    )>,
+    load_addr: u64,
+}
+
+impl<'data, 'object> Iterator for MachOSectionIterator<'data, 'object> {
+    type Item = Section<'data>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (segment_name, sections) = match self.current {
+                Some(ref mut current) => current,
+                None => {
+                    let segment = self.segments.next()?;
+                    let name = segment.name().ok().map(|name| Cow::Owned(name.to_owned()));
+                    self.current.get_or_insert((name, segment.into_iter()))
+                }
+            };
+
+            let section = match sections.next() {
+                Some(Ok((section, _data))) => section,
+                Some(Err(_)) => {
+                    // Stop iteration at the first section header that cannot be parsed.
+                    self.segments = [].iter();
+                    self.current = None;
+                    return None;
+                }
+                None => {
+                    self.current = None;
+                    continue;
+                }
+            };
+
+            let name = match section.name() {
+                Ok(name) => name.to_owned(),
+                Err(_) => continue,
+            };
+
+            let file_offset = match section.flags & SECTION_TYPE {
+                S_ZEROFILL | S_GB_ZEROFILL | S_THREAD_LOCAL_ZEROFILL => None,
+                _ if section.offset == 0 => None,
+                _ => Some(section.offset.into()),
+            };
+
+            return Some(Section {
+                compressed: name.starts_with("__zdebug"),
+                name: Cow::Owned(name),
+                segment: segment_name.clone(),
+                address: section.addr.saturating_sub(self.load_addr),
+                file_offset,
+                size: section.size,
+            });
+        }
+    }
+}
+
 /// An iterator over objects in a [`FatMachO`](struct.FatMachO.html).
 ///
 /// Objects are parsed just-in-time while iterating, which may result in errors. The iterator is
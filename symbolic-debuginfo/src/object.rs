@@ -281,6 +281,14 @@ impl<'data> Object<'data> {
         match_inner!(self, Object(ref o) => o.symbol_map())
     }
 
+    /// Returns an iterator over the sections of this object.
+    ///
+    /// This lists sections of ELF and PE files, sections of all segments in Mach-O files, and
+    /// custom sections of WASM modules. Other formats return an empty iterator.
+    pub fn sections(&self) -> SectionIterator<'data, '_> {
+        map_inner!(self, Object(ref o) => SectionIterator(ObjectLike::sections(o)))
+    }
+
     /// Determines whether this object contains debug information.
     pub fn has_debug_info(&self) -> bool {
         match_inner!(self, Object(ref o) => o.has_debug_info())
@@ -365,6 +373,7 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for Object<'data> {
     type Error = ObjectError;
     type Session = ObjectDebugSession<'data>;
     type SymbolIterator = SymbolIterator<'data, 'object>;
+    type SectionIterator = SectionIterator<'data, 'object>;
 
     fn file_format(&self) -> FileFormat {
         self.file_format()
@@ -402,6 +411,10 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for Object<'data> {
         self.symbols()
     }
 
+    fn sections(&'object self) -> Self::SectionIterator {
+        self.sections()
+    }
+
     fn has_debug_info(&self) -> bool {
         self.has_debug_info()
     }
@@ -454,6 +467,26 @@ impl<'d> ObjectDebugSession<'d> {
         }
     }
 
+    /// Returns an iterator over all functions in this debug file, reading compilation units in
+    /// parallel.
+    ///
+    /// This yields the same functions as [`functions`](Self::functions) in the same order. Only
+    /// DWARF and PDB debug sessions are read in parallel.
+    #[cfg(feature = "rayon")]
+    pub fn functions_par(&self) -> DynIterator<'_, Result<Function<'_>, ObjectError>> {
+        match *self {
+            ObjectDebugSession::Dwarf(ref s) => Box::new(
+                s.functions_par()
+                    .map(|result| result.map_err(ObjectError::transparent)),
+            ),
+            ObjectDebugSession::Pdb(ref s) => Box::new(
+                s.functions_par()
+                    .map(|result| result.map_err(ObjectError::transparent)),
+            ),
+            _ => Box::new(self.functions()),
+        }
+    }
+
     /// Returns an iterator over all source files referenced by this debug file.
     pub fn files(&self) -> ObjectFileIterator<'_> {
         match *self {
@@ -487,6 +520,47 @@ impl<'d> ObjectDebugSession<'d> {
             }
         }
     }
+
+    /// Returns the parameters and local variables that are live at the given address.
+    ///
+    /// Variable information is only available for DWARF and PDB debug information. For all other
+    /// formats, this returns an empty list.
+    pub fn variables_at(&self, address: u64) -> Result<Vec<Variable<'_>>, ObjectError> {
+        match *self {
+            ObjectDebugSession::Dwarf(ref s) => {
+                s.variables_at(address).map_err(ObjectError::transparent)
+            }
+            ObjectDebugSession::Pdb(ref s) => {
+                s.variables_at(address).map_err(ObjectError::transparent)
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Returns the layouts of all named structures, classes, unions and enumerations.
+    ///
+    /// Type information is only available for DWARF and PDB debug information. For all other
+    /// formats, this returns an empty list.
+    pub fn types(&self) -> Result<Vec<TypeInfo<'_>>, ObjectError> {
+        match *self {
+            ObjectDebugSession::Dwarf(ref s) => s.types().map_err(ObjectError::transparent),
+            ObjectDebugSession::Pdb(ref s) => s.types().map_err(ObjectError::transparent),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Looks up the layout of a type by its fully qualified name.
+    pub fn type_by_name(&self, name: &str) -> Result<Option<TypeInfo<'_>>, ObjectError> {
+        match *self {
+            ObjectDebugSession::Dwarf(ref s) => {
+                s.type_by_name(name).map_err(ObjectError::transparent)
+            }
+            ObjectDebugSession::Pdb(ref s) => {
+                s.type_by_name(name).map_err(ObjectError::transparent)
+            }
+            _ => Ok(None),
+        }
+    }
 }
 
 impl<'session> DebugSession<'session> for ObjectDebugSession<'_> {
@@ -498,6 +572,13 @@ impl<'session> DebugSession<'session> for ObjectDebugSession<'_> {
         self.functions()
     }
 
+    #[cfg(feature = "rayon")]
+    fn functions_par(
+        &'session self,
+    ) -> DynIterator<'session, Result<Function<'session>, Self::Error>> {
+        self.functions_par()
+    }
+
     fn files(&'session self) -> Self::FileIterator {
         self.files()
     }
@@ -505,6 +586,18 @@ impl<'session> DebugSession<'session> for ObjectDebugSession<'_> {
     fn source_by_path(&self, path: &str) -> Result<Option<Cow<'_, str>>, Self::Error> {
         self.source_by_path(path)
     }
+
+    fn variables_at(&'session self, address: u64) -> Result<Vec<Variable<'session>>, Self::Error> {
+        self.variables_at(address)
+    }
+
+    fn types(&'session self) -> Result<Vec<TypeInfo<'session>>, Self::Error> {
+        self.types()
+    }
+
+    fn type_by_name(&'session self, name: &str) -> Result<Option<TypeInfo<'session>>, Self::Error> {
+        self.type_by_name(name)
+    }
 }
 
 /// An iterator over functions in an [`Object`](enum.Object.html).
@@ -592,6 +685,26 @@ impl<'data, 'object> Iterator for SymbolIterator<'data, 'object> {
     }
 }
 
+/// A generic section iterator
+#[allow(missing_docs)]
+pub enum SectionIterator<'data, 'object> {
+    Breakpad(BreakpadSectionIterator<'data>),
+    Elf(ElfSectionIterator<'data, 'object>),
+    MachO(MachOSectionIterator<'data, 'object>),
+    Pdb(PdbSectionIterator<'data>),
+    Pe(PeSectionIterator<'data, 'object>),
+    SourceBundle(SourceBundleSectionIterator<'data>),
+    Wasm(WasmSectionIterator<'data, 'object>),
+}
+
+impl<'data, 'object> Iterator for SectionIterator<'data, 'object> {
+    type Item = Section<'data>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match_inner!(self, SectionIterator(ref mut iter) => iter.next())
+    }
+}
+
 #[derive(Debug)]
 enum ArchiveInner<'d> {
     Breakpad(MonoArchive<'d, BreakpadObject<'d>>),
@@ -1,20 +1,21 @@
 //! Support for Program Database, the debug companion format on Windows.
 
 use std::borrow::Cow;
-use std::cell::{RefCell, RefMut};
 use std::cmp::Ordering;
 use std::collections::btree_map::{BTreeMap, Entry};
+use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt;
-use std::io::Cursor;
+use std::io::{Cursor, Read};
 use std::sync::Arc;
 
-use lazycell::LazyCell;
-use parking_lot::RwLock;
+use flate2::read::DeflateDecoder;
+use parking_lot::{Mutex, RwLock};
 use pdb::{
     AddressMap, FallibleIterator, InlineSiteSymbol, ItemIndex, LineProgram, MachineType, Module,
     ModuleInfo, PdbInternalSectionOffset, ProcedureSymbol, SymbolData,
 };
+use scroll::Pread;
 use smallvec::SmallVec;
 use thiserror::Error;
 
@@ -23,12 +24,26 @@ use symbolic_common::{
 };
 
 use crate::base::*;
-use crate::shared::{FunctionStack, Parse};
+use crate::shared::{FunctionStack, Parse, SyncLazyCell};
+#[cfg(feature = "rayon")]
+use crate::shared::{UnitBatches, UNITS_PER_THREAD};
 
 type Pdb<'data> = pdb::PDB<'data, Cursor<&'data [u8]>>;
 
 const MAGIC_BIG: &[u8] = b"Microsoft C/C++ MSF 7.00\r\n\x1a\x44\x53\x00\x00\x00";
 
+/// Named stream that holds the string table.
+const STRING_TABLE_STREAM: &[u8] = b"/names";
+
+/// Magic number at the start of the string table stream.
+const STRING_TABLE_MAGIC: u32 = 0xeffe_effe;
+
+/// Named stream that lists all source files embedded into the PDB.
+const SOURCE_HEADER_STREAM: &[u8] = b"/src/headerblock";
+
+/// Prefix of the named streams that hold contents of embedded source files.
+const SOURCE_FILE_STREAM_PREFIX: &str = "/src/files/";
+
 // Used for CFI, remove once abstraction is complete
 #[doc(hidden)]
 pub use pdb;
@@ -95,6 +110,18 @@ impl From<pdb::Error> for PdbError {
     }
 }
 
+impl From<scroll::Error> for PdbError {
+    fn from(e: scroll::Error) -> Self {
+        Self::new(PdbErrorKind::BadObject, e)
+    }
+}
+
+impl From<std::io::Error> for PdbError {
+    fn from(e: std::io::Error) -> Self {
+        Self::new(PdbErrorKind::BadObject, e)
+    }
+}
+
 impl From<fmt::Error> for PdbError {
     fn from(e: fmt::Error) -> Self {
         Self::new(PdbErrorKind::FormattingFailed, e)
@@ -231,8 +258,18 @@ impl<'data> PdbObject<'data> {
     }
 
     /// Determines whether this object contains embedded source.
+    ///
+    /// Source files are embedded into the PDB by the MSVC linker when using `/SOURCELINK` or
+    /// `/PDBSOURCEPATH` together with embedded sources, or by .NET compilers writing Windows PDBs.
     pub fn has_sources(&self) -> bool {
-        false
+        // The linker usually writes the header block stream, even if it does not list any files.
+        match self.pdb.write().named_stream(SOURCE_HEADER_STREAM) {
+            Ok(stream) => match parse_source_header_block(stream.as_slice()) {
+                Ok(entries) => !entries.is_empty(),
+                Err(_) => false,
+            },
+            Err(_) => false,
+        }
     }
 
     /// Determines whether this object is malformed and was only partially parsed
@@ -303,6 +340,7 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for PdbObject<'data> {
     type Error = PdbError;
     type Session = PdbDebugSession<'data>;
     type SymbolIterator = PdbSymbolIterator<'data, 'object>;
+    type SectionIterator = PdbSectionIterator<'data>;
 
     fn file_format(&self) -> FileFormat {
         self.file_format()
@@ -336,6 +374,10 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for PdbObject<'data> {
         self.symbols()
     }
 
+    fn sections(&'object self) -> Self::SectionIterator {
+        std::iter::empty()
+    }
+
     fn symbol_map(&self) -> SymbolMap<'data> {
         self.symbol_map()
     }
@@ -372,6 +414,9 @@ pub(crate) fn arch_from_machine(machine: MachineType) -> Arch {
     }
 }
 
+/// An iterator over sections in the PDB file, which has no sections.
+pub type PdbSectionIterator<'data> = std::iter::Empty<Section<'data>>;
+
 /// An iterator over symbols in the PDB file.
 ///
 /// Returned by [`PdbObject::symbols`](struct.PdbObject.html#method.symbols).
@@ -422,6 +467,8 @@ impl<'data, 'object> Iterator for PdbSymbolIterator<'data, 'object> {
 struct ItemMap<'s, I: ItemIndex> {
     iter: pdb::ItemIter<'s, I>,
     finder: pdb::ItemFinder<'s, I>,
+    /// Indexes of the first and last item that have been read from the stream.
+    loaded: Option<(I, I)>,
 }
 
 impl<'s, I> ItemMap<'s, I>
@@ -433,8 +480,7 @@ where
             return Ok(self.finder.find(index)?);
         }
 
-        while let Some(item) = self.iter.next()? {
-            self.finder.update(&self.iter);
+        while let Some(item) = self.load_next()? {
             match item.index().partial_cmp(&index) {
                 Some(Ordering::Equal) => return Ok(item),
                 Some(Ordering::Greater) => break,
@@ -444,6 +490,38 @@ where
 
         Err(pdb::Error::TypeNotFound(index.into()).into())
     }
+
+    /// Calls `f` for every item in stream order, loading all remaining items.
+    pub fn try_for_each<F>(&mut self, mut f: F) -> Result<(), PdbError>
+    where
+        F: FnMut(pdb::Item<'s, I>) -> Result<(), PdbError>,
+    {
+        while self.load_next()?.is_some() {}
+
+        // The maximum index of the finder is rounded up, so iterate to the last item read instead.
+        let (first, last) = match self.loaded {
+            Some((first, last)) => (first.into(), last.into()),
+            None => return Ok(()),
+        };
+
+        for index in first..=last {
+            f(self.finder.find(I::from(index))?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the next item from the stream and makes it available to the finder.
+    fn load_next(&mut self) -> Result<Option<pdb::Item<'s, I>>, PdbError> {
+        let item = self.iter.next()?;
+        if let Some(ref item) = item {
+            self.finder.update(&self.iter);
+            let first = self.loaded.map_or(item.index(), |(first, _)| first);
+            self.loaded = Some((first, item.index()));
+        }
+
+        Ok(item)
+    }
 }
 
 type TypeMap<'d> = ItemMap<'d, pdb::TypeIndex>;
@@ -453,16 +531,27 @@ struct PdbStreams<'d> {
     debug_info: Arc<pdb::DebugInformation<'d>>,
     type_info: pdb::TypeInformation<'d>,
     id_info: pdb::IdInformation<'d>,
+    /// Contents of the string table stream, which is shared with all threads.
+    string_table: Option<Vec<u8>>,
 }
 
 impl<'d> PdbStreams<'d> {
     fn from_pdb(pdb: &PdbObject<'d>) -> Result<Self, PdbError> {
         let mut p = pdb.pdb.write();
 
+        // The named stream for the string table is missing in certain PDBs. This does not
+        // automatically indicate an error.
+        let string_table = match p.named_stream(STRING_TABLE_STREAM) {
+            Ok(stream) => Some(stream.as_slice().to_vec()),
+            Err(pdb::Error::StreamNameNotFound) => None,
+            Err(e) => return Err(e.into()),
+        };
+
         Ok(Self {
             debug_info: pdb.debug_info.clone(),
             type_info: p.type_information()?,
             id_info: p.id_information()?,
+            string_table,
         })
     }
 
@@ -470,6 +559,7 @@ impl<'d> PdbStreams<'d> {
         ItemMap {
             iter: self.type_info.iter(),
             finder: self.type_info.finder(),
+            loaded: None,
         }
     }
 
@@ -477,61 +567,241 @@ impl<'d> PdbStreams<'d> {
         ItemMap {
             iter: self.id_info.iter(),
             finder: self.id_info.finder(),
+            loaded: None,
         }
     }
 }
 
-struct PdbDebugInfo<'d> {
-    /// The original PDB to load module streams on demand.
-    pdb: Arc<RwLock<Pdb<'d>>>,
+/// A string table, which stores file names and other strings referenced by offset.
+///
+/// Unlike `pdb::StringTable`, this only borrows the contents of the stream and can therefore be
+/// shared between threads.
+#[derive(Clone, Copy, Debug)]
+struct PdbStringTable<'d> {
+    /// NUL-terminated strings following the header of the stream.
+    strings: &'d [u8],
+}
+
+impl<'d> PdbStringTable<'d> {
+    /// Parses the header of the string table stream.
+    ///
+    /// The header consists of a magic number, a hash version and the size of the string buffer.
+    fn parse(data: &'d [u8]) -> Result<Self, PdbError> {
+        let magic: u32 = data.pread_with(0, scroll::LE)?;
+        if magic != STRING_TABLE_MAGIC {
+            return Err(PdbError::new(
+                PdbErrorKind::BadObject,
+                "invalid string table magic",
+            ));
+        }
+
+        let size: u32 = data.pread_with(8, scroll::LE)?;
+        let strings = data
+            .get(12..)
+            .and_then(|strings| strings.get(..size as usize))
+            .ok_or(PdbErrorKind::BadObject)?;
+
+        Ok(PdbStringTable { strings })
+    }
+
+    /// Returns the raw bytes of the referenced string without its NUL terminator.
+    fn get(&self, string_ref: pdb::StringRef) -> Result<&'d [u8], PdbError> {
+        let strings = self.strings;
+        let start = string_ref.0 as usize;
+        let end = strings
+            .get(start..)
+            .and_then(|rest| rest.iter().position(|&b| b == 0))
+            .ok_or(PdbErrorKind::BadObject)?;
+
+        Ok(&strings[start..start + end])
+    }
+
+    /// Returns the referenced string, replacing invalid UTF-8 sequences.
+    fn get_lossy(&self, string_ref: pdb::StringRef) -> Result<Cow<'d, str>, PdbError> {
+        Ok(String::from_utf8_lossy(self.get(string_ref)?))
+    }
+}
+
+/// Debug information that can be shared between threads.
+///
+/// Streams of the `pdb` crate cannot be sent to other threads, so this only holds data borrowed
+/// from them and the lazily loaded type maps behind locks. Module streams are read separately
+/// by every thread, see [`PdbWorker`].
+struct PdbSharedInfo<'d> {
+    /// The CPU architecture, used to resolve frame pointer registers.
+    arch: Arch,
     /// All module headers for repeated iteration.
     modules: Vec<Module<'d>>,
-    /// Lazy loaded module streams in the same order as headers.
-    module_infos: Vec<LazyCell<Option<ModuleInfo<'d>>>>,
     /// Cache for module by name lookup for cross module imports.
-    module_exports: RefCell<BTreeMap<pdb::ModuleRef, Option<pdb::CrossModuleExports>>>,
-    /// OMAP structure to map reordered sections to RVAs.
-    address_map: pdb::AddressMap<'d>,
+    module_exports: Mutex<BTreeMap<pdb::ModuleRef, Option<pdb::CrossModuleExports>>>,
     /// String table for name lookups.
-    string_table: Option<pdb::StringTable<'d>>,
+    string_table: Option<PdbStringTable<'d>>,
     /// Lazy loaded map of the TPI stream.
-    type_map: RefCell<TypeMap<'d>>,
+    type_map: Mutex<TypeMap<'d>>,
     /// Lazy loaded map of the IPI stream.
-    id_map: RefCell<IdMap<'d>>,
+    id_map: Mutex<IdMap<'d>>,
+    /// Lazy loaded index of named type definitions in the TPI stream.
+    type_names: SyncLazyCell<TypeNameIndex>,
+}
+
+impl<'d> PdbSharedInfo<'d> {
+    fn file_info(&self, file_info: pdb::FileInfo<'_>) -> Result<FileInfo<'d>, PdbError> {
+        let file_path = match self.string_table {
+            Some(ref string_table) => string_table.get(file_info.name)?,
+            None => &[],
+        };
+
+        Ok(FileInfo::from_path(file_path))
+    }
+
+    fn get_exports(
+        &self,
+        pdb: &RwLock<Pdb<'d>>,
+        module_ref: pdb::ModuleRef,
+    ) -> Result<Option<pdb::CrossModuleExports>, PdbError> {
+        let name = match self.string_table {
+            Some(ref string_table) => string_table.get_lossy(module_ref.0)?,
+            None => return Ok(None),
+        };
+
+        let module = self
+            .modules
+            .iter()
+            .find(|m| m.module_name().eq_ignore_ascii_case(&name));
+
+        let module = match module {
+            Some(module) => pdb.write().module_info(module)?,
+            None => None,
+        };
+
+        Ok(match module {
+            Some(module) => Some(module.exports()?),
+            None => None,
+        })
+    }
+
+    /// Resolves a cross module reference, reading the exporting module from the given PDB.
+    ///
+    /// Exports are cached across all threads. The module is read without holding the lock on the
+    /// cache, so another thread may read the same exports concurrently.
+    fn resolve_import<I: ItemIndex>(
+        &self,
+        pdb: &RwLock<Pdb<'d>>,
+        cross_ref: pdb::CrossModuleRef<I>,
+    ) -> Result<Option<I>, PdbError> {
+        let pdb::CrossModuleRef(module_ref, local_index) = cross_ref;
+
+        if let Some(exports) = self.module_exports.lock().get(&module_ref) {
+            return match exports {
+                Some(exports) => Ok(exports.resolve_import(local_index)?),
+                None => Ok(None),
+            };
+        }
+
+        let exports = self.get_exports(pdb, module_ref)?;
+        let mut module_exports = self.module_exports.lock();
+        let exports = match module_exports.entry(module_ref) {
+            Entry::Vacant(vacant) => vacant.insert(exports),
+            Entry::Occupied(occupied) => occupied.into_mut(),
+        };
+
+        Ok(if let Some(ref exports) = *exports {
+            exports.resolve_import(local_index)?
+        } else {
+            None
+        })
+    }
+
+    /// Returns the index of named type definitions, building it on first access.
+    fn type_names(&self) -> Result<&TypeNameIndex, PdbError> {
+        self.type_names.try_borrow_with(|| {
+            let mut index = TypeNameIndex::default();
+
+            self.type_map.lock().try_for_each(|item| {
+                let (name, size) = match item.parse() {
+                    Ok(pdb::TypeData::Class(data)) if !data.properties.forward_reference() => {
+                        (data.name, Some(data.size.into()))
+                    }
+                    Ok(pdb::TypeData::Union(data)) if !data.properties.forward_reference() => {
+                        (data.name, Some(data.size.into()))
+                    }
+                    Ok(pdb::TypeData::Enumeration(data))
+                        if !data.properties.forward_reference() =>
+                    {
+                        (data.name, None)
+                    }
+                    _ => return Ok(()),
+                };
+
+                // Anonymous types are named `<unnamed-tag>` or similar and cannot be looked up.
+                let name = name.to_string();
+                if name.starts_with('<') {
+                    return Ok(());
+                }
+
+                if let Some(size) = size {
+                    index.sizes.entry(name.clone().into_owned()).or_insert(size);
+                }
+
+                if let Entry::Vacant(entry) = index.by_name.entry(name.into_owned()) {
+                    entry.insert(item.index());
+                    index.definitions.push(item.index());
+                }
+
+                Ok(())
+            })?;
+
+            Ok(index)
+        })
+    }
+}
+
+struct PdbDebugInfo<'d> {
+    /// The original PDB to load module streams on demand.
+    pdb: Arc<RwLock<Pdb<'d>>>,
+    /// The raw PDB data, which threads of `functions_par` open separately.
+    #[cfg(feature = "rayon")]
+    data: &'d [u8],
+    /// Lazy loaded module streams in the same order as headers.
+    module_infos: Vec<SyncLazyCell<Option<ModuleInfo<'d>>>>,
+    /// OMAP structure to map reordered sections to RVAs.
+    address_map: pdb::AddressMap<'d>,
+    /// Lazy loaded index of embedded source files by lowercase path.
+    sources: SyncLazyCell<BTreeMap<String, PdbSourceEntry>>,
+    /// Debug information that is shared with threads of `functions_par`.
+    shared: PdbSharedInfo<'d>,
 }
 
 impl<'d> PdbDebugInfo<'d> {
     fn build(pdb: &PdbObject<'d>, streams: &'d PdbStreams<'d>) -> Result<Self, PdbError> {
         let modules = streams.debug_info.modules()?.collect::<Vec<_>>()?;
-        let module_infos = modules.iter().map(|_| LazyCell::new()).collect();
-        let module_exports = RefCell::new(BTreeMap::new());
-        let type_map = RefCell::new(streams.type_map());
-        let id_map = RefCell::new(streams.id_map());
-
-        // Avoid deadlocks by only covering the two access to the address map and string table. For
-        // instance, `pdb.symbol_map()` requires a mutable borrow of the PDB as well.
-        let mut p = pdb.pdb.write();
-        let address_map = p.address_map()?;
+        let module_infos = modules.iter().map(|_| SyncLazyCell::new()).collect();
 
-        // PDB::string_table errors if the named stream for the string table is not present.
-        // However, this occurs in certain PDBs and does not automatically indicate an error.
-        let string_table = match p.string_table() {
-            Ok(string_table) => Some(string_table),
-            Err(pdb::Error::StreamNameNotFound) => None,
-            Err(e) => return Err(e.into()),
+        let string_table = match streams.string_table {
+            Some(ref data) => Some(PdbStringTable::parse(data)?),
+            None => None,
         };
 
-        drop(p);
+        // Avoid deadlocks by only covering the access to the address map. For instance,
+        // `pdb.symbol_map()` requires a mutable borrow of the PDB as well.
+        let address_map = pdb.pdb.write().address_map()?;
 
         Ok(PdbDebugInfo {
             pdb: pdb.pdb.clone(),
-            modules,
+            #[cfg(feature = "rayon")]
+            data: pdb.data,
             module_infos,
-            module_exports,
             address_map,
-            string_table,
-            type_map,
-            id_map,
+            sources: SyncLazyCell::new(),
+            shared: PdbSharedInfo {
+                arch: pdb.arch(),
+                modules,
+                module_exports: Mutex::new(BTreeMap::new()),
+                string_table,
+                type_map: Mutex::new(streams.type_map()),
+                id_map: Mutex::new(streams.id_map()),
+                type_names: SyncLazyCell::new(),
+            },
         })
     }
 
@@ -551,64 +821,60 @@ impl<'d> PdbDebugInfo<'d> {
         };
 
         let module_opt = cell.try_borrow_with(|| {
-            let module = &self.modules[index];
+            let module = &self.shared.modules[index];
             self.pdb.write().module_info(module)
         })?;
 
         Ok(module_opt.as_ref())
     }
 
-    fn file_info(&self, file_info: pdb::FileInfo<'d>) -> Result<FileInfo<'_>, PdbError> {
-        let file_path = match self.string_table {
-            Some(ref string_table) => file_info.name.to_raw_string(string_table)?,
-            None => "".into(),
-        };
-
-        Ok(FileInfo::from_path(file_path.as_bytes()))
-    }
+    /// Returns the index of embedded source files, loading it on first access.
+    fn sources(&self) -> Result<&BTreeMap<String, PdbSourceEntry>, PdbError> {
+        self.sources.try_borrow_with(|| {
+            let mut sources = BTreeMap::new();
 
-    fn get_exports(
-        &'d self,
-        module_ref: pdb::ModuleRef,
-    ) -> Result<Option<pdb::CrossModuleExports>, PdbError> {
-        let name = match self.string_table {
-            Some(ref string_table) => module_ref.0.to_string_lossy(string_table)?,
-            None => return Ok(None),
-        };
+            let string_table = match self.shared.string_table {
+                Some(ref string_table) => string_table,
+                None => return Ok(sources),
+            };
 
-        let module_index = self
-            .modules
-            .iter()
-            .position(|m| m.module_name().eq_ignore_ascii_case(&name));
+            let stream = match self.pdb.write().named_stream(SOURCE_HEADER_STREAM) {
+                Ok(stream) => stream,
+                Err(pdb::Error::StreamNameNotFound) => return Ok(sources),
+                Err(e) => return Err(e.into()),
+            };
 
-        let module = match module_index {
-            Some(index) => self.get_module(index)?,
-            None => None,
-        };
+            for entry in parse_source_header_block(stream.as_slice())? {
+                let path = string_table.get_lossy(entry.file_name)?;
+                sources.insert(path.to_lowercase(), entry);
+            }
 
-        Ok(match module {
-            Some(module) => Some(module.exports()?),
-            None => None,
+            Ok(sources)
         })
     }
 
-    fn resolve_import<I: ItemIndex>(
-        &'d self,
-        cross_ref: pdb::CrossModuleRef<I>,
-    ) -> Result<Option<I>, PdbError> {
-        let pdb::CrossModuleRef(module_ref, local_index) = cross_ref;
+    /// Reads and decompresses the contents of an embedded source file.
+    fn source_contents(&self, entry: &PdbSourceEntry) -> Result<Option<Vec<u8>>, PdbError> {
+        let string_table = match self.shared.string_table {
+            Some(ref string_table) => string_table,
+            None => return Ok(None),
+        };
 
-        let mut module_exports = self.module_exports.borrow_mut();
-        let exports = match module_exports.entry(module_ref) {
-            Entry::Vacant(vacant) => vacant.insert(self.get_exports(module_ref)?),
-            Entry::Occupied(occupied) => occupied.into_mut(),
+        // The linker names content streams after the lowercase virtual file name.
+        let virtual_name = string_table.get_lossy(entry.virtual_name)?;
+        let stream_name = format!(
+            "{}{}",
+            SOURCE_FILE_STREAM_PREFIX,
+            virtual_name.to_lowercase()
+        );
+
+        let stream = match self.pdb.write().named_stream(stream_name.as_bytes()) {
+            Ok(stream) => stream,
+            Err(pdb::Error::StreamNameNotFound) => return Ok(None),
+            Err(e) => return Err(e.into()),
         };
 
-        Ok(if let Some(ref exports) = *exports {
-            exports.resolve_import(local_index)?
-        } else {
-            None
-        })
+        entry.decompress(stream.as_slice())
     }
 }
 
@@ -620,6 +886,17 @@ impl<'slf, 'd: 'slf> AsSelf<'slf> for PdbDebugInfo<'d> {
     }
 }
 
+/// Index of the named class, union and enumeration definitions in the TPI stream.
+#[derive(Debug, Default)]
+struct TypeNameIndex {
+    /// The first definition of every name in stream order.
+    definitions: Vec<pdb::TypeIndex>,
+    /// Maps fully qualified names to their first definition.
+    by_name: BTreeMap<String, pdb::TypeIndex>,
+    /// Sizes of all defined classes and unions, used to resolve forward references.
+    sizes: BTreeMap<String, u64>,
+}
+
 /// Debug session for PDB objects.
 pub struct PdbDebugSession<'d> {
     cell: SelfCell<Box<PdbStreams<'d>>, PdbDebugInfo<'d>>,
@@ -654,11 +931,81 @@ impl<'d> PdbDebugSession<'d> {
         }
     }
 
+    /// Returns an iterator over all functions in this debug file, reading modules in parallel.
+    ///
+    /// This yields the same functions as [`functions`](Self::functions) in the same order. Modules
+    /// are read in batches, and every thread opens its own reader on the PDB data.
+    #[cfg(feature = "rayon")]
+    pub fn functions_par(&self) -> PdbParFunctionIterator<'_> {
+        let debug_info = self.cell.get();
+        PdbParFunctionIterator {
+            debug_info,
+            batches: UnitBatches::new(debug_info.shared.modules.len()),
+        }
+    }
+
     /// Looks up a file's source contents by its full canonicalized path.
     ///
-    /// The given path must be canonicalized.
-    pub fn source_by_path(&self, _path: &str) -> Result<Option<Cow<'_, str>>, PdbError> {
-        Ok(None)
+    /// The given path must be canonicalized. Only source files embedded into the PDB can be
+    /// resolved. Since paths on Windows are case insensitive, the lookup ignores case.
+    pub fn source_by_path(&self, path: &str) -> Result<Option<Cow<'_, str>>, PdbError> {
+        let debug_info = self.cell.get();
+        let entry = match debug_info.sources()?.get(&path.to_lowercase()) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        let contents = debug_info.source_contents(entry)?;
+        Ok(contents.map(|bytes| match String::from_utf8(bytes) {
+            Ok(string) => Cow::Owned(string),
+            Err(e) => Cow::Owned(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+        }))
+    }
+
+    /// Returns the parameters and local variables that are live at the given address.
+    ///
+    /// The address is relative to the image base. Register numbers in the returned locations are
+    /// CodeView register numbers.
+    pub fn variables_at(&self, address: u64) -> Result<Vec<Variable<'_>>, PdbError> {
+        let rva = match u32::try_from(address) {
+            Ok(rva) => rva,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        for unit in self.cell.get().units() {
+            if let Some(variables) = unit?.variables_at(rva)? {
+                return Ok(variables);
+            }
+        }
+
+        Ok(Vec::new())
+    }
+
+    /// Returns the layouts of all named classes, structures, unions and enumerations.
+    ///
+    /// Forward references are skipped. If the type stream contains multiple definitions with the
+    /// same name, only the first definition is returned.
+    pub fn types(&self) -> Result<Vec<TypeInfo<'_>>, PdbError> {
+        let debug_info = self.cell.get();
+        let mut formatter = TypeFormatter::for_types(&debug_info.shared);
+        let mut types = Vec::new();
+
+        for &index in &debug_info.shared.type_names()?.definitions {
+            if let Some(info) = formatter.type_info(index)? {
+                types.push(info);
+            }
+        }
+
+        Ok(types)
+    }
+
+    /// Looks up the layout of a type by its fully qualified name.
+    pub fn type_by_name(&self, name: &str) -> Result<Option<TypeInfo<'_>>, PdbError> {
+        let debug_info = self.cell.get();
+        match debug_info.shared.type_names()?.by_name.get(name) {
+            Some(&index) => TypeFormatter::for_types(&debug_info.shared).type_info(index),
+            None => Ok(None),
+        }
     }
 }
 
@@ -671,6 +1018,13 @@ impl<'session> DebugSession<'session> for PdbDebugSession<'_> {
         self.functions()
     }
 
+    #[cfg(feature = "rayon")]
+    fn functions_par(
+        &'session self,
+    ) -> DynIterator<'session, Result<Function<'session>, Self::Error>> {
+        Box::new(self.functions_par())
+    }
+
     fn files(&'session self) -> Self::FileIterator {
         self.files()
     }
@@ -678,6 +1032,184 @@ impl<'session> DebugSession<'session> for PdbDebugSession<'_> {
     fn source_by_path(&self, path: &str) -> Result<Option<Cow<'_, str>>, Self::Error> {
         self.source_by_path(path)
     }
+
+    fn variables_at(&'session self, address: u64) -> Result<Vec<Variable<'session>>, Self::Error> {
+        self.variables_at(address)
+    }
+
+    fn types(&'session self) -> Result<Vec<TypeInfo<'session>>, Self::Error> {
+        self.types()
+    }
+
+    fn type_by_name(&'session self, name: &str) -> Result<Option<TypeInfo<'session>>, Self::Error> {
+        self.type_by_name(name)
+    }
+}
+
+/// Version of the embedded source header block and its entries.
+const SOURCE_HEADER_VERSION: u32 = 19980827;
+
+/// Size of the header at the start of the embedded source header block.
+const SOURCE_HEADER_SIZE: usize = 64;
+
+/// Minimum size of a single entry record in the embedded source header block.
+///
+/// This matches `SrcHeaderBlockEntry` in LLVM. Every record stores its actual size in its first
+/// field, which is used to advance to the next record.
+const SOURCE_ENTRY_SIZE: usize = 40;
+
+/// The source file is stored without compression.
+const SOURCE_COMPRESSION_NONE: u8 = 0;
+
+/// The source file is stored in the format used by .NET compilers: a 32-bit uncompressed size
+/// followed by deflated data, or the raw contents if the size is zero.
+const SOURCE_COMPRESSION_DOTNET: u8 = 101;
+
+/// An entry of the embedded source header block.
+#[derive(Clone, Debug)]
+struct PdbSourceEntry {
+    /// Reference to the original file path in the string table.
+    file_name: pdb::StringRef,
+    /// Reference to the virtual file name, which names the stream holding the contents.
+    virtual_name: pdb::StringRef,
+    /// Compression applied to the contents stream.
+    compression: u8,
+}
+
+impl PdbSourceEntry {
+    /// Decompresses the raw contents of the source file stream.
+    ///
+    /// Returns `Ok(None)` if the compression method is not supported.
+    fn decompress(&self, data: &[u8]) -> Result<Option<Vec<u8>>, PdbError> {
+        match self.compression {
+            SOURCE_COMPRESSION_NONE => Ok(Some(data.to_vec())),
+            SOURCE_COMPRESSION_DOTNET => {
+                let size: u32 = data.pread_with(0, scroll::LE)?;
+                let compressed = &data[4..];
+                if size == 0 {
+                    return Ok(Some(compressed.to_vec()));
+                }
+
+                let mut contents = Vec::new();
+                DeflateDecoder::new(compressed).read_to_end(&mut contents)?;
+                Ok(Some(contents))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Parses the entries of the embedded source header block.
+///
+/// The stream starts with a fixed-size header, followed by a serialized hash table that maps
+/// string table references of file names to entry records. Each record starts with its own size.
+/// An empty stream does not list any files.
+fn parse_source_header_block(data: &[u8]) -> Result<Vec<PdbSourceEntry>, PdbError> {
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let version: u32 = data.pread_with(0, scroll::LE)?;
+    if version != SOURCE_HEADER_VERSION {
+        return Err(PdbError::new(
+            PdbErrorKind::BadObject,
+            format!("unsupported source header block version {}", version),
+        ));
+    }
+
+    let mut offset = SOURCE_HEADER_SIZE;
+    let count: u32 = data.gread_with(&mut offset, scroll::LE)?;
+    let _capacity: u32 = data.gread_with(&mut offset, scroll::LE)?;
+
+    // Skip the bit vectors of present and deleted buckets. Present entries are stored densely
+    // after them, so the bit vectors are not needed to read the entries.
+    for _ in 0..2 {
+        let words: u32 = data.gread_with(&mut offset, scroll::LE)?;
+        offset += words as usize * 4;
+    }
+
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let _key: u32 = data.gread_with(&mut offset, scroll::LE)?;
+        let size: u32 = data.pread_with(offset, scroll::LE)?;
+        let size = size as usize;
+        if size < SOURCE_ENTRY_SIZE {
+            return Err(PdbError::new(
+                PdbErrorKind::BadObject,
+                format!("invalid source entry size {}", size),
+            ));
+        }
+
+        let record = offset
+            .checked_add(size)
+            .and_then(|end| data.get(offset..end))
+            .ok_or(PdbErrorKind::BadObject)?;
+        offset += size;
+
+        // Layout: size (u32), version (u32), crc (u32), file size (u32), file name (u32), object
+        // name (u32), virtual file name (u32), compression (u8), is virtual (u8), padding (u16),
+        // reserved (8 bytes).
+        entries.push(PdbSourceEntry {
+            file_name: pdb::StringRef(record.pread_with(16, scroll::LE)?),
+            virtual_name: pdb::StringRef(record.pread_with(24, scroll::LE)?),
+            compression: record.pread_with(28, scroll::LE)?,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Frame layout of a procedure, including the registers used to address locals and parameters.
+const S_FRAMEPROC: u16 = 0x1012;
+
+/// Offset of the flags in an `S_FRAMEPROC` record, after the symbol kind.
+const FRAMEPROC_FLAGS_OFFSET: usize = 22;
+
+/// Live range of a local variable described by a DIA program.
+const S_DEFRANGE: u16 = 0x113f;
+
+/// Live range of a sub field of a local variable described by a DIA program.
+const S_DEFRANGE_SUBFIELD: u16 = 0x1140;
+
+/// Live range of a local variable held in a register.
+const S_DEFRANGE_REGISTER: u16 = 0x1141;
+
+/// Live range of a local variable stored relative to the frame pointer.
+const S_DEFRANGE_FRAMEPOINTER_REL: u16 = 0x1142;
+
+/// Live range of a sub field of a local variable held in a register.
+const S_DEFRANGE_SUBFIELD_REGISTER: u16 = 0x1143;
+
+/// Location of a local variable stored relative to the frame pointer in the entire function.
+const S_DEFRANGE_FRAMEPOINTER_REL_FULL_SCOPE: u16 = 0x1144;
+
+/// Live range of a local variable stored relative to a register.
+const S_DEFRANGE_REGISTER_REL: u16 = 0x1145;
+
+/// Resolves the frame pointer register for locals or parameters from the flags of `S_FRAMEPROC`.
+///
+/// The flags encode the register in two bits, separately for locals and parameters. `1` is the
+/// stack pointer, `2` the frame pointer and `3` the base pointer of functions that realign the
+/// stack. The CodeView register numbers follow `decodeFramePtrReg` in LLVM. Returns `None` if the
+/// register is not specified or the CPU is not supported.
+fn frame_pointer_register(arch: Arch, flags: u32, is_param: bool) -> Option<u16> {
+    let shift = if is_param { 16 } else { 14 };
+    let encoded = (flags >> shift) & 0x3;
+
+    let registers: [u16; 3] = match arch.cpu_family() {
+        // VFRAME, EBP, EBX
+        CpuFamily::Intel32 => [30006, 22, 20],
+        // RSP, RBP, R13
+        CpuFamily::Amd64 => [335, 334, 341],
+        // SP, FP, X19
+        CpuFamily::Arm64 => [81, 79, 69],
+        _ => return None,
+    };
+
+    match encoded {
+        0 => None,
+        encoded => Some(registers[encoded as usize - 1]),
+    }
 }
 
 /// Checks whether the given name declares an anonymous namespace.
@@ -689,23 +1221,51 @@ fn is_anonymous_namespace(name: &str) -> bool {
         .map_or(false, |rest| u32::from_str_radix(rest, 16).is_ok())
 }
 
-/// Formatter for function types.
+/// Formatter for function and data types.
 ///
-/// This formatter currently only contains the minimum implementation requried to format inline
-/// function names without parameters.
+/// This formatter contains the minimum implementation requried to format inline function names
+/// without parameters and the names of data types. It also resolves the layouts of classes,
+/// unions and enumerations.
+///
+/// The type maps are locked for every lookup, so that threads formatting names of different
+/// modules only contend while reading a single record.
 struct TypeFormatter<'u, 'd> {
-    unit: &'u Unit<'d>,
-    type_map: RefMut<'u, TypeMap<'d>>,
-    id_map: RefMut<'u, IdMap<'d>>,
+    shared: &'u PdbSharedInfo<'d>,
+    unit: Option<&'u Unit<'u, 'd>>,
+    /// Whether to write primitive and array types, which are omitted from function names.
+    full_types: bool,
 }
 
 impl<'u, 'd> TypeFormatter<'u, 'd> {
     /// Creates a new `TypeFormatter`.
-    pub fn new(unit: &'u Unit<'d>) -> Self {
+    pub fn new(unit: &'u Unit<'u, 'd>) -> Self {
         Self {
-            unit,
-            type_map: unit.debug_info.type_map.borrow_mut(),
-            id_map: unit.debug_info.id_map.borrow_mut(),
+            shared: unit.shared,
+            unit: Some(unit),
+            full_types: false,
+        }
+    }
+
+    /// Creates a new `TypeFormatter` for the type stream outside of a module.
+    ///
+    /// Cross module references cannot be resolved by this formatter.
+    pub fn for_types(shared: &'u PdbSharedInfo<'d>) -> Self {
+        Self {
+            shared,
+            unit: None,
+            full_types: false,
+        }
+    }
+
+    /// Resolves cross module references using the module of this formatter.
+    fn resolve_index<I>(&self, index: I) -> Result<Option<I>, PdbError>
+    where
+        I: ItemIndex,
+    {
+        match self.unit {
+            Some(unit) => unit.resolve_index(index),
+            None if index.is_cross_module() => Ok(None),
+            None => Ok(Some(index)),
         }
     }
 
@@ -715,12 +1275,12 @@ impl<'u, 'd> TypeFormatter<'u, 'd> {
         target: &mut W,
         index: pdb::IdIndex,
     ) -> Result<(), PdbError> {
-        let index = match self.unit.resolve_index(index)? {
+        let index = match self.resolve_index(index)? {
             Some(index) => index,
             None => return Ok(write!(target, "<redacted>")?),
         };
 
-        let id = self.id_map.try_get(index)?;
+        let id = self.shared.id_map.lock().try_get(index)?;
         match id.parse() {
             Ok(pdb::IdData::Function(data)) => {
                 if let Some(scope) = data.scope {
@@ -777,13 +1337,19 @@ impl<'u, 'd> TypeFormatter<'u, 'd> {
         target: &mut W,
         index: pdb::TypeIndex,
     ) -> Result<(), PdbError> {
-        let index = match self.unit.resolve_index(index)? {
+        let index = match self.resolve_index(index)? {
             Some(index) => index,
             None => return Ok(write!(target, "<redacted>")?),
         };
 
-        let ty = self.type_map.try_get(index)?;
+        let ty = self.shared.type_map.lock().try_get(index)?;
         match ty.parse() {
+            Ok(pdb::TypeData::Primitive(data)) if self.full_types => {
+                write!(target, "{}", primitive_name(data.kind))?;
+                if data.indirection.is_some() {
+                    write!(target, "*")?;
+                }
+            }
             Ok(pdb::TypeData::Primitive(_)) => {
                 // nothing to do
             }
@@ -877,6 +1443,23 @@ impl<'u, 'd> TypeFormatter<'u, 'd> {
             Ok(pdb::TypeData::Enumerate(data)) => {
                 write!(target, "{}", data.name.to_string())?;
             }
+            Ok(pdb::TypeData::Array(data)) if self.full_types => {
+                // Nested arrays are written as a single array with multiple dimensions.
+                let element_type = self.array_element_type(data.element_type)?;
+                self.write_type(target, element_type)?;
+
+                let bounds = self.array_bounds(index)?;
+                for &count in &bounds {
+                    match count {
+                        0 => write!(target, "[]")?,
+                        count => write!(target, "[{}]", count)?,
+                    }
+                }
+
+                if bounds.is_empty() {
+                    write!(target, "[]")?;
+                }
+            }
             Ok(pdb::TypeData::Array(_)) => {
                 // nothing to do
             }
@@ -920,23 +1503,385 @@ impl<'u, 'd> TypeFormatter<'u, 'd> {
         self.write_id(&mut string, index)?;
         Ok(string)
     }
+
+    /// Formats the `Type` with the given index to a string.
+    ///
+    /// Unlike within function names, this writes out primitive and array types, which is required
+    /// to name the types of variables and members.
+    pub fn format_type(&mut self, index: pdb::TypeIndex) -> Result<String, PdbError> {
+        let full_types = std::mem::replace(&mut self.full_types, true);
+        let mut string = String::new();
+        let result = self.write_type(&mut string, index);
+        self.full_types = full_types;
+        result.map(|()| string)
+    }
+
+    /// Parses the `Type` with the given index.
+    ///
+    /// Returns `None` if the type is a cross module reference that cannot be resolved, or if the
+    /// pdb crate does not implement its kind.
+    fn parse_type(&mut self, index: pdb::TypeIndex) -> Result<Option<pdb::TypeData<'d>>, PdbError> {
+        let index = match self.resolve_index(index)? {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+
+        let ty = self.shared.type_map.lock().try_get(index)?;
+        match ty.parse() {
+            Ok(data) => Ok(Some(data)),
+            Err(pdb::Error::UnimplementedTypeKind(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Computes the size of the `Type` with the given index in bytes.
+    ///
+    /// Forward references to classes and unions are resolved through the sizes of defined types
+    /// in the type name index.
+    fn type_size(&mut self, index: pdb::TypeIndex) -> Result<Option<u64>, PdbError> {
+        let data = match self.parse_type(index)? {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+
+        Ok(match data {
+            pdb::TypeData::Primitive(data) => primitive_size(data),
+            pdb::TypeData::Class(data) if data.properties.forward_reference() => {
+                self.defined_size(&data.name.to_string())?
+            }
+            pdb::TypeData::Class(data) => Some(data.size.into()),
+            pdb::TypeData::Union(data) if data.properties.forward_reference() => {
+                self.defined_size(&data.name.to_string())?
+            }
+            pdb::TypeData::Union(data) => Some(data.size.into()),
+            pdb::TypeData::Enumeration(data) => self.type_size(data.underlying_type)?,
+            pdb::TypeData::Pointer(data) => Some(data.attributes.size().into()),
+            pdb::TypeData::Modifier(data) => self.type_size(data.underlying_type)?,
+            pdb::TypeData::Bitfield(data) => self.type_size(data.underlying_type)?,
+            pdb::TypeData::Array(data) => data.dimensions.last().map(|&size| size.into()),
+            _ => None,
+        })
+    }
+
+    /// Returns the size of the defined class or union with the given name.
+    fn defined_size(&self, name: &str) -> Result<Option<u64>, PdbError> {
+        Ok(self.shared.type_names()?.sizes.get(name).copied())
+    }
+
+    /// Computes the number of elements in each dimension of an array type, outermost first.
+    ///
+    /// Nested arrays are flattened into a single list of dimensions. Returns an empty list if the
+    /// type is not an array.
+    fn array_bounds(&mut self, index: pdb::TypeIndex) -> Result<Vec<u64>, PdbError> {
+        let data = match self.parse_type(index)? {
+            Some(pdb::TypeData::Array(data)) => data,
+            Some(pdb::TypeData::Modifier(data)) => return self.array_bounds(data.underlying_type),
+            _ => return Ok(Vec::new()),
+        };
+
+        // Dimensions are specified as byte sizes, where each dimension includes all lower
+        // dimensions. Without the element size, the element counts cannot be determined.
+        let mut bounds = Vec::with_capacity(data.dimensions.len());
+        let mut inner_size = self.type_size(data.element_type)?.unwrap_or(0);
+        for &size in &data.dimensions {
+            let size = u64::from(size);
+            bounds.push(size.checked_div(inner_size).unwrap_or(0));
+            inner_size = size;
+        }
+
+        bounds.reverse();
+        bounds.extend(self.array_bounds(data.element_type)?);
+        Ok(bounds)
+    }
+
+    /// Returns the element type of an array after removing all nested array dimensions.
+    fn array_element_type(&mut self, index: pdb::TypeIndex) -> Result<pdb::TypeIndex, PdbError> {
+        match self.parse_type(index)? {
+            Some(pdb::TypeData::Array(data)) => self.array_element_type(data.element_type),
+            _ => Ok(index),
+        }
+    }
+
+    /// Resolves a data member of a class or union.
+    fn type_member(&mut self, data: pdb::MemberType<'d>) -> Result<TypeMember<'d>, PdbError> {
+        let (type_index, bit_offset, bit_size) = match self.parse_type(data.field_type)? {
+            Some(pdb::TypeData::Bitfield(bitfield)) => (
+                bitfield.underlying_type,
+                Some(bitfield.position.into()),
+                Some(bitfield.length.into()),
+            ),
+            _ => (data.field_type, None, None),
+        };
+
+        Ok(TypeMember {
+            name: data.name.to_string(),
+            offset: data.offset.into(),
+            type_name: Some(self.format_type(type_index)?),
+            array_bounds: self.array_bounds(type_index)?,
+            bit_offset,
+            bit_size,
+        })
+    }
+
+    /// Resolves the layout of a class, union or enumeration.
+    ///
+    /// Returns `None` if the type with the given index is not one of these types.
+    fn type_info(&mut self, index: pdb::TypeIndex) -> Result<Option<TypeInfo<'d>>, PdbError> {
+        let (name, kind, size, fields) = match self.parse_type(index)? {
+            Some(pdb::TypeData::Class(data)) => {
+                let kind = match data.kind {
+                    pdb::ClassKind::Class => TypeKind::Class,
+                    _ => TypeKind::Struct,
+                };
+                (data.name, kind, Some(data.size.into()), data.fields)
+            }
+            Some(pdb::TypeData::Union(data)) => (
+                data.name,
+                TypeKind::Union,
+                Some(data.size.into()),
+                Some(data.fields),
+            ),
+            Some(pdb::TypeData::Enumeration(data)) => {
+                let size = self.type_size(data.underlying_type)?;
+                (data.name, TypeKind::Enum, size, Some(data.fields))
+            }
+            _ => return Ok(None),
+        };
+
+        let mut info = TypeInfo {
+            name: name.to_string(),
+            kind,
+            size,
+            members: Vec::new(),
+            base_classes: Vec::new(),
+            enum_values: Vec::new(),
+        };
+
+        // Long field lists are split into multiple records linked by continuations.
+        let mut next_fields = fields;
+        while let Some(fields) = next_fields {
+            let list = match self.parse_type(fields)? {
+                Some(pdb::TypeData::FieldList(list)) => list,
+                _ => break,
+            };
+
+            for field in list.fields {
+                match field {
+                    pdb::TypeData::Member(data) => {
+                        let member = self.type_member(data)?;
+                        info.members.push(member);
+                    }
+                    pdb::TypeData::BaseClass(data) => info.base_classes.push(BaseClass {
+                        type_name: Some(self.format_type(data.base_class)?),
+                        offset: Some(data.offset.into()),
+                    }),
+                    pdb::TypeData::VirtualBaseClass(data) => info.base_classes.push(BaseClass {
+                        type_name: Some(self.format_type(data.base_class)?),
+                        offset: None,
+                    }),
+                    pdb::TypeData::Enumerate(data) => info.enum_values.push(EnumValue {
+                        name: data.name.to_string(),
+                        value: variant_value(data.value),
+                    }),
+                    _ => (),
+                }
+            }
+
+            next_fields = list.continuation;
+        }
+
+        Ok(Some(info))
+    }
 }
 
-struct Unit<'s> {
-    debug_info: &'s PdbDebugInfo<'s>,
-    module: &'s pdb::ModuleInfo<'s>,
-    imports: pdb::CrossModuleImports<'s>,
+/// Returns the size of a primitive type in bytes.
+fn primitive_size(data: pdb::PrimitiveType) -> Option<u64> {
+    use pdb::{Indirection, PrimitiveKind};
+
+    if let Some(indirection) = data.indirection {
+        return Some(match indirection {
+            Indirection::Near16 => 2,
+            Indirection::Far16 | Indirection::Huge16 | Indirection::Near32 => 4,
+            Indirection::Far32 => 6,
+            Indirection::Near64 => 8,
+            Indirection::Near128 => 16,
+        });
+    }
+
+    Some(match data.kind {
+        PrimitiveKind::Char
+        | PrimitiveKind::UChar
+        | PrimitiveKind::RChar
+        | PrimitiveKind::I8
+        | PrimitiveKind::U8
+        | PrimitiveKind::Bool8 => 1,
+        PrimitiveKind::WChar
+        | PrimitiveKind::RChar16
+        | PrimitiveKind::Short
+        | PrimitiveKind::UShort
+        | PrimitiveKind::I16
+        | PrimitiveKind::U16
+        | PrimitiveKind::F16
+        | PrimitiveKind::Bool16 => 2,
+        PrimitiveKind::RChar32
+        | PrimitiveKind::Long
+        | PrimitiveKind::ULong
+        | PrimitiveKind::I32
+        | PrimitiveKind::U32
+        | PrimitiveKind::F32
+        | PrimitiveKind::F32PP
+        | PrimitiveKind::Bool32
+        | PrimitiveKind::HRESULT => 4,
+        PrimitiveKind::F48 => 6,
+        PrimitiveKind::Quad
+        | PrimitiveKind::UQuad
+        | PrimitiveKind::I64
+        | PrimitiveKind::U64
+        | PrimitiveKind::F64
+        | PrimitiveKind::Bool64
+        | PrimitiveKind::Complex32 => 8,
+        PrimitiveKind::F80 => 10,
+        PrimitiveKind::Octa
+        | PrimitiveKind::UOcta
+        | PrimitiveKind::I128
+        | PrimitiveKind::U128
+        | PrimitiveKind::F128
+        | PrimitiveKind::Complex64 => 16,
+        PrimitiveKind::Complex80 => 20,
+        PrimitiveKind::Complex128 => 32,
+        _ => return None,
+    })
+}
+
+/// Converts the value of an enumerator to a signed integer.
+fn variant_value(value: pdb::Variant) -> i64 {
+    match value {
+        pdb::Variant::U8(value) => value.into(),
+        pdb::Variant::U16(value) => value.into(),
+        pdb::Variant::U32(value) => value.into(),
+        pdb::Variant::U64(value) => value as i64,
+        pdb::Variant::I8(value) => value.into(),
+        pdb::Variant::I16(value) => value.into(),
+        pdb::Variant::I32(value) => value.into(),
+        pdb::Variant::I64(value) => value,
+    }
+}
+
+/// Returns the C name of a primitive type.
+fn primitive_name(kind: pdb::PrimitiveKind) -> &'static str {
+    use pdb::PrimitiveKind;
+
+    match kind {
+        PrimitiveKind::NoType => "<no type>",
+        PrimitiveKind::Void => "void",
+        PrimitiveKind::Char => "signed char",
+        PrimitiveKind::UChar => "unsigned char",
+        PrimitiveKind::RChar => "char",
+        PrimitiveKind::WChar => "wchar_t",
+        PrimitiveKind::RChar16 => "char16_t",
+        PrimitiveKind::RChar32 => "char32_t",
+        PrimitiveKind::I8 => "int8_t",
+        PrimitiveKind::U8 => "uint8_t",
+        PrimitiveKind::Short => "short",
+        PrimitiveKind::UShort => "unsigned short",
+        PrimitiveKind::I16 => "int16_t",
+        PrimitiveKind::U16 => "uint16_t",
+        PrimitiveKind::Long => "long",
+        PrimitiveKind::ULong => "unsigned long",
+        PrimitiveKind::I32 => "int",
+        PrimitiveKind::U32 => "unsigned int",
+        PrimitiveKind::Quad => "__int64",
+        PrimitiveKind::UQuad => "unsigned __int64",
+        PrimitiveKind::I64 => "int64_t",
+        PrimitiveKind::U64 => "uint64_t",
+        PrimitiveKind::Octa => "__int128",
+        PrimitiveKind::UOcta => "unsigned __int128",
+        PrimitiveKind::I128 => "int128_t",
+        PrimitiveKind::U128 => "uint128_t",
+        PrimitiveKind::F16 => "_Float16",
+        PrimitiveKind::F32 | PrimitiveKind::F32PP => "float",
+        PrimitiveKind::F48 => "_Float48",
+        PrimitiveKind::F64 => "double",
+        PrimitiveKind::F80 => "long double",
+        PrimitiveKind::F128 => "__float128",
+        PrimitiveKind::Complex32 => "_Complex float",
+        PrimitiveKind::Complex64 => "_Complex double",
+        PrimitiveKind::Complex80 => "_Complex long double",
+        PrimitiveKind::Complex128 => "_Complex __float128",
+        PrimitiveKind::Bool8 => "bool",
+        PrimitiveKind::Bool16 => "__bool16",
+        PrimitiveKind::Bool32 => "__bool32",
+        PrimitiveKind::Bool64 => "__bool64",
+        PrimitiveKind::HRESULT => "HRESULT",
+        _ => "<unknown>",
+    }
+}
+
+/// Checks whether a `CV_LVAR_ADDR_RANGE` and its trailing gaps cover the given address.
+///
+/// The range starts at `offset` within `data`. Gaps follow the range until the end of the record.
+fn def_range_contains(
+    data: &[u8],
+    offset: usize,
+    address_map: &AddressMap<'_>,
+    rva: u32,
+) -> Result<bool, PdbError> {
+    let mut offset = offset;
+    let start = PdbInternalSectionOffset {
+        offset: data.gread_with(&mut offset, scroll::LE)?,
+        section: data.gread_with(&mut offset, scroll::LE)?,
+    };
+    let len: u16 = data.gread_with(&mut offset, scroll::LE)?;
+
+    let start = match start.to_rva(address_map) {
+        Some(start) => start.0,
+        None => return Ok(false),
+    };
+
+    if rva < start || rva - start >= u32::from(len) {
+        return Ok(false);
+    }
+
+    // Gaps are specified relative to the start of the range.
+    let relative = rva - start;
+    while offset + 4 <= data.len() {
+        let gap_start: u16 = data.gread_with(&mut offset, scroll::LE)?;
+        let gap_len: u16 = data.gread_with(&mut offset, scroll::LE)?;
+        if relative >= u32::from(gap_start) && relative - u32::from(gap_start) < u32::from(gap_len)
+        {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// A module of the PDB.
+///
+/// Module streams are borrowed for `'u`, while names and files of functions are borrowed from the
+/// shared debug information for `'d`. This allows reading modules with a separate PDB reader.
+struct Unit<'u, 'd> {
+    shared: &'u PdbSharedInfo<'d>,
+    /// The PDB to read other modules from when resolving cross module references.
+    pdb: &'u RwLock<Pdb<'d>>,
+    address_map: &'u pdb::AddressMap<'d>,
+    module: &'u pdb::ModuleInfo<'d>,
+    imports: pdb::CrossModuleImports<'u>,
 }
 
-impl<'s> Unit<'s> {
+impl<'u, 'd> Unit<'u, 'd> {
     fn load(
-        debug_info: &'s PdbDebugInfo<'s>,
-        module: &'s pdb::ModuleInfo<'s>,
+        shared: &'u PdbSharedInfo<'d>,
+        pdb: &'u RwLock<Pdb<'d>>,
+        address_map: &'u pdb::AddressMap<'d>,
+        module: &'u pdb::ModuleInfo<'d>,
     ) -> Result<Self, PdbError> {
         let imports = module.imports()?;
 
         Ok(Self {
-            debug_info,
+            shared,
+            pdb,
+            address_map,
             module,
             imports,
         })
@@ -948,7 +1893,7 @@ impl<'s> Unit<'s> {
     {
         if index.is_cross_module() {
             let cross_ref = self.imports.resolve_import(index)?;
-            self.debug_info.resolve_import(cross_ref)
+            self.shared.resolve_import(self.pdb, cross_ref)
         } else {
             Ok(Some(index))
         }
@@ -957,13 +1902,13 @@ impl<'s> Unit<'s> {
     fn collect_lines<I>(
         &self,
         mut line_iter: I,
-        program: &LineProgram<'s>,
-    ) -> Result<Vec<LineInfo<'s>>, PdbError>
+        program: &LineProgram<'u>,
+    ) -> Result<Vec<LineInfo<'d>>, PdbError>
     where
         I: FallibleIterator<Item = pdb::LineInfo>,
         PdbError: From<I::Error>,
     {
-        let address_map = &self.debug_info.address_map;
+        let address_map = self.address_map;
 
         let mut lines = Vec::new();
         while let Some(line_info) = line_iter.next()? {
@@ -977,8 +1922,10 @@ impl<'s> Unit<'s> {
             lines.push(LineInfo {
                 address: rva,
                 size: line_info.length.map(u64::from),
-                file: self.debug_info.file_info(file_info)?,
+                file: self.shared.file_info(file_info)?,
                 line: line_info.line_start.into(),
+                // Column records are not read reliably by the `pdb` crate, so they are skipped.
+                column: 0,
             });
         }
 
@@ -987,10 +1934,10 @@ impl<'s> Unit<'s> {
 
     fn handle_procedure(
         &self,
-        proc: ProcedureSymbol<'s>,
-        program: &LineProgram<'s>,
-    ) -> Result<Option<Function<'s>>, PdbError> {
-        let address_map = &self.debug_info.address_map;
+        proc: ProcedureSymbol<'u>,
+        program: &LineProgram<'u>,
+    ) -> Result<Option<Function<'d>>, PdbError> {
+        let address_map = self.address_map;
 
         // Translate the function's address to the PE's address space. If this fails, we're
         // likely dealing with an invalid function and can skip it.
@@ -1003,7 +1950,7 @@ impl<'s> Unit<'s> {
         // scope and name of the function itself, including type parameters, but do not contain
         // parameter lists or return types. This is good enough for us at the moment.
         let name = Name::new(
-            proc.name.to_string(),
+            proc.name.to_string().into_owned(),
             NameMangling::Unmangled,
             Language::Unknown,
         );
@@ -1024,11 +1971,11 @@ impl<'s> Unit<'s> {
 
     fn handle_inlinee(
         &self,
-        inline_site: InlineSiteSymbol<'s>,
+        inline_site: InlineSiteSymbol<'u>,
         parent_offset: PdbInternalSectionOffset,
-        inlinee: &pdb::Inlinee<'s>,
-        program: &LineProgram<'s>,
-    ) -> Result<Option<Function<'s>>, PdbError> {
+        inlinee: &pdb::Inlinee<'u>,
+        program: &LineProgram<'u>,
+    ) -> Result<Option<Function<'d>>, PdbError> {
         let line_iter = inlinee.lines(parent_offset, &inline_site);
         let lines = self.collect_lines(line_iter, program)?;
 
@@ -1067,7 +2014,7 @@ impl<'s> Unit<'s> {
         }))
     }
 
-    fn functions(&self) -> Result<Vec<Function<'s>>, PdbError> {
+    fn functions(&self) -> Result<Vec<Function<'d>>, PdbError> {
         let program = self.module.line_program()?;
         let mut symbols = self.module.symbols()?;
 
@@ -1163,6 +2110,288 @@ impl<'s> Unit<'s> {
 
         Ok(functions)
     }
+
+    /// Formats the name of the type with the given index.
+    fn type_name(&self, index: pdb::TypeIndex) -> Option<String> {
+        TypeFormatter::new(self).format_type(index).ok()
+    }
+
+    /// Resolves the location of a local variable from a `S_DEFRANGE_*` record.
+    ///
+    /// Frame pointer relative locations are resolved relative to `frame_pointer`, the register
+    /// declared by the procedure's `S_FRAMEPROC` record. Returns `None` if the record does not
+    /// describe a location at the given address, and `Unsupported` if the address is covered but
+    /// the location cannot be resolved.
+    fn def_range_location(
+        &self,
+        symbol: &pdb::Symbol<'u>,
+        rva: u32,
+        frame_pointer: Option<u16>,
+    ) -> Result<Option<VariableLocation<'u>>, PdbError> {
+        let address_map = self.address_map;
+
+        // Skip the symbol kind at the start of the record.
+        let data = symbol.raw_bytes().get(2..).unwrap_or_default();
+
+        let frame_relative = |offset: i32| match frame_pointer {
+            Some(register) => VariableLocation::RegisterRelative {
+                register,
+                offset: i64::from(offset),
+            },
+            None => VariableLocation::Unsupported,
+        };
+
+        let (location, range_offset) = match symbol.raw_kind() {
+            // These records start with a DIA program or the offset of a field within the variable,
+            // followed by the range.
+            S_DEFRANGE => (VariableLocation::Unsupported, 4),
+            S_DEFRANGE_SUBFIELD | S_DEFRANGE_SUBFIELD_REGISTER => {
+                (VariableLocation::Unsupported, 8)
+            }
+            S_DEFRANGE_REGISTER => {
+                let register: u16 = data.pread_with(0, scroll::LE)?;
+                (VariableLocation::Register(register), 4)
+            }
+            S_DEFRANGE_FRAMEPOINTER_REL => (frame_relative(data.pread_with(0, scroll::LE)?), 4),
+            S_DEFRANGE_FRAMEPOINTER_REL_FULL_SCOPE => {
+                return Ok(Some(frame_relative(data.pread_with(0, scroll::LE)?)));
+            }
+            S_DEFRANGE_REGISTER_REL => {
+                let register: u16 = data.pread_with(0, scroll::LE)?;
+                let offset: i32 = data.pread_with(4, scroll::LE)?;
+                let offset = i64::from(offset);
+                (VariableLocation::RegisterRelative { register, offset }, 8)
+            }
+            _ => return Ok(None),
+        };
+
+        if def_range_contains(data, range_offset, address_map, rva)? {
+            Ok(Some(location))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Checks whether the code of an inline site covers the given address.
+    fn inline_site_contains(
+        &self,
+        site: &InlineSiteSymbol<'u>,
+        parent_offset: PdbInternalSectionOffset,
+        inlinee: &pdb::Inlinee<'u>,
+        rva: u32,
+    ) -> Result<bool, PdbError> {
+        let address_map = self.address_map;
+
+        let mut lines = inlinee.lines(parent_offset, site);
+        while let Some(line) = lines.next()? {
+            let start = match line.offset.to_rva(address_map) {
+                Some(start) => start.0,
+                None => continue,
+            };
+
+            if rva >= start && rva - start < line.length.unwrap_or(1) {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Reads the flags of the `S_FRAMEPROC` record of the procedure at the given index.
+    ///
+    /// Compilers may emit `S_FRAMEPROC` after the local variables of the procedure, so this reads
+    /// ahead with a separate iterator. The pdb crate does not parse this record, so the flags are
+    /// read from the raw data.
+    fn frame_proc_flags(&self, index: pdb::SymbolIndex) -> Result<Option<u32>, PdbError> {
+        let mut symbols = self.module.symbols_at(index)?;
+
+        // Skip the procedure itself, then look at its direct children.
+        symbols.next()?;
+        let mut depth = 0;
+        while let Some(symbol) = symbols.next()? {
+            if symbol.ends_scope() {
+                if depth == 0 {
+                    break;
+                }
+                depth -= 1;
+            } else if depth == 0 && symbol.raw_kind() == S_FRAMEPROC {
+                let data = symbol.raw_bytes().get(2..).unwrap_or_default();
+                return Ok(Some(data.pread_with(FRAMEPROC_FLAGS_OFFSET, scroll::LE)?));
+            }
+
+            if symbol.starts_scope() {
+                depth += 1;
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Collects all parameters and local variables that are live at the given address.
+    ///
+    /// Returns `None` if no procedure in this module covers the address.
+    fn variables_at(&self, rva: u32) -> Result<Option<Vec<Variable<'u>>>, PdbError> {
+        let address_map = self.address_map;
+        let mut symbols = self.module.symbols()?;
+
+        let inlinees: BTreeMap<_, _> = self
+            .module
+            .inlinees()?
+            .map(|i| Ok((i.index(), i)))
+            .collect()?;
+
+        let mut depth = 0;
+        let mut inc_next = false;
+        let mut skipped_depth = None;
+        let mut function = None;
+        let mut frame_flags = None;
+
+        let mut variables = Vec::new();
+
+        // `S_LOCAL` records are followed by `S_DEFRANGE_*` records that describe where the variable
+        // lives. This holds the local variable until all of its ranges have been inspected.
+        let mut pending: Option<(Variable<'u>, bool)> = None;
+
+        while let Some(symbol) = symbols.next()? {
+            if inc_next {
+                depth += 1;
+            }
+
+            inc_next = symbol.starts_scope();
+            if symbol.ends_scope() {
+                depth -= 1;
+            }
+
+            match skipped_depth {
+                Some(skipped) if depth > skipped => continue,
+                _ => skipped_depth = None,
+            }
+
+            let kind = symbol.raw_kind();
+            if let Some((ref mut variable, ref mut found)) = pending {
+                let is_def_range = matches!(
+                    kind,
+                    S_DEFRANGE
+                        | S_DEFRANGE_SUBFIELD
+                        | S_DEFRANGE_REGISTER
+                        | S_DEFRANGE_FRAMEPOINTER_REL
+                        | S_DEFRANGE_SUBFIELD_REGISTER
+                        | S_DEFRANGE_FRAMEPOINTER_REL_FULL_SCOPE
+                        | S_DEFRANGE_REGISTER_REL
+                );
+
+                if is_def_range {
+                    // Prefer a supported location if multiple ranges cover the address.
+                    if !*found || variable.location == VariableLocation::Unsupported {
+                        let is_param = variable.kind == VariableKind::Parameter;
+                        let frame_pointer = frame_flags.and_then(|flags| {
+                            frame_pointer_register(self.shared.arch, flags, is_param)
+                        });
+
+                        if let Some(location) =
+                            self.def_range_location(&symbol, rva, frame_pointer)?
+                        {
+                            variable.location = location;
+                            *found = true;
+                        }
+                    }
+                    continue;
+                }
+
+                if let Some((variable, true)) = pending.take() {
+                    variables.push(variable);
+                }
+            }
+
+            // Once we leave the procedure containing the address, there is nothing more to find.
+            let parent_offset = match function {
+                Some((function_depth, _)) if depth <= function_depth => break,
+                Some((_, offset)) => Some(offset),
+                None => None,
+            };
+
+            match (symbol.parse(), parent_offset) {
+                (Ok(SymbolData::Procedure(proc)), None) => {
+                    let contains = match proc.offset.to_rva(address_map) {
+                        Some(start) => rva >= start.0 && rva - start.0 < proc.len,
+                        None => false,
+                    };
+
+                    if contains {
+                        function = Some((depth, proc.offset));
+                        frame_flags = self.frame_proc_flags(symbol.index())?;
+                    } else {
+                        skipped_depth = Some(depth);
+                    }
+                }
+                (Ok(SymbolData::Block(block)), Some(_)) => {
+                    let contains = match block.offset.to_rva(address_map) {
+                        Some(start) => rva >= start.0 && rva - start.0 < block.len,
+                        None => false,
+                    };
+
+                    if !contains {
+                        skipped_depth = Some(depth);
+                    }
+                }
+                (Ok(SymbolData::InlineSite(site)), Some(parent_offset)) => {
+                    let contains = match inlinees.get(&site.inlinee) {
+                        Some(inlinee) => {
+                            self.inline_site_contains(&site, parent_offset, inlinee, rva)?
+                        }
+                        None => false,
+                    };
+
+                    if !contains {
+                        skipped_depth = Some(depth);
+                    }
+                }
+                (Ok(SymbolData::Local(local)), Some(_)) => {
+                    let kind = if local.flags.isparam {
+                        VariableKind::Parameter
+                    } else {
+                        VariableKind::Local
+                    };
+
+                    let variable = Variable {
+                        name: local.name.to_string(),
+                        type_name: self.type_name(local.type_index),
+                        kind,
+                        location: VariableLocation::Unsupported,
+                    };
+
+                    pending = Some((variable, false));
+                }
+                (Ok(SymbolData::RegisterRelative(data)), Some(_)) => {
+                    variables.push(Variable {
+                        name: data.name.to_string(),
+                        type_name: self.type_name(data.type_index),
+                        kind: VariableKind::Unknown,
+                        location: VariableLocation::RegisterRelative {
+                            register: data.register.0,
+                            offset: i64::from(data.offset),
+                        },
+                    });
+                }
+                (Ok(SymbolData::RegisterVariable(data)), Some(_)) => {
+                    variables.push(Variable {
+                        name: data.name.to_string(),
+                        type_name: self.type_name(data.type_index),
+                        kind: VariableKind::Unknown,
+                        location: VariableLocation::Register(data.register.0),
+                    });
+                }
+                // Skip symbols the PDB crate cannot parse, see `functions`.
+                _ => continue,
+            }
+        }
+
+        if let Some((variable, true)) = pending {
+            variables.push(variable);
+        }
+
+        Ok(function.map(|_| variables))
+    }
 }
 
 struct PdbUnitIterator<'s> {
@@ -1171,11 +2400,11 @@ struct PdbUnitIterator<'s> {
 }
 
 impl<'s> Iterator for PdbUnitIterator<'s> {
-    type Item = Result<Unit<'s>, PdbError>;
+    type Item = Result<Unit<'s, 's>, PdbError>;
 
     fn next(&mut self) -> Option<Self::Item> {
         let debug_info = self.debug_info;
-        while self.index < debug_info.modules.len() {
+        while self.index < debug_info.shared.modules.len() {
             let result = debug_info.get_module(self.index);
             self.index += 1;
 
@@ -1185,7 +2414,12 @@ impl<'s> Iterator for PdbUnitIterator<'s> {
                 Err(error) => return Some(Err(error)),
             };
 
-            return Some(Unit::load(debug_info, module));
+            return Some(Unit::load(
+                &debug_info.shared,
+                &debug_info.pdb,
+                &debug_info.address_map,
+                module,
+            ));
         }
 
         None
@@ -1212,7 +2446,7 @@ impl<'s> Iterator for PdbFileIterator<'s> {
             if let Some(file_result) = self.files.next().transpose() {
                 let result = file_result
                     .map_err(|err| err.into())
-                    .and_then(|i| self.debug_info.file_info(i))
+                    .and_then(|i| self.debug_info.shared.file_info(i))
                     .map(|info| FileEntry {
                         compilation_dir: &[],
                         info,
@@ -1278,3 +2512,198 @@ impl<'s> Iterator for PdbFunctionIterator<'s> {
 }
 
 impl std::iter::FusedIterator for PdbFunctionIterator<'_> {}
+
+/// A PDB reader owned by a single thread of [`PdbDebugSession::functions_par`].
+///
+/// Streams of the `pdb` crate cannot be shared between threads. Instead, every thread opens the PDB
+/// data again and reads module streams on its own, while sharing [`PdbSharedInfo`].
+#[cfg(feature = "rayon")]
+struct PdbWorker<'d> {
+    pdb: RwLock<Pdb<'d>>,
+    address_map: pdb::AddressMap<'d>,
+}
+
+#[cfg(feature = "rayon")]
+impl<'d> PdbWorker<'d> {
+    fn open(data: &'d [u8]) -> Result<Self, PdbError> {
+        let mut pdb = Pdb::open(Cursor::new(data))?;
+        let address_map = pdb.address_map()?;
+
+        Ok(PdbWorker {
+            pdb: RwLock::new(pdb),
+            address_map,
+        })
+    }
+
+    /// Reads the functions of the module with the given index.
+    fn functions(
+        &self,
+        shared: &PdbSharedInfo<'d>,
+        index: usize,
+    ) -> Result<Vec<Function<'d>>, PdbError> {
+        let module = match shared.modules.get(index) {
+            Some(module) => self.pdb.write().module_info(module)?,
+            None => None,
+        };
+
+        match module {
+            Some(ref module) => {
+                Unit::load(shared, &self.pdb, &self.address_map, module)?.functions()
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// An iterator over functions in a PDB file that reads modules in parallel.
+#[cfg(feature = "rayon")]
+pub struct PdbParFunctionIterator<'s> {
+    debug_info: &'s PdbDebugInfo<'s>,
+    batches: UnitBatches<Function<'s>, PdbError>,
+}
+
+#[cfg(feature = "rayon")]
+impl<'s> Iterator for PdbParFunctionIterator<'s> {
+    type Item = Result<Function<'s>, PdbError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        use rayon::prelude::*;
+
+        let data = self.debug_info.data;
+        let shared = &self.debug_info.shared;
+
+        self.batches.next(|modules| {
+            modules
+                .into_par_iter()
+                .with_min_len(UNITS_PER_THREAD)
+                .map_init(
+                    || PdbWorker::open(data),
+                    |worker, index| match worker {
+                        Ok(worker) => worker.functions(shared, index),
+                        Err(error) => Err(PdbError::new(error.kind(), error.to_string())),
+                    },
+                )
+                .collect()
+        })
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl std::iter::FusedIterator for PdbParFunctionIterator<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+
+    fn source_header_block(entries: &[(u32, u32, u8)]) -> Vec<u8> {
+        source_header_block_sized(SOURCE_ENTRY_SIZE, entries)
+    }
+
+    fn source_header_block_sized(entry_size: usize, entries: &[(u32, u32, u8)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&SOURCE_HEADER_VERSION.to_le_bytes());
+        data.resize(SOURCE_HEADER_SIZE, 0);
+
+        // hash table size, capacity, and empty present / deleted bit vectors
+        data.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        data.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+
+        for &(file_name, virtual_name, compression) in entries {
+            let mut record = vec![0; entry_size];
+            record[0..4].copy_from_slice(&(entry_size as u32).to_le_bytes());
+            record[16..20].copy_from_slice(&file_name.to_le_bytes());
+            record[24..28].copy_from_slice(&virtual_name.to_le_bytes());
+            record[28] = compression;
+
+            data.extend_from_slice(&file_name.to_le_bytes());
+            data.extend_from_slice(&record);
+        }
+
+        data
+    }
+
+    #[test]
+    fn test_parse_source_header_block() {
+        let data = source_header_block(&[(1, 2, 0), (3, 4, 101)]);
+        let entries = parse_source_header_block(&data).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].file_name, pdb::StringRef(1));
+        assert_eq!(entries[0].virtual_name, pdb::StringRef(2));
+        assert_eq!(entries[0].compression, SOURCE_COMPRESSION_NONE);
+        assert_eq!(entries[1].file_name, pdb::StringRef(3));
+        assert_eq!(entries[1].virtual_name, pdb::StringRef(4));
+        assert_eq!(entries[1].compression, SOURCE_COMPRESSION_DOTNET);
+    }
+
+    #[test]
+    fn test_parse_source_header_block_entry_size() {
+        // Records may be larger than the known layout, so the parser must advance by their size.
+        let data = source_header_block_sized(SOURCE_ENTRY_SIZE + 4, &[(1, 2, 0), (3, 4, 101)]);
+        let entries = parse_source_header_block(&data).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].file_name, pdb::StringRef(3));
+        assert_eq!(entries[1].virtual_name, pdb::StringRef(4));
+        assert_eq!(entries[1].compression, SOURCE_COMPRESSION_DOTNET);
+
+        let data = source_header_block_sized(SOURCE_ENTRY_SIZE - 4, &[(1, 2, 0)]);
+        let error = parse_source_header_block(&data).unwrap_err();
+        assert_eq!(error.kind(), PdbErrorKind::BadObject);
+    }
+
+    #[test]
+    fn test_parse_source_header_block_empty() {
+        let entries = parse_source_header_block(&[]).unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn test_parse_source_header_block_truncated() {
+        let mut data = source_header_block(&[(1, 2, 0)]);
+        data.truncate(data.len() - 1);
+
+        let error = parse_source_header_block(&data).unwrap_err();
+        assert_eq!(error.kind(), PdbErrorKind::BadObject);
+    }
+
+    #[test]
+    fn test_decompress_source() {
+        let contents = b"int main() { return 0; }";
+        let entry = |compression| PdbSourceEntry {
+            file_name: pdb::StringRef(0),
+            virtual_name: pdb::StringRef(0),
+            compression,
+        };
+
+        let plain = entry(SOURCE_COMPRESSION_NONE).decompress(contents).unwrap();
+        assert_eq!(plain.as_deref(), Some(&contents[..]));
+
+        let mut stored = 0u32.to_le_bytes().to_vec();
+        stored.extend_from_slice(contents);
+        let uncompressed = entry(SOURCE_COMPRESSION_DOTNET)
+            .decompress(&stored)
+            .unwrap();
+        assert_eq!(uncompressed.as_deref(), Some(&contents[..]));
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(contents).unwrap();
+        let mut deflated = (contents.len() as u32).to_le_bytes().to_vec();
+        deflated.extend_from_slice(&encoder.finish().unwrap());
+        let inflated = entry(SOURCE_COMPRESSION_DOTNET)
+            .decompress(&deflated)
+            .unwrap();
+        assert_eq!(inflated.as_deref(), Some(&contents[..]));
+
+        let unsupported = entry(1).decompress(contents).unwrap();
+        assert_eq!(unsupported, None);
+    }
+}
@@ -279,6 +279,7 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for PeObject<'data> {
     type Error = PeError;
     type Session = PeDebugSession<'data>;
     type SymbolIterator = PeSymbolIterator<'data, 'object>;
+    type SectionIterator = PeSectionIterator<'data, 'object>;
 
     fn file_format(&self) -> FileFormat {
         self.file_format()
@@ -316,6 +317,16 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for PeObject<'data> {
         self.symbol_map()
     }
 
+    /// Returns an iterator over the sections in the section table.
+    ///
+    /// Use [`PeObject::sections`] to access the raw section headers instead.
+    fn sections(&'object self) -> Self::SectionIterator {
+        PeSectionIterator {
+            sections: self.pe.sections.iter(),
+            _marker: PhantomData,
+        }
+    }
+
     fn has_debug_info(&self) -> bool {
         self.has_debug_info()
     }
@@ -356,6 +367,48 @@ impl<'data, 'object> Iterator for PeSymbolIterator<'data, 'object> {
     }
 }
 
+/// An iterator over sections in the PE file.
+///
+/// Returned by [`ObjectLike::sections`] for [`PeObject`].
+pub struct PeSectionIterator<'data, 'object> {
+    sections: std::slice::Iter<'object, SectionTable>,
+    _marker: PhantomData<&'data ()>,
+}
+
+impl<'data, 'object> Iterator for PeSectionIterator<'data, 'object> {
+    type Item = Section<'data>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for section in &mut self.sections {
+            let name = match section.name() {
+                Ok(name) => name.to_owned(),
+                Err(_) => continue,
+            };
+
+            let file_offset = match section.size_of_raw_data {
+                0 => None,
+                _ => Some(section.pointer_to_raw_data.into()),
+            };
+
+            let size = match section.virtual_size {
+                0 => section.size_of_raw_data,
+                size => size,
+            };
+
+            return Some(Section {
+                name: Cow::Owned(name),
+                segment: None,
+                address: section.virtual_address.into(),
+                file_offset,
+                size: size.into(),
+                compressed: false,
+            });
+        }
+
+        None
+    }
+}
+
 /// Debug session for PE objects.
 ///
 /// Since debug information in PE containers is not supported, this session consists of NoOps and
@@ -85,3 +85,95 @@ impl<'a> FunctionStack<'a> {
         }
     }
 }
+
+/// A thread-safe cell that is initialized lazily on first access.
+///
+/// This mirrors the `try_borrow_with` API of `lazycell::LazyCell`, but may be shared between
+/// threads. If multiple threads initialize the cell concurrently, the first value to be stored
+/// wins and the others are dropped.
+#[cfg(any(feature = "dwarf", feature = "ms"))]
+pub struct SyncLazyCell<T>(std::sync::OnceLock<T>);
+
+#[cfg(any(feature = "dwarf", feature = "ms"))]
+impl<T> SyncLazyCell<T> {
+    /// Creates a new, empty cell.
+    pub fn new() -> Self {
+        SyncLazyCell(std::sync::OnceLock::new())
+    }
+
+    /// Returns a reference to the contents, initializing them with `f` if the cell is empty.
+    ///
+    /// If `f` fails, the cell remains empty and the error is returned.
+    pub fn try_borrow_with<E, F>(&self, f: F) -> Result<&T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        if let Some(value) = self.0.get() {
+            return Ok(value);
+        }
+
+        let value = f()?;
+        Ok(self.0.get_or_init(|| value))
+    }
+}
+
+/// Number of compilation units that each thread loads per batch in [`UnitBatches`].
+#[cfg(all(feature = "rayon", any(feature = "dwarf", feature = "ms")))]
+pub const UNITS_PER_THREAD: usize = 16;
+
+/// Loads compilation units in parallel batches and yields their items in unit order.
+///
+/// Only the items of one batch are held in memory at a time. Each batch contains
+/// [`UNITS_PER_THREAD`] units for every thread of the current rayon pool. Errors are yielded in
+/// place of the items of the failing unit, after which iteration continues with the next unit.
+#[cfg(all(feature = "rayon", any(feature = "dwarf", feature = "ms")))]
+pub struct UnitBatches<T, E> {
+    /// Indexes of the units that have not been loaded yet.
+    units: std::ops::Range<usize>,
+    /// Items of the current batch that have not been yielded yet.
+    items: std::vec::IntoIter<Result<T, E>>,
+}
+
+#[cfg(all(feature = "rayon", any(feature = "dwarf", feature = "ms")))]
+impl<T, E> UnitBatches<T, E> {
+    /// Creates batches for the given number of units.
+    pub fn new(count: usize) -> Self {
+        UnitBatches {
+            units: 0..count,
+            items: Vec::new().into_iter(),
+        }
+    }
+
+    /// Returns the next item, loading the next batch of units once the current one is exhausted.
+    ///
+    /// `load` receives the indexes of the units in the batch and returns their items in order.
+    pub fn next<F>(&mut self, mut load: F) -> Option<Result<T, E>>
+    where
+        F: FnMut(std::ops::Range<usize>) -> Vec<Result<Vec<T>, E>>,
+    {
+        loop {
+            if let Some(item) = self.items.next() {
+                return Some(item);
+            }
+
+            if self.units.is_empty() {
+                return None;
+            }
+
+            let batch_size = rayon::current_num_threads() * UNITS_PER_THREAD;
+            let end = std::cmp::min(self.units.start.saturating_add(batch_size), self.units.end);
+            let batch = self.units.start..end;
+            self.units.start = end;
+
+            let mut items = Vec::new();
+            for result in load(batch) {
+                match result {
+                    Ok(unit_items) => items.extend(unit_items.into_iter().map(Ok)),
+                    Err(error) => items.push(Err(error)),
+                }
+            }
+
+            self.items = items.into_iter();
+        }
+    }
+}
@@ -535,6 +535,7 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for SourceBundle<'data>
     type Error = SourceBundleError;
     type Session = SourceBundleDebugSession<'data>;
     type SymbolIterator = SourceBundleSymbolIterator<'data>;
+    type SectionIterator = SourceBundleSectionIterator<'data>;
 
     fn file_format(&self) -> FileFormat {
         self.file_format()
@@ -572,6 +573,10 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for SourceBundle<'data>
         self.symbols()
     }
 
+    fn sections(&self) -> Self::SectionIterator {
+        std::iter::empty()
+    }
+
     fn has_debug_info(&self) -> bool {
         self.has_debug_info()
     }
@@ -596,6 +601,9 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for SourceBundle<'data>
 /// An iterator yielding symbols from a source bundle.
 pub type SourceBundleSymbolIterator<'data> = std::iter::Empty<Symbol<'data>>;
 
+/// An iterator yielding sections from a source bundle, which has no sections.
+pub type SourceBundleSectionIterator<'data> = std::iter::Empty<Section<'data>>;
+
 /// Debug session for SourceBundle objects.
 pub struct SourceBundleDebugSession<'data> {
     manifest: Arc<SourceBundleManifest>,
@@ -886,6 +894,10 @@ where
     /// Returns `Ok(true)` if any source files were added to the bundle, or `Ok(false)` if no
     /// sources could be resolved. Otherwise, an error is returned if writing the bundle fails.
     ///
+    /// Source contents embedded in the object (see [`DebugSession::source_by_path`]) take
+    /// precedence over files on the local file system. If the embedded sources cannot be read,
+    /// files are read from the local file system instead.
+    ///
     /// This finishes the source bundle and flushes the underlying writer.
     pub fn write_object<'data, 'object, O, E>(
         self,
@@ -945,7 +957,12 @@ where
             {
                 None
             } else {
-                std::fs::read(&filename).ok()
+                // Prefer sources embedded in the debug file over the local file system. A corrupt
+                // embedded source table should not prevent bundling the sources from disk.
+                match session.source_by_path(&filename) {
+                    Ok(Some(source)) => Some(source.into_owned().into_bytes()),
+                    Ok(None) | Err(_) => std::fs::read(&filename).ok(),
+                }
             };
 
             if let Some(source) = source {
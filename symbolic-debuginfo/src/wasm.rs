@@ -29,6 +29,7 @@ pub enum WasmError {
 ///
 /// This can only parse binary wasm file and not wast files.
 pub struct WasmObject<'data> {
+    sections: Vec<Section<'data>>,
     dwarf_sections: Vec<(&'data str, &'data [u8])>,
     funcs: Vec<Symbol<'data>>,
     build_id: Option<&'data [u8]>,
@@ -107,6 +108,13 @@ impl<'data> WasmObject<'data> {
         self.symbols().collect()
     }
 
+    /// Returns an iterator over the custom sections of this module.
+    ///
+    /// Wasm modules have no virtual addresses, so the address of every section is `0`.
+    pub fn sections(&self) -> WasmSectionIterator<'data, '_> {
+        self.sections.iter().cloned()
+    }
+
     /// Determines whether this object contains debug information.
     #[inline]
     pub fn has_debug_info(&self) -> bool {
@@ -191,6 +199,7 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for WasmObject<'data> {
     type Error = DwarfError;
     type Session = DwarfDebugSession<'data>;
     type SymbolIterator = WasmSymbolIterator<'data, 'object>;
+    type SectionIterator = WasmSectionIterator<'data, 'object>;
 
     fn file_format(&self) -> FileFormat {
         self.file_format()
@@ -228,6 +237,10 @@ impl<'data: 'object, 'object> ObjectLike<'data, 'object> for WasmObject<'data> {
         self.symbol_map()
     }
 
+    fn sections(&'object self) -> Self::SectionIterator {
+        self.sections()
+    }
+
     fn has_debug_info(&self) -> bool {
         self.has_debug_info()
     }
@@ -271,6 +284,13 @@ impl<'data> Dwarf<'data> for WasmObject<'data> {
     }
 }
 
+/// An iterator over custom sections in the WASM file.
+///
+/// The sections are read when the module is parsed. Returned by
+/// [`WasmObject::sections`](struct.WasmObject.html#method.sections).
+pub type WasmSectionIterator<'data, 'object> =
+    std::iter::Cloned<std::slice::Iter<'object, Section<'data>>>;
+
 /// An iterator over symbols in the WASM file.
 ///
 /// Returned by [`WasmObject::symbols`](struct.WasmObject.html#method.symbols).
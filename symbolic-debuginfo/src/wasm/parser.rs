@@ -1,7 +1,7 @@
 //! Contains utilities for parsing a WASM module to retrieve the information needed by [`super::WasmObject`]
 
 use super::WasmError;
-use crate::base::{ObjectKind, Symbol};
+use crate::base::{ObjectKind, Section, Symbol};
 use wasmparser::{ImportSectionEntryType, Payload, Validator, WasmFeatures};
 
 impl<'data> super::WasmObject<'data> {
@@ -9,6 +9,7 @@ impl<'data> super::WasmObject<'data> {
     pub fn parse(data: &'data [u8]) -> Result<Self, WasmError> {
         let mut code_offset = 0;
         let mut build_id = None;
+        let mut sections = Vec::new();
         let mut dwarf_sections = Vec::new();
         let mut kind = ObjectKind::Debug;
 
@@ -143,6 +144,15 @@ impl<'data> super::WasmObject<'data> {
                     data_offset,
                     ..
                 } => {
+                    sections.push(Section {
+                        name: std::borrow::Cow::Borrowed(name),
+                        segment: None,
+                        address: 0,
+                        file_offset: Some(data_offset as u64),
+                        size: data.len() as u64,
+                        compressed: false,
+                    });
+
                     match name {
                         // this section is not defined yet
                         // see https://github.com/WebAssembly/tool-conventions/issues/133
@@ -219,6 +229,7 @@ impl<'data> super::WasmObject<'data> {
         }
 
         Ok(Self {
+            sections,
             dwarf_sections,
             funcs,
             build_id,
@@ -1,7 +1,11 @@
-use std::{ffi::CString, fmt};
+use std::{ffi::CString, fmt, io::Cursor};
 
 use symbolic_common::ByteView;
-use symbolic_debuginfo::{elf::ElfObject, FileEntry, Function, Object, SymbolMap};
+use symbolic_debuginfo::{
+    elf::ElfObject,
+    sourcebundle::{SourceBundle, SourceBundleWriter},
+    EnumValue, FileEntry, Function, Object, SymbolMap, TypeKind, VariableKind, VariableLocation,
+};
 use symbolic_testutils::fixture;
 
 use similar_asserts::assert_eq;
@@ -257,6 +261,110 @@ fn check_debug_info(filename: &'static str, debug_info: &'static str) -> Result<
     Ok(())
 }
 
+#[cfg(feature = "rayon")]
+#[test]
+fn test_elf_functions_par() -> Result<(), Error> {
+    let view = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&view)?;
+
+    let session = object.debug_session()?;
+    let functions = session.functions().collect::<Result<Vec<_>, _>>()?;
+    let functions_par = session.functions_par().collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(format!("{:?}", functions), format!("{:?}", functions_par));
+
+    Ok(())
+}
+
+#[test]
+fn test_elf_variables() -> Result<(), Error> {
+    let view = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&view)?;
+
+    let session = object.debug_session()?;
+    let variables = session.variables_at(0x1d16)?;
+
+    let argc = variables.iter().find(|v| v.name == "argc").expect("argc");
+    assert_eq!(argc.type_name.as_deref(), Some("int"));
+    assert_eq!(argc.kind, VariableKind::Parameter);
+    assert_eq!(argc.location, VariableLocation::Register(5));
+
+    let descriptor = variables.iter().find(|v| v.name == "descriptor");
+    let descriptor = descriptor.expect("descriptor");
+    assert_eq!(descriptor.type_name.as_deref(), Some("MinidumpDescriptor"));
+    assert_eq!(descriptor.kind, VariableKind::Local);
+    assert_eq!(
+        descriptor.location,
+        VariableLocation::FrameBaseRelative {
+            offset: -416,
+            // DW_OP_call_frame_cfa
+            frame_base: &[0x9c],
+        }
+    );
+
+    assert!(session.variables_at(0)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_elf_types() -> Result<(), Error> {
+    let view = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&view)?;
+
+    let session = object.debug_session()?;
+    let file = session.type_by_name("_IO_FILE")?.expect("_IO_FILE");
+    assert_eq!(file.kind, TypeKind::Struct);
+    assert_eq!(file.size, Some(216));
+    assert_eq!(file.members[0].name, "_flags");
+    assert_eq!(file.members[0].type_name.as_deref(), Some("int"));
+
+    let shortbuf = file.members.iter().find(|m| m.name == "_shortbuf");
+    let shortbuf = shortbuf.expect("_shortbuf");
+    assert_eq!(shortbuf.offset, 131);
+    assert_eq!(shortbuf.type_name.as_deref(), Some("char[1]"));
+    assert_eq!(shortbuf.array_bounds, vec![1]);
+
+    let policy = session.type_by_name("__gnu_cxx::_Lock_policy")?;
+    let policy = policy.expect("_Lock_policy");
+    assert_eq!(policy.kind, TypeKind::Enum);
+    assert_eq!(
+        policy.enum_values[2],
+        EnumValue {
+            name: "_S_atomic".into(),
+            value: 2
+        }
+    );
+
+    let types = session.types()?;
+    let listed = types.iter().find(|t| t.name == "_IO_FILE").expect("_IO_FILE");
+    assert_eq!(format!("{:?}", listed), format!("{:?}", file));
+    assert!(session.type_by_name("does_not_exist")?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_elf_sections() -> Result<(), Error> {
+    let view = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&view)?;
+
+    let sections = object.sections().collect::<Vec<_>>();
+    assert_eq!(sections.len(), 39);
+
+    // Code was stripped from the debug companion, so there is no file data.
+    let text = sections.iter().find(|s| s.name() == ".text").unwrap();
+    assert_eq!(text.address, 0x1900);
+    assert_eq!(text.file_offset, None);
+    assert_eq!(text.size, 0x133a2);
+
+    let debug_info = sections.iter().find(|s| s.name() == ".debug_info").unwrap();
+    assert_eq!(debug_info.address, 0);
+    assert_eq!(debug_info.file_offset, Some(0x7bc));
+    assert!(!debug_info.compressed);
+
+    Ok(())
+}
+
 #[test]
 fn test_elf_debug_link() -> Result<(), Error> {
     check_debug_info("elf_with_debuglink", "debug_info.txt")
@@ -383,6 +491,47 @@ fn test_mach_functions() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn test_mach_function_columns() -> Result<(), Error> {
+    let view = ByteView::open(fixture("macos/crash.dSYM/Contents/Resources/DWARF/crash"))?;
+    let object = Object::parse(&view)?;
+
+    let session = object.debug_session()?;
+    let main = session
+        .functions()
+        .filter_map(Result::ok)
+        .find(|f| f.name.as_str() == "main")
+        .expect("main function");
+
+    let line = main.lines.iter().find(|l| l.address == 0xdbe4).unwrap();
+    assert_eq!((line.line, line.column), (31, 39));
+
+    // rows without a column are reported as column 0
+    assert_eq!(main.lines[0].column, 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_mach_sections() -> Result<(), Error> {
+    let view = ByteView::open(fixture("macos/crash"))?;
+    let object = Object::parse(&view)?;
+
+    let sections = object.sections().collect::<Vec<_>>();
+    assert_eq!(sections.len(), 17);
+
+    let text = &sections[0];
+    assert_eq!((text.segment(), text.name()), (Some("__TEXT"), "__text"));
+    assert_eq!(text.address, 0xd20);
+    assert_eq!(text.file_offset, Some(0xd20));
+
+    let bss = sections.iter().find(|s| s.name() == "__bss").unwrap();
+    assert_eq!(bss.segment(), Some("__DATA"));
+    assert_eq!(bss.file_offset, None);
+
+    Ok(())
+}
+
 #[test]
 fn test_pe_32() -> Result<(), Error> {
     let view = ByteView::open(fixture("windows/crash.exe"))?;
@@ -415,6 +564,22 @@ fn test_pe_32() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn test_pe_sections() -> Result<(), Error> {
+    let view = ByteView::open(fixture("windows/crash.exe"))?;
+    let object = Object::parse(&view)?;
+
+    let names = object.sections().map(|s| s.name).collect::<Vec<_>>();
+    assert_eq!(names, [".text", ".rdata", ".data", ".rsrc", ".reloc"]);
+
+    let text = object.sections().next().unwrap();
+    assert_eq!(text.address, 0x1000);
+    assert_eq!(text.file_offset, Some(0x400));
+    assert_eq!(text.size, 0x291b);
+
+    Ok(())
+}
+
 #[test]
 fn test_pe_64() -> Result<(), Error> {
     let view = ByteView::open(fixture("windows/CrashWithException.exe"))?;
@@ -511,6 +676,190 @@ fn test_pdb_functions() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn test_pdb_no_embedded_sources() -> Result<(), Error> {
+    let view = ByteView::open(fixture("windows/crash.pdb"))?;
+    let object = Object::parse(&view)?;
+    assert!(!object.has_sources());
+
+    let session = object.debug_session()?;
+    let file = session.files().next().expect("pdb has files")?;
+    assert_eq!(session.source_by_path(&file.abs_path_str())?, None);
+
+    Ok(())
+}
+
+/// Paths and contents of the sources in `windows/embedded_sources/CrashWithException.pdb`.
+///
+/// The first file is stored without compression, the second one deflated.
+const PDB_EMBEDDED_SOURCES: [(&str, &str); 2] = [
+    (
+        r"c:\users\sentry\source\repos\crashwithexception\crashwithexception\crashwithexception.cpp",
+        "#include \"pch.h\"\r\n\r\nint main()\r\n{\r\n    throw std::runtime_error(\"crash\");\r\n}\r\n",
+    ),
+    (
+        r"c:\users\sentry\source\repos\crashwithexception\crashwithexception\pch.h",
+        "#ifndef PCH_H\r\n#define PCH_H\r\n\r\n#include <stdexcept>\r\n\r\n#endif // PCH_H\r\n",
+    ),
+];
+
+#[test]
+fn test_pdb_embedded_sources() -> Result<(), Error> {
+    let view = ByteView::open(fixture("windows/embedded_sources/CrashWithException.pdb"))?;
+    let object = Object::parse(&view)?;
+    assert!(object.has_sources());
+
+    let session = object.debug_session()?;
+    for &(path, contents) in PDB_EMBEDDED_SOURCES.iter() {
+        assert_eq!(session.source_by_path(path)?.as_deref(), Some(contents));
+        let upper = path.to_uppercase();
+        assert_eq!(session.source_by_path(&upper)?.as_deref(), Some(contents));
+    }
+
+    let other = r"c:\users\sentry\source\repos\crashwithexception\crashwithexception\other.h";
+    assert_eq!(session.source_by_path(other)?, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_pdb_embedded_sources_bundle() -> Result<(), Error> {
+    let view = ByteView::open(fixture("windows/embedded_sources/CrashWithException.pdb"))?;
+    let object = Object::parse(&view)?;
+
+    let mut output = Cursor::new(Vec::new());
+    let writer = SourceBundleWriter::start(&mut output)?;
+    assert!(writer.write_object(&object, "CrashWithException.exe")?);
+
+    let output = output.into_inner();
+    let bundle = SourceBundle::parse(&output)?;
+    let session = bundle.debug_session()?;
+
+    // The sources of all other files are neither embedded nor available on disk.
+    let mut files = Vec::new();
+    for file in session.files() {
+        let path = file?.abs_path_str();
+        if let Some(contents) = session.source_by_path(&path)? {
+            files.push((path, contents.into_owned()));
+        }
+    }
+
+    let expected = PDB_EMBEDDED_SOURCES
+        .iter()
+        .map(|&(path, contents)| (path.to_owned(), contents.to_owned()))
+        .collect::<Vec<_>>();
+    assert_eq!(files, expected);
+
+    Ok(())
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_pdb_functions_par() -> Result<(), Error> {
+    let view = ByteView::open(fixture("windows/crash.pdb"))?;
+    let object = Object::parse(&view)?;
+
+    let session = object.debug_session()?;
+    let functions = session.functions().collect::<Result<Vec<_>, _>>()?;
+    let functions_par = session.functions_par().collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(format!("{:?}", functions), format!("{:?}", functions_par));
+
+    Ok(())
+}
+
+#[test]
+fn test_pdb_variables() -> Result<(), Error> {
+    let view = ByteView::open(fixture("windows/crash.pdb"))?;
+    let object = Object::parse(&view)?;
+
+    let session = object.debug_session()?;
+    let variables = session.variables_at(0x1cce)?;
+
+    let exinfo = variables
+        .iter()
+        .find(|v| v.name == "exinfo")
+        .expect("exinfo");
+    assert_eq!(exinfo.type_name.as_deref(), Some("_EXCEPTION_POINTERS*"));
+    assert_eq!(exinfo.kind, VariableKind::Parameter);
+
+    let code = variables.iter().find(|v| v.name == "code").expect("code");
+    assert_eq!(code.type_name.as_deref(), Some("unsigned long"));
+    assert_eq!(code.kind, VariableKind::Local);
+
+    assert!(session.variables_at(0)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_pdb_variable_locations() -> Result<(), Error> {
+    let view = ByteView::open(fixture("windows/crash.pdb"))?;
+    let object = Object::parse(&view)?;
+
+    // In `ExceptionHandler::WriteMinidumpWithExceptionForProcess`, `S_FRAMEPROC` declares EBP (22)
+    // as the frame pointer for parameters, and VFRAME (30006) for locals.
+    let session = object.debug_session()?;
+    let variables = session.variables_at(0x2326)?;
+    let location = |name: &str| {
+        variables
+            .iter()
+            .find(|v| v.name == name)
+            .map(|v| v.location)
+            .expect(name)
+    };
+
+    assert_eq!(
+        location("requesting_thread_id"),
+        VariableLocation::RegisterRelative {
+            register: 22,
+            offset: 8
+        }
+    );
+    assert_eq!(
+        location("success"),
+        VariableLocation::RegisterRelative {
+            register: 30006,
+            offset: -129
+        }
+    );
+
+    // The 64-bit value is split into two registers on x86, which is described by subfield ranges.
+    assert_eq!(location("instruction_pointer"), VariableLocation::Unsupported);
+
+    Ok(())
+}
+
+#[test]
+fn test_pdb_types() -> Result<(), Error> {
+    let view = ByteView::open(fixture("windows/crash.pdb"))?;
+    let object = Object::parse(&view)?;
+
+    let session = object.debug_session()?;
+    let guid = session.type_by_name("_GUID")?.expect("_GUID");
+    assert_eq!(guid.kind, TypeKind::Struct);
+    assert_eq!(guid.size, Some(16));
+
+    let data4 = guid
+        .members
+        .iter()
+        .find(|m| m.name == "Data4")
+        .expect("Data4");
+    assert_eq!(data4.offset, 8);
+    assert_eq!(data4.type_name.as_deref(), Some("unsigned char[8]"));
+    assert_eq!(data4.array_bounds, vec![8]);
+
+    let handler = session.type_by_name("google_breakpad::ExceptionHandler")?;
+    let handler = handler.expect("ExceptionHandler");
+    assert_eq!(handler.kind, TypeKind::Class);
+    assert_eq!(handler.size, Some(200));
+
+    let types = session.types()?;
+    let listed = types.iter().find(|t| t.name == "_GUID").expect("_GUID");
+    assert_eq!(format!("{:?}", listed), format!("{:?}", guid));
+
+    Ok(())
+}
+
 #[test]
 fn test_pdb_anonymous_namespace() -> Result<(), Error> {
     // Regression test for ?A0x<hash> namespaces
@@ -557,6 +906,19 @@ fn test_wasm_symbols() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn test_wasm_sections() -> Result<(), Error> {
+    let view = ByteView::open(fixture("wasm/simple.wasm"))?;
+    let object = Object::parse(&view)?;
+
+    let build_id = object.sections().find(|s| s.name() == "build_id").unwrap();
+    assert_eq!(build_id.address, 0);
+    assert_eq!(build_id.file_offset, Some(2844));
+    assert_eq!(build_id.size, 16);
+
+    Ok(())
+}
+
 #[test]
 fn test_wasm_line_program() -> Result<(), Error> {
     let view = ByteView::open(fixture("wasm/simple.wasm"))?;
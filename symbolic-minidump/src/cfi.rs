@@ -1,7 +1,9 @@
 //! Handling of Call Frame Information (stack frame info).
 //!
 //! The root type exposed by this crate is [`CfiCache`], which offers a high-level API to extract
-//! CFI from object files and serialize a format that the Breakpad processor can understand.
+//! CFI from object files and serialize a format that the Breakpad processor can understand. To
+//! look up unwind information from Rust, [`BinaryCfiCache`] stores the same information in an
+//! indexed binary format.
 //!
 //! # Background
 //!
@@ -16,9 +18,10 @@
 //!
 //! [processor]: ../processor/index.html
 //! [`CfiCache`]: struct.CfiCache.html
+//! [`BinaryCfiCache`]: struct.BinaryCfiCache.html
 
 use std::collections::HashMap;
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 use std::error::Error;
 use std::fmt;
 use std::io::{self, Write};
@@ -27,7 +30,10 @@ use std::ops::Range;
 use thiserror::Error;
 
 use symbolic_common::{Arch, ByteView, CpuFamily, UnknownArchError};
-use symbolic_debuginfo::breakpad::{BreakpadError, BreakpadObject, BreakpadStackRecord};
+use symbolic_debuginfo::breakpad::{
+    BreakpadError, BreakpadObject, BreakpadStackRecord, BreakpadStackRecords,
+    BreakpadStackWinRecord, BreakpadStackWinRecordType,
+};
 use symbolic_debuginfo::dwarf::gimli::{
     BaseAddresses, CfaRule, CieOrFde, DebugFrame, EhFrame, Error as GimliError,
     FrameDescriptionEntry, Reader, ReaderOffset, Register, RegisterRule, UnwindContext,
@@ -42,6 +48,10 @@ use symbolic_debuginfo::pdb::PdbObject;
 use symbolic_debuginfo::pe::{PeObject, RuntimeFunction, StackFrameOffset, UnwindOperation};
 use symbolic_debuginfo::{Object, ObjectError, ObjectLike};
 
+mod binary;
+
+pub use binary::*;
+
 /// The magic file preamble to identify cficache files.
 ///
 /// Files with version < 2 do not have the full preamble with magic+version, but rather start
@@ -93,6 +103,12 @@ pub enum CfiErrorKind {
 
     /// Invalid magic bytes in the cfi cache header.
     BadFileMagic,
+
+    /// The cfi cache file format version is not supported.
+    UnsupportedVersion,
+
+    /// The cfi cache is truncated or its header does not match its size.
+    BadFileLength,
 }
 
 impl fmt::Display for CfiErrorKind {
@@ -105,6 +121,8 @@ impl fmt::Display for CfiErrorKind {
             Self::InvalidAddress => write!(f, "invalid cfi address"),
             Self::WriteFailed => write!(f, "failed to write cfi"),
             Self::BadFileMagic => write!(f, "bad cfi cache magic"),
+            Self::UnsupportedVersion => write!(f, "unsupported cfi cache version"),
+            Self::BadFileLength => write!(f, "bad cfi cache length"),
         }
     }
 }
@@ -1162,6 +1180,76 @@ impl<'a> CfiCache<'a> {
         }
     }
 
+    /// Looks up the unwind information for the given address, relative to the module's load
+    /// address.
+    ///
+    /// Like the Breakpad processor, this prefers `STACK WIN` records of type `FrameData` over
+    /// those of type `FPO`, and both over `STACK CFI` records. This returns the same results as
+    /// [`BinaryCfiCache::lookup`], but parses the CFI on every call. To look up many addresses,
+    /// convert the cache with [`BinaryCfiCache::from_cfi_cache`] first.
+    pub fn lookup(&self, address: u64) -> Result<Option<CfiLookupResult<'_>>, CfiError> {
+        let mut cfi = None;
+        let mut cfi_ranges = CfiRanges::default();
+        let mut frame_data: Option<BreakpadStackWinRecord<'_>> = None;
+        let mut fpo = None;
+
+        for record in BreakpadStackRecords::new(self.as_slice()) {
+            match record? {
+                BreakpadStackRecord::Cfi(record) => {
+                    if cfi_ranges.insert(record.range()) && record.range().contains(&address) {
+                        cfi = Some(record);
+                    }
+                }
+                BreakpadStackRecord::Win(record) => {
+                    let contained = matches!(
+                        u32::try_from(address),
+                        Ok(address) if record.code_range().contains(&address)
+                    );
+                    let innermost = match record.ty {
+                        BreakpadStackWinRecordType::FrameData => &mut frame_data,
+                        BreakpadStackWinRecordType::Fpo => &mut fpo,
+                        _ => continue,
+                    };
+
+                    // Of nested records, the one with the greatest start address wins. For equal
+                    // start addresses, the binary format prefers the one that comes last.
+                    let is_inner = match innermost {
+                        Some(current) => record.code_start >= current.code_start,
+                        None => true,
+                    };
+                    if contained && is_inner {
+                        *innermost = Some(record);
+                    }
+                }
+            }
+        }
+
+        if let Some(record) = frame_data.or(fpo) {
+            return Ok(Some(CfiLookupResult::Win(record)));
+        }
+
+        let record = match cfi {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+
+        let mut deltas = Vec::new();
+        for delta in record.deltas() {
+            let delta = delta?;
+            if record.start <= delta.address && delta.address <= address {
+                deltas.push(delta);
+            }
+        }
+        deltas.sort_by_key(|delta| delta.address);
+
+        Ok(Some(CfiLookupResult::Cfi(CfiRules {
+            start: record.start,
+            size: record.size,
+            init_rules: record.init_rules,
+            delta_rules: deltas.into_iter().map(|delta| delta.rules).collect(),
+        })))
+    }
+
     /// Writes the cache to the given writer.
     pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), io::Error> {
         if let CfiCacheInner::Versioned(version, _) = self.inner {
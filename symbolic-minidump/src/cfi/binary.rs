@@ -0,0 +1,750 @@
+//! A binary, indexed format for call frame information.
+//!
+//! [`CfiCache`] stores CFI as Breakpad ASCII text, which needs to be parsed entirely before the
+//! rules for a single address can be found. A [`BinaryCfiCache`] instead stores sorted records
+//! with fixed sizes and deduplicated rule strings, so that the unwind rules for an address are
+//! found with a binary search directly on the (memory mapped) file.
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::{TryFrom, TryInto};
+use std::io::{self, Write};
+use std::ops::Range;
+use std::str;
+
+use symbolic_common::ByteView;
+use symbolic_debuginfo::breakpad::{
+    BreakpadStackRecord, BreakpadStackRecords, BreakpadStackWinRecord, BreakpadStackWinRecordType,
+};
+use symbolic_debuginfo::Object;
+
+use super::{AsciiCfiWriter, CfiCache, CfiError, CfiErrorKind};
+
+/// The magic file preamble to identify binary cficache files.
+///
+/// The magic is a `u32` corresponding to the big-endian `CFIB`. Like [`CFICACHE_MAGIC`], it is
+/// written in native endianness, so mismatches between writer and reader result in a
+/// [`CfiErrorKind::BadFileMagic`] error.
+///
+/// [`CFICACHE_MAGIC`]: super::CFICACHE_MAGIC
+pub const BINARY_CFICACHE_MAGIC: u32 = u32::from_be_bytes(*b"CFIB");
+
+/// The latest version of the binary file format.
+pub const BINARY_CFICACHE_LATEST_VERSION: u32 = 3;
+
+// The file starts with the same 8 byte preamble as versioned cficaches, followed by a header and
+// the sections below. All integers are stored in native endianness:
+//
+// - header: the number of CFI records, CFI deltas and WIN records, and the size of the strings,
+//   each as `u32`.
+// - CFI records: one per `STACK CFI INIT` record, sorted by start address. Records overlapping a
+//   record earlier in the input are dropped, like the Breakpad processor does.
+// - CFI deltas: one per `STACK CFI` record, grouped by their INIT record and sorted by address.
+// - WIN records: `STACK WIN` records of type `FrameData`, followed by those of type `FPO`, each
+//   sorted by start address. Breakpad does not use any other types. Records of the same type may
+//   be nested, so each record also stores the index of its parent: the closest previous record of
+//   its type that covers its start address. Like Breakpad's `ContainedRangeMap`, the innermost
+//   record for an address is found by following the parents of the last record starting at or
+//   before the address.
+// - strings: the rules and program strings, each prefixed with its `u32` length in bytes.
+//
+// Version history:
+//
+// 1: Initial binary implementation
+// 2: WIN records store the largest end address of all previous records of their type (`max_end`)
+//    to find the innermost of nested records
+// 3: WIN records store the index of their enclosing record (`parent`) instead of `max_end`, so
+//    that finding the innermost record does not walk back over sibling records
+
+/// Size of the preamble and the header.
+const HEADER_SIZE: usize = 8 + 4 * 4;
+
+/// Sentinel string offset for a missing string.
+const NO_STRING: u32 = u32::MAX;
+
+/// Sentinel record index for a WIN record without a parent.
+const NO_PARENT: u32 = u32::MAX;
+
+/// A `STACK CFI INIT` record along with the range of its deltas.
+#[derive(Clone, Debug)]
+struct RawCfiRecord {
+    start: u64,
+    size: u64,
+    rules: u32,
+    first_delta: u32,
+    num_deltas: u32,
+}
+
+impl RawCfiRecord {
+    const SIZE: usize = 32;
+
+    fn read(bytes: &[u8]) -> Self {
+        Self {
+            start: read_u64(bytes, 0),
+            size: read_u64(bytes, 8),
+            rules: read_u32(bytes, 16),
+            first_delta: read_u32(bytes, 20),
+            num_deltas: read_u32(bytes, 24),
+        }
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.start.to_ne_bytes());
+        buffer.extend_from_slice(&self.size.to_ne_bytes());
+        buffer.extend_from_slice(&self.rules.to_ne_bytes());
+        buffer.extend_from_slice(&self.first_delta.to_ne_bytes());
+        buffer.extend_from_slice(&self.num_deltas.to_ne_bytes());
+        buffer.extend_from_slice(&0u32.to_ne_bytes());
+    }
+
+    fn range(&self) -> Range<u64> {
+        self.start..self.start.saturating_add(self.size)
+    }
+}
+
+/// A `STACK CFI` record.
+#[derive(Clone, Debug)]
+struct RawCfiDelta {
+    address: u64,
+    rules: u32,
+}
+
+impl RawCfiDelta {
+    const SIZE: usize = 16;
+
+    fn read(bytes: &[u8]) -> Self {
+        Self {
+            address: read_u64(bytes, 0),
+            rules: read_u32(bytes, 8),
+        }
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.address.to_ne_bytes());
+        buffer.extend_from_slice(&self.rules.to_ne_bytes());
+        buffer.extend_from_slice(&0u32.to_ne_bytes());
+    }
+}
+
+/// A `STACK WIN` record.
+#[derive(Clone, Debug)]
+struct RawWinRecord {
+    ty: u32,
+    code_start: u32,
+    code_size: u32,
+    prolog_size: u32,
+    epilog_size: u32,
+    params_size: u32,
+    saved_regs_size: u32,
+    locals_size: u32,
+    max_stack_size: u32,
+    uses_base_pointer: u32,
+    program_string: u32,
+    /// The index of the closest previous record of the same type covering `code_start`, or
+    /// [`NO_PARENT`].
+    parent: u32,
+}
+
+impl RawWinRecord {
+    const SIZE: usize = 48;
+
+    fn read(bytes: &[u8]) -> Self {
+        Self {
+            ty: read_u32(bytes, 0),
+            code_start: read_u32(bytes, 4),
+            code_size: read_u32(bytes, 8),
+            prolog_size: read_u32(bytes, 12),
+            epilog_size: read_u32(bytes, 16),
+            params_size: read_u32(bytes, 20),
+            saved_regs_size: read_u32(bytes, 24),
+            locals_size: read_u32(bytes, 28),
+            max_stack_size: read_u32(bytes, 32),
+            uses_base_pointer: read_u32(bytes, 36),
+            program_string: read_u32(bytes, 40),
+            parent: read_u32(bytes, 44),
+        }
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        for value in [
+            self.ty,
+            self.code_start,
+            self.code_size,
+            self.prolog_size,
+            self.epilog_size,
+            self.params_size,
+            self.saved_regs_size,
+            self.locals_size,
+            self.max_stack_size,
+            self.uses_base_pointer,
+            self.program_string,
+            self.parent,
+        ] {
+            buffer.extend_from_slice(&value.to_ne_bytes());
+        }
+    }
+
+    /// Returns the key by which WIN records are sorted.
+    fn sort_key(&self) -> (u32, u32) {
+        (win_type_rank(self.ty), self.code_start)
+    }
+
+    fn end(&self) -> u32 {
+        self.code_start.saturating_add(self.code_size)
+    }
+
+    fn contains(&self, address: u64) -> bool {
+        let start = u64::from(self.code_start);
+        start <= address && address < start + u64::from(self.code_size)
+    }
+}
+
+/// Returns the order in which `STACK WIN` records of the given type are searched.
+fn win_type_rank(ty: u32) -> u32 {
+    if ty == BreakpadStackWinRecordType::FrameData as u32 {
+        0
+    } else {
+        1
+    }
+}
+
+/// The ranges of accepted `STACK CFI INIT` records.
+///
+/// Like the Breakpad processor, a record is rejected if it is empty or overlaps a record that was
+/// accepted before, so the result depends on the order of the records in the file.
+#[derive(Debug, Default)]
+pub(super) struct CfiRanges(BTreeMap<u64, u64>);
+
+impl CfiRanges {
+    /// Accepts the range unless it is empty or overlaps a previously accepted range.
+    pub(super) fn insert(&mut self, range: Range<u64>) -> bool {
+        if range.is_empty() {
+            return false;
+        }
+
+        let overlaps_previous = matches!(
+            self.0.range(..=range.start).next_back(),
+            Some((_, &end)) if end > range.start
+        );
+        let overlaps_next = matches!(
+            self.0.range(range.start..).next(),
+            Some((&start, _)) if start < range.end
+        );
+        if overlaps_previous || overlaps_next {
+            return false;
+        }
+
+        self.0.insert(range.start, range.end);
+        true
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_ne_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+/// The `STACK CFI` rules in effect at an address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CfiRules<'a> {
+    /// The start address of the `STACK CFI INIT` record covering the address.
+    pub start: u64,
+
+    /// The number of bytes covered by the `STACK CFI INIT` record.
+    pub size: u64,
+
+    /// The rules of the `STACK CFI INIT` record.
+    pub init_rules: &'a str,
+
+    /// The rules of all `STACK CFI` records from the start up to the address, in ascending order
+    /// of their addresses.
+    pub delta_rules: Vec<&'a str>,
+}
+
+impl<'a> CfiRules<'a> {
+    /// Returns all rule strings in the order in which they apply.
+    ///
+    /// Rules for a register override all previous rules for the same register.
+    pub fn rules(&self) -> impl Iterator<Item = &'a str> + '_ {
+        std::iter::once(self.init_rules).chain(self.delta_rules.iter().copied())
+    }
+
+    /// Returns the effective rules at the address as a single rules string.
+    ///
+    /// Every register appears once, with the rule of the last record that defines it. The result
+    /// can be passed to `Evaluator::add_cfi_rules_string` in `symbolic-unwind` to compute the
+    /// registers of the caller's frame.
+    pub fn effective_rules(&self) -> String {
+        let mut registers: Vec<(&str, Vec<&str>)> = Vec::new();
+        for rules in self.rules() {
+            let mut current = None;
+            for token in rules.split_whitespace() {
+                if token.ends_with(':') {
+                    let index = match registers.iter().position(|(reg, _)| *reg == token) {
+                        Some(index) => {
+                            registers[index].1.clear();
+                            index
+                        }
+                        None => {
+                            registers.push((token, Vec::new()));
+                            registers.len() - 1
+                        }
+                    };
+                    current = Some(index);
+                } else if let Some(index) = current {
+                    registers[index].1.push(token);
+                }
+            }
+        }
+
+        let mut effective = String::new();
+        for (register, expr) in registers {
+            if !effective.is_empty() {
+                effective.push(' ');
+            }
+            effective.push_str(register);
+            for token in expr {
+                effective.push(' ');
+                effective.push_str(token);
+            }
+        }
+        effective
+    }
+}
+
+/// Unwind information for an address, found in a [`BinaryCfiCache`] or [`CfiCache`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CfiLookupResult<'a> {
+    /// The rules of `STACK CFI INIT` and `STACK CFI` records, used for all platforms other than
+    /// Windows x86.
+    Cfi(CfiRules<'a>),
+
+    /// A `STACK WIN` record, used for Windows x86.
+    Win(BreakpadStackWinRecord<'a>),
+}
+
+/// A cache file for call frame information (CFI) in an indexed binary format.
+///
+/// Unlike [`CfiCache`], which contains Breakpad ASCII text for the minidump processor, this
+/// allows to look up the unwind information of a single address without parsing the entire file.
+/// It is usually converted from a `CfiCache` or created directly from an `Object`.
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use symbolic_common::ByteView;
+/// use symbolic_debuginfo::Object;
+/// use symbolic_minidump::cfi::BinaryCfiCache;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let view = ByteView::open("/path/to/object")?;
+/// let object = Object::parse(&view)?;
+/// let cache = BinaryCfiCache::from_object(&object)?;
+/// cache.write_to(File::create("my.cficache")?)?;
+///
+/// let view = ByteView::open("my.cficache")?;
+/// let cache = BinaryCfiCache::from_bytes(view)?;
+/// let unwind_info = cache.lookup(0x1234);
+/// # Ok(())
+/// # }
+/// ```
+pub struct BinaryCfiCache<'a> {
+    byteview: ByteView<'a>,
+    version: u32,
+    num_cfi_records: usize,
+    num_cfi_deltas: usize,
+    num_win_records: usize,
+    string_bytes: usize,
+}
+
+impl BinaryCfiCache<'static> {
+    /// Construct a binary CFI cache from an `Object`.
+    pub fn from_object(object: &Object<'_>) -> Result<Self, CfiError> {
+        let ascii = AsciiCfiWriter::<Vec<u8>>::transform(object)?;
+        Self::from_ascii(&ascii)
+    }
+
+    /// Converts the CFI of a [`CfiCache`] into the binary format.
+    pub fn from_cfi_cache(cache: &CfiCache<'_>) -> Result<Self, CfiError> {
+        Self::from_ascii(cache.as_slice())
+    }
+
+    /// Converts Breakpad `STACK CFI` and `STACK WIN` records into the binary format.
+    ///
+    /// All other records are ignored, so this also accepts entire Breakpad symbol files.
+    pub fn from_ascii(data: &[u8]) -> Result<Self, CfiError> {
+        let mut converter = Converter::default();
+        for record in BreakpadStackRecords::new(data) {
+            match record? {
+                BreakpadStackRecord::Cfi(record) => {
+                    if !converter.cfi_ranges.insert(record.range()) {
+                        continue;
+                    }
+
+                    let mut deltas = Vec::new();
+                    for delta in record.deltas() {
+                        let delta = delta?;
+                        if record.range().contains(&delta.address) {
+                            deltas.push(RawCfiDelta {
+                                address: delta.address,
+                                rules: converter.insert_string(delta.rules),
+                            });
+                        }
+                    }
+                    deltas.sort_by_key(|delta| delta.address);
+
+                    let record = RawCfiRecord {
+                        start: record.start,
+                        size: record.size,
+                        rules: converter.insert_string(record.init_rules),
+                        first_delta: 0,
+                        num_deltas: deltas.len() as u32,
+                    };
+                    converter.cfi_records.push((record, deltas));
+                }
+                BreakpadStackRecord::Win(record) => {
+                    if matches!(
+                        record.ty,
+                        BreakpadStackWinRecordType::Fpo | BreakpadStackWinRecordType::FrameData
+                    ) {
+                        let program_string = match record.program_string {
+                            Some(program_string) => converter.insert_string(program_string),
+                            None => NO_STRING,
+                        };
+
+                        converter.win_records.push(RawWinRecord {
+                            ty: record.ty as u32,
+                            code_start: record.code_start,
+                            code_size: record.code_size,
+                            prolog_size: record.prolog_size.into(),
+                            epilog_size: record.epilog_size.into(),
+                            params_size: record.params_size,
+                            saved_regs_size: record.saved_regs_size.into(),
+                            locals_size: record.locals_size,
+                            max_stack_size: record.max_stack_size,
+                            uses_base_pointer: record.uses_base_pointer.into(),
+                            program_string,
+                            parent: NO_PARENT,
+                        });
+                    }
+                }
+            }
+        }
+
+        let buffer = converter.serialize();
+        Self::from_bytes(ByteView::from_vec(buffer))
+    }
+}
+
+impl<'a> BinaryCfiCache<'a> {
+    /// Load a binary CFI cache from a `ByteView`.
+    pub fn from_bytes(byteview: ByteView<'a>) -> Result<Self, CfiError> {
+        let header = byteview
+            .get(..HEADER_SIZE)
+            .ok_or(CfiErrorKind::BadFileMagic)?;
+        if read_u32(header, 0) != BINARY_CFICACHE_MAGIC {
+            return Err(CfiErrorKind::BadFileMagic.into());
+        }
+
+        let version = read_u32(header, 4);
+        if version != BINARY_CFICACHE_LATEST_VERSION {
+            return Err(CfiErrorKind::UnsupportedVersion.into());
+        }
+
+        let cache = BinaryCfiCache {
+            version,
+            num_cfi_records: read_u32(header, 8) as usize,
+            num_cfi_deltas: read_u32(header, 12) as usize,
+            num_win_records: read_u32(header, 16) as usize,
+            string_bytes: read_u32(header, 20) as usize,
+            byteview,
+        };
+
+        if cache.byteview.len() < cache.strings_offset() + cache.string_bytes {
+            return Err(CfiErrorKind::BadFileLength.into());
+        }
+
+        Ok(cache)
+    }
+
+    /// Returns the cache file format version.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns whether this cache is up-to-date.
+    pub fn is_latest(&self) -> bool {
+        self.version == BINARY_CFICACHE_LATEST_VERSION
+    }
+
+    /// Writes the cache to the given writer.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), io::Error> {
+        writer.write_all(&self.byteview)
+    }
+
+    /// Looks up the unwind information for the given address, relative to the module's load
+    /// address.
+    ///
+    /// Like the Breakpad processor, this prefers `STACK WIN` records of type `FrameData` over
+    /// those of type `FPO`, and both over `STACK CFI` records. Among nested `STACK WIN` records of
+    /// the same type, the innermost one is returned. Returns `None` if no record covers the
+    /// address, or if the record refers to invalid strings.
+    pub fn lookup(&self, address: u64) -> Option<CfiLookupResult<'_>> {
+        self.lookup_win(address)
+            .map(CfiLookupResult::Win)
+            .or_else(|| self.lookup_cfi(address).map(CfiLookupResult::Cfi))
+    }
+
+    fn lookup_cfi(&self, address: u64) -> Option<CfiRules<'_>> {
+        let index = partition_point(self.num_cfi_records, |i| {
+            self.cfi_record(i).start <= address
+        });
+        let record = self.cfi_record(index.checked_sub(1)?);
+        if !record.range().contains(&address) {
+            return None;
+        }
+
+        let mut delta_rules = Vec::new();
+        for i in 0..record.num_deltas as usize {
+            let delta = self.cfi_delta(record.first_delta as usize + i)?;
+            if delta.address > address {
+                break;
+            }
+            delta_rules.push(self.get_string(delta.rules)?);
+        }
+
+        Some(CfiRules {
+            start: record.start,
+            size: record.size,
+            init_rules: self.get_string(record.rules)?,
+            delta_rules,
+        })
+    }
+
+    fn lookup_win(&self, address: u64) -> Option<BreakpadStackWinRecord<'_>> {
+        let address_key = u32::try_from(address).ok()?;
+
+        for rank in 0..2 {
+            let index = partition_point(self.num_win_records, |i| {
+                self.win_record(i).sort_key() <= (rank, address_key)
+            });
+
+            // Records of the same type may be nested. Starting at the last record starting at or
+            // before the address, follow the chain of enclosing records until one covers the
+            // address. Every record covering the address also covers the start of all later
+            // records up to the address, so the innermost one is never skipped.
+            let mut found = None;
+            let mut next = index.checked_sub(1);
+            while let Some(i) = next {
+                let record = self.win_record(i);
+                if win_type_rank(record.ty) != rank {
+                    break;
+                }
+                if record.contains(address) {
+                    found = Some(record);
+                    break;
+                }
+                next = match record.parent {
+                    NO_PARENT => None,
+                    parent => Some(parent as usize).filter(|&parent| parent < i),
+                };
+            }
+
+            let record = match found {
+                Some(record) => record,
+                None => continue,
+            };
+
+            let program_string = match record.program_string {
+                NO_STRING => None,
+                offset => Some(self.get_string(offset)?),
+            };
+
+            return Some(BreakpadStackWinRecord {
+                ty: if record.ty == BreakpadStackWinRecordType::FrameData as u32 {
+                    BreakpadStackWinRecordType::FrameData
+                } else {
+                    BreakpadStackWinRecordType::Fpo
+                },
+                code_start: record.code_start,
+                code_size: record.code_size,
+                prolog_size: record.prolog_size as u16,
+                epilog_size: record.epilog_size as u16,
+                params_size: record.params_size,
+                saved_regs_size: record.saved_regs_size as u16,
+                locals_size: record.locals_size,
+                max_stack_size: record.max_stack_size,
+                uses_base_pointer: record.uses_base_pointer != 0,
+                program_string,
+            });
+        }
+
+        None
+    }
+
+    fn cfi_records_offset(&self) -> usize {
+        HEADER_SIZE
+    }
+
+    fn cfi_deltas_offset(&self) -> usize {
+        self.cfi_records_offset() + self.num_cfi_records * RawCfiRecord::SIZE
+    }
+
+    fn win_records_offset(&self) -> usize {
+        self.cfi_deltas_offset() + self.num_cfi_deltas * RawCfiDelta::SIZE
+    }
+
+    fn strings_offset(&self) -> usize {
+        self.win_records_offset() + self.num_win_records * RawWinRecord::SIZE
+    }
+
+    /// Reads the CFI record at the given index, which must be in bounds.
+    fn cfi_record(&self, index: usize) -> RawCfiRecord {
+        let offset = self.cfi_records_offset() + index * RawCfiRecord::SIZE;
+        RawCfiRecord::read(&self.byteview[offset..])
+    }
+
+    fn cfi_delta(&self, index: usize) -> Option<RawCfiDelta> {
+        if index >= self.num_cfi_deltas {
+            return None;
+        }
+        let offset = self.cfi_deltas_offset() + index * RawCfiDelta::SIZE;
+        Some(RawCfiDelta::read(&self.byteview[offset..]))
+    }
+
+    /// Reads the WIN record at the given index, which must be in bounds.
+    fn win_record(&self, index: usize) -> RawWinRecord {
+        let offset = self.win_records_offset() + index * RawWinRecord::SIZE;
+        RawWinRecord::read(&self.byteview[offset..])
+    }
+
+    /// Resolves a string reference to the pointed-to `&str` data.
+    fn get_string(&self, offset: u32) -> Option<&str> {
+        let start = self.strings_offset();
+        let strings = &self.byteview[start..start + self.string_bytes];
+
+        let offset = offset as usize;
+        let len = u32::from_ne_bytes(strings.get(offset..offset + 4)?.try_into().unwrap());
+        let bytes = strings.get(offset + 4..offset + 4 + len as usize)?;
+        str::from_utf8(bytes).ok()
+    }
+}
+
+impl std::fmt::Debug for BinaryCfiCache<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BinaryCfiCache")
+            .field("version", &self.version)
+            .field("cfi_records", &self.num_cfi_records)
+            .field("cfi_deltas", &self.num_cfi_deltas)
+            .field("win_records", &self.num_win_records)
+            .field("string_bytes", &self.string_bytes)
+            .finish()
+    }
+}
+
+/// Returns the number of leading indices in `0..len` for which the predicate holds.
+///
+/// The predicate must hold for a prefix of the indices, like in [`slice::partition_point`].
+fn partition_point<P: Fn(usize) -> bool>(len: usize, pred: P) -> usize {
+    let (mut low, mut high) = (0, len);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if pred(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+/// Collects stack records and deduplicated strings for a binary CFI cache.
+#[derive(Default)]
+struct Converter {
+    /// Every accepted `STACK CFI INIT` record along with its deltas, which are not yet indexed.
+    cfi_records: Vec<(RawCfiRecord, Vec<RawCfiDelta>)>,
+    cfi_ranges: CfiRanges,
+    win_records: Vec<RawWinRecord>,
+    string_bytes: Vec<u8>,
+    strings: HashMap<String, u32>,
+}
+
+impl Converter {
+    /// Inserts a string into the string data, returning its offset.
+    fn insert_string(&mut self, s: &str) -> u32 {
+        if let Some(&offset) = self.strings.get(s) {
+            return offset;
+        }
+
+        let offset = self.string_bytes.len() as u32;
+        self.string_bytes
+            .extend_from_slice(&(s.len() as u32).to_ne_bytes());
+        self.string_bytes.extend_from_slice(s.as_bytes());
+        self.strings.insert(s.to_owned(), offset);
+        offset
+    }
+
+    fn serialize(mut self) -> Vec<u8> {
+        self.cfi_records.sort_by_key(|(record, _)| record.start);
+        self.win_records.sort_by_key(RawWinRecord::sort_key);
+
+        // The records that may still enclose a later record, innermost last. Since records are
+        // sorted by start address, a record that ends before the start of one record cannot
+        // enclose any later record either.
+        let mut enclosing: Vec<usize> = Vec::new();
+        let mut previous_rank = None;
+        for index in 0..self.win_records.len() {
+            let record = &self.win_records[index];
+            let rank = win_type_rank(record.ty);
+            if previous_rank != Some(rank) {
+                enclosing.clear();
+                previous_rank = Some(rank);
+            }
+
+            while let Some(&parent) = enclosing.last() {
+                if self.win_records[parent].end() > record.code_start {
+                    break;
+                }
+                enclosing.pop();
+            }
+
+            let parent = enclosing.last().map_or(NO_PARENT, |&parent| parent as u32);
+            self.win_records[index].parent = parent;
+            enclosing.push(index);
+        }
+
+        let mut cfi_records = Vec::with_capacity(self.cfi_records.len());
+        let mut cfi_deltas = Vec::new();
+        for (mut record, deltas) in self.cfi_records {
+            record.first_delta = cfi_deltas.len() as u32;
+            cfi_records.push(record);
+            cfi_deltas.extend(deltas);
+        }
+
+        let mut buffer = Vec::new();
+        for value in [
+            BINARY_CFICACHE_MAGIC,
+            BINARY_CFICACHE_LATEST_VERSION,
+            cfi_records.len() as u32,
+            cfi_deltas.len() as u32,
+            self.win_records.len() as u32,
+            self.string_bytes.len() as u32,
+        ] {
+            buffer.extend_from_slice(&value.to_ne_bytes());
+        }
+
+        for record in &cfi_records {
+            record.write(&mut buffer);
+        }
+        for delta in &cfi_deltas {
+            delta.write(&mut buffer);
+        }
+        for record in &self.win_records {
+            record.write(&mut buffer);
+        }
+        buffer.extend_from_slice(&self.string_bytes);
+
+        buffer
+    }
+}
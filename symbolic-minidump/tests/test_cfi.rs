@@ -1,9 +1,17 @@
+use std::collections::BTreeMap;
 use std::str;
 
 use symbolic_common::ByteView;
+use symbolic_debuginfo::breakpad::{
+    BreakpadStackRecord, BreakpadStackRecords, BreakpadStackWinRecordType,
+};
 use symbolic_debuginfo::Object;
-use symbolic_minidump::cfi::{AsciiCfiWriter, CfiCache};
+use symbolic_minidump::cfi::{
+    AsciiCfiWriter, BinaryCfiCache, CfiCache, CfiErrorKind, CfiLookupResult,
+};
 use symbolic_testutils::fixture;
+use symbolic_unwind::evaluator::{Evaluator, Identifier, Variable};
+use symbolic_unwind::{LittleEndian, MemoryRegion};
 
 use similar_asserts::assert_eq;
 
@@ -106,3 +114,289 @@ fn cfi_from_pe_windows() -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Looks up the CFI rules at an address by scanning all records of a Breakpad ASCII CFI.
+fn scan_cfi_rules(cfi: &[u8], address: u64) -> Option<Vec<&str>> {
+    for record in BreakpadStackRecords::new(cfi) {
+        if let BreakpadStackRecord::Cfi(record) = record.unwrap() {
+            if record.range().contains(&address) {
+                let mut rules = vec![record.init_rules];
+                for delta in record.deltas() {
+                    let delta = delta.unwrap();
+                    if delta.address <= address {
+                        rules.push(delta.rules);
+                    }
+                }
+                return Some(rules);
+            }
+        }
+    }
+
+    None
+}
+
+#[test]
+fn binary_cfi_lookup_linux() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash"))?;
+    let object = Object::parse(&buffer)?;
+
+    let ascii: Vec<u8> = AsciiCfiWriter::transform(&object)?;
+    let cache = BinaryCfiCache::from_ascii(&ascii)?;
+
+    let mut num_lookups = 0;
+    for record in BreakpadStackRecords::new(&ascii) {
+        let record = match record? {
+            BreakpadStackRecord::Cfi(record) => record,
+            BreakpadStackRecord::Win(_) => unreachable!(),
+        };
+
+        let mut addresses = vec![record.start, record.start + record.size - 1];
+        for delta in record.deltas() {
+            addresses.push(delta?.address);
+        }
+
+        for address in addresses {
+            let rules = match cache.lookup(address) {
+                Some(CfiLookupResult::Cfi(rules)) => rules,
+                other => panic!("unexpected result at {:#x}: {:?}", address, other),
+            };
+            let expected = scan_cfi_rules(&ascii, address).unwrap();
+            assert_eq!(rules.rules().collect::<Vec<_>>(), expected);
+            num_lookups += 1;
+        }
+    }
+
+    assert!(num_lookups > 0);
+    assert_eq!(cache.lookup(0), None);
+    assert_eq!(cache.lookup(u64::MAX), None);
+
+    Ok(())
+}
+
+#[test]
+fn binary_cfi_lookup_windows() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("windows/crash.sym"))?;
+    let object = Object::parse(&buffer)?;
+
+    let cfi_cache = CfiCache::from_object(&object)?;
+    let cache = BinaryCfiCache::from_cfi_cache(&cfi_cache)?;
+
+    let records: Vec<_> = BreakpadStackRecords::new(cfi_cache.as_slice())
+        .filter_map(|record| match record.unwrap() {
+            BreakpadStackRecord::Win(record) => Some(record),
+            BreakpadStackRecord::Cfi(_) => None,
+        })
+        .collect();
+    assert!(!records.is_empty());
+
+    for record in &records {
+        let address = record.code_start.into();
+        let found = match cache.lookup(address) {
+            Some(CfiLookupResult::Win(found)) => found,
+            other => panic!("unexpected result at {:#x}: {:?}", address, other),
+        };
+
+        // frame data records take precedence over FPO records
+        assert!(found.code_range().contains(&record.code_start));
+        if record.ty == BreakpadStackWinRecordType::FrameData {
+            assert_eq!(found.ty, BreakpadStackWinRecordType::FrameData);
+        }
+        assert!(records.contains(&found));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn binary_cfi_lookup_nested_win() -> Result<(), Error> {
+    let ascii = b"STACK WIN 4 1000 100 4 0 8 0 10 0 1 $eip 4 + ^ =
+STACK WIN 4 1010 10 0 0 0 0 0 0 1 $eip 8 + ^ =
+STACK WIN 4 1020 4 0 0 0 0 0 0 1 $eip 12 + ^ =
+STACK WIN 4 1030 8 0 0 0 0 0 0 1 $eip 16 + ^ =
+STACK WIN 4 1040 8 0 0 0 0 0 0 1 $eip 20 + ^ =
+STACK WIN 4 1044 20 0 0 0 0 0 0 1 $eip 24 + ^ =
+STACK WIN 0 1018 10 0 0 0 0 0 0 0 1
+";
+    let cache = BinaryCfiCache::from_ascii(ascii)?;
+
+    let lookup = |address| match cache.lookup(address) {
+        Some(CfiLookupResult::Win(record)) => Some((record.ty, record.code_start)),
+        other => panic!("unexpected result at {:#x}: {:?}", address, other),
+    };
+
+    // the innermost record covering the address wins, even after a nested record ended
+    assert_eq!(
+        lookup(0x1000),
+        Some((BreakpadStackWinRecordType::FrameData, 0x1000))
+    );
+    assert_eq!(
+        lookup(0x1015),
+        Some((BreakpadStackWinRecordType::FrameData, 0x1010))
+    );
+    assert_eq!(
+        lookup(0x101f),
+        Some((BreakpadStackWinRecordType::FrameData, 0x1010))
+    );
+    assert_eq!(
+        lookup(0x1022),
+        Some((BreakpadStackWinRecordType::FrameData, 0x1020))
+    );
+    assert_eq!(
+        lookup(0x1024),
+        Some((BreakpadStackWinRecordType::FrameData, 0x1000))
+    );
+    // sibling records that ended before the address are skipped
+    assert_eq!(
+        lookup(0x1038),
+        Some((BreakpadStackWinRecordType::FrameData, 0x1000))
+    );
+    // a record partially overlapping its predecessor still covers addresses after it
+    assert_eq!(
+        lookup(0x1046),
+        Some((BreakpadStackWinRecordType::FrameData, 0x1044))
+    );
+    assert_eq!(
+        lookup(0x1050),
+        Some((BreakpadStackWinRecordType::FrameData, 0x1044))
+    );
+    assert_eq!(
+        lookup(0x1064),
+        Some((BreakpadStackWinRecordType::FrameData, 0x1000))
+    );
+    assert_eq!(
+        lookup(0x10ff),
+        Some((BreakpadStackWinRecordType::FrameData, 0x1000))
+    );
+    assert_eq!(cache.lookup(0x1100), None);
+
+    Ok(())
+}
+
+#[test]
+fn binary_cfi_reject_overlapping_cfi() -> Result<(), Error> {
+    let ascii = b"STACK CFI INIT 1000 20 .cfa: $rsp 8 + .ra: .cfa -8 + ^
+STACK CFI INIT 1010 20 .cfa: $rsp 16 + .ra: .cfa -8 + ^
+STACK CFI INIT 1020 10 .cfa: $rsp 24 + .ra: .cfa -8 + ^
+STACK CFI INIT 0ff0 20 .cfa: $rsp 32 + .ra: .cfa -8 + ^
+";
+    let cache = BinaryCfiCache::from_ascii(ascii)?;
+
+    let lookup = |address| match cache.lookup(address) {
+        Some(CfiLookupResult::Cfi(rules)) => Some(rules.start),
+        None => None,
+        other => panic!("unexpected result at {:#x}: {:?}", address, other),
+    };
+
+    // records overlapping an earlier record in the file are dropped, regardless of their address
+    assert_eq!(lookup(0xff0), None);
+    assert_eq!(lookup(0x1000), Some(0x1000));
+    assert_eq!(lookup(0x1018), Some(0x1000));
+    assert_eq!(lookup(0x1020), Some(0x1020));
+    assert_eq!(lookup(0x102f), Some(0x1020));
+    assert_eq!(lookup(0x1030), None);
+
+    Ok(())
+}
+
+#[test]
+fn binary_cfi_roundtrip() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("macos/crash.sym"))?;
+    let object = Object::parse(&buffer)?;
+    let cache = BinaryCfiCache::from_object(&object)?;
+    assert!(cache.is_latest());
+
+    let mut buffer = Vec::new();
+    cache.write_to(&mut buffer)?;
+    let loaded = BinaryCfiCache::from_bytes(ByteView::from_slice(&buffer))?;
+    for address in 0..0x2000 {
+        assert_eq!(loaded.lookup(address), cache.lookup(address));
+    }
+
+    let error =
+        BinaryCfiCache::from_bytes(ByteView::from_slice(&buffer[..buffer.len() - 1])).unwrap_err();
+    assert_eq!(error.kind(), CfiErrorKind::BadFileLength);
+
+    // older versions stored no or different information about nested WIN records
+    for version in [1u32, 2] {
+        let mut old_version = buffer.clone();
+        old_version[4..8].copy_from_slice(&version.to_ne_bytes());
+        let error = BinaryCfiCache::from_bytes(ByteView::from_slice(&old_version)).unwrap_err();
+        assert_eq!(error.kind(), CfiErrorKind::UnsupportedVersion);
+    }
+
+    let mut ascii = Vec::new();
+    CfiCache::from_object(&object)?.write_to(&mut ascii)?;
+    let error = BinaryCfiCache::from_bytes(ByteView::from_slice(&ascii)).unwrap_err();
+    assert_eq!(error.kind(), CfiErrorKind::BadFileMagic);
+
+    Ok(())
+}
+
+#[test]
+fn cfi_cache_lookup() -> Result<(), Error> {
+    for name in ["linux/crash", "macos/crash.sym", "windows/crash.sym"] {
+        let buffer = ByteView::open(fixture(name))?;
+        let object = Object::parse(&buffer)?;
+
+        let cfi_cache = CfiCache::from_object(&object)?;
+        let binary = BinaryCfiCache::from_cfi_cache(&cfi_cache)?;
+        for address in (0..0x8000).step_by(7) {
+            assert_eq!(
+                cfi_cache.lookup(address)?,
+                binary.lookup(address),
+                "{}",
+                name
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cfi_cache_evaluate_rules() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash"))?;
+    let object = Object::parse(&buffer)?;
+    let cfi_cache = CfiCache::from_object(&object)?;
+
+    let rules = match cfi_cache.lookup(0x1c90)? {
+        Some(CfiLookupResult::Cfi(rules)) => rules,
+        other => panic!("unexpected result: {:?}", other),
+    };
+    assert_eq!(rules.start, 0x1c70);
+    assert_eq!(rules.delta_rules.len(), 3);
+
+    let effective = rules.effective_rules();
+    assert_eq!(
+        effective,
+        ".cfa: $rbp 16 + .ra: .cfa -8 + ^ $rbp: .cfa -16 + ^ $r14: .cfa -24 + ^ $rbx: .cfa -32 + ^"
+    );
+
+    // the saved registers are stored right below the CFA
+    let stack: Vec<u8> = [0xb0u64, 0xe0, 0xb9, 0xa0]
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+    let memory = MemoryRegion {
+        base_addr: 0xff0,
+        contents: &stack,
+    };
+
+    let mut variables = BTreeMap::new();
+    variables.insert("$rbp".parse::<Variable>()?, 0x1000u64);
+    let mut evaluator = Evaluator::new(LittleEndian)
+        .memory(memory)
+        .variables(variables);
+    evaluator.add_cfi_rules_string(&effective)?;
+
+    let registers = evaluator.evaluate_cfi_rules()?;
+    let constant = |name: &str| registers[&Identifier::Const(name.parse().unwrap())];
+    let variable = |name: &str| registers[&Identifier::Var(name.parse().unwrap())];
+    assert_eq!(constant(".cfa"), 0x1010);
+    assert_eq!(constant(".ra"), 0xa0);
+    assert_eq!(variable("$rbp"), 0xb9);
+    assert_eq!(variable("$r14"), 0xe0);
+    assert_eq!(variable("$rbx"), 0xb0);
+
+    Ok(())
+}
@@ -13,10 +13,23 @@ impl From<new::Error> for SymCacheError {
         let kind = match new_error {
             new::Error::BufferNotAligned
             | new::Error::BadFormatLength
-            | new::Error::WrongEndianness => old::SymCacheErrorKind::BadCacheFile,
+            | new::Error::WrongEndianness
+            | new::Error::ChecksumMismatch
+            | new::Error::InvalidStringOffset
+            | new::Error::InvalidFileIndex
+            | new::Error::InvalidFunctionIndex
+            | new::Error::InvalidSourceLocationIndex
+            | new::Error::CyclicInlineChain
+            | new::Error::UnsortedRanges
+            | new::Error::InvalidDemangledNames
+            | new::Error::UnsortedFunctionEntries
+            | new::Error::UnsortedLineRanges => old::SymCacheErrorKind::BadCacheFile,
             new::Error::HeaderTooSmall => old::SymCacheErrorKind::BadFileHeader,
             new::Error::WrongFormat => old::SymCacheErrorKind::BadFileMagic,
-            new::Error::WrongVersion => old::SymCacheErrorKind::UnsupportedVersion,
+            new::Error::WrongVersion | new::Error::UnknownFlags => {
+                old::SymCacheErrorKind::UnsupportedVersion
+            }
+            new::Error::UnsupportedCfi => old::SymCacheErrorKind::UnsupportedCfi,
         };
 
         Self::from(kind)
@@ -24,7 +37,7 @@ impl From<new::Error> for SymCacheError {
 }
 
 #[derive(Debug)]
-enum SymCacheInner<'data> {
+pub(crate) enum SymCacheInner<'data> {
     Old(old::SymCache<'data>),
     New(new::SymCache<'data>),
 }
@@ -33,7 +46,7 @@ enum SymCacheInner<'data> {
 ///
 /// Use [`SymCacheWriter`](crate::SymCacheWriter) writer to create SymCaches,
 /// including the conversion from object files.
-pub struct SymCache<'data>(SymCacheInner<'data>);
+pub struct SymCache<'data>(pub(crate) SymCacheInner<'data>);
 
 impl<'data> SymCache<'data> {
     /// Parses a SymCache from a binary buffer.
@@ -74,6 +87,17 @@ impl<'data> SymCache<'data> {
         }
     }
 
+    /// Returns the call frame information stored in this SymCache, if any.
+    ///
+    /// See [`new::SymCache::cfi`] for more information. SymCaches of versions prior to 7 never
+    /// contain call frame information.
+    pub fn cfi(&self) -> Option<&'data [u8]> {
+        match &self.0 {
+            SymCacheInner::New(symc) => symc.cfi(),
+            SymCacheInner::Old(_) => None,
+        }
+    }
+
     /// Returns true if line information is included.
     #[deprecated(since = "8.6.0", note = "this will be removed in a future version")]
     pub fn has_line_info(&self) -> bool {
@@ -124,6 +148,23 @@ impl<'data> SymCache<'data> {
             }
         }
     }
+
+    /// Looks up the symbols at a batch of addresses.
+    ///
+    /// This returns one [`Lookup`] for every address, in the same order as `addrs`. For SymCaches
+    /// in the new format, all addresses are resolved in a single pass over the SymCache, which is
+    /// considerably faster than calling [`lookup`](Self::lookup) for large numbers of addresses.
+    pub fn lookup_batch(&self, addrs: &[u64]) -> Result<Vec<Lookup<'data, '_>>, SymCacheError> {
+        match &self.0 {
+            SymCacheInner::New(symc) => Ok(symc
+                .lookup_batch(addrs)
+                .into_iter()
+                .zip(addrs)
+                .map(|(iter, &lookup_addr)| Lookup(LookupInner::New { iter, lookup_addr }))
+                .collect()),
+            SymCacheInner::Old(_) => addrs.iter().map(|&addr| self.lookup(addr)).collect(),
+        }
+    }
 }
 
 impl<'data> fmt::Debug for SymCache<'data> {
@@ -143,6 +184,15 @@ impl<'slf, 'd: 'slf> AsSelf<'slf> for SymCache<'d> {
     }
 }
 
+/// Returns the entry pc of a function in the new format, using the sentinel value `u32::MAX` for
+/// inlined functions that was exposed before 64-bit addresses were supported.
+fn compat_entry_pc(function: &new::Function<'_>) -> u64 {
+    match function.entry_pc() {
+        u64::MAX => u32::MAX.into(),
+        entry_pc => entry_pc,
+    }
+}
+
 #[derive(Clone, Debug)]
 enum FunctionInner<'data> {
     Old(old::Function<'data>),
@@ -178,7 +228,7 @@ impl<'data> Function<'data> {
     pub fn address(&self) -> u64 {
         match &self.0 {
             FunctionInner::Old(function) => function.address(),
-            FunctionInner::New((_, function)) => function.entry_pc() as u64,
+            FunctionInner::New((_, function)) => compat_entry_pc(function),
         }
     }
 
@@ -315,11 +365,12 @@ impl<'data, 'cache> Iterator for Lookup<'data, 'cache> {
                     debug_id: sl.cache.debug_id(),
                     sym_addr: sl
                         .function()
-                        .map(|f| f.entry_pc() as u64)
+                        .map(|f| compat_entry_pc(&f))
                         .unwrap_or(u64::MAX),
                     line_addr: *lookup_addr,
                     instr_addr: *lookup_addr,
                     line: sl.line(),
+                    column: sl.column(),
                     lang: sl.function().map(|f| f.language()).unwrap_or_default(),
                     symbol: sl.function().and_then(|f| f.name()),
                     filename: sl.file().map(|f| f.path_name()).unwrap_or_default(),
@@ -5,19 +5,32 @@
 //!
 //! # Structure of a SymCache
 //!
-//! A SymCache (version 7) contains the following primary kinds of data, written in the following
+//! A SymCache (version 8) contains the following primary kinds of data, written in the following
 //! order:
 //!
 //! 1. Files
 //! 2. Functions
-//! 3. Source Locations
-//! 4. Address Ranges
-//! 5. String Data
+//! 3. Demangled Names (optional)
+//! 4. Source Locations
+//! 5. Columns (optional)
+//! 6. Address Ranges
+//! 7. String Data
+//! 8. Reverse Index (optional)
+//! 9. Call Frame Information (optional)
+//!
+//! Optional sections are announced by flags in the header. The header can also store a checksum of
+//! all data following it.
 //!
 //! The format uses `u32`s to represent line numbers, addresses, references, and string offsets.
 //! Line numbers use `0` to represent an unknown or invalid value. Addresses, references, and string
 //! offsets instead use `u32::MAX`.
 //!
+//! Addresses are stored relative to a 64-bit range offset in the header, which allows addresses
+//! larger than `u32::MAX` as long as the entire address span fits into a `u32`. Otherwise, the
+//! SymCache is written with 64-bit addresses in functions and ranges, using `u64::MAX` as the
+//! sentinel value. Version 7 SymCaches, which always use `u32` addresses without a range offset,
+//! can still be read.
+//!
 //! Strings are saved in one contiguous section with each individual string prefixed by 4 bytes
 //! denoting its length. Functions and files refer to strings by an offset into this string section,
 //! hence "string offset".
@@ -28,12 +41,14 @@
 //!
 //! ## Functions
 //!
-//! A function contains string offsets for its name and compilation directory, a u32 for its entry
-//! address, and a u32 representing the source language.
+//! A function contains string offsets for its name and compilation directory, a u32 (or u64) for
+//! its entry address and its size, and a u32 representing the source language. The size is `0` if
+//! it is not known, for instance for inlined functions.
 //!
 //! ## Address Ranges
 //!
-//! Ranges are saved as a contiguous list of `u32`s, representing their starting addresses.
+//! Ranges are saved as a contiguous list of `u32`s (or `u64`s), representing their starting
+//! addresses.
 //!
 //! ## Source Locations
 //!
@@ -42,6 +57,19 @@
 //! and a reference to the source location into which this source location was inlined. All of these
 //! data are optional.
 //!
+//! ## Columns
+//!
+//! If any source location has a column number, a flag is set in the header and a `u32` column is
+//! stored for every source location in a separate list following the source locations. Like line
+//! numbers, columns use `0` to represent an unknown value.
+//!
+//! ## Reverse Index
+//!
+//! If requested, a reverse index is written after the string data. It contains the entry addresses
+//! of all functions and their inlined copies sorted by function name, as well as code ranges sorted
+//! by the file and line they map to. This allows to find functions by name and the code generated
+//! for a source line via binary search.
+//!
 //! ## Mapping From Ranges To Source Locations
 //!
 //! Every range in the SymCache is associated with at least one source location. As mentioned above,
@@ -95,25 +123,25 @@
 //!    in step 2. The iterator climbs up through the inlining hierarchy, ending at the root source
 //!    location.
 //!
-//! The returned source locations contain accessor methods for their function, file, and line
-//! number.
+//! The returned source locations contain accessor methods for their function, file, line, and
+//! column number.
 
 #![warn(missing_docs)]
 
 mod compat;
-mod new;
+pub mod new;
 mod old;
 pub(crate) mod preamble;
 
 pub use compat::*;
 pub use new::transform;
-pub use new::SymCacheWriter;
+pub use new::{SpillStorage, SymCacheWriter, UpgradeReport};
 #[allow(deprecated)]
 pub use old::format;
 pub use old::{Line, LineInfo, SymCacheError, SymCacheErrorKind, ValueKind};
 
 /// The latest version of the file format.
-pub const SYMCACHE_VERSION: u32 = 7;
+pub const SYMCACHE_VERSION: u32 = 8;
 
 // Version history:
 //
@@ -124,3 +152,6 @@ pub const SYMCACHE_VERSION: u32 = 7;
 // 5: PR #221: Invalid inlinee nesting leading to wrong stack traces
 // 6: PR #319: Correct line offsets and spacer line records
 // 7: PR #459: A new binary format fundamentally based on addr ranges
+// 8: A range offset for 64-bit addresses, function sizes and header flags. Wide address records,
+//    columns, a reverse index, demangled names, a checksum and CFI are optional sections that are
+//    only present if the corresponding header flag is set. Unknown flags are rejected.
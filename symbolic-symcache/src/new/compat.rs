@@ -10,6 +10,7 @@ use symbolic_il2cpp::usym::UsymSymbols;
 
 use super::writer::SymCacheConverter;
 use super::*;
+use crate::compat::SymCacheInner;
 use crate::{SymCacheError, SymCacheErrorKind};
 
 impl<'data> SymCache<'data> {
@@ -22,31 +23,6 @@ impl<'data> SymCache<'data> {
     pub fn has_file_info(&self) -> bool {
         !self.files.is_empty()
     }
-
-    /// An iterator over the functions in this SymCache.
-    pub fn functions(&self) -> Functions<'data> {
-        Functions {
-            cache: self.clone(),
-            function_idx: 0,
-        }
-    }
-}
-
-#[derive(Debug, Clone)]
-pub struct Functions<'data> {
-    cache: SymCache<'data>,
-    function_idx: u32,
-}
-
-impl<'data> Iterator for Functions<'data> {
-    type Item = Function<'data>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        self.cache.get_function(self.function_idx).map(|file| {
-            self.function_idx += 1;
-            file
-        })
-    }
 }
 
 /// A high level writer that can construct SymCaches.
@@ -73,10 +49,6 @@ where
         O::Error: std::error::Error + Send + Sync + 'static,
     {
         let mut converter = SymCacheConverter::new();
-
-        converter.set_arch(object.arch());
-        converter.set_debug_id(object.debug_id());
-
         converter.process_object(object)?;
 
         Self {
@@ -107,19 +79,37 @@ where
 
     /// Processes the [`ObjectLike`], writing its functions, line information and symbols into the
     /// SymCache.
+    ///
+    /// This can be called multiple times to merge several objects into one SymCache, for instance
+    /// an executable and its debug companion file. Functions from debug information take
+    /// precedence over symbols, and symbols fill the gaps between them. All objects need to have
+    /// the same architecture and debug identifier, otherwise an error with
+    /// [`SymCacheErrorKind::IncompatibleObject`] is returned.
     pub fn process_object<'d, 'o, O>(&mut self, object: &'o O) -> Result<(), SymCacheError>
     where
         O: ObjectLike<'d, 'o>,
         O::Error: std::error::Error + Send + Sync + 'static,
     {
-        self.converter.set_arch(object.arch());
-        self.converter.set_debug_id(object.debug_id());
-
         self.converter.process_object(object)?;
 
         Ok(())
     }
 
+    /// Processes the [`ObjectLike`] like [`process_object`](Self::process_object), but reads
+    /// functions from its compilation units in parallel.
+    ///
+    /// The resulting SymCache is identical to the one written after `process_object`.
+    #[cfg(feature = "rayon")]
+    pub fn process_object_par<'d, 'o, O>(&mut self, object: &'o O) -> Result<(), SymCacheError>
+    where
+        O: ObjectLike<'d, 'o>,
+        O::Error: std::error::Error + Send + Sync + 'static,
+    {
+        self.converter.process_object_par(object)?;
+
+        Ok(())
+    }
+
     #[cfg(feature = "il2cpp")]
     /// Processes a set of [`UsymSymbols`], passing all mapped symbols into the converter.
     pub fn process_usym(&mut self, usym: &UsymSymbols) -> Result<(), SymCacheError> {
@@ -136,6 +126,43 @@ where
         Ok(())
     }
 
+    /// Processes a SymCache in the [text format](super::text), for instance one written by
+    /// [`SymCache::write_text`].
+    ///
+    /// This allows to write SymCaches by hand, or to convert them after editing their text. The
+    /// text format is [lossy](super::text#lossiness), so information such as demangled names and
+    /// call frame information needs to be added separately.
+    pub fn process_text(&mut self, text: &str) -> Result<(), SymCacheError> {
+        self.converter.process_text(text)
+    }
+
+    /// Processes a SymCache of any version, upgrading it to the current format.
+    ///
+    /// SymCaches in the legacy format of versions 1 to 6 are converted on a best effort basis.
+    /// The returned [`UpgradeReport`] lists the information that could not be read, so that the
+    /// result can be discarded if it is incomplete. Transformers apply to the functions and source
+    /// locations of legacy SymCaches, but not to newer SymCaches. These are validated and copied
+    /// with their demangled names, reverse index, checksum and call frame information, so that no
+    /// information is lost. An invalid SymCache is rejected with
+    /// [`SymCacheErrorKind::BadCacheFile`].
+    pub fn process_symcache(
+        &mut self,
+        symcache: &crate::SymCache<'_>,
+    ) -> Result<UpgradeReport, SymCacheError> {
+        match symcache.0 {
+            SymCacheInner::Old(ref symcache) => self.converter.process_legacy(symcache),
+            SymCacheInner::New(ref symcache) => {
+                self.converter.process_symcache(symcache)?;
+
+                Ok(UpgradeReport {
+                    version: symcache.version(),
+                    functions: symcache.functions().len(),
+                    ..UpgradeReport::default()
+                })
+            }
+        }
+    }
+
     /// Sets the CPU architecture of this SymCache.
     pub fn set_arch(&mut self, arch: Arch) {
         self.converter.set_arch(arch)
@@ -146,6 +173,54 @@ where
         self.converter.set_debug_id(debug_id)
     }
 
+    /// Sets whether a reverse index is written to this SymCache.
+    ///
+    /// See [`SymCache::lookup_function_name`] and [`SymCache::lookup_file_line`] for the queries
+    /// answered by the reverse index.
+    pub fn set_reverse_index(&mut self, reverse_index: bool) {
+        self.converter.set_reverse_index(reverse_index)
+    }
+
+    /// Sets whether a checksum is written to the header of this SymCache.
+    ///
+    /// The checksum is verified by [`SymCache::validate`] to detect corrupted SymCaches.
+    pub fn set_checksum(&mut self, checksum: bool) {
+        self.converter.set_checksum(checksum)
+    }
+
+    /// Sets the call frame information (CFI) stored in this SymCache, or removes it.
+    ///
+    /// The CFI is stored as it is and can be retrieved with [`SymCache::cfi`]. It must be the
+    /// contents of a `CfiCache` file created for the same object, as written by
+    /// `CfiCache::write_to`. If the CFI does not start with the preamble of a supported `CfiCache`
+    /// version, [`SymCacheErrorKind::UnsupportedCfi`] is returned.
+    pub fn set_cfi(&mut self, cfi: Option<Vec<u8>>) -> Result<(), SymCacheError> {
+        self.converter.set_cfi(cfi)
+    }
+
+    /// Sets the options for demangling function names when writing, or disables demangling.
+    ///
+    /// When enabled, [`Function::short_name`] and [`Function::full_name`] return the demangled
+    /// names, so that they do not need to be demangled on every lookup. The short name never
+    /// contains return types or parameters, while the full name is formatted with `options`.
+    #[cfg(feature = "demangle")]
+    pub fn set_demangle_options(&mut self, options: Option<symbolic_demangle::DemangleOptions>) {
+        self.converter.set_demangle_options(options)
+    }
+
+    /// Limits the memory used for code ranges by spilling them to the given storage.
+    ///
+    /// At most `range_buffer_size` bytes of code ranges and line ranges are buffered in memory
+    /// before they are written to `storage`, for instance a temporary file. Strings, files and
+    /// functions are always kept in memory. The resulting SymCache is identical to one written
+    /// without spilling. This needs to be called before any objects are processed.
+    pub fn set_spill_storage<S>(&mut self, storage: S, range_buffer_size: usize)
+    where
+        S: SpillStorage + 'static,
+    {
+        self.converter.set_spill_storage(storage, range_buffer_size)
+    }
+
     /// Adds a new symbol to this SymCache.
     ///
     /// Symbols **must** be added in ascending order using this method. This will emit a function
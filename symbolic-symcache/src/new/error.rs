@@ -23,7 +23,43 @@ pub enum Error {
     /// The format version in the header is wrong/unknown.
     #[error("unknown SymCache version")]
     WrongVersion,
+    /// The header announces optional data that is unknown to this version.
+    #[error("unknown SymCache header flags")]
+    UnknownFlags,
     /// The self-advertised size of the buffer is not correct.
     #[error("incorrect buffer length")]
     BadFormatLength,
+    /// The checksum in the header does not match the data.
+    #[error("checksum mismatch")]
+    ChecksumMismatch,
+    /// A reference to a string is out of bounds or not valid UTF-8.
+    #[error("invalid string reference")]
+    InvalidStringOffset,
+    /// A reference to a file is out of bounds.
+    #[error("invalid file reference")]
+    InvalidFileIndex,
+    /// A reference to a function is out of bounds.
+    #[error("invalid function reference")]
+    InvalidFunctionIndex,
+    /// A reference to a source location is out of bounds.
+    #[error("invalid source location reference")]
+    InvalidSourceLocationIndex,
+    /// A source location is directly or indirectly inlined into itself.
+    #[error("cyclic inline chain")]
+    CyclicInlineChain,
+    /// The ranges are not sorted by their start address.
+    #[error("ranges are not sorted")]
+    UnsortedRanges,
+    /// The demangled names do not match the functions.
+    #[error("invalid demangled names")]
+    InvalidDemangledNames,
+    /// The function entries of the reverse index are not sorted by name and address.
+    #[error("function entries are not sorted")]
+    UnsortedFunctionEntries,
+    /// The line ranges of the reverse index are not sorted by file, line and address.
+    #[error("line ranges are not sorted")]
+    UnsortedLineRanges,
+    /// The call frame information is not a supported `CfiCache` file.
+    #[error("unsupported call frame information")]
+    UnsupportedCfi,
 }
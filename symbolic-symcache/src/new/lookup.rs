@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use symbolic_common::Language;
 
 use super::{raw, SymCache};
@@ -8,10 +10,9 @@ impl<'data> SymCache<'data> {
     /// This always returns an iterator, however that iterator might be empty in case no [`SourceLocation`]
     /// was found for the given `addr`.
     pub fn lookup(&self, addr: u64) -> SourceLocationIter<'data, '_> {
-        use std::convert::TryFrom;
-        let addr = match u32::try_from(addr) {
-            Ok(addr) => addr,
-            Err(_) => {
+        let addr = match addr.checked_sub(self.range_offset) {
+            Some(addr) => addr,
+            None => {
                 return SourceLocationIter {
                     cache: self,
                     source_location_idx: u32::MAX,
@@ -19,13 +20,53 @@ impl<'data> SymCache<'data> {
             }
         };
 
-        let source_location_start = (self.source_locations.len() - self.ranges.len()) as u32;
-        let mut source_location_idx = match self.ranges.binary_search_by_key(&addr, |r| r.0) {
-            Ok(idx) => source_location_start + idx as u32,
-            Err(idx) if idx == 0 => u32::MAX,
-            Err(idx) => source_location_start + idx as u32 - 1,
+        let range_idx = match self.ranges.binary_search(addr) {
+            Ok(idx) => Some(idx),
+            Err(idx) => idx.checked_sub(1),
         };
 
+        self.range_source_locations(range_idx)
+    }
+
+    /// Looks up a batch of instruction addresses in the SymCache.
+    ///
+    /// This yields one iterator of [`SourceLocation`]s for every address, in the same order as
+    /// `addrs`, like calling [`lookup`](Self::lookup) for each of them. Instead of searching the
+    /// ranges for every address, all addresses are resolved in a single pass over the ranges. The
+    /// addresses do not need to be sorted, but sorted addresses avoid an additional sort.
+    pub fn lookup_batch(&self, addrs: &[u64]) -> Vec<SourceLocationIter<'data, '_>> {
+        let mut order: Vec<usize> = (0..addrs.len()).collect();
+        if !addrs.windows(2).all(|w| w[0] <= w[1]) {
+            order.sort_by_key(|&idx| addrs[idx]);
+        }
+
+        let empty = SourceLocationIter {
+            cache: self,
+            source_location_idx: u32::MAX,
+        };
+        let mut results = vec![empty; addrs.len()];
+
+        // The number of ranges starting at or before the current address.
+        let mut num_ranges = 0;
+        for idx in order {
+            let addr = match addrs[idx].checked_sub(self.range_offset) {
+                Some(addr) => addr,
+                None => continue,
+            };
+
+            num_ranges = self.ranges.gallop(num_ranges, addr);
+            results[idx] = self.range_source_locations(num_ranges.checked_sub(1));
+        }
+
+        results
+    }
+
+    /// Returns the inlining hierarchy of source locations for the range at the given index.
+    fn range_source_locations(&self, range_idx: Option<usize>) -> SourceLocationIter<'data, '_> {
+        let source_location_start = self.source_locations.len() - self.ranges.len();
+        let mut source_location_idx =
+            range_idx.map_or(u32::MAX, |idx| (source_location_start + idx) as u32);
+
         if let Some(source_location) = self.source_locations.get(source_location_idx as usize) {
             if *source_location == raw::NO_SOURCE_LOCATION {
                 source_location_idx = u32::MAX;
@@ -38,6 +79,18 @@ impl<'data> SymCache<'data> {
         }
     }
 
+    /// An iterator over the functions in this SymCache.
+    ///
+    /// Functions are returned in the order they were added to the SymCache, which is not
+    /// necessarily sorted by address. Inlined functions are included once, regardless of how often
+    /// they were inlined.
+    pub fn functions(&self) -> Functions<'data> {
+        Functions {
+            cache: self.clone(),
+            function_idx: 0,
+        }
+    }
+
     pub(crate) fn get_file(&self, file_idx: u32) -> Option<File<'data>> {
         let raw_file = self.files.get(file_idx as usize)?;
         Some(File {
@@ -51,10 +104,19 @@ impl<'data> SymCache<'data> {
 
     pub(crate) fn get_function(&self, function_idx: u32) -> Option<Function<'data>> {
         let raw_function = self.functions.get(function_idx as usize)?;
+        let entry_pc = match raw_function.entry_pc {
+            u64::MAX => u64::MAX,
+            entry_pc => entry_pc.saturating_add(self.range_offset),
+        };
+
+        let demangled_name = self.demangled_names.get(function_idx as usize);
         Some(Function {
             name: self.get_string(raw_function.name_offset),
+            short_name: demangled_name.and_then(|n| self.get_string(n.short_name_offset)),
+            full_name: demangled_name.and_then(|n| self.get_string(n.full_name_offset)),
             comp_dir: self.get_string(raw_function.comp_dir_offset),
-            entry_pc: raw_function.entry_pc,
+            entry_pc,
+            size: raw_function.size,
             language: Language::from_u32(raw_function.lang),
         })
     }
@@ -124,8 +186,11 @@ impl<'data> File<'data> {
 #[derive(Clone, Debug)]
 pub struct Function<'data> {
     name: Option<&'data str>,
+    short_name: Option<&'data str>,
+    full_name: Option<&'data str>,
     comp_dir: Option<&'data str>,
-    entry_pc: u32,
+    entry_pc: u64,
+    size: u64,
     language: Language,
 }
 
@@ -135,22 +200,89 @@ impl<'data> Function<'data> {
         self.name
     }
 
+    /// The demangled name of this function without return type and parameters.
+    ///
+    /// This is only available if the SymCache was written with demangling enabled and the name
+    /// could be demangled. Otherwise, [`name`](Self::name) may still need to be demangled.
+    pub fn short_name(&self) -> Option<&'data str> {
+        self.short_name
+    }
+
+    /// The demangled name of this function, formatted with the options chosen when writing.
+    ///
+    /// This is only available if the SymCache was written with demangling enabled and the name
+    /// could be demangled. Otherwise, [`name`](Self::name) may still need to be demangled.
+    pub fn full_name(&self) -> Option<&'data str> {
+        self.full_name
+    }
+
     /// The compilation directory of this function.
     pub fn comp_dir(&self) -> Option<&'data str> {
         self.comp_dir
     }
 
     /// The entry pc of the function.
-    pub fn entry_pc(&self) -> u32 {
+    ///
+    /// This is `u64::MAX` for inlined functions, which do not have a single entry pc.
+    pub fn entry_pc(&self) -> u64 {
         self.entry_pc
     }
 
+    /// The end address of the function, exclusive.
+    ///
+    /// This is `None` if the size of the function is not known, which is the case for inlined
+    /// functions, symbols without a size, and all functions in version 7 SymCaches.
+    pub fn end_pc(&self) -> Option<u64> {
+        if self.entry_pc == u64::MAX || self.size == 0 {
+            return None;
+        }
+
+        self.entry_pc.checked_add(self.size)
+    }
+
+    /// The address range covered by the function, if its size is known.
+    ///
+    /// See [`end_pc`](Self::end_pc) for when the size is not known.
+    pub fn range(&self) -> Option<Range<u64>> {
+        Some(self.entry_pc..self.end_pc()?)
+    }
+
     /// The language the function is written in.
     pub fn language(&self) -> Language {
         self.language
     }
 }
 
+/// An iterator over the functions in a [`SymCache`].
+///
+/// Returned by [`SymCache::functions`].
+#[derive(Debug, Clone)]
+pub struct Functions<'data> {
+    cache: SymCache<'data>,
+    function_idx: u32,
+}
+
+impl<'data> Iterator for Functions<'data> {
+    type Item = Function<'data>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let function = self.cache.get_function(self.function_idx)?;
+        self.function_idx += 1;
+        Some(function)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self
+            .cache
+            .functions
+            .len()
+            .saturating_sub(self.function_idx as usize);
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Functions<'_> {}
+
 /// A Source Location as included in the SymCache.
 ///
 /// The source location represents a `(function, file, line, inlined_into)` tuple corresponding to
@@ -159,6 +291,7 @@ impl<'data> Function<'data> {
 pub struct SourceLocation<'data, 'cache> {
     pub(crate) cache: &'cache SymCache<'data>,
     pub(crate) source_location: &'data raw::SourceLocation,
+    pub(crate) column: u32,
 }
 
 impl<'data, 'cache> SourceLocation<'data, 'cache> {
@@ -169,6 +302,14 @@ impl<'data, 'cache> SourceLocation<'data, 'cache> {
         self.source_location.line
     }
 
+    /// The source column corresponding to the instruction.
+    ///
+    /// This returns `0` when no column information is available, which is always the case for
+    /// SymCaches created from formats without column information and for version 7 SymCaches.
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+
     /// The source file corresponding to the instruction.
     pub fn file(&self) -> Option<File<'data>> {
         self.cache.get_file(self.source_location.file_idx)
@@ -201,10 +342,17 @@ impl<'data, 'cache> Iterator for SourceLocationIter<'data, 'cache> {
             .source_locations
             .get(self.source_location_idx as usize)
             .map(|source_location| {
+                let column = self
+                    .cache
+                    .columns
+                    .get(self.source_location_idx as usize)
+                    .copied()
+                    .unwrap_or(0);
                 self.source_location_idx = source_location.inlined_into_idx;
                 SourceLocation {
                     cache: self.cache,
                     source_location,
+                    column,
                 }
             })
     }
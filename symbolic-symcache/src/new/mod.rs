@@ -1,4 +1,9 @@
-use std::convert::TryInto;
+//! Direct access to SymCaches in the binary format introduced with version 7.
+//!
+//! Unlike the [`SymCache`](crate::SymCache) in the crate root, which can also read SymCaches of
+//! older versions, this exposes the functions and source locations of the format directly.
+
+use std::convert::{TryFrom, TryInto};
 use std::{mem, ptr};
 
 use symbolic_common::{Arch, DebugId};
@@ -7,12 +12,22 @@ mod compat;
 mod error;
 mod lookup;
 pub(crate) mod raw;
+mod reverse;
+mod spill;
+pub mod text;
 pub mod transform;
+mod upgrade;
+mod validate;
+mod verify;
 mod writer;
 
 pub use compat::*;
 pub use error::Error;
 pub use lookup::*;
+pub use reverse::*;
+pub use spill::SpillStorage;
+pub use upgrade::UpgradeReport;
+pub use verify::*;
 
 use raw::align_to_eight;
 
@@ -26,10 +41,133 @@ type Result<T, E = Error> = std::result::Result<T, E>;
 pub struct SymCache<'data> {
     header: &'data raw::Header,
     files: &'data [raw::File],
-    functions: &'data [raw::Function],
+    functions: FunctionRecords<'data>,
+    demangled_names: &'data [raw::DemangledName],
     source_locations: &'data [raw::SourceLocation],
-    ranges: &'data [raw::Range],
+    columns: &'data [u32],
+    ranges: RangeRecords<'data>,
+    range_offset: u64,
     string_bytes: &'data [u8],
+    function_entries: &'data [raw::FunctionEntry],
+    line_ranges: &'data [raw::LineRange],
+    cfi: Option<&'data [u8]>,
+    /// All data following the header, which is covered by the checksum.
+    data: &'data [u8],
+}
+
+/// The function records of a SymCache, stored with either 32-bit or 64-bit addresses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FunctionRecords<'data> {
+    V7(&'data [raw::FunctionV7]),
+    Compact(&'data [raw::Function]),
+    Wide(&'data [raw::WideFunction]),
+}
+
+impl<'data> FunctionRecords<'data> {
+    fn len(&self) -> usize {
+        match *self {
+            FunctionRecords::V7(functions) => functions.len(),
+            FunctionRecords::Compact(functions) => functions.len(),
+            FunctionRecords::Wide(functions) => functions.len(),
+        }
+    }
+
+    /// Returns the raw function at the given index with its entry pc and size widened to 64 bits.
+    ///
+    /// The entry pc is still relative to the range offset and `u64::MAX` for inlined functions.
+    fn get(&self, idx: usize) -> Option<raw::WideFunction> {
+        fn widen(entry_pc: u32) -> u64 {
+            match entry_pc {
+                u32::MAX => u64::MAX,
+                entry_pc => entry_pc.into(),
+            }
+        }
+
+        match *self {
+            FunctionRecords::V7(functions) => {
+                let function = functions.get(idx)?;
+                Some(raw::WideFunction {
+                    entry_pc: widen(function.entry_pc),
+                    size: 0,
+                    name_offset: function.name_offset,
+                    comp_dir_offset: function.comp_dir_offset,
+                    lang: function.lang,
+                    _padding: 0,
+                })
+            }
+            FunctionRecords::Compact(functions) => {
+                let function = functions.get(idx)?;
+                Some(raw::WideFunction {
+                    entry_pc: widen(function.entry_pc),
+                    size: function.size.into(),
+                    name_offset: function.name_offset,
+                    comp_dir_offset: function.comp_dir_offset,
+                    lang: function.lang,
+                    _padding: 0,
+                })
+            }
+            FunctionRecords::Wide(functions) => functions.get(idx).cloned(),
+        }
+    }
+}
+
+/// The range records of a SymCache, stored with either 32-bit or 64-bit addresses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RangeRecords<'data> {
+    Compact(&'data [raw::Range]),
+    Wide(&'data [raw::WideRange]),
+}
+
+impl<'data> RangeRecords<'data> {
+    fn len(&self) -> usize {
+        match *self {
+            RangeRecords::Compact(ranges) => ranges.len(),
+            RangeRecords::Wide(ranges) => ranges.len(),
+        }
+    }
+
+    /// Returns the start address of the range at the given index, relative to the range offset.
+    fn get(&self, idx: usize) -> Option<u64> {
+        match *self {
+            RangeRecords::Compact(ranges) => ranges.get(idx).map(|r| r.0.into()),
+            RangeRecords::Wide(ranges) => ranges.get(idx).map(|r| r.0),
+        }
+    }
+
+    /// Binary searches the ranges for an address relative to the range offset.
+    ///
+    /// See [`slice::binary_search`] for the semantics of the return value.
+    fn binary_search(&self, addr: u64) -> Result<usize, usize> {
+        match *self {
+            RangeRecords::Compact(ranges) => match u32::try_from(addr) {
+                Ok(addr) => ranges.binary_search_by_key(&addr, |r| r.0),
+                Err(_) => Err(ranges.len()),
+            },
+            RangeRecords::Wide(ranges) => ranges.binary_search_by_key(&addr, |r| r.0),
+        }
+    }
+
+    /// Returns the number of ranges starting at or before an address relative to the range offset.
+    ///
+    /// The search gallops forward from the index `from`, which must not be larger than the result.
+    /// This makes a pass over sorted addresses logarithmic in the distance between them.
+    fn gallop(&self, from: usize, addr: u64) -> usize {
+        fn gallop_slice<T>(slice: &[T], from: usize, is_before: impl Fn(&T) -> bool) -> usize {
+            let mut lo = from;
+            let mut step = 1;
+            while lo + step <= slice.len() && is_before(&slice[lo + step - 1]) {
+                lo += step;
+                step *= 2;
+            }
+            let hi = slice.len().min(lo + step);
+            lo + slice[lo..hi].partition_point(is_before)
+        }
+
+        match *self {
+            RangeRecords::Compact(ranges) => gallop_slice(ranges, from, |r| u64::from(r.0) <= addr),
+            RangeRecords::Wide(ranges) => gallop_slice(ranges, from, |r| r.0 <= addr),
+        }
+    }
 }
 
 impl<'data> std::fmt::Debug for SymCache<'data> {
@@ -71,31 +209,78 @@ impl<'data> SymCache<'data> {
         if header.magic != raw::SYMCACHE_MAGIC {
             return Err(Error::WrongFormat);
         }
-        if header.version != raw::SYMCACHE_VERSION {
+        if header.version != raw::SYMCACHE_VERSION && header.version != raw::SYMCACHE_VERSION_V7 {
             return Err(Error::WrongVersion);
         }
 
+        // Version 7 did not define the range offset and flags, which were reserved (zeroed) space.
+        let is_v7 = header.version == raw::SYMCACHE_VERSION_V7;
+        if !is_v7 && header.flags & !raw::KNOWN_FLAGS != 0 {
+            return Err(Error::UnknownFlags);
+        }
+        let range_offset = if is_v7 { 0 } else { header.range_offset };
+        let wide = !is_v7 && header.flags & raw::FLAG_WIDE_ADDRESSES != 0;
+        let has_columns = !is_v7 && header.flags & raw::FLAG_COLUMNS != 0;
+        let has_reverse_index = !is_v7 && header.flags & raw::FLAG_REVERSE_INDEX != 0;
+        let has_demangled_names = !is_v7 && header.flags & raw::FLAG_DEMANGLED_NAMES != 0;
+        let has_cfi = !is_v7 && header.flags & raw::FLAG_CFI != 0;
+
+        let (function_size, range_size) = if is_v7 {
+            (
+                mem::size_of::<raw::FunctionV7>(),
+                mem::size_of::<raw::Range>(),
+            )
+        } else if wide {
+            (
+                mem::size_of::<raw::WideFunction>(),
+                mem::size_of::<raw::WideRange>(),
+            )
+        } else {
+            (
+                mem::size_of::<raw::Function>(),
+                mem::size_of::<raw::Range>(),
+            )
+        };
+
         let mut files_size = mem::size_of::<raw::File>() * header.num_files as usize;
         files_size += align_to_eight(files_size);
 
-        let mut functions_size = mem::size_of::<raw::Function>() * header.num_functions as usize;
+        let mut functions_size = function_size * header.num_functions as usize;
         functions_size += align_to_eight(functions_size);
 
+        let num_demangled_names = if has_demangled_names {
+            header.num_functions as usize
+        } else {
+            0
+        };
+        let mut demangled_names_size = mem::size_of::<raw::DemangledName>() * num_demangled_names;
+        demangled_names_size += align_to_eight(demangled_names_size);
+
         let mut source_locations_size =
             mem::size_of::<raw::SourceLocation>() * header.num_source_locations as usize;
         source_locations_size += align_to_eight(source_locations_size);
 
-        let mut ranges_size = mem::size_of::<raw::Range>() * header.num_ranges as usize;
+        let num_columns = if has_columns {
+            header.num_source_locations as usize
+        } else {
+            0
+        };
+        let mut columns_size = mem::size_of::<u32>() * num_columns;
+        columns_size += align_to_eight(columns_size);
+
+        let mut ranges_size = range_size * header.num_ranges as usize;
         ranges_size += align_to_eight(ranges_size);
 
         let expected_buf_size = header_size
             + files_size
             + functions_size
+            + demangled_names_size
             + source_locations_size
+            + columns_size
             + ranges_size
             + header.string_bytes as usize;
 
-        if buf.len() < expected_buf_size || source_locations_size < ranges_size {
+        if buf.len() < expected_buf_size || header.num_source_locations < header.num_ranges {
             return Err(Error::BadFormatLength);
         }
 
@@ -103,8 +288,10 @@ impl<'data> SymCache<'data> {
         // arithmetic are within `buf`
         let files_start = unsafe { buf.as_ptr().add(header_size) };
         let functions_start = unsafe { files_start.add(files_size) };
-        let source_locations_start = unsafe { functions_start.add(functions_size) };
-        let ranges_start = unsafe { source_locations_start.add(source_locations_size) };
+        let demangled_names_start = unsafe { functions_start.add(functions_size) };
+        let source_locations_start = unsafe { demangled_names_start.add(demangled_names_size) };
+        let columns_start = unsafe { source_locations_start.add(source_locations_size) };
+        let ranges_start = unsafe { columns_start.add(columns_size) };
         let string_bytes_start = unsafe { ranges_start.add(ranges_size) };
 
         // SAFETY: the above buffer size check also made sure we are not going out of bounds
@@ -112,10 +299,32 @@ impl<'data> SymCache<'data> {
         let files = unsafe {
             &*ptr::slice_from_raw_parts(files_start as *const raw::File, header.num_files as usize)
         };
-        let functions = unsafe {
+        let functions = if is_v7 {
+            FunctionRecords::V7(unsafe {
+                &*ptr::slice_from_raw_parts(
+                    functions_start as *const raw::FunctionV7,
+                    header.num_functions as usize,
+                )
+            })
+        } else if wide {
+            FunctionRecords::Wide(unsafe {
+                &*ptr::slice_from_raw_parts(
+                    functions_start as *const raw::WideFunction,
+                    header.num_functions as usize,
+                )
+            })
+        } else {
+            FunctionRecords::Compact(unsafe {
+                &*ptr::slice_from_raw_parts(
+                    functions_start as *const raw::Function,
+                    header.num_functions as usize,
+                )
+            })
+        };
+        let demangled_names = unsafe {
             &*ptr::slice_from_raw_parts(
-                functions_start as *const raw::Function,
-                header.num_functions as usize,
+                demangled_names_start as *const raw::DemangledName,
+                num_demangled_names,
             )
         };
         let source_locations = unsafe {
@@ -124,23 +333,111 @@ impl<'data> SymCache<'data> {
                 header.num_source_locations as usize,
             )
         };
-        let ranges = unsafe {
-            &*ptr::slice_from_raw_parts(
-                ranges_start as *const raw::Range,
-                header.num_ranges as usize,
-            )
+        let columns =
+            unsafe { &*ptr::slice_from_raw_parts(columns_start as *const u32, num_columns) };
+        let ranges = if wide {
+            RangeRecords::Wide(unsafe {
+                &*ptr::slice_from_raw_parts(
+                    ranges_start as *const raw::WideRange,
+                    header.num_ranges as usize,
+                )
+            })
+        } else {
+            RangeRecords::Compact(unsafe {
+                &*ptr::slice_from_raw_parts(
+                    ranges_start as *const raw::Range,
+                    header.num_ranges as usize,
+                )
+            })
         };
         let string_bytes = unsafe {
             &*ptr::slice_from_raw_parts(string_bytes_start, header.string_bytes as usize)
         };
 
+        let (function_entries, line_ranges, data_end) = if has_reverse_index {
+            let reverse_index_start = expected_buf_size + align_to_eight(expected_buf_size);
+            let reverse_header_size = mem::size_of::<raw::ReverseIndexHeader>();
+            if buf.len() < reverse_index_start + reverse_header_size {
+                return Err(Error::BadFormatLength);
+            }
+
+            // SAFETY: we checked that the buffer is large enough to fit the reverse index header,
+            // which starts at an aligned offset.
+            let reverse_header = unsafe {
+                &*(buf.as_ptr().add(reverse_index_start) as *const raw::ReverseIndexHeader)
+            };
+            let num_function_entries = reverse_header.num_function_entries as usize;
+            let num_line_ranges = reverse_header.num_line_ranges as usize;
+
+            let function_entries_size = mem::size_of::<raw::FunctionEntry>() * num_function_entries;
+            let line_ranges_size = mem::size_of::<raw::LineRange>() * num_line_ranges;
+            let reverse_index_end = reverse_index_start
+                + reverse_header_size
+                + function_entries_size
+                + line_ranges_size;
+            if buf.len() < reverse_index_end {
+                return Err(Error::BadFormatLength);
+            }
+
+            // SAFETY: we just made sure that both sections are within `buf`, and their start is
+            // aligned since the header size is a multiple of eight.
+            let function_entries_start =
+                unsafe { buf.as_ptr().add(reverse_index_start + reverse_header_size) };
+            let line_ranges_start = unsafe { function_entries_start.add(function_entries_size) };
+            unsafe {
+                (
+                    &*ptr::slice_from_raw_parts(
+                        function_entries_start as *const raw::FunctionEntry,
+                        num_function_entries,
+                    ),
+                    &*ptr::slice_from_raw_parts(
+                        line_ranges_start as *const raw::LineRange,
+                        num_line_ranges,
+                    ),
+                    reverse_index_end,
+                )
+            }
+        } else {
+            (&[][..], &[][..], expected_buf_size)
+        };
+
+        let (cfi, data_end) = if has_cfi {
+            let cfi_start = data_end + align_to_eight(data_end);
+            let cfi_header_size = mem::size_of::<raw::CfiHeader>();
+            if buf.len() < cfi_start + cfi_header_size {
+                return Err(Error::BadFormatLength);
+            }
+
+            // SAFETY: we checked that the buffer is large enough to fit the CFI header, which
+            // starts at an aligned offset.
+            let cfi_header = unsafe { &*(buf.as_ptr().add(cfi_start) as *const raw::CfiHeader) };
+            let cfi_end = usize::try_from(cfi_header.num_bytes)
+                .ok()
+                .and_then(|num_bytes| (cfi_start + cfi_header_size).checked_add(num_bytes))
+                .filter(|&cfi_end| cfi_end <= buf.len())
+                .ok_or(Error::BadFormatLength)?;
+
+            let cfi = &buf[cfi_start + cfi_header_size..cfi_end];
+            check_cfi(cfi)?;
+            (Some(cfi), cfi_end)
+        } else {
+            (None, data_end)
+        };
+
         Ok(SymCache {
             header,
             files,
             functions,
+            demangled_names,
             source_locations,
+            columns,
             ranges,
+            range_offset,
             string_bytes,
+            function_entries,
+            line_ranges,
+            cfi,
+            data: &buf[header_size..data_end],
         })
     }
 
@@ -179,4 +476,32 @@ impl<'data> SymCache<'data> {
     pub fn debug_id(&self) -> DebugId {
         self.header.debug_id
     }
+
+    /// Returns the call frame information (CFI) stored in this SymCache, if any.
+    ///
+    /// The CFI is stored exactly as written by
+    /// [`SymCacheWriter::set_cfi`](crate::SymCacheWriter::set_cfi), which are the contents of a
+    /// `CfiCache` file. Its preamble is checked when parsing the SymCache, so the CFI always starts
+    /// with the magic and supported version of `CfiCache`. Such CFI can be loaded without copying via
+    /// `CfiCache::from_bytes(ByteView::from_slice(cfi))` and passed to the minidump processor,
+    /// so that a single file can be used for symbolication and stackwalking.
+    pub fn cfi(&self) -> Option<&'data [u8]> {
+        self.cfi
+    }
+}
+
+/// Checks that the CFI starts with the preamble of a supported `CfiCache` file.
+///
+/// See [`raw::CFI_MAGIC`] and [`raw::CFI_VERSION`].
+pub(crate) fn check_cfi(cfi: &[u8]) -> Result<(), Error> {
+    let read_u32 = |offset: usize| {
+        cfi.get(offset..offset + 4)
+            .map(|bytes| u32::from_ne_bytes(bytes.try_into().unwrap()))
+    };
+
+    if read_u32(0) == Some(raw::CFI_MAGIC) && read_u32(4) == Some(raw::CFI_VERSION) {
+        Ok(())
+    } else {
+        Err(Error::UnsupportedCfi)
+    }
 }
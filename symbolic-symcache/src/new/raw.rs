@@ -4,6 +4,62 @@ use symbolic_common::{Arch, DebugId};
 
 pub use crate::SYMCACHE_VERSION;
 
+/// The last version that stores all addresses as `u32` without a [`Header::range_offset`].
+pub const SYMCACHE_VERSION_V7: u32 = 7;
+
+/// Header flag indicating that [`WideFunction`]s and [`WideRange`]s are stored instead of
+/// [`Function`]s and [`Range`]s.
+pub const FLAG_WIDE_ADDRESSES: u32 = 0x1;
+
+/// Header flag indicating that a `u32` column number is stored for every [`SourceLocation`].
+///
+/// The columns follow the source locations in a separate section with the same number of entries.
+pub const FLAG_COLUMNS: u32 = 0x2;
+
+/// Header flag indicating that a reverse index follows the string data.
+///
+/// The reverse index starts with a [`ReverseIndexHeader`], followed by [`FunctionEntry`]s and
+/// [`LineRange`]s.
+pub const FLAG_REVERSE_INDEX: u32 = 0x4;
+
+/// Header flag indicating that a [`DemangledName`] is stored for every [`Function`].
+///
+/// The demangled names follow the functions in a separate section with the same number of entries.
+pub const FLAG_DEMANGLED_NAMES: u32 = 0x8;
+
+/// Header flag indicating that [`Header::checksum`] contains a checksum of the data following the
+/// header.
+pub const FLAG_CHECKSUM: u32 = 0x10;
+
+/// Header flag indicating that call frame information (CFI) follows the string data and the
+/// reverse index.
+///
+/// The CFI starts with a [`CfiHeader`], followed by the contents of a CFI cache file, which begin
+/// with [`CFI_MAGIC`] and [`CFI_VERSION`].
+pub const FLAG_CFI: u32 = 0x20;
+
+/// All header flags known to this version of the format.
+///
+/// Files with any other flag set use a layout this version cannot read.
+pub const KNOWN_FLAGS: u32 = FLAG_WIDE_ADDRESSES
+    | FLAG_COLUMNS
+    | FLAG_REVERSE_INDEX
+    | FLAG_DEMANGLED_NAMES
+    | FLAG_CHECKSUM
+    | FLAG_CFI;
+
+/// The magic at the start of the `CfiCache` file stored as CFI, see [`FLAG_CFI`].
+///
+/// `CfiCache` is defined in `symbolic-minidump`, which this crate does not depend on. This mirrors
+/// its `CFICACHE_MAGIC` and needs to be kept in sync with the `CfiCache` format.
+pub const CFI_MAGIC: u32 = u32::from_be_bytes(*b"CFIC");
+
+/// The only supported `CfiCache` version, which follows [`CFI_MAGIC`].
+///
+/// This mirrors `CFICACHE_LATEST_VERSION` of `symbolic-minidump`. Unversioned `CfiCache` files
+/// without the magic are not supported.
+pub const CFI_VERSION: u32 = 2;
+
 /// The magic file preamble as individual bytes.
 const SYMCACHE_MAGIC_BYTES: [u8; 4] = *b"SYMC";
 
@@ -48,9 +104,22 @@ pub struct Header {
     /// Total number of bytes used for string data.
     pub string_bytes: u32,
 
-    /// Some reserved space in the header for future extensions that would not require a
-    /// completely new parsing method.
-    pub _reserved: [u8; 16],
+    /// The offset added to all addresses stored in [`Function`]s and [`Range`]s.
+    ///
+    /// This allows to store addresses larger than `u32::MAX` as long as all of them fit into
+    /// a `u32` relative to this offset. This is always `0` in version 7.
+    pub range_offset: u64,
+    /// Flags describing the layout of the data, see [`FLAG_WIDE_ADDRESSES`], [`FLAG_COLUMNS`],
+    /// [`FLAG_REVERSE_INDEX`], [`FLAG_DEMANGLED_NAMES`], [`FLAG_CHECKSUM`] and [`FLAG_CFI`].
+    ///
+    /// This is always `0` in version 7.
+    pub flags: u32,
+
+    /// The CRC32 checksum of all data following the header, including the reverse index and call
+    /// frame information.
+    ///
+    /// This is only set if [`FLAG_CHECKSUM`] is present, and was reserved (zeroed) space before.
+    pub checksum: u32,
 }
 
 /// Serialized Function metadata in the SymCache.
@@ -61,10 +130,65 @@ pub struct Function {
     pub name_offset: u32,
     /// The compilation directory (reference to a [`String`]).
     pub comp_dir_offset: u32,
-    /// The first address covered by this function.
+    /// The first address covered by this function, relative to [`Header::range_offset`].
+    ///
+    /// This is `u32::MAX` for inlined functions.
     pub entry_pc: u32,
+    /// The size of the function in bytes, or `0` if unknown.
+    ///
+    /// This is always `0` for inlined functions.
+    pub size: u32,
+    /// The language of the function.
+    pub lang: u32,
+}
+
+/// Serialized Function metadata in version 7 SymCaches, which did not record function sizes.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[repr(C)]
+pub struct FunctionV7 {
+    /// The functions name (reference to a [`String`]).
+    pub name_offset: u32,
+    /// The compilation directory (reference to a [`String`]).
+    pub comp_dir_offset: u32,
+    /// The first address covered by this function, or `u32::MAX` for inlined functions.
+    pub entry_pc: u32,
+    /// The language of the function.
+    pub lang: u32,
+}
+
+/// Serialized Function metadata with a 64-bit entry address.
+///
+/// These are written instead of [`Function`]s if the address span of the SymCache does not fit
+/// into a `u32`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[repr(C)]
+pub struct WideFunction {
+    /// The first address covered by this function, or `u64::MAX` for inlined functions.
+    pub entry_pc: u64,
+    /// The size of the function in bytes, or `0` if unknown.
+    pub size: u64,
+    /// The functions name (reference to a [`String`]).
+    pub name_offset: u32,
+    /// The compilation directory (reference to a [`String`]).
+    pub comp_dir_offset: u32,
     /// The language of the function.
     pub lang: u32,
+    /// Explicit padding, always `0`.
+    pub _padding: u32,
+}
+
+/// The demangled names of a [`Function`], which are written when demangling at write time.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[repr(C)]
+pub struct DemangledName {
+    /// The demangled name without return type and parameters (reference to a [`String`]).
+    ///
+    /// This is `u32::MAX` if the name could not be demangled.
+    pub short_name_offset: u32,
+    /// The demangled name with the options chosen by the writer (reference to a [`String`]).
+    ///
+    /// This is `u32::MAX` if the name could not be demangled.
+    pub full_name_offset: u32,
 }
 
 /// Serialized File in the SymCache.
@@ -102,12 +226,72 @@ pub struct SourceLocation {
 
 /// A representation of a code range in the SymCache.
 ///
-/// We only save the start address, the end is implicitly given
-/// by the next range's start.
+/// We only save the start address relative to [`Header::range_offset`], the end is implicitly
+/// given by the next range's start.
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 #[repr(C)]
 pub struct Range(pub u32);
 
+/// A representation of a code range with a 64-bit start address.
+///
+/// These are written instead of [`Range`]s if the address span of the SymCache does not fit into
+/// a `u32`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[repr(C)]
+pub struct WideRange(pub u64);
+
+/// The header of the optional reverse index, which is aligned to 8 bytes after the string data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub struct ReverseIndexHeader {
+    /// Number of included [`FunctionEntry`]s.
+    pub num_function_entries: u32,
+    /// Number of included [`LineRange`]s.
+    pub num_line_ranges: u32,
+}
+
+/// An entry address of a function, including the entry addresses of inlined copies.
+///
+/// Function entries are sorted by the name of the function and then by address.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[repr(C)]
+pub struct FunctionEntry {
+    /// The absolute address at which the function or its inlined copy starts.
+    pub address: u64,
+    /// The function (reference to a [`Function`]).
+    pub function_idx: u32,
+    /// Explicit padding, always `0`.
+    pub _padding: u32,
+}
+
+/// A contiguous code range that maps to a line in a source file.
+///
+/// Line ranges are sorted by file, line and start address. A range maps to a line if any of its
+/// [`SourceLocation`]s does, including the call sites of inlined functions.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[repr(C)]
+pub struct LineRange {
+    /// The absolute start address of the range.
+    pub start: u64,
+    /// The absolute end address of the range, exclusive.
+    ///
+    /// This is `u64::MAX` if the range extends to the end of the address space.
+    pub end: u64,
+    /// The source file (reference to a [`File`]).
+    pub file_idx: u32,
+    /// The line number.
+    pub line: u32,
+}
+
+/// The header of the optional call frame information, which is aligned to 8 bytes after the
+/// string data or the reverse index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub struct CfiHeader {
+    /// Number of bytes of call frame information following this header.
+    pub num_bytes: u64,
+}
+
 /// Returns the amount left to add to the remainder to get 8 if
 /// `to_align` isn't a multiple of 8.
 pub fn align_to_eight(to_align: usize) -> usize {
@@ -128,11 +312,17 @@ mod tests {
     #[test]
     fn test_sizeof() {
         assert_eq!(mem::size_of::<Header>(), 80);
-        assert_eq!(mem::align_of::<Header>(), 4);
+        assert_eq!(mem::align_of::<Header>(), 8);
 
-        assert_eq!(mem::size_of::<Function>(), 16);
+        assert_eq!(mem::size_of::<Function>(), 20);
         assert_eq!(mem::align_of::<Function>(), 4);
 
+        assert_eq!(mem::size_of::<FunctionV7>(), 16);
+        assert_eq!(mem::align_of::<FunctionV7>(), 4);
+
+        assert_eq!(mem::size_of::<WideFunction>(), 32);
+        assert_eq!(mem::align_of::<WideFunction>(), 8);
+
         assert_eq!(mem::size_of::<File>(), 12);
         assert_eq!(mem::align_of::<File>(), 4);
 
@@ -141,5 +331,20 @@ mod tests {
 
         assert_eq!(mem::size_of::<Range>(), 4);
         assert_eq!(mem::align_of::<Range>(), 4);
+
+        assert_eq!(mem::size_of::<WideRange>(), 8);
+        assert_eq!(mem::align_of::<WideRange>(), 8);
+
+        assert_eq!(mem::size_of::<ReverseIndexHeader>(), 8);
+        assert_eq!(mem::align_of::<ReverseIndexHeader>(), 4);
+
+        assert_eq!(mem::size_of::<FunctionEntry>(), 16);
+        assert_eq!(mem::align_of::<FunctionEntry>(), 8);
+
+        assert_eq!(mem::size_of::<LineRange>(), 24);
+        assert_eq!(mem::align_of::<LineRange>(), 8);
+
+        assert_eq!(mem::size_of::<CfiHeader>(), 8);
+        assert_eq!(mem::align_of::<CfiHeader>(), 8);
     }
 }
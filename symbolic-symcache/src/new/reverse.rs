@@ -0,0 +1,123 @@
+use std::ops::Range;
+
+use super::{raw, Function, SymCache};
+
+impl<'data> SymCache<'data> {
+    /// Returns true if this SymCache contains a reverse index.
+    ///
+    /// Without a reverse index, [`lookup_function_name`](Self::lookup_function_name) and
+    /// [`lookup_file_line`](Self::lookup_file_line) never return any results. The reverse index
+    /// is written if requested via `SymCacheWriter::set_reverse_index`.
+    pub fn has_reverse_index(&self) -> bool {
+        self.header.version != raw::SYMCACHE_VERSION_V7
+            && self.header.flags & raw::FLAG_REVERSE_INDEX != 0
+    }
+
+    /// Looks up the entry addresses of all functions with the given name.
+    ///
+    /// This yields the entry address of the function itself, as well as the start addresses of all
+    /// of its inlined copies, sorted by address. The name has to match the possibly mangled name
+    /// stored in the SymCache exactly.
+    pub fn lookup_function_name(&self, name: &str) -> FunctionEntries<'data, '_> {
+        let function_name = |entry: &raw::FunctionEntry| {
+            self.get_function(entry.function_idx)
+                .and_then(|function| function.name())
+                .unwrap_or_default()
+        };
+
+        let entries = self.function_entries;
+        let start = entries.partition_point(|entry| function_name(entry) < name);
+        let len = entries[start..].partition_point(|entry| function_name(entry) == name);
+
+        FunctionEntries {
+            cache: self,
+            entries: entries[start..start + len].iter(),
+        }
+    }
+
+    /// Looks up all code ranges that map to the given line in a source file.
+    ///
+    /// The `path` is compared to the [full path](super::File::full_path) of the files in the
+    /// SymCache. A range maps to a line if the line appears anywhere in its inlining hierarchy, so
+    /// this includes the call sites of inlined functions. The returned ranges are sorted by their
+    /// start address.
+    pub fn lookup_file_line(&self, path: &str, line: u32) -> Vec<Range<u64>> {
+        let mut ranges = Vec::new();
+        if self.line_ranges.is_empty() {
+            return ranges;
+        }
+
+        for file_idx in 0..self.files.len() as u32 {
+            match self.get_file(file_idx) {
+                Some(file) if file.full_path() == path => (),
+                _ => continue,
+            }
+
+            let key = (file_idx, line);
+            let start = self
+                .line_ranges
+                .partition_point(|range| (range.file_idx, range.line) < key);
+            let matching = self.line_ranges[start..]
+                .iter()
+                .take_while(|range| (range.file_idx, range.line) == key);
+            ranges.extend(matching.map(|range| range.start..range.end));
+        }
+
+        ranges.sort_by_key(|range| range.start);
+        ranges
+    }
+}
+
+/// An entry address of a function found via [`SymCache::lookup_function_name`].
+#[derive(Clone, Debug)]
+pub struct FunctionEntry<'data> {
+    address: u64,
+    function: Function<'data>,
+}
+
+impl<'data> FunctionEntry<'data> {
+    /// The address at which the function or its inlined copy starts.
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// The function that starts at this address.
+    pub fn function(&self) -> &Function<'data> {
+        &self.function
+    }
+
+    /// Returns true if this is the start of an inlined copy of the function.
+    pub fn is_inlined(&self) -> bool {
+        self.function.entry_pc() == u64::MAX
+    }
+}
+
+/// An iterator over the entry addresses of a function.
+///
+/// Returned by [`SymCache::lookup_function_name`].
+#[derive(Clone, Debug)]
+pub struct FunctionEntries<'data, 'cache> {
+    cache: &'cache SymCache<'data>,
+    entries: std::slice::Iter<'data, raw::FunctionEntry>,
+}
+
+impl<'data, 'cache> Iterator for FunctionEntries<'data, 'cache> {
+    type Item = FunctionEntry<'data>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for entry in &mut self.entries {
+            if let Some(function) = self.cache.get_function(entry.function_idx) {
+                return Some(FunctionEntry {
+                    address: entry.address,
+                    function,
+                });
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.entries.len()))
+    }
+}
@@ -0,0 +1,712 @@
+//! Temporary storage for code ranges that exceed the range buffer of a
+//! [`SymCacheWriter`](super::SymCacheWriter).
+//!
+//! The converter collects code ranges in a map, which makes up the bulk of its memory usage for
+//! large debug files. Only these ranges and the line ranges of the reverse index are spilled; all
+//! other data of the converter stays in memory. Once the map grows beyond the range buffer size, it
+//! is written to a [`SpillStorage`] as a sorted run and cleared. During serialization, all runs are merged into a
+//! single sorted segment of resolved ranges, which is then read sequentially.
+//!
+//! Runs are applied in the order they were written, which reproduces the semantics of the
+//! in-memory map: Ranges are either *set*, overriding all previous values, or *weak*, which only
+//! applies if there is no previous value for the same address.
+
+use std::cmp::Reverse;
+use std::collections::{btree_map, BTreeMap, BTreeSet, BinaryHeap};
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::{mem, ptr, slice};
+
+use super::raw;
+
+/// A seekable storage for the intermediate data of a [`SymCacheWriter`](super::SymCacheWriter).
+///
+/// This is implemented for all types that implement [`Read`], [`Write`] and [`Seek`], such as a
+/// temporary [`File`](std::fs::File). The storage is written from its start, and its contents are
+/// no longer needed once the SymCache has been serialized.
+pub trait SpillStorage: Read + Write + Seek {}
+
+impl<T: Read + Write + Seek> SpillStorage for T {}
+
+/// A value in the map of code ranges.
+pub(crate) type RangeValue = (raw::SourceLocation, u32);
+
+/// Records that are written to a [`SpillFile`] in their in-memory representation.
+///
+/// # Safety
+///
+/// Implementors must be `#[repr(C)]` structs without padding bytes, which are valid for any bit
+/// pattern.
+pub(crate) unsafe trait Record: Clone {}
+
+/// A code range in a spilled run.
+#[derive(Clone, Debug)]
+#[repr(C)]
+pub(crate) struct RangeEntry {
+    address: u64,
+    location: raw::SourceLocation,
+    column: u32,
+    /// `1` if this range was only inserted if there was no previous range at its address.
+    weak: u32,
+}
+
+// SAFETY: Both types consist of integers only and have no padding.
+unsafe impl Record for RangeEntry {}
+unsafe impl Record for raw::LineRange {}
+
+/// A contiguous sequence of records in a [`SpillFile`].
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Segment {
+    offset: u64,
+    len: usize,
+}
+
+/// A [`SpillStorage`] that records are appended to.
+pub(crate) struct SpillFile {
+    storage: Box<dyn SpillStorage>,
+    end: u64,
+}
+
+impl fmt::Debug for SpillFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpillFile").field("end", &self.end).finish()
+    }
+}
+
+impl SpillFile {
+    fn new(storage: Box<dyn SpillStorage>) -> Self {
+        Self { storage, end: 0 }
+    }
+
+    /// Appends the records to the end of the storage.
+    fn append<T: Record>(&mut self, records: &[T]) -> io::Result<()> {
+        let len = mem::size_of_val(records);
+        // SAFETY: `Record`s have no padding, so all of their bytes are initialized.
+        let bytes = unsafe { slice::from_raw_parts(records.as_ptr() as *const u8, len) };
+
+        self.storage.seek(SeekFrom::Start(self.end))?;
+        self.storage.write_all(bytes)?;
+        self.end += len as u64;
+        Ok(())
+    }
+
+    /// Reads `count` records of the segment starting at `index` into the buffer.
+    fn read<T: Record>(
+        &mut self,
+        segment: &Segment,
+        index: usize,
+        count: usize,
+        buffer: &mut Vec<T>,
+    ) -> io::Result<()> {
+        let size = mem::size_of::<T>();
+        let mut bytes = vec![0; count * size];
+        let offset = segment.offset + (index * size) as u64;
+        self.storage.seek(SeekFrom::Start(offset))?;
+        self.storage.read_exact(&mut bytes)?;
+
+        buffer.clear();
+        buffer.extend(bytes.chunks_exact(size).map(|chunk| {
+            // SAFETY: `Record`s are valid for any bit pattern, and the chunk has the right size.
+            unsafe { ptr::read_unaligned(chunk.as_ptr() as *const T) }
+        }));
+        Ok(())
+    }
+
+    /// Reads a single record of the segment.
+    fn get<T: Record>(&mut self, segment: &Segment, index: usize) -> io::Result<T> {
+        let mut buffer = Vec::with_capacity(1);
+        self.read(segment, index, 1, &mut buffer)?;
+        Ok(buffer.remove(0))
+    }
+}
+
+/// Sequentially reads the records of a [`Segment`] in chunks.
+struct SegmentReader<T> {
+    segment: Segment,
+    /// The index of the first record that has not been read into the buffer.
+    position: usize,
+    buffer: Vec<T>,
+    buffer_position: usize,
+    chunk_len: usize,
+}
+
+impl<T: Record> SegmentReader<T> {
+    fn new(segment: Segment, chunk_len: usize) -> Self {
+        Self {
+            segment,
+            position: 0,
+            buffer: Vec::new(),
+            buffer_position: 0,
+            chunk_len: chunk_len.max(1),
+        }
+    }
+
+    fn peek(&mut self, file: &mut SpillFile) -> io::Result<Option<&T>> {
+        if self.buffer_position == self.buffer.len() && self.position < self.segment.len {
+            let count = self.chunk_len.min(self.segment.len - self.position);
+            file.read(&self.segment, self.position, count, &mut self.buffer)?;
+            self.position += count;
+            self.buffer_position = 0;
+        }
+
+        Ok(self.buffer.get(self.buffer_position))
+    }
+
+    fn next(&mut self, file: &mut SpillFile) -> io::Result<Option<T>> {
+        let record = self.peek(file)?.cloned();
+        if record.is_some() {
+            self.buffer_position += 1;
+        }
+        Ok(record)
+    }
+}
+
+/// Appends records to a new [`Segment`] in chunks.
+///
+/// Nothing else may be appended to the [`SpillFile`] until the writer is finished.
+struct SegmentWriter<T> {
+    segment: Segment,
+    buffer: Vec<T>,
+    chunk_len: usize,
+}
+
+impl<T: Record> SegmentWriter<T> {
+    fn new(file: &SpillFile, chunk_len: usize) -> Self {
+        Self {
+            segment: Segment {
+                offset: file.end,
+                len: 0,
+            },
+            buffer: Vec::new(),
+            chunk_len: chunk_len.max(1),
+        }
+    }
+
+    fn push(&mut self, file: &mut SpillFile, record: T) -> io::Result<()> {
+        self.buffer.push(record);
+        if self.buffer.len() >= self.chunk_len {
+            self.flush(file)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self, file: &mut SpillFile) -> io::Result<()> {
+        debug_assert_eq!(
+            file.end,
+            self.segment.offset + (self.segment.len * mem::size_of::<T>()) as u64
+        );
+        file.append(&self.buffer)?;
+        self.segment.len += self.buffer.len();
+        self.buffer.clear();
+        Ok(())
+    }
+
+    fn finish(mut self, file: &mut SpillFile) -> io::Result<Segment> {
+        self.flush(file)?;
+        Ok(self.segment)
+    }
+}
+
+/// Merges the records of multiple sorted [`Segment`]s by their key.
+///
+/// The next record of every reader is kept in a heap. If multiple readers have records with the
+/// same key, the one with the lowest index is returned first.
+struct SegmentMerger<T, K, F> {
+    readers: Vec<SegmentReader<T>>,
+    heap: BinaryHeap<Reverse<(K, usize)>>,
+    key: F,
+}
+
+impl<T, K, F> SegmentMerger<T, K, F>
+where
+    T: Record,
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    fn new(readers: Vec<SegmentReader<T>>, file: &mut SpillFile, key: F) -> io::Result<Self> {
+        let mut merger = Self {
+            heap: BinaryHeap::with_capacity(readers.len()),
+            readers,
+            key,
+        };
+        for index in 0..merger.readers.len() {
+            merger.push_next(index, file)?;
+        }
+        Ok(merger)
+    }
+
+    /// Adds the next record of the reader at `index` to the heap.
+    fn push_next(&mut self, index: usize, file: &mut SpillFile) -> io::Result<()> {
+        if let Some(record) = self.readers[index].peek(file)? {
+            self.heap.push(Reverse(((self.key)(record), index)));
+        }
+        Ok(())
+    }
+
+    /// Returns the record with the smallest key of all readers.
+    fn next(&mut self, file: &mut SpillFile) -> io::Result<Option<T>> {
+        let index = match self.heap.pop() {
+            Some(Reverse((_, index))) => index,
+            None => return Ok(None),
+        };
+
+        let record = self.readers[index].next(file)?;
+        self.push_next(index, file)?;
+        Ok(record)
+    }
+}
+
+/// A sorted run of spilled code ranges.
+#[derive(Debug)]
+struct Run {
+    segment: Segment,
+    first_addr: u64,
+    last_addr: u64,
+}
+
+/// The result of merging all runs.
+#[derive(Debug, Default)]
+pub(crate) struct ResolvedRanges {
+    segment: Segment,
+    /// Whether any of the ranges has a column number.
+    pub has_columns: bool,
+    /// The start address of the first range.
+    pub first_addr: Option<u64>,
+    /// The start address of the last range.
+    pub last_addr: Option<u64>,
+    /// The sorted addresses that were queried during resolution and have a range.
+    present: Vec<u64>,
+}
+
+impl ResolvedRanges {
+    /// The number of resolved ranges.
+    pub fn len(&self) -> usize {
+        self.segment.len
+    }
+
+    /// Returns true if there is a range at the given address.
+    ///
+    /// Only addresses passed to [`RangeSpill::resolve`] are known.
+    pub fn contains(&self, address: u64) -> bool {
+        self.present.binary_search(&address).is_ok()
+    }
+}
+
+/// Code ranges that have been spilled to a [`SpillStorage`].
+#[derive(Debug)]
+pub(crate) struct RangeSpill {
+    file: SpillFile,
+    buffer_size: usize,
+    runs: Vec<Run>,
+    /// The first error that occurred while spilling or looking up ranges, which is reported during
+    /// serialization.
+    error: Option<io::Error>,
+    resolved: Option<ResolvedRanges>,
+}
+
+impl RangeSpill {
+    pub fn new(storage: Box<dyn SpillStorage>, buffer_size: usize) -> Self {
+        Self {
+            file: SpillFile::new(storage),
+            buffer_size,
+            runs: Vec::new(),
+            error: None,
+            resolved: None,
+        }
+    }
+
+    /// The number of ranges that can be kept in memory before they are spilled.
+    ///
+    /// This is an estimate that accounts for the overhead of the map holding the ranges.
+    pub fn max_ranges(&self) -> usize {
+        (self.buffer_size / (2 * mem::size_of::<RangeEntry>())).max(1)
+    }
+
+    /// The number of records that are read or written at once, if the buffer is split into
+    /// `num_chunks` chunks.
+    fn chunk_len<T>(&self, num_chunks: usize) -> usize {
+        self.buffer_size / num_chunks.max(1) / mem::size_of::<T>()
+    }
+
+    /// Returns true if any ranges have been spilled.
+    pub fn has_runs(&self) -> bool {
+        !self.runs.is_empty()
+    }
+
+    /// Returns true if an error has occurred, after which no more ranges should be spilled.
+    pub fn has_error(&self) -> bool {
+        self.error.is_some()
+    }
+
+    /// Stores an error to be returned by [`take_error`](Self::take_error), unless there is one.
+    pub fn set_error(&mut self, error: io::Error) {
+        self.error.get_or_insert(error);
+    }
+
+    /// Returns the first error that occurred while spilling or looking up ranges.
+    pub fn take_error(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the ranges as a new run.
+    ///
+    /// `weak` contains the addresses of all ranges that only apply if there is no range at the same
+    /// address in a previous run. If writing fails, no run is added and the ranges need to be kept.
+    pub fn spill(
+        &mut self,
+        ranges: &BTreeMap<u64, RangeValue>,
+        weak: &BTreeSet<u64>,
+    ) -> io::Result<()> {
+        if ranges.is_empty() {
+            return Ok(());
+        }
+
+        let chunk_len = self.chunk_len::<RangeEntry>(1);
+        let mut writer = SegmentWriter::new(&self.file, chunk_len);
+        let result = ranges
+            .iter()
+            .try_for_each(|(&address, (location, column))| {
+                let entry = RangeEntry {
+                    address,
+                    location: location.clone(),
+                    column: *column,
+                    weak: weak.contains(&address) as u32,
+                };
+                writer.push(&mut self.file, entry)
+            });
+
+        let segment = result.and_then(|()| writer.finish(&mut self.file))?;
+        self.runs.push(Run {
+            segment,
+            first_addr: *ranges.keys().next().unwrap(),
+            last_addr: *ranges.keys().next_back().unwrap(),
+        });
+        Ok(())
+    }
+
+    /// Looks up the range at the given address in all runs.
+    pub fn lookup(&mut self, address: u64) -> Option<RangeValue> {
+        let mut value = None;
+        for run in &self.runs {
+            if address < run.first_addr || address > run.last_addr {
+                continue;
+            }
+
+            let entry = match find_range(&mut self.file, &run.segment, address) {
+                Ok(Some(entry)) => entry,
+                Ok(None) => continue,
+                Err(error) => {
+                    self.set_error(error);
+                    return None;
+                }
+            };
+
+            if entry.weak == 0 || value.is_none() {
+                value = Some((entry.location, entry.column));
+            }
+        }
+
+        value
+    }
+
+    /// Merges all runs into a single segment of resolved ranges.
+    ///
+    /// The returned ranges record whether there is a range at any of the `queries`, which need to
+    /// be sorted.
+    pub fn resolve(&mut self, queries: &[u64]) -> io::Result<&ResolvedRanges> {
+        self.take_error()?;
+
+        let chunk_len = self.chunk_len::<RangeEntry>(self.runs.len() + 1);
+        let readers = self
+            .runs
+            .iter()
+            .map(|run| SegmentReader::new(run.segment, chunk_len))
+            .collect();
+        let mut merger =
+            SegmentMerger::new(readers, &mut self.file, |entry: &RangeEntry| entry.address)?;
+        let mut writer = SegmentWriter::new(&self.file, chunk_len);
+        let mut resolved = ResolvedRanges::default();
+        let mut queries = queries.iter().peekable();
+
+        let mut current: Option<RangeEntry> = None;
+        loop {
+            let next = merger.next(&mut self.file)?;
+
+            let entry = match (current.take(), next) {
+                (Some(current_entry), Some(entry)) if current_entry.address == entry.address => {
+                    // Runs are merged in the order they were written, so later ranges override
+                    // earlier ones unless they are weak.
+                    current = Some(if entry.weak == 0 {
+                        entry
+                    } else {
+                        current_entry
+                    });
+                    continue;
+                }
+                (Some(current_entry), next) => {
+                    current = next;
+                    current_entry
+                }
+                (None, Some(entry)) => {
+                    current = Some(entry);
+                    continue;
+                }
+                (None, None) => break,
+            };
+
+            let address = entry.address;
+            while let Some(&&query) = queries.peek() {
+                if query > address {
+                    break;
+                }
+                if query == address {
+                    resolved.present.push(query);
+                }
+                queries.next();
+            }
+
+            resolved.has_columns |= entry.column != 0;
+            resolved.first_addr.get_or_insert(address);
+            resolved.last_addr = Some(address);
+            writer.push(&mut self.file, RangeEntry { weak: 0, ..entry })?;
+        }
+
+        resolved.segment = writer.finish(&mut self.file)?;
+        self.runs.clear();
+        Ok(self.resolved.insert(resolved))
+    }
+
+    /// Returns the resolved ranges after [`resolve`](Self::resolve) has been called.
+    pub fn resolved(&self) -> Option<&ResolvedRanges> {
+        self.resolved.as_ref()
+    }
+
+    /// The number of line ranges that can be kept in memory before they are spilled.
+    fn max_line_ranges(&self) -> usize {
+        (self.buffer_size / mem::size_of::<raw::LineRange>()).max(1)
+    }
+}
+
+/// Iterates over resolved and in-memory code ranges in the order of their addresses.
+pub(crate) struct RangeIter<'a> {
+    spilled: Option<SegmentReader<RangeEntry>>,
+    ranges: std::iter::Peekable<btree_map::Iter<'a, u64, RangeValue>>,
+}
+
+impl<'a> RangeIter<'a> {
+    /// Creates an iterator over the given ranges and the resolved ranges of the spill.
+    ///
+    /// The given ranges must not contain any of the resolved addresses.
+    pub fn new(ranges: &'a BTreeMap<u64, RangeValue>, spill: &Option<RangeSpill>) -> Self {
+        let spilled = spill.as_ref().and_then(|spill| {
+            let resolved = spill.resolved.as_ref()?;
+            let chunk_len = spill.chunk_len::<RangeEntry>(2);
+            Some(SegmentReader::new(resolved.segment, chunk_len))
+        });
+
+        Self {
+            spilled,
+            ranges: ranges.iter().peekable(),
+        }
+    }
+
+    /// Returns the next range, reading resolved ranges from the spill.
+    pub fn next(
+        &mut self,
+        spill: &mut Option<RangeSpill>,
+    ) -> io::Result<Option<(u64, RangeValue)>> {
+        let spilled = match (&mut self.spilled, spill) {
+            (Some(reader), Some(spill)) => Some((reader, &mut spill.file)),
+            _ => None,
+        };
+
+        let (reader, file) = match spilled {
+            Some(spilled) => spilled,
+            None => return Ok(self.next_in_memory()),
+        };
+
+        let spilled_addr = reader.peek(file)?.map(|entry| entry.address);
+        match (spilled_addr, self.ranges.peek()) {
+            (Some(spilled_addr), Some((&address, _))) if address < spilled_addr => {
+                Ok(self.next_in_memory())
+            }
+            (Some(_), _) => {
+                let entry = reader.next(file)?.unwrap();
+                Ok(Some((entry.address, (entry.location, entry.column))))
+            }
+            (None, _) => Ok(self.next_in_memory()),
+        }
+    }
+
+    fn next_in_memory(&mut self) -> Option<(u64, RangeValue)> {
+        self.ranges
+            .next()
+            .map(|(&address, value)| (address, value.clone()))
+    }
+}
+
+/// Sorts [`raw::LineRange`]s and merges adjacent ranges of the same line.
+#[derive(Debug, Default)]
+pub(crate) struct LineRangeSorter {
+    buffer: Vec<raw::LineRange>,
+    runs: Vec<Segment>,
+}
+
+impl LineRangeSorter {
+    fn key(range: &raw::LineRange) -> (u32, u32, u64) {
+        (range.file_idx, range.line, range.start)
+    }
+
+    /// Adds a line range, spilling sorted runs if the spill's buffer size is exceeded.
+    pub fn push(
+        &mut self,
+        spill: &mut Option<RangeSpill>,
+        range: raw::LineRange,
+    ) -> io::Result<()> {
+        self.buffer.push(range);
+        match spill {
+            Some(spill) if self.buffer.len() >= spill.max_line_ranges() => self.spill(spill),
+            _ => Ok(()),
+        }
+    }
+
+    fn spill(&mut self, spill: &mut RangeSpill) -> io::Result<()> {
+        self.buffer.sort_by_key(Self::key);
+        let segment = Segment {
+            offset: spill.file.end,
+            len: self.buffer.len(),
+        };
+        spill.file.append(&self.buffer)?;
+        self.runs.push(segment);
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Returns all line ranges sorted by file, line and start address.
+    ///
+    /// Adjacent or overlapping ranges of the same line are merged.
+    pub fn finish(mut self, spill: &mut Option<RangeSpill>) -> io::Result<LineRanges> {
+        let spill = match spill {
+            Some(spill) if !self.runs.is_empty() => spill,
+            _ => {
+                self.buffer.sort_by_key(Self::key);
+                let mut merged: Vec<raw::LineRange> = Vec::with_capacity(self.buffer.len());
+                for range in self.buffer {
+                    if let Some(last) = merged.last_mut() {
+                        if merge_line_range(last, &range) {
+                            continue;
+                        }
+                    }
+                    merged.push(range);
+                }
+                return Ok(LineRanges::Memory(merged));
+            }
+        };
+
+        if !self.buffer.is_empty() {
+            self.spill(spill)?;
+        }
+
+        let chunk_len = spill.chunk_len::<raw::LineRange>(self.runs.len() + 1);
+        let readers = self
+            .runs
+            .iter()
+            .map(|segment| SegmentReader::new(*segment, chunk_len))
+            .collect();
+        let mut merger = SegmentMerger::new(readers, &mut spill.file, Self::key)?;
+        let mut writer = SegmentWriter::new(&spill.file, chunk_len);
+
+        let mut last: Option<raw::LineRange> = None;
+        while let Some(range) = merger.next(&mut spill.file)? {
+            if let Some(ref mut last) = last {
+                if merge_line_range(last, &range) {
+                    continue;
+                }
+            }
+            if let Some(last) = last.replace(range) {
+                writer.push(&mut spill.file, last)?;
+            }
+        }
+        if let Some(last) = last {
+            writer.push(&mut spill.file, last)?;
+        }
+
+        Ok(LineRanges::Spilled(writer.finish(&mut spill.file)?))
+    }
+}
+
+/// Extends `last` by `range` if both cover the same line and are adjacent or overlapping.
+fn merge_line_range(last: &mut raw::LineRange, range: &raw::LineRange) -> bool {
+    if (last.file_idx, last.line) == (range.file_idx, range.line) && range.start <= last.end {
+        last.end = last.end.max(range.end);
+        true
+    } else {
+        false
+    }
+}
+
+/// Sorted and merged line ranges, see [`LineRangeSorter::finish`].
+#[derive(Debug)]
+pub(crate) enum LineRanges {
+    Memory(Vec<raw::LineRange>),
+    Spilled(Segment),
+}
+
+impl LineRanges {
+    /// The number of line ranges.
+    pub fn len(&self) -> usize {
+        match self {
+            LineRanges::Memory(ranges) => ranges.len(),
+            LineRanges::Spilled(segment) => segment.len,
+        }
+    }
+
+    /// Calls `f` with consecutive chunks of all line ranges.
+    pub fn for_each_chunk<F>(&self, spill: &mut Option<RangeSpill>, mut f: F) -> io::Result<()>
+    where
+        F: FnMut(&[raw::LineRange]) -> io::Result<()>,
+    {
+        let (segment, spill) = match (self, spill) {
+            (LineRanges::Memory(ranges), _) => return f(ranges),
+            (LineRanges::Spilled(segment), Some(spill)) => (segment, spill),
+            (LineRanges::Spilled(_), None) => unreachable!("spilled line ranges without a spill"),
+        };
+
+        let chunk_len = spill.chunk_len::<raw::LineRange>(1).max(1);
+        let mut buffer = Vec::new();
+        let mut index = 0;
+        while index < segment.len {
+            let count = chunk_len.min(segment.len - index);
+            spill.file.read(segment, index, count, &mut buffer)?;
+            f(&buffer)?;
+            index += count;
+        }
+
+        Ok(())
+    }
+}
+
+/// Finds the range at the given address in a sorted run.
+fn find_range(
+    file: &mut SpillFile,
+    segment: &Segment,
+    address: u64,
+) -> io::Result<Option<RangeEntry>> {
+    let (mut low, mut high) = (0, segment.len);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        let entry: RangeEntry = file.get(segment, mid)?;
+        if entry.address == address {
+            return Ok(Some(entry));
+        } else if entry.address < address {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    Ok(None)
+}
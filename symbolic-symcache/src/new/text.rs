@@ -0,0 +1,587 @@
+//! A textual representation of SymCaches.
+//!
+//! The text format lists the contents of a SymCache in a form that can be read, diffed and written
+//! by hand. It is written by [`SymCache::write_text`] and can be converted back into a SymCache with
+//! [`SymCacheWriter::process_text`](super::SymCacheWriter::process_text).
+//!
+//! # Format
+//!
+//! The text consists of a header followed by four sections, each starting with a line containing
+//! only the name of the section. Every following line describes one record. Empty lines and lines
+//! starting with `#` are ignored:
+//!
+//! ```text
+//! SYMCACHE 8
+//! DEBUG_ID c0bcc3f1-9827-fe65-3058-404b2831d9e6
+//! ARCH x86_64
+//!
+//! FILES
+//! # index path_name directory comp_dir
+//! 0 "main.c" "src" "/home/user/project"
+//!
+//! FUNCTIONS
+//! # index entry_pc size language name comp_dir
+//! 0 0x1000 0x40 c "main" "/home/user/project"
+//! 1 - 0x0 c "inlined_helper" "/home/user/project"
+//!
+//! LOCATIONS
+//! # index file line column function inlined_into
+//! 0 0 12 5 0 -
+//!
+//! RANGES
+//! # address file line column function inlined_into
+//! 0x1000 0 10 1 0 -
+//! 0x1010 0 3 9 1 0
+//! 0x1040 -
+//! ```
+//!
+//! - `FILES` lists source files with their path name, directory and compilation directory.
+//! - `FUNCTIONS` lists functions with their entry address and size. Inlined functions do not have
+//!   an entry address. The language is given by its [name](symbolic_common::Language::name).
+//! - `LOCATIONS` lists the source locations that functions have been inlined into. They are
+//!   referenced by the `inlined_into` column of other locations and ranges.
+//! - `RANGES` maps the start address of each code range to a source location, given by its file,
+//!   line, column, function and the location it is inlined into. A range extends to the start of
+//!   the next range. A range without source location is written as `-`, which ends the previous
+//!   range.
+//!
+//! Records are referenced by their index, which is given in the first column and must count up
+//! from `0`. Locations can only be inlined into previous locations. Strings are quoted, with `\\`,
+//! `\"`, `\n`, `\r` and `\t` as escape sequences, and missing values are written as `-`. Numbers
+//! are either decimal or hexadecimal with a `0x` prefix. The version in the header is informational,
+//! text is always converted into the current version of the format.
+//!
+//! # Lossiness
+//!
+//! The text format only covers the records listed above, so converting a SymCache to text and back
+//! loses the following information:
+//!
+//! - Demangled names. They are recomputed if demangling is enabled on the writer.
+//! - The checksum, which is only written if enabled on the writer.
+//! - Call frame information.
+//! - The reverse index. It is rebuilt from the entry addresses of functions if requested via
+//!   [`SymCacheWriter::set_reverse_index`](super::SymCacheWriter::set_reverse_index), which does
+//!   not include the entry addresses of inlined copies.
+//!
+//! The text format is therefore meant for inspecting and editing SymCaches, not for copying or
+//! upgrading them.
+
+use std::io;
+
+use symbolic_common::{Arch, DebugId, Language};
+
+use super::{raw, SymCache};
+use crate::{SymCacheError, SymCacheErrorKind};
+
+impl<'data> SymCache<'data> {
+    /// Writes the contents of this SymCache in the [text format](super::text).
+    ///
+    /// The text format is lossy, see [its documentation](super::text#lossiness) for the information
+    /// that is not written.
+    pub fn write_text<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "SYMCACHE {}", self.version())?;
+        writeln!(writer, "DEBUG_ID {}", self.debug_id())?;
+        writeln!(writer, "ARCH {}", self.arch())?;
+
+        writeln!(writer, "\nFILES")?;
+        for (idx, file) in self.files.iter().enumerate() {
+            writeln!(
+                writer,
+                "{} {} {} {}",
+                idx,
+                Quoted(self.get_string(file.path_name_offset)),
+                Quoted(self.get_string(file.directory_offset)),
+                Quoted(self.get_string(file.comp_dir_offset)),
+            )?;
+        }
+
+        writeln!(writer, "\nFUNCTIONS")?;
+        for idx in 0..self.functions.len() {
+            let function = self.functions.get(idx).unwrap();
+            let entry_pc = match function.entry_pc {
+                u64::MAX => None,
+                entry_pc => Some(entry_pc.saturating_add(self.range_offset)),
+            };
+            let language = match function.lang {
+                u32::MAX => None,
+                lang => Some(Language::from_u32(lang).name()),
+            };
+
+            writeln!(
+                writer,
+                "{} {} {:#x} {} {} {}",
+                idx,
+                Hex(entry_pc),
+                function.size,
+                Opt(language),
+                Quoted(self.get_string(function.name_offset)),
+                Quoted(self.get_string(function.comp_dir_offset)),
+            )?;
+        }
+
+        // The last `num_ranges` source locations belong to the ranges, all others are the
+        // locations that other source locations are inlined into.
+        let num_locations = self
+            .source_locations
+            .len()
+            .saturating_sub(self.ranges.len());
+
+        writeln!(writer, "\nLOCATIONS")?;
+        for idx in 0..num_locations {
+            write!(writer, "{} ", idx)?;
+            self.write_location(&mut writer, idx)?;
+        }
+
+        writeln!(writer, "\nRANGES")?;
+        for idx in 0..self.ranges.len() {
+            let address = self
+                .ranges
+                .get(idx)
+                .unwrap()
+                .saturating_add(self.range_offset);
+            write!(writer, "{:#x} ", address)?;
+            self.write_location(&mut writer, num_locations + idx)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_location<W: io::Write>(&self, writer: &mut W, idx: usize) -> io::Result<()> {
+        let location = match self.source_locations.get(idx) {
+            Some(location) if *location != raw::NO_SOURCE_LOCATION => location,
+            _ => return writeln!(writer, "-"),
+        };
+
+        writeln!(
+            writer,
+            "{} {} {} {} {}",
+            Index(location.file_idx),
+            location.line,
+            self.columns.get(idx).copied().unwrap_or(0),
+            Index(location.function_idx),
+            Index(location.inlined_into_idx),
+        )
+    }
+}
+
+/// Formats an optional string in quotes, or `-` if it is missing.
+struct Quoted<'a>(Option<&'a str>);
+
+impl std::fmt::Display for Quoted<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let string = match self.0 {
+            Some(string) => string,
+            None => return f.write_str("-"),
+        };
+
+        f.write_str("\"")?;
+        for c in string.chars() {
+            match c {
+                '\\' => f.write_str("\\\\")?,
+                '"' => f.write_str("\\\"")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                c => write!(f, "{}", c)?,
+            }
+        }
+        f.write_str("\"")
+    }
+}
+
+/// Formats an optional value, or `-` if it is missing.
+struct Opt<T>(Option<T>);
+
+impl<T: std::fmt::Display> std::fmt::Display for Opt<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(ref value) => value.fmt(f),
+            None => f.write_str("-"),
+        }
+    }
+}
+
+/// Formats an optional address in hexadecimal, or `-` if it is missing.
+struct Hex(Option<u64>);
+
+impl std::fmt::Display for Hex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(value) => write!(f, "{:#x}", value),
+            None => f.write_str("-"),
+        }
+    }
+}
+
+/// Formats an index, or `-` if it is `u32::MAX`.
+struct Index(u32);
+
+impl std::fmt::Display for Index {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            u32::MAX => f.write_str("-"),
+            idx => idx.fmt(f),
+        }
+    }
+}
+
+/// A source file parsed from the text format.
+#[derive(Debug)]
+pub(crate) struct TextFile {
+    pub path_name: Option<String>,
+    pub directory: Option<String>,
+    pub comp_dir: Option<String>,
+}
+
+/// A function parsed from the text format.
+#[derive(Debug)]
+pub(crate) struct TextFunction {
+    pub entry_pc: u64,
+    pub size: u64,
+    pub lang: u32,
+    pub name: Option<String>,
+    pub comp_dir: Option<String>,
+}
+
+/// A SymCache parsed from the text format.
+///
+/// All indices have been checked to refer to existing records, and locations are only inlined into
+/// previous locations.
+#[derive(Debug)]
+pub(crate) struct TextSymCache {
+    pub debug_id: DebugId,
+    pub arch: Arch,
+    pub files: Vec<TextFile>,
+    pub functions: Vec<TextFunction>,
+    pub locations: Vec<(raw::SourceLocation, u32)>,
+    pub ranges: Vec<(u64, (raw::SourceLocation, u32))>,
+}
+
+impl TextSymCache {
+    /// Collects the records of a binary SymCache, as they would be written in the text format.
+    ///
+    /// The SymCache is validated first, and locations must only be inlined into previous locations,
+    /// which holds for all SymCaches written by a [`SymCacheWriter`](super::SymCacheWriter).
+    pub fn from_symcache(cache: &SymCache<'_>) -> Result<Self, SymCacheError> {
+        cache
+            .validate()
+            .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadCacheFile, e))?;
+
+        let string = |offset| cache.get_string(offset).map(str::to_owned);
+
+        let files = cache
+            .files
+            .iter()
+            .map(|file| TextFile {
+                path_name: string(file.path_name_offset),
+                directory: string(file.directory_offset),
+                comp_dir: string(file.comp_dir_offset),
+            })
+            .collect();
+
+        let functions = (0..cache.functions.len())
+            .filter_map(|idx| cache.functions.get(idx))
+            .map(|function| TextFunction {
+                entry_pc: match function.entry_pc {
+                    u64::MAX => u64::MAX,
+                    entry_pc => entry_pc.saturating_add(cache.range_offset),
+                },
+                size: function.size,
+                lang: function.lang,
+                name: string(function.name_offset),
+                comp_dir: string(function.comp_dir_offset),
+            })
+            .collect();
+
+        let num_locations = cache.source_locations.len() - cache.ranges.len();
+        let location = |idx: usize, max_inlined_into_idx: usize| {
+            let location = cache.source_locations[idx].clone();
+            let column = cache.columns.get(idx).copied().unwrap_or(0);
+            match location.inlined_into_idx {
+                u32::MAX => Ok((location, column)),
+                inlined_into_idx if (inlined_into_idx as usize) < max_inlined_into_idx => {
+                    Ok((location, column))
+                }
+                _ => Err(SymCacheError::new(
+                    SymCacheErrorKind::BadCacheFile,
+                    "source location is inlined into a later location",
+                )),
+            }
+        };
+
+        let locations = (0..num_locations)
+            .map(|idx| location(idx, idx))
+            .collect::<Result<_, _>>()?;
+
+        let ranges = (0..cache.ranges.len())
+            .map(|idx| {
+                let address = cache
+                    .ranges
+                    .get(idx)
+                    .unwrap()
+                    .saturating_add(cache.range_offset);
+                let location = location(num_locations + idx, num_locations)?;
+                Ok((address, location))
+            })
+            .collect::<Result<_, SymCacheError>>()?;
+
+        Ok(Self {
+            debug_id: cache.debug_id(),
+            arch: cache.arch(),
+            files,
+            functions,
+            locations,
+            ranges,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Section {
+    Header,
+    Files,
+    Functions,
+    Locations,
+    Ranges,
+}
+
+/// Parses a SymCache in the [text format](super::text).
+pub(crate) fn parse(text: &str) -> Result<TextSymCache, SymCacheError> {
+    let mut cache = TextSymCache {
+        debug_id: DebugId::default(),
+        arch: Arch::Unknown,
+        files: Vec::new(),
+        functions: Vec::new(),
+        locations: Vec::new(),
+        ranges: Vec::new(),
+    };
+
+    let mut section = Section::Header;
+    for (line_idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let next_section = match line {
+            "FILES" => Some(Section::Files),
+            "FUNCTIONS" => Some(Section::Functions),
+            "LOCATIONS" => Some(Section::Locations),
+            "RANGES" => Some(Section::Ranges),
+            _ => None,
+        };
+        if let Some(next_section) = next_section {
+            section = next_section;
+            continue;
+        }
+
+        let mut tokens = Tokens { line };
+        parse_record(&mut cache, section, &mut tokens)
+            .and_then(|()| tokens.finish())
+            .map_err(|message| {
+                let message = format!("line {}: {}", line_idx + 1, message);
+                SymCacheError::new(SymCacheErrorKind::BadTextFormat, message)
+            })?;
+    }
+
+    Ok(cache)
+}
+
+fn parse_record(
+    cache: &mut TextSymCache,
+    section: Section,
+    tokens: &mut Tokens<'_>,
+) -> Result<(), String> {
+    match section {
+        Section::Header => match tokens.word()? {
+            "SYMCACHE" => {
+                tokens.number::<u32>()?;
+            }
+            "DEBUG_ID" => {
+                let debug_id = tokens.word()?;
+                cache.debug_id = debug_id
+                    .parse()
+                    .map_err(|_| format!("invalid debug id `{}`", debug_id))?;
+            }
+            "ARCH" => {
+                let arch = tokens.word()?;
+                cache.arch = arch
+                    .parse()
+                    .map_err(|_| format!("invalid architecture `{}`", arch))?;
+            }
+            word => return Err(format!("unexpected `{}`", word)),
+        },
+        Section::Files => {
+            tokens.record_index(cache.files.len())?;
+            cache.files.push(TextFile {
+                path_name: tokens.string()?,
+                directory: tokens.string()?,
+                comp_dir: tokens.string()?,
+            });
+        }
+        Section::Functions => {
+            tokens.record_index(cache.functions.len())?;
+            let entry_pc = tokens.optional_number()?.unwrap_or(u64::MAX);
+            let size = tokens.number()?;
+            let lang = match tokens.word()? {
+                "-" => u32::MAX,
+                lang => lang
+                    .parse::<Language>()
+                    .map_err(|_| format!("invalid language `{}`", lang))?
+                    as u32,
+            };
+            cache.functions.push(TextFunction {
+                entry_pc,
+                size,
+                lang,
+                name: tokens.string()?,
+                comp_dir: tokens.string()?,
+            });
+        }
+        Section::Locations => {
+            tokens.record_index(cache.locations.len())?;
+            match parse_location(cache, tokens)? {
+                Some(location) => cache.locations.push(location),
+                None => return Err("missing source location".into()),
+            }
+        }
+        Section::Ranges => {
+            let address = tokens.number()?;
+            if matches!(cache.ranges.last(), Some(&(last, _)) if last >= address) {
+                return Err(format!("range {:#x} is not in ascending order", address));
+            }
+            let location = parse_location(cache, tokens)?.unwrap_or((raw::NO_SOURCE_LOCATION, 0));
+            cache.ranges.push((address, location));
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a source location, which is `None` if written as a single `-`.
+fn parse_location(
+    cache: &TextSymCache,
+    tokens: &mut Tokens<'_>,
+) -> Result<Option<(raw::SourceLocation, u32)>, String> {
+    let file_idx = tokens.index("file", cache.files.len())?;
+    if file_idx == u32::MAX && tokens.is_empty() {
+        return Ok(None);
+    }
+
+    let line = tokens.number()?;
+    let column = tokens.number()?;
+    let function_idx = tokens.index("function", cache.functions.len())?;
+    let inlined_into_idx = tokens.index("location", cache.locations.len())?;
+
+    let location = raw::SourceLocation {
+        file_idx,
+        line,
+        function_idx,
+        inlined_into_idx,
+    };
+    Ok(Some((location, column)))
+}
+
+/// Splits a line of the text format into whitespace separated tokens.
+struct Tokens<'a> {
+    line: &'a str,
+}
+
+impl<'a> Tokens<'a> {
+    fn is_empty(&self) -> bool {
+        self.line.is_empty()
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        match self.line {
+            "" => Ok(()),
+            rest => Err(format!("unexpected `{}`", rest)),
+        }
+    }
+
+    fn word(&mut self) -> Result<&'a str, String> {
+        if self.line.is_empty() {
+            return Err("unexpected end of line".into());
+        }
+
+        let end = self
+            .line
+            .find(char::is_whitespace)
+            .unwrap_or(self.line.len());
+        let (word, rest) = self.line.split_at(end);
+        self.line = rest.trim_start();
+        Ok(word)
+    }
+
+    fn number<T: std::convert::TryFrom<u64>>(&mut self) -> Result<T, String> {
+        let word = self.word()?;
+        let number = match word.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => word.parse(),
+        };
+
+        number
+            .ok()
+            .and_then(|number| T::try_from(number).ok())
+            .ok_or_else(|| format!("invalid number `{}`", word))
+    }
+
+    fn optional_number(&mut self) -> Result<Option<u64>, String> {
+        if self.line.starts_with('-') {
+            self.word()?;
+            return Ok(None);
+        }
+
+        self.number().map(Some)
+    }
+
+    /// Parses the index of a record, which must be the next one in its section.
+    fn record_index(&mut self, expected: usize) -> Result<(), String> {
+        let idx: usize = self.number()?;
+        if idx != expected {
+            return Err(format!("expected index {}, got {}", expected, idx));
+        }
+        Ok(())
+    }
+
+    /// Parses a reference to one of `len` records, which is `u32::MAX` if written as `-`.
+    fn index(&mut self, kind: &str, len: usize) -> Result<u32, String> {
+        match self.optional_number()? {
+            None => Ok(u32::MAX),
+            Some(idx) if idx < len as u64 => Ok(idx as u32),
+            Some(idx) => Err(format!("{} {} does not exist", kind, idx)),
+        }
+    }
+
+    fn string(&mut self) -> Result<Option<String>, String> {
+        if !self.line.starts_with('"') {
+            return match self.word()? {
+                "-" => Ok(None),
+                word => Err(format!("expected a quoted string, got `{}`", word)),
+            };
+        }
+
+        let mut string = String::new();
+        let mut chars = self.line[1..].char_indices();
+        while let Some((idx, c)) = chars.next() {
+            let c = match c {
+                '"' => {
+                    self.line = self.line[idx + 2..].trim_start();
+                    return Ok(Some(string));
+                }
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => '\n',
+                    Some((_, 'r')) => '\r',
+                    Some((_, 't')) => '\t',
+                    Some((_, c @ '\\')) | Some((_, c @ '"')) => c,
+                    Some((_, c)) => return Err(format!("invalid escape sequence `\\{}`", c)),
+                    None => break,
+                },
+                c => c,
+            };
+            string.push(c);
+        }
+
+        Err("unterminated string".into())
+    }
+}
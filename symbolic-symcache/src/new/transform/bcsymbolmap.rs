@@ -40,6 +40,7 @@ impl Transformer for BcSymbolMap<'_> {
                     .map(|dir| map_cow!(dir, |s| self.resolve(s))),
             },
             line: sl.line,
+            column: sl.column,
         }
     }
 }
@@ -3,6 +3,9 @@
 mod bcsymbolmap;
 pub use bcsymbolmap::*;
 
+mod prefix_map;
+pub use prefix_map::*;
+
 #[cfg(feature = "il2cpp")]
 pub mod il2cpp;
 
@@ -28,13 +31,34 @@ pub struct File<'s> {
     pub comp_dir: Option<Cow<'s, str>>,
 }
 
-/// A Source Location (File + Line) to be written to the SymCache.
+/// A Source Location (File + Line + Column) to be written to the SymCache.
 #[non_exhaustive]
 pub struct SourceLocation<'s> {
     /// The [`File`] part of this [`SourceLocation`].
     pub file: File<'s>,
     /// The line number.
     pub line: u32,
+    /// The column number, or `0` if unknown.
+    pub column: u32,
+}
+
+/// The decision of a [`Transformer`] whether a [`Function`] is written to the SymCache.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum FunctionFilter {
+    /// The function is written to the SymCache.
+    Keep,
+    /// An inlined function is removed from the inline chain, and its inlinees are attributed to
+    /// its caller instead.
+    ///
+    /// Lookups within the inlined function return the call site in its caller. This has the same
+    /// effect as [`Keep`](Self::Keep) for functions that are not inlined.
+    Collapse,
+    /// The function is removed along with all of its inlinees.
+    ///
+    /// Lookups within an inlined function return the call site in its caller. Lookups within a
+    /// function that is not inlined return nothing, even if there is a symbol for it.
+    Drop,
 }
 
 /// A transformer that is applied to each [`Function`] and [`SourceLocation`] record in the SymCache.
@@ -53,12 +77,62 @@ pub trait Transformer {
     fn transform_source_location<'f>(&'f self, sl: SourceLocation<'f>) -> SourceLocation<'f> {
         sl
     }
+
+    /// Decides whether a [`Function`] is written to the SymCache.
+    ///
+    /// This is called after all transformers have transformed the function. If transformers
+    /// disagree, [`Drop`](FunctionFilter::Drop) takes precedence over
+    /// [`Collapse`](FunctionFilter::Collapse), which takes precedence over
+    /// [`Keep`](FunctionFilter::Keep).
+    fn filter_function(&self, f: &Function<'_>) -> FunctionFilter {
+        let _ = f;
+        FunctionFilter::Keep
+    }
+
+    /// Decides whether a [`SourceLocation`] is written to the SymCache.
+    ///
+    /// This is called after all transformers have transformed the source location, and it is
+    /// written only if all transformers keep it. Lookups within a removed source location of an
+    /// inlined function return the call site in its caller, like for a collapsed function.
+    /// Otherwise, they return the function without file and line information.
+    fn filter_source_location(&self, sl: &SourceLocation<'_>) -> bool {
+        let _ = sl;
+        true
+    }
 }
 
 // This is essentially just a newtype in order to implement `Debug`.
 #[derive(Default)]
 pub(crate) struct Transformers(pub Vec<Box<dyn Transformer>>);
 
+impl Transformers {
+    /// Transforms a [`Function`] with all transformers and decides whether it is written.
+    pub fn function<'f>(&'f self, mut f: Function<'f>) -> (Function<'f>, FunctionFilter) {
+        for transformer in &self.0 {
+            f = transformer.transform_function(f);
+        }
+
+        let filter = self
+            .0
+            .iter()
+            .map(|transformer| transformer.filter_function(&f))
+            .max()
+            .unwrap_or(FunctionFilter::Keep);
+
+        (f, filter)
+    }
+
+    /// Transforms a [`SourceLocation`] with all transformers and returns it if it is written.
+    pub fn source_location<'f>(&'f self, mut sl: SourceLocation<'f>) -> Option<SourceLocation<'f>> {
+        for transformer in &self.0 {
+            sl = transformer.transform_source_location(sl);
+        }
+
+        let keep = self.0.iter().all(|t| t.filter_source_location(&sl));
+        Some(sl).filter(|_| keep)
+    }
+}
+
 impl std::fmt::Debug for Transformers {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let len = self.0.len();
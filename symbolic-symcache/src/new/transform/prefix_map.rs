@@ -0,0 +1,197 @@
+//! Remaps path prefixes of files and compilation directories before writing them to a SymCache.
+
+use std::borrow::Cow;
+
+use symbolic_common::clean_path;
+
+use super::{File, Function, SourceLocation, Transformer};
+
+/// A [`Transformer`] that rewrites path prefixes, similar to `-fdebug-prefix-map`.
+///
+/// Debug information usually contains absolute paths of the machine that built the code, such as
+/// `/home/buildbot/work/1234/src/main.c` or `C:\agent\_work\1\s\src\main.c`. This replaces such
+/// prefixes with stable paths, for instance relative to the repository root.
+///
+/// Prefixes are compared to the full path of each file, which is joined from its compilation
+/// directory, directory and name. Both paths and prefixes are cleaned with [`clean_path`] before,
+/// `/` and `\` are treated as the same separator, and Windows drive letters match regardless of
+/// their case. A prefix only matches entire path components,
+/// and mappings are tried in the order they were added, so more specific prefixes should be added
+/// first. The remaining path is joined to the replacement with the separator used by the
+/// replacement, which is `/` unless the replacement is a Windows path.
+///
+/// Files with a remapped path are written without compilation directory, and the directory and
+/// name are split from the remapped path. Paths without a matching prefix are only cleaned.
+///
+/// # Example
+///
+/// ```
+/// use symbolic_symcache::transform::PathPrefixMap;
+///
+/// let mut prefix_map = PathPrefixMap::new();
+/// prefix_map.add_mapping("/home/buildbot/work/1234", "");
+/// prefix_map.add_mapping("C:\\agent\\_work\\1\\s", "");
+///
+/// assert_eq!(
+///     prefix_map.remap("/home/buildbot/work/1234/src/main.c"),
+///     "src/main.c"
+/// );
+/// assert_eq!(prefix_map.remap("C:\\agent\\_work\\1\\s\\src\\main.c"), "src/main.c");
+/// assert_eq!(prefix_map.remap("c:\\agent\\_work\\1\\s\\src\\main.c"), "src/main.c");
+/// assert_eq!(prefix_map.remap("/usr/include/stdio.h"), "/usr/include/stdio.h");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PathPrefixMap {
+    mappings: Vec<(String, String)>,
+}
+
+impl PathPrefixMap {
+    /// Creates an empty `PathPrefixMap`, which only cleans paths.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a mapping that replaces the path prefix `from` with `to`.
+    ///
+    /// An empty `to` removes the prefix, which makes the remaining path relative.
+    pub fn add_mapping<F, T>(&mut self, from: F, to: T)
+    where
+        F: AsRef<str>,
+        T: AsRef<str>,
+    {
+        let from = clean_path(from.as_ref()).into_owned();
+        let to = clean_path(to.as_ref()).into_owned();
+        self.mappings.push((from, to));
+    }
+
+    /// Cleans the given path and replaces the first matching prefix.
+    pub fn remap<'p>(&self, path: &'p str) -> Cow<'p, str> {
+        match self.remap_prefix(path) {
+            Some(remapped) => Cow::Owned(remapped),
+            None => clean_path(path),
+        }
+    }
+
+    /// Replaces the first matching prefix of the cleaned path, if any.
+    fn remap_prefix(&self, path: &str) -> Option<String> {
+        let path = clean_path(path);
+
+        self.mappings.iter().find_map(|(from, to)| {
+            let rest = strip_path_prefix(&path, from)?;
+            let separator = if is_windows_path(to) { '\\' } else { '/' };
+
+            let mut remapped = to.clone();
+            for component in rest.split(is_separator).filter(|c| !c.is_empty()) {
+                if !remapped.is_empty() && !remapped.ends_with(is_separator) {
+                    remapped.push(separator);
+                }
+                remapped.push_str(component);
+            }
+
+            Some(remapped)
+        })
+    }
+
+    /// Remaps a directory, returning `None` if nothing remains of it.
+    fn remap_dir(&self, dir: &str) -> Option<String> {
+        let dir = self
+            .remap_prefix(dir)
+            .unwrap_or_else(|| clean_path(dir).into_owned());
+
+        Some(dir).filter(|dir| !dir.is_empty())
+    }
+}
+
+impl Transformer for PathPrefixMap {
+    fn transform_function<'f>(&'f self, f: Function<'f>) -> Function<'f> {
+        Function {
+            name: f.name,
+            comp_dir: f
+                .comp_dir
+                .and_then(|dir| self.remap_dir(&dir))
+                .map(Cow::Owned),
+        }
+    }
+
+    fn transform_source_location<'f>(&'f self, sl: SourceLocation<'f>) -> SourceLocation<'f> {
+        let File {
+            name,
+            directory,
+            comp_dir,
+        } = sl.file;
+
+        let comp_dir_ref = comp_dir.as_deref().unwrap_or_default();
+        let directory_ref = directory.as_deref().unwrap_or_default();
+        let dir = symbolic_common::join_path(comp_dir_ref, directory_ref);
+        let full_path = symbolic_common::join_path(&dir, &name);
+
+        let file = match self.remap_prefix(&full_path) {
+            Some(remapped) => {
+                let (directory, name) = symbolic_common::split_path(&remapped);
+                File {
+                    name: Cow::Owned(name.to_owned()),
+                    directory: directory.map(|dir| Cow::Owned(dir.to_owned())),
+                    comp_dir: None,
+                }
+            }
+            // the compilation directory is remapped on its own, since an absolute directory
+            // overrides it in the full path.
+            None => File {
+                name: Cow::Owned(clean_path(&name).into_owned()),
+                directory: directory.map(|dir| Cow::Owned(clean_path(&dir).into_owned())),
+                comp_dir: comp_dir
+                    .and_then(|dir| self.remap_dir(&dir))
+                    .map(Cow::Owned),
+            },
+        };
+
+        SourceLocation {
+            file,
+            line: sl.line,
+            column: sl.column,
+        }
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, '/' | '\\')
+}
+
+fn has_drive_letter(path: &str) -> bool {
+    matches!(path.as_bytes(), [drive, b':', ..] if drive.is_ascii_alphabetic())
+}
+
+fn is_windows_path(path: &str) -> bool {
+    path.contains('\\') || has_drive_letter(path)
+}
+
+/// Returns the rest of `path` if it starts with the components of `prefix`.
+fn strip_path_prefix<'p>(path: &'p str, prefix: &str) -> Option<&'p str> {
+    let mut path_chars = path.char_indices();
+    let has_drive = has_drive_letter(path) && has_drive_letter(prefix);
+
+    for (index, prefix_char) in prefix.chars().enumerate() {
+        let (_, path_char) = path_chars.next()?;
+        let matches = if is_separator(prefix_char) {
+            is_separator(path_char)
+        } else if index == 0 && has_drive {
+            path_char.eq_ignore_ascii_case(&prefix_char)
+        } else {
+            path_char == prefix_char
+        };
+
+        if !matches {
+            return None;
+        }
+    }
+
+    let rest = match path_chars.next() {
+        None => "",
+        Some((index, c)) if is_separator(c) => &path[index..],
+        // the prefix ends within a path component, unless it ends with a separator itself
+        Some((index, _)) if prefix.ends_with(is_separator) => &path[index..],
+        Some(_) => return None,
+    };
+
+    Some(rest)
+}
@@ -0,0 +1,327 @@
+//! Upgrades SymCaches of previous versions to the current format.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use symbolic_common::{Name, NameMangling};
+use symbolic_debuginfo::{FileInfo, Function, LineInfo, Symbol};
+
+use super::raw;
+use super::writer::SymCacheConverter;
+use crate::{old, SymCacheError};
+
+/// A summary of a SymCache that was upgraded to the current format.
+///
+/// Upgrading is best effort. Records that cannot be read are skipped and counted here, so that a
+/// caller can decide whether to keep the upgraded SymCache.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UpgradeReport {
+    /// The version of the SymCache before upgrading.
+    pub version: u32,
+    /// The number of functions that were upgraded, including inlined functions.
+    pub functions: usize,
+    /// The number of functions that could not be read, or whose parent could not be read. These
+    /// are skipped along with all of their inlinees.
+    pub skipped_functions: usize,
+    /// The number of line records that could not be read. These are skipped along with all
+    /// following line records of the same function.
+    pub skipped_lines: usize,
+    /// The number of line records referring to a file that could not be read. These are upgraded
+    /// without file.
+    pub lines_without_file: usize,
+    /// The number of top-level functions without a known size. Functions with line records are
+    /// upgraded with a size reaching to the next function, and all others are upgraded as symbols.
+    pub unsized_functions: usize,
+}
+
+impl UpgradeReport {
+    /// Returns true if all information of the SymCache was upgraded.
+    pub fn is_complete(&self) -> bool {
+        self.skipped_functions == 0 && self.skipped_lines == 0 && self.lines_without_file == 0
+    }
+}
+
+/// A function record of a legacy SymCache along with the ids of its inlinees.
+struct LegacyFunction<'a> {
+    function: old::Function<'a>,
+    inlinees: Vec<usize>,
+}
+
+impl SymCacheConverter {
+    /// Processes a SymCache in the legacy format of versions 1 to 6.
+    ///
+    /// Function records are converted back into functions with inlinees, which are added like
+    /// functions from debug information, including all transformers. Unknown function sizes are
+    /// inferred from the next function.
+    pub(crate) fn process_legacy(
+        &mut self,
+        cache: &old::SymCache<'_>,
+    ) -> Result<UpgradeReport, SymCacheError> {
+        self.merge_object_info(cache.arch(), cache.debug_id())?;
+
+        let mut report = UpgradeReport {
+            version: cache.version(),
+            ..UpgradeReport::default()
+        };
+
+        let mut functions = Vec::new();
+        for function in cache.functions() {
+            match function {
+                Ok(function) => functions.push(LegacyFunction {
+                    function,
+                    inlinees: Vec::new(),
+                }),
+                // the remaining records cannot be located after a bad record.
+                Err(_) => {
+                    report.skipped_functions += 1;
+                    break;
+                }
+            }
+        }
+
+        // parents always precede their inlinees, so this also skips inlinees of skipped functions.
+        let mut valid = vec![true; functions.len()];
+        let mut roots = Vec::new();
+        for id in 0..functions.len() {
+            if !functions[id].function.has_valid_range() {
+                valid[id] = false;
+                report.skipped_functions += 1;
+                continue;
+            }
+
+            match functions[id].function.parent_id() {
+                None => roots.push(id),
+                Some(parent_id) if parent_id < id && valid[parent_id] => {
+                    functions[parent_id].inlinees.push(id)
+                }
+                Some(_) => {
+                    valid[id] = false;
+                    report.skipped_functions += 1;
+                }
+            }
+        }
+
+        let mut ends = Vec::new();
+        let mut symbols = BTreeSet::new();
+        for (index, &id) in roots.iter().enumerate() {
+            let legacy = &functions[id].function;
+            let address = legacy.address();
+            let size = match legacy.size() {
+                Some(size) => size,
+                None => {
+                    report.unsized_functions += 1;
+                    let next_address = roots[index + 1..]
+                        .iter()
+                        .map(|&next| functions[next].function.address())
+                        .find(|&next| next > address);
+
+                    match next_address {
+                        Some(next_address) if functions[id].has_lines() => next_address - address,
+                        _ => {
+                            report.functions += 1;
+                            symbols.insert(address);
+                            self.process_symbolic_symbol(&Symbol {
+                                name: Some(legacy.symbol().into()),
+                                address,
+                                size: 0,
+                            });
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            let end = match address.checked_add(size) {
+                Some(end) => end,
+                None => {
+                    report.skipped_functions += 1;
+                    continue;
+                }
+            };
+
+            let function = build_function(cache, &functions, id, end, &mut report);
+            self.process_symbolic_function(&function);
+            ends.push(function.end_address());
+        }
+
+        // legacy functions end where their size says, even if the next function starts later.
+        let starts: BTreeSet<_> = roots
+            .iter()
+            .map(|&id| functions[id].function.address())
+            .collect();
+        for end in ends {
+            if !starts.contains(&end) && !symbols.contains(&end) {
+                self.insert_range_if_vacant(end, (raw::NO_SOURCE_LOCATION, 0));
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+impl LegacyFunction<'_> {
+    /// Returns true if the function has at least one readable line record.
+    fn has_lines(&self) -> bool {
+        matches!(self.function.lines().next(), Some(Ok(_)))
+    }
+}
+
+/// Converts the legacy function with the given id and all of its inlinees into a [`Function`].
+///
+/// Legacy SymCaches only store where line records start, and lookups resolve an address to the
+/// inlinee or caller with the closest line record before it. Instead of reimplementing this, the
+/// legacy lookup is run at every address where a line record or function starts or ends, and its
+/// frames become the line records of the converted functions. This also inserts line records of
+/// callers at every line record of their inlinees, which the converter expects.
+fn build_function<'a>(
+    cache: &old::SymCache<'a>,
+    functions: &[LegacyFunction<'a>],
+    root_id: usize,
+    end: u64,
+    report: &mut UpgradeReport,
+) -> Function<'a> {
+    let address = functions[root_id].function.address();
+
+    let mut boundaries = BTreeSet::new();
+    boundaries.insert(address);
+    let mut pending = vec![root_id];
+    while let Some(id) = pending.pop() {
+        let legacy = &functions[id].function;
+        boundaries.insert(legacy.address());
+        boundaries.extend(
+            legacy
+                .size()
+                .and_then(|size| legacy.address().checked_add(size)),
+        );
+
+        for line in legacy.lines() {
+            let address = line.map(|line| (legacy.address().checked_add(line.address()), line));
+            match address {
+                Ok((Some(address), line)) => {
+                    if !line.has_file() {
+                        report.lines_without_file += 1;
+                    }
+                    boundaries.insert(address);
+                }
+                Ok((None, _)) | Err(_) => {
+                    report.skipped_lines += 1;
+                    break;
+                }
+            }
+        }
+
+        pending.extend_from_slice(&functions[id].inlinees);
+    }
+
+    let mut lines = BTreeMap::<usize, Vec<LineInfo<'a>>>::new();
+    let mut previous_frames = Vec::new();
+    for boundary in boundaries.range(address..end) {
+        let frames = match lookup_frames(cache, *boundary) {
+            Some(frames) => frames,
+            None => continue,
+        };
+
+        // skip addresses that resolve to an overlapping function, or that change nothing.
+        if frames.last().map(|&(id, _)| id) != Some(root_id)
+            || same_frames(&frames, &previous_frames)
+        {
+            continue;
+        }
+
+        for &(id, ref line) in &frames {
+            lines.entry(id).or_default().push(LineInfo {
+                address: *boundary,
+                ..line.clone()
+            });
+        }
+
+        previous_frames = frames;
+    }
+
+    assemble_function(functions, root_id, end, false, &mut lines, report)
+}
+
+/// Looks up the frames of an address in a legacy SymCache along with the ids of their functions.
+///
+/// Returns `None` if the lookup fails, or the address does not resolve to any function.
+fn lookup_frames<'a>(
+    cache: &old::SymCache<'a>,
+    address: u64,
+) -> Option<Vec<(usize, LineInfo<'a>)>> {
+    let mut lookup = cache.lookup(address).ok()?;
+    let mut frames = Vec::new();
+
+    while let Some(id) = lookup.next_id() {
+        let line_info = lookup.next()?.ok()?;
+        frames.push((
+            id,
+            LineInfo {
+                address,
+                size: None,
+                file: FileInfo {
+                    name: line_info.filename().as_bytes(),
+                    dir: line_info.base_dir().as_bytes(),
+                },
+                line: line_info.line().into(),
+                column: 0,
+            },
+        ));
+    }
+
+    Some(frames).filter(|frames| !frames.is_empty())
+}
+
+/// Returns true if both lookups resolved to the same functions and lines.
+fn same_frames(frames: &[(usize, LineInfo<'_>)], other: &[(usize, LineInfo<'_>)]) -> bool {
+    frames.len() == other.len()
+        && frames
+            .iter()
+            .zip(other)
+            .all(|((id, line), (other_id, other_line))| {
+                id == other_id && line.line == other_line.line && line.file == other_line.file
+            })
+}
+
+/// Creates the [`Function`] with the given id from its previously resolved line records.
+fn assemble_function<'a>(
+    functions: &[LegacyFunction<'a>],
+    id: usize,
+    end: u64,
+    inline: bool,
+    lines: &mut BTreeMap<usize, Vec<LineInfo<'a>>>,
+    report: &mut UpgradeReport,
+) -> Function<'a> {
+    let legacy = &functions[id].function;
+    let address = legacy.address();
+    let end = match legacy.size() {
+        Some(size) => address.saturating_add(size),
+        None => end.max(address.saturating_add(1)),
+    };
+
+    report.functions += 1;
+
+    let mut function_lines = lines.remove(&id).unwrap_or_default();
+    for index in 0..function_lines.len() {
+        let next_address = function_lines
+            .get(index + 1)
+            .map_or(end, |next| next.address);
+        function_lines[index].size =
+            Some(next_address.saturating_sub(function_lines[index].address));
+    }
+
+    let inlinees = functions[id]
+        .inlinees
+        .iter()
+        .map(|&inlinee| assemble_function(functions, inlinee, end, true, lines, report))
+        .collect();
+
+    Function {
+        address,
+        size: end - address,
+        name: Name::new(legacy.symbol(), NameMangling::Unknown, legacy.language()),
+        compilation_dir: legacy.compilation_dir().as_bytes(),
+        lines: function_lines,
+        inlinees,
+        inline,
+    }
+}
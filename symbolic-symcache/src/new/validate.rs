@@ -0,0 +1,176 @@
+//! Integrity checks of SymCaches.
+
+use super::{raw, Error, Result, SymCache};
+
+impl<'data> SymCache<'data> {
+    /// Checks the integrity of all data in the SymCache.
+    ///
+    /// [`SymCache::parse`] only checks the header and the size of the buffer. Lookups in a
+    /// corrupted SymCache might return wrong results, or never end if a source location is inlined
+    /// into itself. This checks that:
+    ///  - the checksum matches the data, if the SymCache was written with one,
+    ///  - all references to strings, files, functions and source locations are in bounds,
+    ///  - no source location is directly or indirectly inlined into itself,
+    ///  - all ranges are sorted by their start address, and every range has a source location,
+    ///  - there is a demangled name for every function, and only for functions with a name,
+    ///  - the reverse index is sorted the way its lookups expect.
+    ///
+    /// Since this reads the entire SymCache, it should be called once when loading a SymCache from
+    /// an untrusted source rather than before every lookup.
+    pub fn validate(&self) -> Result<()> {
+        if self.has_checksum() && crc32fast::hash(self.data) != self.header.checksum {
+            return Err(Error::ChecksumMismatch);
+        }
+
+        for file in self.files {
+            self.check_string(file.comp_dir_offset)?;
+            self.check_string(file.directory_offset)?;
+            self.check_string(file.path_name_offset)?;
+        }
+
+        for idx in 0..self.functions.len() {
+            if let Some(function) = self.functions.get(idx) {
+                self.check_string(function.name_offset)?;
+                self.check_string(function.comp_dir_offset)?;
+            }
+        }
+
+        if !self.demangled_names.is_empty() && self.demangled_names.len() != self.functions.len() {
+            return Err(Error::InvalidDemangledNames);
+        }
+
+        for (idx, demangled_name) in self.demangled_names.iter().enumerate() {
+            self.check_string(demangled_name.short_name_offset)?;
+            self.check_string(demangled_name.full_name_offset)?;
+
+            let has_name = matches!(self.functions.get(idx), Some(function) if function.name_offset != u32::MAX);
+            let has_demangled_name = demangled_name.short_name_offset != u32::MAX
+                || demangled_name.full_name_offset != u32::MAX;
+            if has_demangled_name && !has_name {
+                return Err(Error::InvalidDemangledNames);
+            }
+        }
+
+        for source_location in self.source_locations {
+            let file_idx = source_location.file_idx;
+            check_index(file_idx, self.files.len(), Error::InvalidFileIndex)?;
+            let function_idx = source_location.function_idx;
+            check_index(
+                function_idx,
+                self.functions.len(),
+                Error::InvalidFunctionIndex,
+            )?;
+            let inlined_into_idx = source_location.inlined_into_idx;
+            let num_source_locations = self.source_locations.len();
+            check_index(
+                inlined_into_idx,
+                num_source_locations,
+                Error::InvalidSourceLocationIndex,
+            )?;
+        }
+
+        self.check_inline_chains()?;
+
+        // Lookups map the ranges to the last source locations.
+        if self.ranges.len() > self.source_locations.len() {
+            return Err(Error::BadFormatLength);
+        }
+
+        for idx in 1..self.ranges.len() {
+            if self.ranges.get(idx - 1) >= self.ranges.get(idx) {
+                return Err(Error::UnsortedRanges);
+            }
+        }
+
+        for entry in self.function_entries {
+            check_index(
+                entry.function_idx,
+                self.functions.len(),
+                Error::InvalidFunctionIndex,
+            )?;
+        }
+
+        let function_entry_key = |entry: &raw::FunctionEntry| {
+            let name = self
+                .get_function(entry.function_idx)
+                .and_then(|function| function.name())
+                .unwrap_or_default();
+            (name, entry.address)
+        };
+        for pair in self.function_entries.windows(2) {
+            if function_entry_key(&pair[0]) > function_entry_key(&pair[1]) {
+                return Err(Error::UnsortedFunctionEntries);
+            }
+        }
+
+        for line_range in self.line_ranges {
+            check_index(
+                line_range.file_idx,
+                self.files.len(),
+                Error::InvalidFileIndex,
+            )?;
+        }
+
+        let line_range_key = |range: &raw::LineRange| (range.file_idx, range.line, range.start);
+        for pair in self.line_ranges.windows(2) {
+            if line_range_key(&pair[0]) > line_range_key(&pair[1]) {
+                return Err(Error::UnsortedLineRanges);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the header contains a checksum of all data following it.
+    pub(crate) fn has_checksum(&self) -> bool {
+        self.header.version != raw::SYMCACHE_VERSION_V7
+            && self.header.flags & raw::FLAG_CHECKSUM != 0
+    }
+
+    /// Checks that a string reference is either absent or points to a valid string.
+    fn check_string(&self, offset: u32) -> Result<()> {
+        if offset != u32::MAX && self.get_string(offset).is_none() {
+            return Err(Error::InvalidStringOffset);
+        }
+
+        Ok(())
+    }
+
+    /// Checks that following the callers of any source location ends eventually.
+    ///
+    /// All references to source locations must be in bounds.
+    fn check_inline_chains(&self) -> Result<()> {
+        // Every source location is marked with the first chain that reached it. Chains stop at
+        // source locations marked by a previous chain, which are known to end.
+        let mut marks = vec![0; self.source_locations.len()];
+
+        for start in 0..self.source_locations.len() {
+            let mark = start + 1;
+            let mut idx = start;
+
+            loop {
+                match marks[idx] {
+                    0 => marks[idx] = mark,
+                    m if m == mark => return Err(Error::CyclicInlineChain),
+                    _ => break,
+                }
+
+                match self.source_locations[idx].inlined_into_idx {
+                    u32::MAX => break,
+                    inlined_into_idx => idx = inlined_into_idx as usize,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks that an index is either absent or smaller than `len`, returning `error` otherwise.
+fn check_index(idx: u32, len: usize, error: Error) -> Result<()> {
+    if idx != u32::MAX && idx as usize >= len {
+        return Err(error);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,215 @@
+//! Verification of SymCaches against the debug information they were created from.
+
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use symbolic_debuginfo::{DebugSession, Function, LineInfo, ObjectLike};
+
+use super::SymCache;
+use crate::{SymCacheError, SymCacheErrorKind};
+
+/// A frame resolved for an address, as compared by [`SymCache::verify`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerificationFrame {
+    /// The name of the function, if known.
+    pub function: Option<String>,
+    /// The full path of the source file, if known.
+    pub file: Option<String>,
+    /// The line number, or `0` if there is no line information.
+    pub line: u32,
+}
+
+/// An address at which a SymCache resolves to different frames than the debug information.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerificationMismatch {
+    /// The address that was looked up.
+    pub address: u64,
+    /// The frames according to the debug information, starting with the innermost inlinee.
+    pub expected: Vec<VerificationFrame>,
+    /// The frames returned by [`SymCache::lookup`], starting with the innermost inlinee.
+    pub actual: Vec<VerificationFrame>,
+}
+
+/// The result of verifying a SymCache with [`SymCache::verify`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct VerificationReport {
+    /// The number of top-level functions in the debug information that were checked.
+    pub functions: usize,
+    /// The number of addresses that were looked up.
+    pub addresses: usize,
+    /// All addresses at which the SymCache returned different frames.
+    pub mismatches: Vec<VerificationMismatch>,
+}
+
+impl VerificationReport {
+    /// Returns true if the SymCache resolved all addresses like the debug information.
+    pub fn is_ok(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl<'data> SymCache<'data> {
+    /// Verifies that lookups in this SymCache return the same frames as the debug information of
+    /// the given object.
+    ///
+    /// For every function in the debug session of the object, this looks up the start address of
+    /// the function and of every line record of the function and its inlinees. The name, full
+    /// file path and line of every frame, including the callers of inlinees, are compared to the
+    /// debug information. This allows to check that SymCaches written by a different version of
+    /// this crate, or upgraded from a previous format, still resolve addresses correctly.
+    ///
+    /// The SymCache must have been written from the same object without transformers, which
+    /// change frames on purpose. It is [validated](Self::validate) before any lookup.
+    pub fn verify<'d, 'o, O>(&self, object: &'o O) -> Result<VerificationReport, SymCacheError>
+    where
+        O: ObjectLike<'d, 'o>,
+        O::Error: std::error::Error + Send + Sync + 'static,
+    {
+        self.validate()?;
+
+        let session = object
+            .debug_session()
+            .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+
+        let mut report = VerificationReport::default();
+        for function in session.functions() {
+            let function =
+                function.map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+
+            // the writer skips empty functions
+            if function.size == 0 {
+                continue;
+            }
+
+            report.functions += 1;
+
+            let mut addresses = BTreeSet::new();
+            addresses.insert(function.address);
+            collect_line_addresses(&function, &mut addresses);
+
+            let tree = FunctionTree::new(&function);
+            for address in addresses.range(function.address..function.end_address()) {
+                report.addresses += 1;
+
+                let mut expected = Vec::new();
+                tree.collect_frames(*address, &mut expected);
+                let actual: Vec<_> = self
+                    .lookup(*address)
+                    .map(|location| VerificationFrame {
+                        function: location
+                            .function()
+                            .and_then(|function| function.name())
+                            .map(String::from),
+                        file: location.file().map(|file| file.full_path()),
+                        line: location.line(),
+                    })
+                    .collect();
+
+                if actual != expected {
+                    report.mismatches.push(VerificationMismatch {
+                        address: *address,
+                        expected,
+                        actual,
+                    });
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// Collects the start addresses of all line records of a function and its inlinees.
+fn collect_line_addresses(function: &Function<'_>, addresses: &mut BTreeSet<u64>) {
+    addresses.extend(function.lines.iter().map(|line| line.address));
+    for inlinee in &function.inlinees {
+        collect_line_addresses(inlinee, addresses);
+    }
+}
+
+/// A function with its line records sorted by address and the code covered by its line records
+/// and those of its inlinees, to resolve frames without scanning all line records per address.
+struct FunctionTree<'a, 'data> {
+    function: &'a Function<'data>,
+    /// The line records of the function, sorted by address.
+    lines: Vec<&'a LineInfo<'data>>,
+    /// Sorted, disjoint address ranges covered by the function and its inlinees.
+    covered: Vec<Range<u64>>,
+    inlinees: Vec<FunctionTree<'a, 'data>>,
+}
+
+impl<'a, 'data> FunctionTree<'a, 'data> {
+    fn new(function: &'a Function<'data>) -> Self {
+        let inlinees: Vec<_> = function.inlinees.iter().map(FunctionTree::new).collect();
+
+        let mut lines: Vec<_> = function.lines.iter().collect();
+        lines.sort_by_key(|line| line.address);
+
+        let mut ranges: Vec<_> = lines
+            .iter()
+            .map(|line| {
+                let size = line.size.unwrap_or(1).max(1);
+                line.address..line.address.saturating_add(size)
+            })
+            .chain(inlinees.iter().flat_map(|inlinee| inlinee.covered.clone()))
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+
+        // Merges overlapping and adjacent ranges in a single sweep over the sorted ranges.
+        let mut covered: Vec<Range<u64>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match covered.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => covered.push(range),
+            }
+        }
+
+        Self {
+            function,
+            lines,
+            covered,
+            inlinees,
+        }
+    }
+
+    /// Returns true if a line record of the function or one of its inlinees covers the address.
+    fn covers(&self, address: u64) -> bool {
+        let idx = self.covered.partition_point(|range| range.end <= address);
+        matches!(self.covered.get(idx), Some(range) if range.start <= address)
+    }
+
+    /// Collects the frames of the function at the given address, starting with the innermost
+    /// inlinee.
+    ///
+    /// Lines are resolved like the SymCache writer does: every line record extends to the next
+    /// line record of the same function.
+    fn collect_frames(&self, address: u64, frames: &mut Vec<VerificationFrame>) {
+        if let Some(inlinee) = self.inlinees.iter().find(|inlinee| inlinee.covers(address)) {
+            inlinee.collect_frames(address, frames);
+        }
+
+        let function = self.function;
+        let name = Some(function.name.as_str().to_owned());
+        let comp_dir = std::str::from_utf8(function.compilation_dir).unwrap_or_default();
+        let num_lines = self.lines.partition_point(|line| line.address <= address);
+        let line = num_lines.checked_sub(1).map(|idx| self.lines[idx]);
+
+        frames.push(match line {
+            Some(line) => {
+                let prefix = symbolic_common::join_path(comp_dir, &line.file.dir_str());
+                let path = symbolic_common::join_path(&prefix, &line.file.name_str());
+                VerificationFrame {
+                    function: name,
+                    file: Some(symbolic_common::clean_path(&path).into_owned()),
+                    line: line.line as u32,
+                }
+            }
+            None => VerificationFrame {
+                function: name,
+                file: None,
+                line: 0,
+            },
+        });
+    }
+}
@@ -2,20 +2,26 @@
 
 #[cfg(feature = "il2cpp")]
 use std::borrow::Cow;
-use std::collections::btree_map;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::convert::{TryFrom, TryInto};
 use std::io::Write;
 
 use indexmap::IndexSet;
 use symbolic_common::{Arch, DebugId};
 use symbolic_debuginfo::{DebugSession, Function, ObjectLike, Symbol};
 
-#[cfg(feature = "il2cpp")]
+#[cfg(any(feature = "il2cpp", feature = "demangle"))]
 use symbolic_common::Language;
+#[cfg(feature = "demangle")]
+use symbolic_common::{Name, NameMangling};
+#[cfg(feature = "demangle")]
+use symbolic_demangle::{Demangle, DemangleOptions};
+
 #[cfg(feature = "il2cpp")]
 use symbolic_il2cpp::usym::{UsymSourceRecord, UsymSymbols};
 
-use super::{raw, transform};
+use super::spill::{LineRangeSorter, LineRanges, RangeIter, RangeSpill, RangeValue};
+use super::{check_cfi, raw, text, transform, SpillStorage, SymCache};
 use crate::{SymCacheError, SymCacheErrorKind};
 
 /// The SymCache Converter.
@@ -38,22 +44,68 @@ pub struct SymCacheConverter {
     strings: HashMap<String, u32>,
     /// The set of all [`raw::File`]s that have been added to this `Converter`.
     files: IndexSet<raw::File>,
-    /// The set of all [`raw::Function`]s that have been added to this `Converter`.
-    functions: IndexSet<raw::Function>,
+    /// The set of all functions that have been added to this `Converter`.
+    ///
+    /// Functions are collected with 64-bit entry addresses and narrowed to [`raw::Function`]s
+    /// during serialization if all addresses fit.
+    functions: IndexSet<raw::WideFunction>,
     /// The set of all [`raw::SourceLocation`]s that have been added to this `Converter` and that
-    /// aren't directly associated with a code range.
-    source_locations: IndexSet<raw::SourceLocation>,
-    /// A map from code ranges to the [`raw::SourceLocation`]s they correspond to.
+    /// aren't directly associated with a code range, along with their column numbers.
+    source_locations: IndexSet<(raw::SourceLocation, u32)>,
+    /// A map from code ranges to the [`raw::SourceLocation`]s they correspond to, along with
+    /// their column numbers.
     ///
     /// Only the starting address of a range is saved, the end address is given implicitly
     /// by the start address of the next range.
-    ranges: BTreeMap<u32, raw::SourceLocation>,
+    ranges: BTreeMap<u64, RangeValue>,
+    /// Storage for code ranges that exceed the range buffer, see
+    /// [`set_spill_storage`](Self::set_spill_storage).
+    spill: Option<RangeSpill>,
+    /// The addresses of ranges in `ranges` that were only inserted because there was no range at
+    /// their address. These only apply if no spilled range exists at the same address.
+    weak_ranges: BTreeSet<u64>,
 
     /// This is highest addr that we know is outside of a valid function.
     /// Functions have an explicit end, while Symbols implicitly extend to infinity.
     /// In case the highest addr belongs to a Symbol, this will be `None` and the SymCache
     /// also extends to infinite, otherwise this is the end of the highest function.
-    last_addr: Option<u32>,
+    last_addr: Option<u64>,
+
+    /// Whether a reverse index is written after the string data.
+    reverse_index: bool,
+    /// Whether a checksum of all data is written to the header.
+    checksum: bool,
+    /// The call frame information written after all other data.
+    cfi: Option<Vec<u8>>,
+    /// The options for demangling function names when writing, if enabled.
+    #[cfg(feature = "demangle")]
+    demangle_options: Option<DemangleOptions>,
+    /// Demangled names copied from other SymCaches by function index, which are written unless
+    /// demangling is enabled.
+    demangled_names: HashMap<u32, raw::DemangledName>,
+    /// The entry addresses of all functions and their inlined copies, as pairs of function index
+    /// and address. These are only written as part of the reverse index.
+    function_entries: Vec<(u32, u64)>,
+
+    /// Whether an object has been processed, which all further objects need to be compatible to.
+    has_object: bool,
+    /// The code ranges covered by functions from debug information, as a map from start to end
+    /// address. Symbols within these ranges are ignored.
+    function_ranges: BTreeMap<u64, u64>,
+    /// Symbols that are added during serialization, once all functions from debug information are
+    /// known.
+    symbols: Vec<PendingSymbol>,
+}
+
+/// A symbol that has not been added to the ranges yet.
+///
+/// The name is copied, since symbols need to outlive the object they were read from until
+/// serialization.
+#[derive(Debug)]
+struct PendingSymbol {
+    address: u64,
+    size: u64,
+    name: String,
 }
 
 impl SymCacheConverter {
@@ -83,6 +135,128 @@ impl SymCacheConverter {
         self.debug_id = debug_id;
     }
 
+    /// Sets whether a reverse index is written to this SymCache.
+    ///
+    /// The reverse index allows to look up the entry addresses of functions by name, and the code
+    /// ranges of a line in a source file. It is not written by default.
+    pub fn set_reverse_index(&mut self, reverse_index: bool) {
+        self.reverse_index = reverse_index;
+    }
+
+    /// Sets whether a checksum is written to the header of this SymCache.
+    ///
+    /// The checksum covers all data following the header and is verified by
+    /// [`SymCache::validate`](super::SymCache::validate). It is not written by default, since
+    /// computing it requires writing all data twice.
+    pub fn set_checksum(&mut self, checksum: bool) {
+        self.checksum = checksum;
+    }
+
+    /// Sets the call frame information (CFI) stored in this SymCache, or removes it.
+    ///
+    /// The CFI is written after all other data and must be the contents of a `CfiCache` file. Only
+    /// its preamble is checked, see [`check_cfi`].
+    pub fn set_cfi(&mut self, cfi: Option<Vec<u8>>) -> Result<(), SymCacheError> {
+        if let Some(ref cfi) = cfi {
+            check_cfi(cfi)?;
+        }
+        self.cfi = cfi;
+        Ok(())
+    }
+
+    /// Sets the options for demangling function names when writing, or disables demangling.
+    ///
+    /// When enabled, the short and full demangled names are stored along with the original name of
+    /// every function, so that they do not need to be demangled on every lookup.
+    #[cfg(feature = "demangle")]
+    pub fn set_demangle_options(&mut self, options: Option<DemangleOptions>) {
+        self.demangle_options = options;
+    }
+
+    /// Limits the memory used for code ranges by spilling them to the given storage.
+    ///
+    /// `range_buffer_size` is the number of bytes of code ranges that are buffered in memory. Once
+    /// the code ranges collected by this converter exceed it, they are written to `storage` as a
+    /// sorted run. During serialization, all runs are merged using buffers of the same total size,
+    /// which produces a SymCache identical to one created entirely in memory. The same applies to
+    /// the line ranges of the [reverse index](Self::set_reverse_index).
+    ///
+    /// This is not a limit on the total memory usage of the converter. Strings, files, functions
+    /// and source locations of inlined functions are deduplicated, but always kept in memory, so
+    /// their memory usage still grows with the size of the debug information. This needs to be
+    /// called before any data is added to the converter.
+    ///
+    /// If writing to the storage fails, the remaining ranges are kept in memory and the error is
+    /// returned during serialization.
+    pub fn set_spill_storage<S>(&mut self, storage: S, range_buffer_size: usize)
+    where
+        S: SpillStorage + 'static,
+    {
+        self.spill = Some(RangeSpill::new(Box::new(storage), range_buffer_size));
+    }
+
+    /// Sets the range at the given address, replacing any previous range.
+    fn set_range(&mut self, address: u64, value: RangeValue) {
+        self.ranges.insert(address, value);
+        self.weak_ranges.remove(&address);
+    }
+
+    /// Inserts a range at the given address unless a range already exists.
+    pub(super) fn insert_range_if_vacant(&mut self, address: u64, value: RangeValue) {
+        if self.ranges.contains_key(&address) {
+            return;
+        }
+
+        self.ranges.insert(address, value);
+        if matches!(self.spill, Some(ref spill) if spill.has_runs()) {
+            self.weak_ranges.insert(address);
+        }
+    }
+
+    /// Returns the current range at the given address, including spilled ranges.
+    fn get_range(&mut self, address: u64) -> Option<RangeValue> {
+        let value = self.ranges.get(&address).cloned();
+        if value.is_some() && !self.weak_ranges.contains(&address) {
+            return value;
+        }
+
+        match self.spill {
+            Some(ref mut spill) if spill.has_runs() => spill.lookup(address).or(value),
+            _ => value,
+        }
+    }
+
+    /// Returns true if there is a range at the given address.
+    ///
+    /// After spilled ranges have been resolved, this only knows the addresses of pending symbols
+    /// and the end of the last function.
+    fn contains_range(&self, address: u64) -> bool {
+        let spilled = match self.spill {
+            Some(ref spill) => matches!(spill.resolved(), Some(r) if r.contains(address)),
+            None => false,
+        };
+
+        spilled || self.ranges.contains_key(&address)
+    }
+
+    /// Spills the ranges if they exceed the range buffer size.
+    ///
+    /// If spilling fails, the ranges are kept in memory and the error is returned during
+    /// serialization.
+    fn spill_ranges(&mut self) {
+        if let Some(ref mut spill) = self.spill {
+            if !spill.has_error() && self.ranges.len() >= spill.max_ranges() {
+                match spill.spill(&self.ranges, &self.weak_ranges) {
+                    Ok(()) => {
+                        self.ranges.clear();
+                        self.weak_ranges.clear();
+                    }
+                    Err(error) => spill.set_error(error),
+                }
+            }
+        }
+    }
+
     /// Insert a string into this converter.
     ///
     /// If the string was already present, it is not added again. A newly added string
@@ -112,16 +286,41 @@ impl SymCacheConverter {
         string_offset
     }
 
+    /// Resolves a string previously inserted via [`insert_string`](Self::insert_string).
+    fn get_string(string_bytes: &[u8], offset: u32) -> &str {
+        if offset == u32::MAX {
+            return "";
+        }
+        let start = offset as usize + std::mem::size_of::<u32>();
+        let len_bytes = &string_bytes[offset as usize..start];
+        let len = u32::from_ne_bytes(len_bytes.try_into().unwrap()) as usize;
+        std::str::from_utf8(&string_bytes[start..start + len]).unwrap_or_default()
+    }
+
     // Methods processing symbolic-debuginfo [`ObjectLike`] below:
     // Feel free to move these to a separate file.
 
     /// This processes the given [`ObjectLike`] object, collecting all its functions and line
     /// information into the converter.
+    ///
+    /// This also sets the architecture and debug identifier of the SymCache. Multiple objects can
+    /// be processed to merge them into one SymCache, for instance an executable and its debug
+    /// companion file:
+    ///
+    ///  - Functions from debug information take precedence over symbols, regardless of the order
+    ///    in which the objects are processed.
+    ///  - Symbols are only added where they are not covered by a function from debug information,
+    ///    which fills gaps in the debug information.
+    ///  - All objects need to have the same architecture and debug identifier, otherwise an error
+    ///    with [`SymCacheErrorKind::IncompatibleObject`] is returned. Unknown values are compatible
+    ///    with all others.
     pub fn process_object<'d, 'o, O>(&mut self, object: &'o O) -> Result<(), SymCacheError>
     where
         O: ObjectLike<'d, 'o>,
         O::Error: std::error::Error + Send + Sync + 'static,
     {
+        self.merge_object_info(object.arch(), object.debug_id())?;
+
         let session = object
             .debug_session()
             .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
@@ -140,59 +339,184 @@ impl SymCacheConverter {
         Ok(())
     }
 
+    /// Processes the given [`ObjectLike`] object like [`process_object`](Self::process_object),
+    /// but reads functions from its compilation units in parallel.
+    ///
+    /// Functions are still added to the converter in the same order, so the resulting SymCache is
+    /// identical to the one created by `process_object`. They are added as soon as their batch of
+    /// compilation units has been read, so the functions of the entire object are never held in
+    /// memory at once.
+    #[cfg(feature = "rayon")]
+    pub fn process_object_par<'d, 'o, O>(&mut self, object: &'o O) -> Result<(), SymCacheError>
+    where
+        O: ObjectLike<'d, 'o>,
+        O::Error: std::error::Error + Send + Sync + 'static,
+    {
+        self.merge_object_info(object.arch(), object.debug_id())?;
+
+        let session = object
+            .debug_session()
+            .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+
+        for function in session.functions_par() {
+            let function =
+                function.map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+
+            self.process_symbolic_function(&function);
+        }
+
+        for symbol in object.symbols() {
+            self.process_symbolic_symbol(&symbol);
+        }
+
+        Ok(())
+    }
+
+    /// Checks that an object is compatible with all previously processed objects and takes over
+    /// its architecture and debug identifier.
+    pub(super) fn merge_object_info(
+        &mut self,
+        arch: Arch,
+        debug_id: DebugId,
+    ) -> Result<(), SymCacheError> {
+        if self.has_object {
+            if arch != Arch::Unknown && self.arch != Arch::Unknown && arch != self.arch {
+                let message = format!("architecture {} does not match {}", arch, self.arch);
+                return Err(SymCacheError::new(
+                    SymCacheErrorKind::IncompatibleObject,
+                    message,
+                ));
+            }
+
+            if !debug_id.is_nil() && !self.debug_id.is_nil() && debug_id != self.debug_id {
+                let message = format!("debug id {} does not match {}", debug_id, self.debug_id);
+                return Err(SymCacheError::new(
+                    SymCacheErrorKind::IncompatibleObject,
+                    message,
+                ));
+            }
+        }
+
+        if arch != Arch::Unknown {
+            self.arch = arch;
+        }
+        if !debug_id.is_nil() {
+            self.debug_id = debug_id;
+        }
+        self.has_object = true;
+
+        Ok(())
+    }
+
+    /// Adds a function with all of its line records and inlinees to the converter.
     pub fn process_symbolic_function(&mut self, function: &Function<'_>) {
-        // skip over empty functions or functions whose address is too large to fit in a u32
-        if function.size == 0 || function.address > u32::MAX as u64 {
+        self.insert_function(function, 0);
+        // inlinees look up the ranges of their parents, so only spill complete functions.
+        self.spill_ranges();
+    }
+
+    /// Inserts a function and its inlinees.
+    ///
+    /// `caller_depth` is the number of callers above the source locations of the caller, which is
+    /// used to attribute lines of collapsed inlined functions to their caller.
+    fn insert_function(&mut self, function: &Function<'_>, caller_depth: usize) {
+        // skip over empty functions
+        if function.size == 0 {
             return;
         }
 
         let comp_dir = std::str::from_utf8(function.compilation_dir).ok();
 
-        let entry_pc = if function.inline {
-            u32::MAX
+        // inlined functions are shared between all of their call sites, so they have neither a
+        // single entry pc nor a size.
+        let (entry_pc, size) = if function.inline {
+            (u64::MAX, 0)
         } else {
-            function.address as u32
+            (function.address, function.size)
         };
 
-        let function_idx = {
-            let language = function.name.language();
-            let mut function = transform::Function {
-                name: function.name.as_str().into(),
-                comp_dir: comp_dir.map(Into::into),
-            };
-            for transformer in &self.transformers.0 {
-                function = transformer.transform_function(function);
-            }
+        if !function.inline {
+            let end = self.function_ranges.entry(function.address).or_insert(0);
+            *end = (*end).max(function.end_address());
+        }
 
-            let string_bytes = &mut self.string_bytes;
-            let strings = &mut self.strings;
-            let name_offset = Self::insert_string(string_bytes, strings, &function.name);
+        let function_end = function.end_address();
+        let last_addr = self.last_addr.get_or_insert(0);
+        if function_end > *last_addr {
+            *last_addr = function_end;
+        }
 
-            let comp_dir_offset = function.comp_dir.map_or(u32::MAX, |comp_dir| {
-                Self::insert_string(string_bytes, strings, &comp_dir)
-            });
-            let lang = language as u32;
-            let (fun_idx, _) = self.functions.insert_full(raw::Function {
-                name_offset,
-                comp_dir_offset,
-                entry_pc,
-                lang,
-            });
-            fun_idx as u32
+        let language = function.name.language();
+        let (transformed, filter) = self.transformers.function(transform::Function {
+            name: function.name.as_str().into(),
+            comp_dir: comp_dir.map(Into::into),
+        });
+
+        let function_idx = match filter {
+            transform::FunctionFilter::Drop => {
+                // inlined functions leave the call site of their caller in place, but the range
+                // of a dropped top-level function must not be attributed to the one before.
+                if !function.inline {
+                    self.insert_range_if_vacant(entry_pc, (raw::NO_SOURCE_LOCATION, 0));
+                }
+                return;
+            }
+            transform::FunctionFilter::Collapse if function.inline => None,
+            _ => {
+                let string_bytes = &mut self.string_bytes;
+                let strings = &mut self.strings;
+                let name_offset = Self::insert_string(string_bytes, strings, &transformed.name);
+
+                let comp_dir_offset = transformed.comp_dir.map_or(u32::MAX, |comp_dir| {
+                    Self::insert_string(string_bytes, strings, &comp_dir)
+                });
+                let lang = language as u32;
+                let (fun_idx, _) = self.functions.insert_full(raw::WideFunction {
+                    entry_pc,
+                    size,
+                    name_offset,
+                    comp_dir_offset,
+                    lang,
+                    _padding: 0,
+                });
+
+                // for inlined functions, this is the start of the inlined copy
+                self.function_entries
+                    .push((fun_idx as u32, function.address));
+                Some(fun_idx as u32)
+            }
         };
 
         for line in &function.lines {
-            let mut location = transform::SourceLocation {
-                file: transform::File {
-                    name: line.file.name_str(),
-                    directory: Some(line.file.dir_str()),
-                    comp_dir: comp_dir.map(Into::into),
-                },
-                line: line.line as u32,
+            let location = self
+                .transformers
+                .source_location(transform::SourceLocation {
+                    file: transform::File {
+                        name: line.file.name_str(),
+                        directory: Some(line.file.dir_str()),
+                        comp_dir: comp_dir.map(Into::into),
+                    },
+                    line: line.line as u32,
+                    column: line.column as u32,
+                });
+
+            let (function_idx, location) = match (function_idx, location) {
+                (Some(function_idx), Some(location)) => (function_idx, location),
+                (Some(function_idx), None) if !function.inline => {
+                    let source_location = raw::SourceLocation {
+                        file_idx: u32::MAX,
+                        line: 0,
+                        function_idx,
+                        inlined_into_idx: u32::MAX,
+                    };
+                    self.set_range(line.address, (source_location, 0));
+                    continue;
+                }
+                _ => {
+                    self.collapse_range(line.address, caller_depth);
+                    continue;
+                }
             };
-            for transformer in &self.transformers.0 {
-                location = transformer.transform_source_location(location);
-            }
 
             let string_bytes = &mut self.string_bytes;
             let strings = &mut self.strings;
@@ -217,104 +541,344 @@ impl SymCacheConverter {
                 function_idx,
                 inlined_into_idx: u32::MAX,
             };
+            let source_location = (source_location, location.column);
 
-            match self.ranges.entry(line.address as u32) {
-                btree_map::Entry::Vacant(entry) => {
+            let caller_source_location = if function.inline {
+                self.caller_range(line.address, caller_depth)
+            } else {
+                None
+            };
+
+            match caller_source_location {
+                None => {
                     if function.inline {
                         // BUG:
                         // the abstraction should have defined this line record inside the caller
                         // function already!
                     }
-                    entry.insert(source_location);
+                    // BUG:
+                    // the abstraction might yield multiple top-level functions for the same
+                    // instruction addr, in which case the last one wins
+                    self.set_range(line.address, source_location);
                 }
-                btree_map::Entry::Occupied(mut entry) => {
-                    if function.inline {
-                        let caller_source_location = entry.get().clone();
-
-                        let mut callee_source_location = source_location;
-                        let (inlined_into_idx, _) =
-                            self.source_locations.insert_full(caller_source_location);
+                Some(caller_source_location) => {
+                    let mut callee_source_location = source_location;
+                    let (inlined_into_idx, _) =
+                        self.source_locations.insert_full(caller_source_location);
 
-                        callee_source_location.inlined_into_idx = inlined_into_idx as u32;
-                        entry.insert(callee_source_location);
-                    } else {
-                        // BUG:
-                        // the abstraction yields multiple top-level functions for the same
-                        // instruction addr
-                        entry.insert(source_location);
-                    }
+                    callee_source_location.0.inlined_into_idx = inlined_into_idx as u32;
+                    self.set_range(line.address, callee_source_location);
                 }
             }
         }
 
         // add the bare minimum of information for the function if there isn't any.
-        self.ranges.entry(entry_pc).or_insert(raw::SourceLocation {
-            file_idx: u32::MAX,
-            line: 0,
-            function_idx,
-            inlined_into_idx: u32::MAX,
-        });
+        if let (Some(function_idx), false) = (function_idx, function.inline) {
+            let source_location = raw::SourceLocation {
+                file_idx: u32::MAX,
+                line: 0,
+                function_idx,
+                inlined_into_idx: u32::MAX,
+            };
+            self.insert_range_if_vacant(entry_pc, (source_location, 0));
+        }
 
+        let depth = match (function.inline, function_idx) {
+            (false, _) => 0,
+            (true, None) => caller_depth,
+            (true, Some(_)) => caller_depth + 1,
+        };
         for inlinee in &function.inlinees {
-            self.process_symbolic_function(inlinee);
+            self.insert_function(inlinee, depth);
         }
+    }
 
-        let function_end = function.end_address() as u32;
-        let last_addr = self.last_addr.get_or_insert(0);
-        if function_end > *last_addr {
-            *last_addr = function_end;
+    /// Attributes an address of a removed inlined function to the source location of its caller.
+    ///
+    /// This adds a range for the address, so that inlinees of the removed function find the caller
+    /// as their parent. `caller_depth` is the number of callers above the caller's source location.
+    fn collapse_range(&mut self, address: u64, caller_depth: usize) {
+        if self.get_range(address).is_some() {
+            return;
+        }
+
+        if let Some(caller) = self.caller_range(address, caller_depth) {
+            self.set_range(address, caller);
         }
     }
 
+    /// Returns the source location of the caller at an address of an inlined function.
+    ///
+    /// The line record of the caller that covers the address usually starts before it. `caller_depth`
+    /// is the number of callers above the caller's source location. Returns `None` if the caller
+    /// has no line record at or before the address.
+    fn caller_range(&mut self, address: u64, caller_depth: usize) -> Option<RangeValue> {
+        // Ranges are only spilled between top-level functions, so the range before the address is
+        // in memory. It can belong to an inlinee of a previous sibling, in which case the caller is
+        // further up its inlining hierarchy.
+        let mut caller = match self.get_range(address) {
+            Some(caller) => caller,
+            None => self.ranges.range(..address).next_back()?.1.clone(),
+        };
+
+        let mut depth = self.inline_depth(&caller.0);
+        if depth < caller_depth {
+            return None;
+        }
+
+        while depth > caller_depth {
+            let idx = caller.0.inlined_into_idx as usize;
+            caller = self.source_locations[idx].clone();
+            depth -= 1;
+        }
+
+        Some(caller)
+    }
+
+    /// Returns the number of source locations that the given one is inlined into.
+    fn inline_depth(&self, source_location: &raw::SourceLocation) -> usize {
+        let mut depth = 0;
+        let mut idx = source_location.inlined_into_idx;
+        while let Some((caller, _)) = self.source_locations.get_index(idx as usize) {
+            idx = caller.inlined_into_idx;
+            depth += 1;
+        }
+        depth
+    }
+
+    /// Adds a symbol to the converter.
+    ///
+    /// Symbols are only added to the SymCache during serialization, where they are skipped if a
+    /// function from debug information covers their address. Until then, the converter holds a
+    /// copy of every symbol name, so memory usage grows with the size of the symbol table.
     pub fn process_symbolic_symbol(&mut self, symbol: &Symbol<'_>) {
-        let name_idx = {
-            let mut function = transform::Function {
-                name: match symbol.name {
-                    Some(ref name) => name.clone(),
-                    None => return,
-                },
-                comp_dir: None,
+        if let Some(ref name) = symbol.name {
+            self.symbols.push(PendingSymbol {
+                address: symbol.address,
+                size: symbol.size,
+                name: name.as_ref().to_owned(),
+            });
+        }
+    }
+
+    /// Adds all pending symbols that are not covered by functions from debug information.
+    fn insert_symbols(&mut self) {
+        // Merge overlapping functions, so that a function is found even if another one starts
+        // between its entry and the symbol.
+        let mut covered: Vec<(u64, u64)> = Vec::with_capacity(self.function_ranges.len());
+        for (&start, &end) in &self.function_ranges {
+            match covered.last_mut() {
+                Some((_, last_end)) if start <= *last_end => *last_end = (*last_end).max(end),
+                _ => covered.push((start, end)),
+            }
+        }
+
+        for symbol in std::mem::take(&mut self.symbols) {
+            let num_before = covered.partition_point(|&(start, _)| start <= symbol.address);
+            let is_covered = match num_before.checked_sub(1) {
+                Some(idx) => symbol.address < covered[idx].1,
+                None => false,
             };
-            for transformer in &self.transformers.0 {
-                function = transformer.transform_function(function);
+            if is_covered {
+                continue;
             }
 
-            Self::insert_string(&mut self.string_bytes, &mut self.strings, &function.name)
-        };
+            self.insert_symbol(symbol);
+        }
+    }
 
-        match self.ranges.entry(symbol.address as u32) {
-            btree_map::Entry::Vacant(entry) => {
-                let function = raw::Function {
-                    name_offset: name_idx,
-                    comp_dir_offset: u32::MAX,
-                    entry_pc: symbol.address as u32,
-                    lang: u32::MAX,
-                };
-                let function_idx = self.functions.insert_full(function).0 as u32;
+    fn insert_symbol(&mut self, symbol: PendingSymbol) {
+        let (function, filter) = self.transformers.function(transform::Function {
+            name: symbol.name.into(),
+            comp_dir: None,
+        });
 
-                entry.insert(raw::SourceLocation {
-                    file_idx: u32::MAX,
-                    line: 0,
-                    function_idx,
-                    inlined_into_idx: u32::MAX,
-                });
-            }
-            btree_map::Entry::Occupied(entry) => {
-                // ASSUMPTION:
-                // the `functions` iterator has already filled in this addr via debug session.
-                // we could trace the caller hierarchy up to the root, and assert that it is
-                // indeed the same function, and maybe update its `entry_pc`, but we don’t do
-                // that for now.
-                let _function_idx = entry.get().function_idx as usize;
+        if filter == transform::FunctionFilter::Drop {
+            // symbols have no size, so end the previous symbol where the dropped one starts.
+            if !self.contains_range(symbol.address) {
+                self.ranges
+                    .insert(symbol.address, (raw::NO_SOURCE_LOCATION, 0));
             }
+            return;
+        }
+
+        let name_idx =
+            Self::insert_string(&mut self.string_bytes, &mut self.strings, &function.name);
+
+        if self.contains_range(symbol.address) {
+            // ASSUMPTION:
+            // the `functions` iterator has already filled in this addr via debug session.
+            // we could trace the caller hierarchy up to the root, and assert that it is
+            // indeed the same function, and maybe update its `entry_pc`, but we don’t do
+            // that for now.
+        } else {
+            let function = raw::WideFunction {
+                entry_pc: symbol.address,
+                size: symbol.size,
+                name_offset: name_idx,
+                comp_dir_offset: u32::MAX,
+                lang: u32::MAX,
+                _padding: 0,
+            };
+            let function_idx = self.functions.insert_full(function).0 as u32;
+            self.function_entries.push((function_idx, symbol.address));
+
+            let source_location = raw::SourceLocation {
+                file_idx: u32::MAX,
+                line: 0,
+                function_idx,
+                inlined_into_idx: u32::MAX,
+            };
+            self.ranges.insert(symbol.address, (source_location, 0));
         }
 
         let last_addr = self.last_addr.get_or_insert(0);
-        if symbol.address as u32 >= *last_addr {
+        if symbol.address >= *last_addr {
             self.last_addr = None;
         }
     }
 
+    /// Processes a SymCache in the [text format](super::text).
+    ///
+    /// The text describes the records of a SymCache, which are added to the converter as they
+    /// are. Transformers are not applied. The architecture and debug identifier are merged like
+    /// those of objects, see [`process_object`](Self::process_object).
+    pub fn process_text(&mut self, text: &str) -> Result<(), SymCacheError> {
+        let cache = text::parse(text)?;
+        self.process_records(cache)?;
+        Ok(())
+    }
+
+    /// Copies a SymCache of version 7 or 8 with all of its optional data.
+    ///
+    /// The records are added like in [`process_text`](Self::process_text). Demangled names, the
+    /// entries of the reverse index and the call frame information are copied, and a reverse index
+    /// and checksum are written if the SymCache has them.
+    pub(crate) fn process_symcache(&mut self, cache: &SymCache<'_>) -> Result<(), SymCacheError> {
+        let function_indices = self.process_records(text::TextSymCache::from_symcache(cache)?)?;
+
+        if cache.has_reverse_index() {
+            self.reverse_index = true;
+            // these include the entry addresses of inlined copies
+            for entry in cache.function_entries {
+                let function_idx = function_indices[entry.function_idx as usize];
+                self.function_entries.push((function_idx, entry.address));
+            }
+        }
+
+        if cache.has_checksum() {
+            self.checksum = true;
+        }
+
+        if let Some(cfi) = cache.cfi() {
+            self.cfi = Some(cfi.to_vec());
+        }
+
+        for (idx, demangled_name) in cache.demangled_names.iter().enumerate() {
+            let string_bytes = &mut self.string_bytes;
+            let strings = &mut self.strings;
+            let mut insert_string = |offset| match cache.get_string(offset) {
+                Some(string) => Self::insert_string(string_bytes, strings, string),
+                None => u32::MAX,
+            };
+
+            let demangled_name = raw::DemangledName {
+                short_name_offset: insert_string(demangled_name.short_name_offset),
+                full_name_offset: insert_string(demangled_name.full_name_offset),
+            };
+            self.demangled_names
+                .insert(function_indices[idx], demangled_name);
+        }
+
+        Ok(())
+    }
+
+    /// Adds the records of a SymCache in the text format to the converter.
+    ///
+    /// Returns the indices in the converter of all functions of the SymCache.
+    fn process_records(&mut self, cache: text::TextSymCache) -> Result<Vec<u32>, SymCacheError> {
+        self.merge_object_info(cache.arch, cache.debug_id)?;
+
+        let string_bytes = &mut self.string_bytes;
+        let strings = &mut self.strings;
+        let mut insert_string = |string: &Option<String>| match string {
+            Some(string) => Self::insert_string(string_bytes, strings, string),
+            None => u32::MAX,
+        };
+
+        let mut file_indices = Vec::with_capacity(cache.files.len());
+        for file in &cache.files {
+            let (file_idx, _) = self.files.insert_full(raw::File {
+                path_name_offset: insert_string(&file.path_name),
+                directory_offset: insert_string(&file.directory),
+                comp_dir_offset: insert_string(&file.comp_dir),
+            });
+            file_indices.push(file_idx as u32);
+        }
+
+        let mut function_indices = Vec::with_capacity(cache.functions.len());
+        for function in &cache.functions {
+            let name_offset = insert_string(&function.name);
+            let comp_dir_offset = insert_string(&function.comp_dir);
+
+            let (function_idx, _) = self.functions.insert_full(raw::WideFunction {
+                entry_pc: function.entry_pc,
+                size: function.size,
+                name_offset,
+                comp_dir_offset,
+                lang: function.lang,
+                _padding: 0,
+            });
+            let function_idx = function_idx as u32;
+            function_indices.push(function_idx);
+
+            if function.entry_pc != u64::MAX {
+                self.function_entries
+                    .push((function_idx, function.entry_pc));
+                if function.size != 0 {
+                    let end_address = function.entry_pc.saturating_add(function.size);
+                    let end = self.function_ranges.entry(function.entry_pc).or_insert(0);
+                    *end = (*end).max(end_address);
+                }
+            }
+        }
+
+        // Indices in the text refer to its own records, which may already exist in the converter.
+        let map_index = |indices: &[u32], idx: u32| match idx {
+            u32::MAX => u32::MAX,
+            idx => indices[idx as usize],
+        };
+
+        let mut location_indices = Vec::with_capacity(cache.locations.len());
+        for (location, column) in &cache.locations {
+            let location = raw::SourceLocation {
+                file_idx: map_index(&file_indices, location.file_idx),
+                line: location.line,
+                function_idx: map_index(&function_indices, location.function_idx),
+                inlined_into_idx: map_index(&location_indices, location.inlined_into_idx),
+            };
+            let (location_idx, _) = self.source_locations.insert_full((location, *column));
+            location_indices.push(location_idx as u32);
+        }
+
+        for (address, (location, column)) in cache.ranges {
+            let location = if location == raw::NO_SOURCE_LOCATION {
+                location
+            } else {
+                raw::SourceLocation {
+                    file_idx: map_index(&file_indices, location.file_idx),
+                    line: location.line,
+                    function_idx: map_index(&function_indices, location.function_idx),
+                    inlined_into_idx: map_index(&location_indices, location.inlined_into_idx),
+                }
+            };
+            self.set_range(address, (location, column));
+            self.spill_ranges();
+        }
+
+        Ok(function_indices)
+    }
+
     #[cfg(feature = "il2cpp")]
     /// Processes a set of [`UsymSymbols`], passing all mapped symbols into the converter.
     pub fn process_usym(&mut self, usym: &UsymSymbols) -> Result<(), SymCacheError> {
@@ -326,55 +890,76 @@ impl SymCacheConverter {
         });
 
         let mut curr_id: Option<(Cow<'_, str>, Cow<'_, str>)> = None;
-        let mut function_idx = 0;
+        let mut function_idx = None;
         for record in mapped_records {
-            // like process_symbolic_function, skip functions whose address is too large to fit in a
-            // u32
-            if record.address > u32::MAX as u64 {
-                continue;
-            }
-            let address = record.address as u32;
+            let address = record.address;
 
             // Records that belong to the same function will have the same identifier.
             // Symbols have GUID-like sections to them that might ensure they're unique across
             // files, but we'll just include the file name and paths to be very safe.
             let identifier = Some((record.native_file, record.native_symbol));
             if identifier != curr_id {
-                function_idx = {
-                    let mut function = transform::Function {
-                        name: record.managed_symbol.clone(),
-                        comp_dir: None,
-                    };
-                    for transformer in &self.transformers.0 {
-                        function = transformer.transform_function(function);
-                    }
+                curr_id = identifier;
+                let (function, filter) = self.transformers.function(transform::Function {
+                    name: record.managed_symbol.clone(),
+                    comp_dir: None,
+                });
 
+                function_idx = if filter == transform::FunctionFilter::Drop {
+                    None
+                } else {
                     let string_bytes = &mut self.string_bytes;
                     let strings = &mut self.strings;
                     let name_offset = Self::insert_string(string_bytes, strings, &function.name);
 
-                    let (fun_idx, _) = self.functions.insert_full(raw::Function {
+                    let (fun_idx, _) = self.functions.insert_full(raw::WideFunction {
+                        entry_pc: address,
+                        size: 0,
                         name_offset,
                         comp_dir_offset: u32::MAX,
-                        entry_pc: address,
                         lang: Language::CSharp as u32,
+                        _padding: 0,
                     });
-                    fun_idx
+                    self.function_entries.push((fun_idx as u32, address));
+                    Some(fun_idx as u32)
                 };
             }
 
+            let function_idx = match function_idx {
+                Some(function_idx) => function_idx,
+                None => {
+                    self.set_range(address, (raw::NO_SOURCE_LOCATION, 0));
+                    self.spill_ranges();
+                    continue;
+                }
+            };
+
             let managed_dir = Some(record.managed_file_info.dir_str()).filter(|d| !d.is_empty());
-            let mut location = transform::SourceLocation {
-                file: transform::File {
-                    name: record.managed_file_info.name_str(),
-                    directory: managed_dir,
-                    comp_dir: None,
-                },
-                line: record.managed_line,
+            let location = self
+                .transformers
+                .source_location(transform::SourceLocation {
+                    file: transform::File {
+                        name: record.managed_file_info.name_str(),
+                        directory: managed_dir,
+                        comp_dir: None,
+                    },
+                    line: record.managed_line,
+                    column: 0,
+                });
+            let location = match location {
+                Some(location) => location,
+                None => {
+                    let source_location = raw::SourceLocation {
+                        file_idx: u32::MAX,
+                        line: 0,
+                        function_idx,
+                        inlined_into_idx: u32::MAX,
+                    };
+                    self.set_range(address, (source_location, 0));
+                    self.spill_ranges();
+                    continue;
+                }
             };
-            for transformer in &self.transformers.0 {
-                location = transformer.transform_source_location(location);
-            }
 
             let string_bytes = &mut self.string_bytes;
             let strings = &mut self.strings;
@@ -393,27 +978,22 @@ impl SymCacheConverter {
             let source_location = raw::SourceLocation {
                 file_idx: file_idx as u32,
                 line: location.line,
-                function_idx: function_idx as u32,
+                function_idx,
                 inlined_into_idx: u32::MAX,
             };
-
-            match self.ranges.entry(address) {
-                btree_map::Entry::Vacant(entry) => {
-                    entry.insert(source_location);
-                }
-                btree_map::Entry::Occupied(mut entry) => {
-                    // TODO: This exists in native-only mappings, but we don't know yet if it's
-                    // possible to generate these types of records in managed code.
-                    // println!(
-                    //     "Found what's probably an inlined source {}::{}:L{}",
-                    //     record.managed_file_info.path_str(),
-                    //     record.managed_symbol,
-                    //     record.managed_line,
-                    // );
-                    entry.insert(source_location);
-                }
-            }
-            curr_id = identifier;
+            let source_location = (source_location, location.column);
+
+            // TODO: If there already is a range at this address, this is probably an inlined
+            // source. This exists in native-only mappings, but we don't know yet if it's possible
+            // to generate these types of records in managed code.
+            // println!(
+            //     "Found what's probably an inlined source {}::{}:L{}",
+            //     record.managed_file_info.path_str(),
+            //     record.managed_symbol,
+            //     record.managed_line,
+            // );
+            self.set_range(address, source_location);
+            self.spill_ranges();
         }
 
         Ok(())
@@ -426,29 +1006,115 @@ impl SymCacheConverter {
     ///
     /// This writes the SymCache binary format into the given [`Write`].
     pub fn serialize<W: Write>(mut self, writer: &mut W) -> std::io::Result<()> {
+        self.resolve_spilled_ranges()?;
+        self.insert_symbols();
+
+        #[cfg(feature = "demangle")]
+        let demangled_names = self
+            .demangle_names()
+            .or_else(|| self.copied_demangled_names());
+        #[cfg(not(feature = "demangle"))]
+        let demangled_names = self.copied_demangled_names();
+
         let mut writer = WriteWrapper::new(writer);
 
         // Insert a trailing sentinel source location in case we have a definite end addr
         if let Some(last_addr) = self.last_addr {
             // TODO: to be extra safe, we might check that `last_addr` is indeed larger than
             // the largest range at some point.
-            match self.ranges.entry(last_addr) {
-                btree_map::Entry::Vacant(entry) => {
-                    entry.insert(raw::NO_SOURCE_LOCATION);
-                }
-                btree_map::Entry::Occupied(_entry) => {
-                    // BUG:
-                    // the last addr should not map to an already defined range
-                }
+            if self.contains_range(last_addr) {
+                // BUG:
+                // the last addr should not map to an already defined range
+            } else {
+                self.ranges.insert(last_addr, (raw::NO_SOURCE_LOCATION, 0));
             }
         }
 
+        let spilled = self.spill.as_ref().and_then(RangeSpill::resolved);
+
         let num_files = self.files.len() as u32;
         let num_functions = self.functions.len() as u32;
-        let num_source_locations = (self.source_locations.len() + self.ranges.len()) as u32;
-        let num_ranges = self.ranges.len() as u32;
+        let num_ranges = self.ranges.len() + spilled.map_or(0, |spilled| spilled.len());
+        let num_source_locations = (self.source_locations.len() + num_ranges) as u32;
+        let num_ranges = num_ranges as u32;
         let string_bytes = self.string_bytes.len() as u32;
 
+        // Columns are only written if there are any, since most formats do not provide them.
+        let has_columns = matches!(spilled, Some(spilled) if spilled.has_columns)
+            || self
+                .source_locations
+                .iter()
+                .chain(self.ranges.values())
+                .any(|(_, column)| *column != 0);
+
+        // Store addresses as `u32` relative to the lowest address if they fit, which is always the
+        // case unless the code spans more than 4GiB. Small addresses are stored as they are.
+        let first_addr = self
+            .functions
+            .iter()
+            .map(|f| f.entry_pc)
+            .filter(|&entry_pc| entry_pc != u64::MAX)
+            .chain(self.ranges.keys().next().copied())
+            .chain(spilled.and_then(|spilled| spilled.first_addr))
+            .min();
+        let last_addr = self
+            .functions
+            .iter()
+            .filter(|f| f.entry_pc != u64::MAX)
+            .map(|f| f.entry_pc.saturating_add(f.size))
+            .chain(self.ranges.keys().next_back().copied())
+            .chain(spilled.and_then(|spilled| spilled.last_addr))
+            .max();
+
+        let (range_offset, wide) = match (first_addr, last_addr) {
+            (Some(_), Some(last)) if last < u32::MAX as u64 => (0, false),
+            (Some(first), Some(last)) if last - first < u32::MAX as u64 => (first, false),
+            (Some(_), Some(_)) => (0, true),
+            _ => (0, false),
+        };
+
+        let reverse_index = if self.reverse_index {
+            Some(self.build_reverse_index()?)
+        } else {
+            None
+        };
+
+        let mut flags = 0;
+        if wide {
+            flags |= raw::FLAG_WIDE_ADDRESSES;
+        }
+        if has_columns {
+            flags |= raw::FLAG_COLUMNS;
+        }
+        if self.reverse_index {
+            flags |= raw::FLAG_REVERSE_INDEX;
+        }
+        if demangled_names.is_some() {
+            flags |= raw::FLAG_DEMANGLED_NAMES;
+        }
+        if self.cfi.is_some() {
+            flags |= raw::FLAG_CFI;
+        }
+
+        let sections = Sections {
+            wide,
+            range_offset,
+            has_columns,
+            demangled_names,
+            reverse_index,
+        };
+
+        // The header is a multiple of eight bytes, so the sections are aligned the same way when
+        // they are written without it.
+        let checksum = if self.checksum {
+            flags |= raw::FLAG_CHECKSUM;
+            let mut hasher = WriteWrapper::new(ChecksumWriter::default());
+            self.write_sections(&mut hasher, &sections)?;
+            hasher.writer.0.finalize()
+        } else {
+            0
+        };
+
         let header = raw::Header {
             magic: raw::SYMCACHE_MAGIC,
             version: raw::SYMCACHE_VERSION,
@@ -461,37 +1127,295 @@ impl SymCacheConverter {
             num_source_locations,
             num_ranges,
             string_bytes,
-            _reserved: [0; 16],
+            range_offset,
+            flags,
+            checksum,
         };
 
         writer.write(&[header])?;
         writer.align()?;
 
-        for f in self.files {
-            writer.write(&[f])?;
+        self.write_sections(&mut writer, &sections)
+    }
+
+    /// Writes all sections following the header.
+    ///
+    /// This can be called repeatedly, for instance to compute the checksum before writing.
+    fn write_sections<W: Write>(
+        &mut self,
+        writer: &mut WriteWrapper<W>,
+        sections: &Sections,
+    ) -> std::io::Result<()> {
+        let Sections {
+            wide,
+            range_offset,
+            has_columns,
+            ref demangled_names,
+            ref reverse_index,
+        } = *sections;
+
+        for f in &self.files {
+            writer.write(std::slice::from_ref(f))?;
         }
         writer.align()?;
 
-        for f in self.functions {
-            writer.write(&[f])?;
+        for f in &self.functions {
+            if wide {
+                writer.write(std::slice::from_ref(f))?;
+            } else {
+                let entry_pc = match f.entry_pc {
+                    u64::MAX => u32::MAX,
+                    entry_pc => narrow_address(entry_pc, range_offset)?,
+                };
+
+                writer.write(&[raw::Function {
+                    name_offset: f.name_offset,
+                    comp_dir_offset: f.comp_dir_offset,
+                    entry_pc,
+                    size: u32::try_from(f.size).map_err(|_| address_out_of_range())?,
+                    lang: f.lang,
+                }])?;
+            }
         }
         writer.align()?;
 
-        for s in self.source_locations {
-            writer.write(&[s])?;
+        if let Some(demangled_names) = demangled_names {
+            writer.write(demangled_names)?;
+            writer.align()?;
         }
-        for s in self.ranges.values() {
+
+        for (s, _) in &self.source_locations {
             writer.write(std::slice::from_ref(s))?;
         }
+        let mut ranges = RangeIter::new(&self.ranges, &self.spill);
+        while let Some((_, (s, _))) = ranges.next(&mut self.spill)? {
+            writer.write(std::slice::from_ref(&s))?;
+        }
         writer.align()?;
 
-        for r in self.ranges.keys() {
-            writer.write(&[raw::Range(*r)])?;
+        if has_columns {
+            for (_, c) in &self.source_locations {
+                writer.write(&[*c])?;
+            }
+            let mut ranges = RangeIter::new(&self.ranges, &self.spill);
+            while let Some((_, (_, c))) = ranges.next(&mut self.spill)? {
+                writer.write(&[c])?;
+            }
+            writer.align()?;
+        }
+
+        let mut ranges = RangeIter::new(&self.ranges, &self.spill);
+        while let Some((r, _)) = ranges.next(&mut self.spill)? {
+            if wide {
+                writer.write(&[raw::WideRange(r)])?;
+            } else {
+                writer.write(&[raw::Range(narrow_address(r, range_offset)?)])?;
+            }
         }
         writer.align()?;
 
         writer.write(&self.string_bytes)?;
 
+        if let Some((function_entries, line_ranges)) = reverse_index {
+            writer.align()?;
+            writer.write(&[raw::ReverseIndexHeader {
+                num_function_entries: function_entries.len() as u32,
+                num_line_ranges: line_ranges.len() as u32,
+            }])?;
+            writer.write(function_entries)?;
+            line_ranges.for_each_chunk(&mut self.spill, |chunk| writer.write(chunk).map(drop))?;
+        }
+
+        if let Some(ref cfi) = self.cfi {
+            writer.align()?;
+            writer.write(&[raw::CfiHeader {
+                num_bytes: cfi.len() as u64,
+            }])?;
+            writer.write(cfi)?;
+        }
+
+        Ok(())
+    }
+
+    /// Demangles the names of all functions if demangling is enabled, adding the demangled names
+    /// to the string data.
+    #[cfg(feature = "demangle")]
+    fn demangle_names(&mut self) -> Option<Vec<raw::DemangledName>> {
+        let options = self.demangle_options?;
+        let string_bytes = &mut self.string_bytes;
+        let strings = &mut self.strings;
+
+        let mut demangled_names = Vec::with_capacity(self.functions.len());
+        for function in &self.functions {
+            let name = Self::get_string(string_bytes, function.name_offset).to_owned();
+            let name = Name::new(
+                name,
+                NameMangling::Unknown,
+                Language::from_u32(function.lang),
+            );
+
+            let mut insert_demangled = |options| match name.demangle(options) {
+                Some(demangled) => Self::insert_string(string_bytes, strings, &demangled),
+                None => u32::MAX,
+            };
+
+            demangled_names.push(raw::DemangledName {
+                short_name_offset: insert_demangled(DemangleOptions::name_only()),
+                full_name_offset: insert_demangled(options),
+            });
+        }
+
+        Some(demangled_names)
+    }
+
+    /// Returns the demangled names copied from other SymCaches, if there are any.
+    fn copied_demangled_names(&self) -> Option<Vec<raw::DemangledName>> {
+        if self.demangled_names.is_empty() {
+            return None;
+        }
+
+        let missing = raw::DemangledName {
+            short_name_offset: u32::MAX,
+            full_name_offset: u32::MAX,
+        };
+        let demangled_names = (0..self.functions.len() as u32)
+            .map(|idx| self.demangled_names.get(&idx).unwrap_or(&missing).clone())
+            .collect();
+
+        Some(demangled_names)
+    }
+
+    /// Merges all spilled ranges, if any, so that they can be read during serialization.
+    ///
+    /// Afterwards, `ranges` is empty and only receives the ranges of symbols and the trailing
+    /// sentinel, which are never inserted over an existing range.
+    fn resolve_spilled_ranges(&mut self) -> std::io::Result<()> {
+        let spill = match self.spill {
+            Some(ref mut spill) => spill,
+            None => return Ok(()),
+        };
+
+        spill.take_error()?;
+        if !spill.has_runs() {
+            return Ok(());
+        }
+
+        spill.spill(&self.ranges, &self.weak_ranges)?;
+        self.ranges.clear();
+        self.weak_ranges.clear();
+
+        let mut queries: Vec<_> = self
+            .symbols
+            .iter()
+            .map(|symbol| symbol.address)
+            .chain(self.last_addr)
+            .collect();
+        queries.sort_unstable();
+        queries.dedup();
+
+        spill.resolve(&queries)?;
+        Ok(())
+    }
+
+    /// Builds the function entries and line ranges of the reverse index.
+    fn build_reverse_index(&mut self) -> std::io::Result<(Vec<raw::FunctionEntry>, LineRanges)> {
+        let function_name = |entry: &raw::FunctionEntry| {
+            let function = &self.functions[entry.function_idx as usize];
+            Self::get_string(&self.string_bytes, function.name_offset)
+        };
+
+        let mut function_entries: Vec<_> = self
+            .function_entries
+            .iter()
+            .map(|&(function_idx, address)| raw::FunctionEntry {
+                address,
+                function_idx,
+                _padding: 0,
+            })
+            .filter(|entry| !function_name(entry).is_empty())
+            .collect();
+        function_entries.sort_by(|a, b| {
+            function_name(a)
+                .cmp(function_name(b))
+                .then(a.address.cmp(&b.address))
+                .then(a.function_idx.cmp(&b.function_idx))
+        });
+        function_entries.dedup();
+
+        // Every range maps to the lines of all source locations in its inlining hierarchy.
+        let mut line_ranges = LineRangeSorter::default();
+        let mut ranges = RangeIter::new(&self.ranges, &self.spill);
+        let mut current = ranges.next(&mut self.spill)?;
+        while let Some((start, (source_location, _))) = current {
+            let next = ranges.next(&mut self.spill)?;
+            let end = next.as_ref().map_or(u64::MAX, |&(end, _)| end);
+
+            let mut source_location = Some(&source_location);
+            while let Some(location) = source_location {
+                if location.file_idx != u32::MAX && location.line != 0 {
+                    let line_range = raw::LineRange {
+                        start,
+                        end,
+                        file_idx: location.file_idx,
+                        line: location.line,
+                    };
+                    line_ranges.push(&mut self.spill, line_range)?;
+                }
+                source_location = self
+                    .source_locations
+                    .get_index(location.inlined_into_idx as usize)
+                    .map(|(location, _)| location);
+            }
+
+            current = next;
+        }
+
+        // Adjacent ranges of the same line are merged, which are common for inlined functions.
+        let line_ranges = line_ranges.finish(&mut self.spill)?;
+
+        Ok((function_entries, line_ranges))
+    }
+}
+
+/// Converts an absolute address into a `u32` relative to the range offset.
+///
+/// The range offset is chosen so that all addresses fit, so this only fails for inconsistent data.
+fn narrow_address(address: u64, range_offset: u64) -> std::io::Result<u32> {
+    address
+        .checked_sub(range_offset)
+        .and_then(|address| u32::try_from(address).ok())
+        .ok_or_else(address_out_of_range)
+}
+
+/// The error returned if an address or size does not fit into the `u32` address layout.
+fn address_out_of_range() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "address out of range for 32-bit SymCache layout",
+    )
+}
+
+/// The layout and data of the sections following the header, which are computed during
+/// serialization.
+struct Sections {
+    wide: bool,
+    range_offset: u64,
+    has_columns: bool,
+    demangled_names: Option<Vec<raw::DemangledName>>,
+    reverse_index: Option<(Vec<raw::FunctionEntry>, LineRanges)>,
+}
+
+/// A writer that computes the checksum of all data written to it.
+#[derive(Default)]
+struct ChecksumWriter(crc32fast::Hasher);
+
+impl Write for ChecksumWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
         Ok(())
     }
 }
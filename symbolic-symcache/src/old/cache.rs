@@ -302,6 +302,7 @@ impl<'a> SymCache<'a> {
             line_addr,
             instr_addr: addr,
             line,
+            column: 0,
             lang: Language::from_u32(fun.lang.into()),
             symbol: read_symbol(self.data, self.header.symbols, fun.symbol_id())?,
             filename,
@@ -350,6 +351,11 @@ impl<'a, 'c> Lookup<'a, 'c> {
             inner: None,
         }
     }
+
+    /// Returns the id of the function that the next line match belongs to.
+    pub(crate) fn next_id(&self) -> Option<usize> {
+        self.current.map(|(_, id, _)| id)
+    }
 }
 
 impl<'a, 'c> Iterator for Lookup<'a, 'c> {
@@ -402,6 +408,7 @@ pub struct LineInfo<'a> {
     pub(crate) line_addr: u64,
     pub(crate) instr_addr: u64,
     pub(crate) line: u32,
+    pub(crate) column: u32,
     pub(crate) lang: Language,
     pub(crate) symbol: Option<&'a str>,
     pub(crate) filename: &'a str,
@@ -472,6 +479,13 @@ impl<'a> LineInfo<'a> {
         self.line
     }
 
+    /// The column number within the line, or `0` if unknown.
+    ///
+    /// Columns are only available in SymCaches of version 8 and later.
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+
     /// The source code language.
     pub fn language(&self) -> Language {
         self.lang
@@ -575,6 +589,24 @@ impl<'a> Function<'a> {
         self.record.addr_start()
     }
 
+    /// The size of the function, if it is known.
+    ///
+    /// This is `None` if the function ends before it starts, see [`has_valid_range`].
+    ///
+    /// [`has_valid_range`]: Self::has_valid_range
+    pub fn size(&self) -> Option<u64> {
+        match self.record.addr_end() {
+            u64::MAX => None,
+            end => end.checked_sub(self.address()),
+        }
+    }
+
+    /// Returns false if the function ends before it starts, which means the SymCache is corrupted.
+    pub(crate) fn has_valid_range(&self) -> bool {
+        let end = self.record.addr_end();
+        end == u64::MAX || end >= self.address()
+    }
+
     /// The raw name of the function.
     pub fn symbol(&self) -> &'a str {
         read_symbol(self.data, self.symbols, self.record.symbol_id())
@@ -595,7 +627,7 @@ impl<'a> Function<'a> {
     }
 
     /// The compilation dir of the function.
-    pub fn compilation_dir(&self) -> &str {
+    pub fn compilation_dir(&self) -> &'a str {
         self.record.comp_dir.read_str(self.data).unwrap_or("")
     }
 
@@ -697,7 +729,7 @@ impl<'a> Line<'a> {
     }
 
     /// The base_dir of the line.
-    pub fn base_dir(&self) -> &str {
+    pub fn base_dir(&self) -> &'a str {
         match self.file {
             Some(record) => record.base_dir.read_str(self.data).unwrap_or(""),
             None => "",
@@ -711,6 +743,11 @@ impl<'a> Line<'a> {
             None => "",
         }
     }
+
+    /// Returns true if the file record of the line could be read.
+    pub(crate) fn has_file(&self) -> bool {
+        self.file.is_some()
+    }
 }
 
 impl fmt::Debug for Line<'_> {
@@ -66,6 +66,16 @@ pub enum SymCacheErrorKind {
 
     /// Generic error when writing a symcache, most likely IO.
     WriteFailed,
+
+    /// The `Object` cannot be merged into the symcache, since its architecture or debug identifier
+    /// differs from previously processed objects.
+    IncompatibleObject,
+
+    /// The text representation of a symcache is malformed.
+    BadTextFormat,
+
+    /// The call frame information is not a supported `CfiCache` file.
+    UnsupportedCfi,
 }
 
 impl fmt::Display for SymCacheErrorKind {
@@ -83,6 +93,9 @@ impl fmt::Display for SymCacheErrorKind {
             Self::ValueTooLarge(kind) => write!(f, "{} too large for symcache file format", kind),
             Self::TooManyValues(kind) => write!(f, "too many {}s for symcache", kind),
             Self::WriteFailed => write!(f, "failed to write symcache"),
+            Self::IncompatibleObject => write!(f, "incompatible object for symcache"),
+            Self::BadTextFormat => write!(f, "malformed symcache text"),
+            Self::UnsupportedCfi => write!(f, "unsupported call frame information"),
         }
     }
 }
@@ -20,7 +20,7 @@ pub use crate::preamble::*;
     since = "8.6.0",
     note = "Use symbolic_symcache::SYMCACHE_VERSION instead"
 )]
-pub const SYMCACHE_VERSION: u32 = 7;
+pub const SYMCACHE_VERSION: u32 = crate::SYMCACHE_VERSION;
 
 // Version history:
 //
@@ -249,7 +249,7 @@ impl FuncRecord {
         if self.parent_offset == !0 {
             None
         } else {
-            Some(func_id - (self.parent_offset as usize))
+            func_id.checked_sub(self.parent_offset as usize)
         }
     }
 }
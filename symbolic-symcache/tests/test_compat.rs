@@ -1,5 +1,7 @@
+use std::io::Cursor;
+
 use symbolic_common::ByteView;
-use symbolic_symcache::SymCache;
+use symbolic_symcache::{new, SymCache, SymCacheWriter};
 use symbolic_testutils::fixture;
 
 use similar_asserts::assert_eq;
@@ -25,3 +27,80 @@ fn test_v1() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Writes the text representation of a SymCache.
+fn write_text(buffer: &[u8]) -> Result<String, Box<dyn std::error::Error>> {
+    let symcache = new::SymCache::parse(buffer)?;
+    let mut text = Vec::new();
+    symcache.write_text(&mut text)?;
+    Ok(String::from_utf8(text)?)
+}
+
+/// Returns the function name, file name and line of every frame at the address.
+#[allow(deprecated)]
+fn lookup_frames(symcache: &SymCache<'_>, addr: u64) -> Vec<(String, String, u32)> {
+    symcache
+        .lookup(addr)
+        .unwrap()
+        .map(|line_info| {
+            let line_info = line_info.unwrap();
+            (
+                line_info.symbol().to_owned(),
+                line_info.filename().to_owned(),
+                line_info.line(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_upgrade_v1() -> Result<(), Box<dyn std::error::Error>> {
+    let buffer = ByteView::open(fixture("symcache/compat/v1.symc"))?;
+    let legacy = SymCache::parse(&buffer)?;
+
+    let mut upgraded = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut upgraded))?;
+    let report = writer.process_symcache(&legacy)?;
+    writer.finish()?;
+
+    assert_eq!(report.version, 1);
+    assert!(report.is_complete(), "{:?}", report);
+
+    let symcache = SymCache::parse(&upgraded)?;
+    assert!(symcache.is_latest());
+    assert_eq!(symcache.debug_id(), legacy.debug_id());
+    assert_eq!(symcache.arch(), legacy.arch());
+
+    // every line of every function resolves to the same frames
+    let mut addrs = Vec::new();
+    for function in legacy.functions() {
+        let function = function?;
+        addrs.push(function.address());
+        for line in function.lines() {
+            addrs.push(function.address() + line?.address());
+        }
+    }
+
+    for addr in addrs {
+        for addr in addr..addr + 2 {
+            assert_eq!(
+                lookup_frames(&symcache, addr),
+                lookup_frames(&legacy, addr),
+                "{:#x}",
+                addr
+            );
+        }
+    }
+
+    // upgrading the upgraded SymCache again keeps its contents
+    let mut again = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut again))?;
+    let report = writer.process_symcache(&symcache)?;
+    writer.finish()?;
+
+    assert_eq!(report.version, symbolic_symcache::SYMCACHE_VERSION);
+    assert_eq!(write_text(&again)?, write_text(&upgraded)?);
+
+    Ok(())
+}
@@ -1,11 +1,11 @@
 use std::fmt;
 use std::io::Cursor;
 
-use symbolic_common::{ByteView, SelfCell};
+use symbolic_common::{ByteView, Language, Name, NameMangling, SelfCell};
 use symbolic_debuginfo::macho::BcSymbolMap;
-use symbolic_debuginfo::Object;
-use symbolic_symcache::transform::{self, Transformer};
-use symbolic_symcache::{SymCache, SymCacheWriter};
+use symbolic_debuginfo::{FileInfo, Function, LineInfo, Object, Symbol};
+use symbolic_symcache::transform::{self, FunctionFilter, Transformer};
+use symbolic_symcache::{new, SymCache, SymCacheWriter};
 
 type Error = Box<dyn std::error::Error>;
 
@@ -89,3 +89,319 @@ fn test_transformer_symbolmap() -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Filters functions by name and source locations by directory.
+struct Filter;
+
+impl Transformer for Filter {
+    fn filter_function(&self, f: &transform::Function<'_>) -> FunctionFilter {
+        if f.name.starts_with("core::iter::") {
+            FunctionFilter::Collapse
+        } else if f.name.starts_with("internal::") {
+            FunctionFilter::Drop
+        } else {
+            FunctionFilter::Keep
+        }
+    }
+
+    fn filter_source_location(&self, sl: &transform::SourceLocation<'_>) -> bool {
+        !matches!(sl.file.directory, Some(ref dir) if dir.starts_with("/rustc/"))
+    }
+}
+
+fn line(address: u64, size: u64, dir: &'static str, line: u64) -> LineInfo<'static> {
+    LineInfo {
+        address,
+        size: Some(size),
+        file: FileInfo {
+            name: b"lib.rs",
+            dir: dir.as_bytes(),
+        },
+        line,
+        column: 0,
+    }
+}
+
+fn function(
+    name: &'static str,
+    address: u64,
+    size: u64,
+    lines: Vec<LineInfo<'static>>,
+    inlinees: Vec<Function<'static>>,
+    inline: bool,
+) -> Function<'static> {
+    Function {
+        address,
+        size,
+        name: Name::new(name, NameMangling::Unmangled, Language::Rust),
+        compilation_dir: b"",
+        lines,
+        inlinees,
+        inline,
+    }
+}
+
+/// Writes `main` at `0x1000` with the inline chain `main > core::iter::map > helper` at `0x1018`,
+/// followed by `internal::setup` and symbols.
+fn write_filtered() -> Result<Vec<u8>, Error> {
+    let helper = function(
+        "helper",
+        0x1018,
+        0x10,
+        vec![line(0x1018, 0x10, "/src", 3)],
+        vec![],
+        true,
+    );
+    let map = function(
+        "core::iter::map",
+        0x1010,
+        0x20,
+        vec![
+            line(0x1010, 0x8, "/src/iter", 5),
+            line(0x1018, 0x10, "/src/iter", 6),
+            line(0x1028, 0x8, "/src/iter", 7),
+        ],
+        vec![helper],
+        true,
+    );
+    let main = function(
+        "main",
+        0x1000,
+        0x40,
+        vec![
+            line(0x1000, 0x10, "/src", 10),
+            line(0x1010, 0x20, "/src", 12),
+            line(0x1030, 0x10, "/rustc/0123/library/std", 14),
+        ],
+        vec![map],
+        false,
+    );
+    let setup = function(
+        "internal::setup",
+        0x1040,
+        0x10,
+        vec![line(0x1040, 0x10, "/src", 20)],
+        vec![],
+        false,
+    );
+
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.add_transformer(Filter);
+    writer.add_function(main)?;
+    writer.add_function(setup)?;
+
+    for (address, name) in [
+        (0x1040, "internal::setup"),
+        (0x1050, "callback"),
+        (0x1060, "internal::callback"),
+    ] {
+        writer.add_symbol(Symbol {
+            name: Some(name.into()),
+            address,
+            size: 0,
+        })?;
+    }
+
+    writer.finish()?;
+    Ok(buffer)
+}
+
+fn frames(symcache: &new::SymCache<'_>, addr: u64) -> Vec<(String, u32)> {
+    symcache
+        .lookup(addr)
+        .map(|sl| {
+            let name = sl.function().and_then(|f| f.name()).unwrap_or_default();
+            (name.to_owned(), sl.line())
+        })
+        .collect()
+}
+
+#[test]
+fn test_transformer_collapse_function() -> Result<(), Error> {
+    let buffer = write_filtered()?;
+    let symcache = new::SymCache::parse(&buffer)?;
+
+    assert_eq!(frames(&symcache, 0x1000), [("main".into(), 10)]);
+    assert_eq!(frames(&symcache, 0x1010), [("main".into(), 12)]);
+    // the inlinee of the collapsed function is inlined into its caller
+    assert_eq!(
+        frames(&symcache, 0x1020),
+        [("helper".into(), 3), ("main".into(), 12)]
+    );
+    assert_eq!(frames(&symcache, 0x1028), [("main".into(), 12)]);
+    assert!(symcache
+        .functions()
+        .all(|f| f.name() != Some("core::iter::map")));
+
+    Ok(())
+}
+
+#[test]
+fn test_transformer_collapse_adjacent_functions() -> Result<(), Error> {
+    // `main > core::iter::map > helper` at `0x1018`, followed by `main > core::iter::filter`
+    let helper = function(
+        "helper",
+        0x1018,
+        0x8,
+        vec![line(0x1018, 0x8, "/src", 3)],
+        vec![],
+        true,
+    );
+    let map = function(
+        "core::iter::map",
+        0x1010,
+        0x10,
+        vec![
+            line(0x1010, 0x8, "/src/iter", 5),
+            line(0x1018, 0x8, "/src/iter", 6),
+        ],
+        vec![helper],
+        true,
+    );
+    let filter = function(
+        "core::iter::filter",
+        0x1020,
+        0x10,
+        vec![line(0x1020, 0x10, "/src/iter", 8)],
+        vec![],
+        true,
+    );
+    let main = function(
+        "main",
+        0x1000,
+        0x30,
+        vec![
+            line(0x1000, 0x10, "/src", 10),
+            line(0x1010, 0x20, "/src", 12),
+        ],
+        vec![map, filter],
+        false,
+    );
+    let init = function(
+        "init",
+        0x800,
+        0x10,
+        vec![line(0x800, 0x10, "/src", 1)],
+        vec![],
+        false,
+    );
+
+    let write = |spill: bool| -> Result<Vec<u8>, Error> {
+        let mut buffer = Vec::new();
+        let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+        writer.add_transformer(Filter);
+        if spill {
+            // spills the ranges of every function
+            writer.set_spill_storage(Cursor::new(Vec::new()), 1);
+        }
+        writer.add_function(init.clone())?;
+        writer.add_function(main.clone())?;
+        writer.finish()?;
+        Ok(buffer)
+    };
+
+    let buffer = write(false)?;
+    assert!(buffer == write(true)?, "spilling changes the SymCache");
+
+    let symcache = new::SymCache::parse(&buffer)?;
+    assert_eq!(
+        frames(&symcache, 0x1018),
+        [("helper".into(), 3), ("main".into(), 12)]
+    );
+    // the second collapsed function is attributed to the caller, not the inlinee of the first
+    assert_eq!(frames(&symcache, 0x1020), [("main".into(), 12)]);
+
+    Ok(())
+}
+
+#[test]
+fn test_transformer_drop_function() -> Result<(), Error> {
+    let buffer = write_filtered()?;
+    let symcache = new::SymCache::parse(&buffer)?;
+
+    // neither the function nor its symbol are written
+    assert_eq!(frames(&symcache, 0x1044), []);
+    assert_eq!(frames(&symcache, 0x1054), [("callback".into(), 0)]);
+    // the dropped symbol ends the previous one
+    assert_eq!(frames(&symcache, 0x1064), []);
+    assert!(symcache
+        .functions()
+        .all(|f| !matches!(f.name(), Some(name) if name.starts_with("internal::"))));
+
+    Ok(())
+}
+
+#[test]
+fn test_transformer_drop_source_location() -> Result<(), Error> {
+    let buffer = write_filtered()?;
+    let symcache = new::SymCache::parse(&buffer)?;
+
+    let location = symcache.lookup(0x1030).next().unwrap();
+    assert_eq!(location.function().and_then(|f| f.name()), Some("main"));
+    assert!(location.file().is_none());
+    assert_eq!(location.line(), 0);
+    assert_eq!(symcache.lookup(0x1030).count(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_transformer_prefix_map() -> Result<(), Error> {
+    let file = |dir, name| FileInfo { name, dir };
+    let lines = vec![
+        LineInfo {
+            address: 0x1000,
+            size: Some(0x10),
+            file: file(&b"src"[..], &b"main.c"[..]),
+            line: 1,
+            column: 0,
+        },
+        LineInfo {
+            address: 0x1010,
+            size: Some(0x10),
+            file: file(&b"C:\\agent\\_work\\1\\s\\src\\win"[..], &b"main.c"[..]),
+            line: 2,
+            column: 0,
+        },
+        LineInfo {
+            address: 0x1020,
+            size: Some(0x10),
+            file: file(&b"/usr/include/./sys"[..], &b"types.h"[..]),
+            line: 3,
+            column: 0,
+        },
+    ];
+    let main = Function {
+        compilation_dir: b"/home/buildbot/work/1234",
+        ..function("main", 0x1000, 0x30, lines, vec![], false)
+    };
+
+    let mut prefix_map = transform::PathPrefixMap::new();
+    prefix_map.add_mapping("/home/buildbot/work/1234", "");
+    prefix_map.add_mapping("C:\\agent\\_work\\1\\s\\", "");
+
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.add_transformer(prefix_map);
+    writer.add_function(main)?;
+    writer.finish()?;
+
+    let symcache = new::SymCache::parse(&buffer)?;
+    let path = |addr| {
+        let location = symcache.lookup(addr).next().unwrap();
+        location.file().unwrap().full_path()
+    };
+
+    assert_eq!(path(0x1000), "src/main.c");
+    assert_eq!(path(0x1010), "src/win/main.c");
+    // paths without a matching prefix are cleaned, but keep the remapped compilation directory
+    assert_eq!(path(0x1020), "/usr/include/sys/types.h");
+    let file = symcache.lookup(0x1020).next().unwrap().file().unwrap();
+    assert_eq!(file.comp_dir(), None);
+
+    let function = symcache.lookup(0x1000).next().unwrap().function().unwrap();
+    assert_eq!(function.comp_dir(), None);
+
+    Ok(())
+}
@@ -1,11 +1,14 @@
 use std::fmt;
-use std::io::Cursor;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
 
-use symbolic_common::ByteView;
-use symbolic_debuginfo::Object;
-use symbolic_symcache::{SymCache, SymCacheWriter};
+use symbolic_common::{ByteView, Language, Name, NameMangling};
+use symbolic_debuginfo::{FileInfo, Function, LineInfo, Object, Symbol};
+use symbolic_minidump::cfi::CfiCache;
+use symbolic_symcache::{new, transform, SymCache, SymCacheErrorKind, SymCacheWriter};
 use symbolic_testutils::fixture;
 
+#[cfg(feature = "demangle")]
+use symbolic_demangle::{Demangle, DemangleOptions};
 #[cfg(feature = "il2cpp")]
 use symbolic_il2cpp::usym::UsymSymbols;
 
@@ -57,7 +60,7 @@ fn test_write_header_linux() -> Result<(), Error> {
     let symcache = SymCache::parse(&buffer)?;
     insta::assert_debug_snapshot!(symcache, @r###"
     SymCache {
-        version: 7,
+        version: 8,
         debug_id: DebugId {
             uuid: "c0bcc3f1-9827-fe65-3058-404b2831d9e6",
             appendix: 0,
@@ -65,9 +68,9 @@ fn test_write_header_linux() -> Result<(), Error> {
         arch: Amd64,
         files: 55,
         functions: 697,
-        source_locations: 8236,
-        ranges: 6762,
-        string_bytes: 52180,
+        source_locations: 8217,
+        ranges: 6761,
+        string_bytes: 51090,
     }
     "###);
 
@@ -97,7 +100,7 @@ fn test_write_header_macos() -> Result<(), Error> {
     let symcache = SymCache::parse(&buffer)?;
     insta::assert_debug_snapshot!(symcache, @r###"
     SymCache {
-        version: 7,
+        version: 8,
         debug_id: DebugId {
             uuid: "67e9247c-814e-392b-a027-dbde6748fcbf",
             appendix: 0,
@@ -105,8 +108,8 @@ fn test_write_header_macos() -> Result<(), Error> {
         arch: Amd64,
         files: 36,
         functions: 639,
-        source_locations: 6033,
-        ranges: 4591,
+        source_locations: 6007,
+        ranges: 4590,
         string_bytes: 42829,
     }
     "###);
@@ -254,7 +257,7 @@ fn test_mapless_usym() -> Result<(), Error> {
 
     insta::assert_debug_snapshot!(cache, @r###"
     SymCache {
-        version: 7,
+        version: 8,
         debug_id: DebugId {
             uuid: "153d10d1-0db0-33d6-aacd-a4e1948da97b",
             appendix: 0,
@@ -287,7 +290,7 @@ fn test_usym() -> Result<(), Error> {
 
     insta::assert_debug_snapshot!(cache, @r###"
     SymCache {
-        version: 7,
+        version: 8,
         debug_id: DebugId {
             uuid: "153d10d1-0db0-33d6-aacd-a4e1948da97b",
             appendix: 0,
@@ -321,3 +324,1052 @@ fn test_write_functions_usym() -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Writes a SymCache containing a symbol at each of the given addresses.
+fn write_symbols(addresses: &[u64]) -> Result<Vec<u8>, Error> {
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+
+    for (index, &address) in addresses.iter().enumerate() {
+        writer.add_symbol(Symbol {
+            name: Some(format!("symbol_{}", index).into()),
+            address,
+            size: 0,
+        })?;
+    }
+
+    writer.finish()?;
+    Ok(buffer)
+}
+
+#[test]
+fn test_lookup_large_addresses() -> Result<(), Error> {
+    // All addresses fit into a `u32` relative to the first one.
+    let buffer = write_symbols(&[0xffff_ffff_8000_1000, 0xffff_ffff_8000_2000])?;
+    let symcache = SymCache::parse(&buffer)?;
+
+    let symbols = symcache
+        .lookup(0xffff_ffff_8000_1800)?
+        .collect::<Vec<_>>()?;
+    assert_eq!(symbols.len(), 1);
+    assert_eq!(symbols[0].symbol(), "symbol_0");
+    assert_eq!(symbols[0].function_address(), 0xffff_ffff_8000_1000);
+
+    let symbols = symcache
+        .lookup(0xffff_ffff_8000_2010)?
+        .collect::<Vec<_>>()?;
+    assert_eq!(symbols[0].symbol(), "symbol_1");
+
+    assert!(symcache.lookup(0x1800)?.next().is_none());
+    assert!(symcache.lookup(0xffff_ffff_8000_0fff)?.next().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_lookup_wide_addresses() -> Result<(), Error> {
+    // The address span does not fit into a `u32`, so addresses are stored as `u64`.
+    let buffer = write_symbols(&[0x1000, 0x2_0000_1000])?;
+    let compact = write_symbols(&[0x1000, 0x2000])?;
+    assert!(buffer.len() > compact.len());
+
+    let symcache = SymCache::parse(&buffer)?;
+
+    let symbols = symcache.lookup(0x2_0000_0000)?.collect::<Vec<_>>()?;
+    assert_eq!(symbols[0].symbol(), "symbol_0");
+
+    let symbols = symcache.lookup(0x2_0000_1004)?.collect::<Vec<_>>()?;
+    assert_eq!(symbols[0].symbol(), "symbol_1");
+    assert_eq!(symbols[0].function_address(), 0x2_0000_1000);
+
+    Ok(())
+}
+
+#[test]
+fn test_read_v7() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("symcache/compat/v7.symc"))?;
+    let symcache = SymCache::parse(&buffer)?;
+    assert_eq!(symcache.version(), 7);
+    assert!(!symcache.is_latest());
+
+    let object_buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&object_buffer)?;
+    let mut latest_buffer = Vec::new();
+    SymCacheWriter::write_object(&object, Cursor::new(&mut latest_buffer))?;
+    let latest = SymCache::parse(&latest_buffer)?;
+
+    for addr in [0x1c70, 0x1d16, 0x1ec7, 0x3cb0, 0x14c00] {
+        let expected = latest.lookup(addr)?.collect::<Vec<_>>()?;
+        let actual = symcache.lookup(addr)?.collect::<Vec<_>>()?;
+        assert_eq!(actual, expected, "lookup of {:#x} differs", addr);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_lookup_columns() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("macos/crash.dSYM/Contents/Resources/DWARF/crash"))?;
+    let object = Object::parse(&buffer)?;
+
+    let mut buffer = Vec::new();
+    SymCacheWriter::write_object(&object, Cursor::new(&mut buffer))?;
+    let symcache = new::SymCache::parse(&buffer)?;
+
+    let sl = symcache.lookup(0x1c90).next().unwrap();
+    assert_eq!((sl.line(), sl.column()), (59, 34));
+    let sl = symcache.lookup(0x1cc0).next().unwrap();
+    assert_eq!((sl.line(), sl.column()), (67, 29));
+
+    // version 7 did not record columns
+    let buffer = ByteView::open(fixture("symcache/compat/v7.symc"))?;
+    let symcache = new::SymCache::parse(&buffer)?;
+    let sl = symcache.lookup(0x1c90).next().unwrap();
+    assert_eq!(sl.column(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_lookup_batch() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&buffer)?;
+
+    let mut buffer = Vec::new();
+    SymCacheWriter::write_object(&object, Cursor::new(&mut buffer))?;
+
+    // unsorted, with duplicates and addresses outside of any function
+    let addrs = [
+        0x1d16,
+        0x1c70,
+        0x0,
+        0x3cb0,
+        0x1c70,
+        0x14c00,
+        u64::MAX,
+        0x1ec7,
+    ];
+
+    let symcache = new::SymCache::parse(&buffer)?;
+    let batch = symcache.lookup_batch(&addrs);
+    assert_eq!(batch.len(), addrs.len());
+    for (iter, &addr) in batch.into_iter().zip(&addrs) {
+        let expected: Vec<_> = symcache.lookup(addr).collect();
+        assert_eq!(iter.collect::<Vec<_>>(), expected, "lookup of {:#x}", addr);
+    }
+
+    let symcache = SymCache::parse(&buffer)?;
+    let batch = symcache.lookup_batch(&addrs)?;
+    for (lookup, &addr) in batch.into_iter().zip(&addrs) {
+        let expected = symcache.lookup(addr)?.collect::<Vec<_>>()?;
+        assert_eq!(
+            lookup.collect::<Vec<_>>()?,
+            expected,
+            "lookup of {:#x}",
+            addr
+        );
+    }
+
+    // sorted addresses covering many ranges, some of which are skipped
+    let symcache = new::SymCache::parse(&buffer)?;
+    let addrs: Vec<u64> = (0x1000..0x4000).step_by(7).collect();
+    let batch = symcache.lookup_batch(&addrs);
+    for (iter, &addr) in batch.into_iter().zip(&addrs) {
+        let expected: Vec<_> = symcache.lookup(addr).collect();
+        assert_eq!(iter.collect::<Vec<_>>(), expected, "lookup of {:#x}", addr);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_reverse_lookups() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&buffer)?;
+
+    let mut writer = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+    writer.set_reverse_index(true);
+    writer.process_object(&object)?;
+    let buffer = writer.finish()?.into_inner();
+    let symcache = new::SymCache::parse(&buffer)?;
+    assert!(symcache.has_reverse_index());
+
+    let entries: Vec<_> = symcache.lookup_function_name("main").collect();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].address(), 0x1c70);
+    assert!(!entries[0].is_inlined());
+
+    // inlined copies are found by the start of every copy
+    let inlined = symcache
+        .functions()
+        .find(|f| f.entry_pc() == u64::MAX)
+        .and_then(|f| f.name())
+        .expect("inlined function");
+    let entries: Vec<_> = symcache.lookup_function_name(inlined).collect();
+    assert!(!entries.is_empty());
+    for entry in entries.iter().filter(|e| e.is_inlined()) {
+        let mut frames = symcache.lookup(entry.address());
+        assert!(frames.any(|sl| sl.function().and_then(|f| f.name()) == Some(inlined)));
+    }
+
+    let frame = symcache.lookup(0x1c90).next().unwrap();
+    let path = frame.file().unwrap().full_path();
+    let ranges = symcache.lookup_file_line(&path, frame.line());
+    assert!(ranges.iter().any(|range| range.contains(&0x1c90)));
+    assert!(symcache.lookup_file_line(&path, 0).is_empty());
+    assert_eq!(symcache.lookup_function_name("does_not_exist").count(), 0);
+
+    // the reverse index is optional
+    let mut buffer = Vec::new();
+    SymCacheWriter::write_object(&object, Cursor::new(&mut buffer))?;
+    let symcache = new::SymCache::parse(&buffer)?;
+    assert!(!symcache.has_reverse_index());
+    assert_eq!(symcache.lookup_function_name("main").count(), 0);
+    assert!(symcache.lookup_file_line(&path, frame.line()).is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_function_ranges() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&buffer)?;
+
+    let mut buffer = Vec::new();
+    SymCacheWriter::write_object(&object, Cursor::new(&mut buffer))?;
+    let symcache = new::SymCache::parse(&buffer)?;
+
+    let main = symcache
+        .functions()
+        .find(|f| f.name() == Some("main"))
+        .expect("main function");
+    assert_eq!(main.entry_pc(), 0x1c70);
+    assert_eq!(main.range(), Some(0x1c70..0x1dbc));
+
+    // inlined functions do not have a single entry pc or size
+    let inlined = symcache
+        .functions()
+        .find(|f| f.entry_pc() == u64::MAX)
+        .expect("inlined function");
+    assert_eq!(inlined.end_pc(), None);
+
+    // version 7 did not record function sizes
+    let buffer = ByteView::open(fixture("symcache/compat/v7.symc"))?;
+    let symcache = new::SymCache::parse(&buffer)?;
+    assert_eq!(symcache.functions().len(), 697);
+    assert!(symcache.functions().all(|f| f.end_pc().is_none()));
+
+    Ok(())
+}
+
+#[test]
+fn test_function_below_first_range() -> Result<(), Error> {
+    // The range offset must also cover function entries that start before the first range.
+    let text = r#"
+SYMCACHE 8
+DEBUG_ID c0bcc3f1-9827-fe65-3058-404b2831d9e6
+ARCH x86_64
+
+FILES
+0 "main.c" "src" "/home/user/project"
+
+FUNCTIONS
+0 0x100000000 0x2000 c "main" "/home/user/project"
+
+LOCATIONS
+
+RANGES
+0x100001000 0 10 0 0 -
+0x100002000 -
+"#;
+
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.process_text(text)?;
+    writer.finish()?;
+
+    let symcache = new::SymCache::parse(&buffer)?;
+    let main = symcache.functions().next().expect("main function");
+    assert_eq!(main.entry_pc(), 0x1_0000_0000);
+    assert_eq!(main.range(), Some(0x1_0000_0000..0x1_0000_2000));
+
+    Ok(())
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_write_object_par() -> Result<(), Error> {
+    let fixtures = [
+        "linux/crash.debug",
+        "macos/crash.dSYM/Contents/Resources/DWARF/crash",
+        "windows/crash.pdb",
+    ];
+
+    for path in fixtures {
+        let buffer = ByteView::open(fixture(path))?;
+        let object = Object::parse(&buffer)?;
+
+        let mut expected = Vec::new();
+        SymCacheWriter::write_object(&object, Cursor::new(&mut expected))?;
+
+        let mut actual = Vec::new();
+        let mut writer = SymCacheWriter::new(Cursor::new(&mut actual))?;
+        writer.process_object_par(&object)?;
+        writer.finish()?;
+
+        assert!(actual == expected, "SymCache for {} differs", path);
+    }
+
+    Ok(())
+}
+
+/// Calls `f` with every executable and its separate debug file from the fixtures.
+fn for_each_debug_file_pair<F>(mut f: F) -> Result<(), Error>
+where
+    F: for<'data> FnMut(&Object<'data>, &Object<'data>) -> Result<(), Error>,
+{
+    let fixtures = [
+        ("linux/crash", "linux/crash.debug"),
+        (
+            "macos/crash",
+            "macos/crash.dSYM/Contents/Resources/DWARF/crash",
+        ),
+        ("windows/crash.exe", "windows/crash.pdb"),
+    ];
+
+    for (executable, debug_file) in fixtures {
+        let executable_buffer = ByteView::open(fixture(executable))?;
+        let debug_buffer = ByteView::open(fixture(debug_file))?;
+        let executable = Object::parse(&executable_buffer)?;
+        let debug_file = Object::parse(&debug_buffer)?;
+        f(&executable, &debug_file)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a SymCache from all given objects, in the given order.
+fn write_merged(objects: &[&Object<'_>]) -> Result<Vec<u8>, Error> {
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    for object in objects {
+        writer.process_object(*object)?;
+    }
+    writer.finish()?;
+
+    Ok(buffer)
+}
+
+#[test]
+fn test_merge_objects() -> Result<(), Error> {
+    let mut outside_symbols = 0;
+    for_each_debug_file_pair(|executable, debug_file| {
+        let expected = write_merged(&[debug_file])?;
+        let merged = write_merged(&[executable, debug_file])?;
+        let reversed = write_merged(&[debug_file, executable])?;
+        assert!(merged == reversed, "merge order changes the SymCache");
+
+        let expected = new::SymCache::parse(&expected)?;
+        let merged = new::SymCache::parse(&merged)?;
+        assert_eq!(merged.debug_id(), debug_file.debug_id());
+        assert_eq!(merged.arch(), debug_file.arch());
+
+        // symbols of the executable must not shadow functions from debug information
+        assert_eq!(merged.functions().len(), expected.functions().len());
+
+        let addresses: Vec<_> = expected
+            .functions()
+            .filter_map(|function| function.range())
+            .map(|range| range.start)
+            .collect();
+        assert!(!addresses.is_empty());
+
+        let names = |symcache: &new::SymCache<'_>, address| {
+            symcache
+                .lookup(address)
+                .map(|location| {
+                    location
+                        .function()
+                        .and_then(|f| f.name())
+                        .map(str::to_owned)
+                })
+                .collect::<Vec<_>>()
+        };
+        for address in addresses {
+            assert_eq!(names(&merged, address), names(&expected, address));
+        }
+
+        // symbols of the executable outside of all functions from debug information are merged
+        let functions = debug_file
+            .debug_session()?
+            .functions()
+            .map(|function| function.map(|f| f.address..f.end_address()))
+            .collect::<Result<Vec<_>, _>>()?;
+        for symbol in executable.symbols() {
+            if functions
+                .iter()
+                .any(|range| range.contains(&symbol.address))
+            {
+                continue;
+            }
+
+            let name = symbol.name().map(str::to_owned);
+            assert!(
+                names(&merged, symbol.address).contains(&name),
+                "symbol {:?} missing from the merged SymCache",
+                name
+            );
+            outside_symbols += 1;
+        }
+
+        Ok(())
+    })?;
+    assert!(outside_symbols > 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_merge_overlapping_functions() -> Result<(), Error> {
+    let function = |name: &'static str, address, size| Function {
+        address,
+        size,
+        name: Name::new(name, NameMangling::Unmangled, Language::C),
+        compilation_dir: b"",
+        lines: vec![LineInfo {
+            address,
+            size: Some(size),
+            file: FileInfo {
+                name: b"main.c",
+                dir: b"",
+            },
+            line: 1,
+            column: 0,
+        }],
+        inlinees: vec![],
+        inline: false,
+    };
+
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.add_function(function("outer", 0x1000, 0x1000))?;
+    writer.add_function(function("nested", 0x1100, 0x100))?;
+    writer.add_symbol(Symbol {
+        name: Some("shadowed".into()),
+        address: 0x1800,
+        size: 0,
+    })?;
+    writer.finish()?;
+
+    // the symbol is covered by `outer`, even though `nested` starts closer to it
+    let symcache = new::SymCache::parse(&buffer)?;
+    let names: Vec<_> = symcache.functions().filter_map(|f| f.name()).collect();
+    assert_eq!(names, ["outer", "nested"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_merge_incompatible_objects() -> Result<(), Error> {
+    let linux_buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let linux = Object::parse(&linux_buffer)?;
+    let macos_buffer = ByteView::open(fixture("macos/crash"))?;
+    let macos = Object::parse(&macos_buffer)?;
+
+    let mut writer = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+    writer.process_object(&linux)?;
+    let error = writer.process_object(&macos).unwrap_err();
+    assert_eq!(error.kind(), SymCacheErrorKind::IncompatibleObject);
+
+    Ok(())
+}
+
+#[test]
+fn test_spill_storage() -> Result<(), Error> {
+    for_each_debug_file_pair(|executable, debug_file| {
+        let write = |buffer_size: Option<usize>| -> Result<Vec<u8>, Error> {
+            let mut buffer = Vec::new();
+            let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+            writer.set_reverse_index(true);
+            if let Some(buffer_size) = buffer_size {
+                writer.set_spill_storage(Cursor::new(Vec::new()), buffer_size);
+            }
+            writer.process_object(debug_file)?;
+            writer.process_object(executable)?;
+            writer.finish()?;
+            Ok(buffer)
+        };
+
+        let expected = write(None)?;
+        for buffer_size in [1 << 10, 1 << 16, 1 << 30] {
+            let actual = write(Some(buffer_size))?;
+            assert!(
+                actual == expected,
+                "SymCache for {} differs with a range buffer size of {}",
+                debug_file.debug_id(),
+                buffer_size
+            );
+        }
+
+        Ok(())
+    })
+}
+
+/// Writes the text representation of a SymCache.
+fn write_text(buffer: &[u8]) -> Result<String, Error> {
+    let symcache = new::SymCache::parse(buffer)?;
+    let mut text = Vec::new();
+    symcache.write_text(&mut text)?;
+    Ok(String::from_utf8(text)?)
+}
+
+/// A spill storage that fails to write.
+struct FullStorage;
+
+impl Read for FullStorage {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Ok(0)
+    }
+}
+
+impl Write for FullStorage {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::WriteZero, "storage is full"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for FullStorage {
+    fn seek(&mut self, _pos: SeekFrom) -> io::Result<u64> {
+        Ok(0)
+    }
+}
+
+#[test]
+fn test_spill_storage_error() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&buffer)?;
+
+    let mut writer = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+    writer.set_spill_storage(FullStorage, 1 << 10);
+    writer.process_object(&object)?;
+    let error = writer.finish().unwrap_err();
+    assert_eq!(error.kind(), SymCacheErrorKind::WriteFailed);
+
+    Ok(())
+}
+
+#[test]
+fn test_text_roundtrip() -> Result<(), Error> {
+    let fixtures = [
+        "linux/crash.debug",
+        "macos/crash.dSYM/Contents/Resources/DWARF/crash",
+        "windows/crash.pdb",
+    ];
+
+    for path in fixtures {
+        let buffer = ByteView::open(fixture(path))?;
+        let object = Object::parse(&buffer)?;
+
+        let mut buffer = Vec::new();
+        SymCacheWriter::write_object(&object, Cursor::new(&mut buffer))?;
+        let text = write_text(&buffer)?;
+
+        let mut roundtrip = Vec::new();
+        let mut writer = SymCacheWriter::new(Cursor::new(&mut roundtrip))?;
+        writer.process_text(&text)?;
+        writer.finish()?;
+
+        similar_asserts::assert_eq!(write_text(&roundtrip)?, text);
+    }
+
+    Ok(())
+}
+
+/// A SymCache in the text format with an inlined function, a column and a trailing range.
+const HANDWRITTEN_TEXT: &str = r#"
+SYMCACHE 8
+DEBUG_ID c0bcc3f1-9827-fe65-3058-404b2831d9e6
+ARCH x86_64
+
+FILES
+# index path_name directory comp_dir
+0 "main.c" "src" "/home/user/project"
+
+FUNCTIONS
+# index entry_pc size language name comp_dir
+0 0x1000 0x40 c "main" "/home/user/project"
+1 - 0x0 c "inlined_helper" -
+
+LOCATIONS
+0 0 12 5 0 -
+
+RANGES
+0x1000 0 10 1 0 -
+0x1010 0 3 9 1 0
+0x1040 -
+"#;
+
+#[test]
+fn test_text_handwritten() -> Result<(), Error> {
+    let text = HANDWRITTEN_TEXT;
+
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.process_text(text)?;
+    writer.finish()?;
+
+    let symcache = new::SymCache::parse(&buffer)?;
+    assert_eq!(symcache.arch(), symbolic_common::Arch::Amd64);
+
+    let frames: Vec<_> = symcache
+        .lookup(0x1020)
+        .map(|location| {
+            let function = location.function().unwrap();
+            let file = location.file().unwrap();
+            (
+                function.name().unwrap(),
+                file.full_path(),
+                location.line(),
+                location.column(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        frames,
+        [
+            (
+                "inlined_helper",
+                "/home/user/project/src/main.c".into(),
+                3,
+                9
+            ),
+            ("main", "/home/user/project/src/main.c".into(), 12, 5),
+        ]
+    );
+
+    assert_eq!(symcache.lookup(0x1040).count(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_text_errors() -> Result<(), Error> {
+    let invalid = [
+        "FILES\n1 \"main.c\" - -\n",
+        "FILES\n0 main.c - -\n",
+        "FILES\n0 \"main.c - -\n",
+        "FUNCTIONS\n0 0x1000 0x10 klingon \"main\" -\n",
+        "RANGES\n0x1000 0 1 0 0 -\n",
+        "RANGES\n0x1000 -\n0x1000 -\n",
+        "ARCH x86_64 extra\n",
+    ];
+
+    for text in invalid {
+        let mut writer = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+        let error = writer.process_text(text).unwrap_err();
+        assert_eq!(error.kind(), SymCacheErrorKind::BadTextFormat, "{:?}", text);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "demangle")]
+#[test]
+fn test_write_demangled_names() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&buffer)?;
+
+    let options = DemangleOptions::name_only().parameters(true);
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.set_demangle_options(Some(options));
+    writer.process_object(&object)?;
+    writer.finish()?;
+
+    let symcache = new::SymCache::parse(&buffer)?;
+    let mut num_demangled = 0;
+    for function in symcache.functions() {
+        let name = symbolic_common::Name::new(
+            function.name().unwrap(),
+            symbolic_common::NameMangling::Unknown,
+            function.language(),
+        );
+
+        let short_name = name.demangle(DemangleOptions::name_only());
+        assert_eq!(function.short_name(), short_name.as_deref());
+        assert_eq!(function.full_name(), name.demangle(options).as_deref());
+        num_demangled += short_name.is_some() as usize;
+    }
+    assert!(num_demangled > 0);
+
+    // lookups return the demangled names of inlined functions, too
+    let frames: Vec<_> = symcache
+        .lookup(0x1b2c)
+        .filter_map(|sl| sl.function()?.short_name())
+        .collect();
+    assert!(!frames.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_write_without_demangled_names() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&buffer)?;
+    let buffer = SymCacheWriter::write_object(&object, Cursor::new(Vec::new()))?.into_inner();
+
+    let symcache = new::SymCache::parse(&buffer)?;
+    assert!(symcache
+        .functions()
+        .all(|f| f.short_name().is_none() && f.full_name().is_none()));
+
+    Ok(())
+}
+
+/// Writes a SymCache from its text format, optionally with a checksum.
+fn write_from_text(text: &str, checksum: bool) -> Result<Vec<u8>, Error> {
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.set_checksum(checksum);
+    writer.process_text(text)?;
+    writer.finish()?;
+    Ok(buffer)
+}
+
+#[test]
+fn test_validate() -> Result<(), Error> {
+    // The handwritten SymCache consists of the header, one file, two functions, four source
+    // locations with columns, and three ranges. Every section is aligned to 8 bytes.
+    const FILES: usize = 80;
+    const SOURCE_LOCATIONS: usize = FILES + 16 + 40;
+    const RANGES: usize = SOURCE_LOCATIONS + 64 + 16;
+
+    let buffer = write_from_text(HANDWRITTEN_TEXT, false)?;
+    new::SymCache::parse(&buffer)?.validate()?;
+
+    let corruptions = [
+        // the path name of the file
+        (FILES + 8, 0xffff, new::Error::InvalidStringOffset),
+        // the file, function and caller of the first source location
+        (SOURCE_LOCATIONS, 1, new::Error::InvalidFileIndex),
+        (SOURCE_LOCATIONS + 8, 2, new::Error::InvalidFunctionIndex),
+        (
+            SOURCE_LOCATIONS + 12,
+            4,
+            new::Error::InvalidSourceLocationIndex,
+        ),
+        (SOURCE_LOCATIONS + 12, 0, new::Error::CyclicInlineChain),
+        // the start of the second range
+        (RANGES + 4, 0x1000, new::Error::UnsortedRanges),
+    ];
+
+    for (offset, value, expected) in corruptions {
+        let mut corrupted = buffer.clone();
+        corrupted[offset..offset + 4].copy_from_slice(&u32::to_ne_bytes(value));
+
+        let symcache = new::SymCache::parse(&corrupted)?;
+        let error = symcache.validate().unwrap_err();
+        assert_eq!(error.to_string(), expected.to_string(), "{:#x}", offset);
+    }
+
+    // One source location less and four more ranges keep the size of all sections, but lookups
+    // would no longer find a source location for every range.
+    let mut corrupted = buffer.clone();
+    corrupted[52..56].copy_from_slice(&u32::to_ne_bytes(3));
+    corrupted[56..60].copy_from_slice(&u32::to_ne_bytes(7));
+    let error = new::SymCache::parse(&corrupted).unwrap_err();
+    assert!(matches!(error, new::Error::BadFormatLength));
+
+    // A flag unknown to this version announces data that cannot be read.
+    let mut corrupted = buffer;
+    corrupted[72..76].copy_from_slice(&u32::to_ne_bytes(0x8000_0000));
+    let error = new::SymCache::parse(&corrupted).unwrap_err();
+    assert!(matches!(error, new::Error::UnknownFlags));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "demangle")]
+fn test_validate_demangled_names_and_reverse_index() -> Result<(), Error> {
+    let text = r#"
+SYMCACHE 8
+DEBUG_ID c0bcc3f1-9827-fe65-3058-404b2831d9e6
+ARCH x86_64
+
+FILES
+0 "main.c" "src" "/home/user/project"
+
+FUNCTIONS
+0 0x1000 0x10 c "main" "/home/user/project"
+1 0x1010 0x10 c "helper" "/home/user/project"
+
+RANGES
+0x1000 0 10 1 0 -
+0x1010 0 20 1 1 -
+0x1020 -
+"#;
+
+    // The SymCache consists of the header, one file, two functions and their demangled names,
+    // two source locations, three ranges and the string data. The reverse index at the end
+    // contains two function entries and two line ranges.
+    const FUNCTIONS: usize = 96;
+    const DEMANGLED_NAMES: usize = FUNCTIONS + 40;
+
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.set_demangle_options(Some(DemangleOptions::complete()));
+    writer.set_reverse_index(true);
+    writer.process_text(text)?;
+    writer.finish()?;
+    new::SymCache::parse(&buffer)?.validate()?;
+
+    let line_ranges = buffer.len() - 2 * 24;
+    let function_entries = line_ranges - 2 * 16;
+
+    let corruptions = [
+        // a demangled name, the first string, for a function without a name
+        (
+            &[(FUNCTIONS + 20, u32::MAX), (DEMANGLED_NAMES + 8, 0)][..],
+            new::Error::InvalidDemangledNames,
+        ),
+        // "helper" at 0x1010 becomes "main" at 0x1010, sorted before "main" at 0x1000
+        (
+            &[(function_entries + 8, 0)][..],
+            new::Error::UnsortedFunctionEntries,
+        ),
+        // line 10 becomes line 30, sorted before line 20
+        (
+            &[(line_ranges + 20, 30)][..],
+            new::Error::UnsortedLineRanges,
+        ),
+    ];
+
+    for (values, expected) in corruptions {
+        let mut corrupted = buffer.clone();
+        for &(offset, value) in values {
+            corrupted[offset..offset + 4].copy_from_slice(&u32::to_ne_bytes(value));
+        }
+
+        let symcache = new::SymCache::parse(&corrupted)?;
+        let error = symcache.validate().unwrap_err();
+        assert_eq!(error.to_string(), expected.to_string(), "{:?}", values);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_validate_checksum() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&buffer)?;
+
+    let write = |checksum| -> Result<Vec<u8>, Error> {
+        let mut buffer = Vec::new();
+        let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+        writer.set_checksum(checksum);
+        writer.set_reverse_index(true);
+        writer.process_object(&object)?;
+        writer.finish()?;
+        Ok(buffer)
+    };
+
+    // the checksum is only stored in the header
+    let with_checksum = write(true)?;
+    let without_checksum = write(false)?;
+    assert_eq!(with_checksum[80..], without_checksum[80..]);
+    new::SymCache::parse(&with_checksum)?.validate()?;
+
+    // the checksum covers all data, including the reverse index at the end
+    for offset in [100, with_checksum.len() - 1] {
+        let mut corrupted = with_checksum.clone();
+        corrupted[offset] ^= 0x1;
+        let error = new::SymCache::parse(&corrupted)?.validate().unwrap_err();
+        assert!(
+            matches!(error, new::Error::ChecksumMismatch),
+            "{:#x}",
+            offset
+        );
+    }
+
+    // without a checksum, changing the line of a line range goes unnoticed
+    let mut corrupted = without_checksum;
+    *corrupted.last_mut().unwrap() ^= 0x1;
+    new::SymCache::parse(&corrupted)?.validate()?;
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_linux() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&buffer)?;
+    let buffer = SymCacheWriter::write_object(&object, Cursor::new(Vec::new()))?.into_inner();
+
+    let report = new::SymCache::parse(&buffer)?.verify(&object)?;
+    assert_eq!(report.functions, 158);
+    assert!(report.addresses > report.functions);
+    assert!(report.is_ok(), "{:#?}", report.mismatches);
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_transformed() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&buffer)?;
+
+    let mut prefix_map = transform::PathPrefixMap::new();
+    prefix_map.add_mapping("/work", "");
+
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.add_transformer(prefix_map);
+    writer.process_object(&object)?;
+    writer.finish()?;
+
+    let report = new::SymCache::parse(&buffer)?.verify(&object)?;
+    assert!(!report.is_ok());
+
+    let mismatch = report
+        .mismatches
+        .iter()
+        .find(|mismatch| mismatch.expected.len() == 1)
+        .unwrap();
+    let expected = mismatch.expected[0].file.as_deref().unwrap();
+    let actual = mismatch.actual[0].file.as_deref().unwrap();
+    assert_eq!(expected.strip_prefix("/work/"), Some(actual));
+
+    Ok(())
+}
+
+#[cfg(feature = "demangle")]
+#[test]
+fn test_upgrade_current_version() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&buffer)?;
+
+    let cfi = write_cfi("linux/crash")?;
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.set_demangle_options(Some(DemangleOptions::complete()));
+    writer.set_reverse_index(true);
+    writer.set_checksum(true);
+    writer.set_cfi(Some(cfi.clone()))?;
+    writer.process_object(&object)?;
+    writer.finish()?;
+
+    // all optional data is copied without enabling it on the writer
+    let mut upgraded = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut upgraded))?;
+    let report = writer.process_symcache(&SymCache::parse(&buffer)?)?;
+    writer.finish()?;
+    assert!(report.is_complete());
+
+    let original = new::SymCache::parse(&buffer)?;
+    let symcache = new::SymCache::parse(&upgraded)?;
+    symcache.validate()?;
+    assert_eq!(symcache.cfi(), Some(&cfi[..]));
+    assert!(symcache.has_reverse_index());
+
+    for (function, expected) in symcache.functions().zip(original.functions()) {
+        assert_eq!(function.name(), expected.name());
+        assert_eq!(function.short_name(), expected.short_name());
+        assert_eq!(function.full_name(), expected.full_name());
+
+        let name = function.name().unwrap();
+        let entries = |symcache: &new::SymCache<'_>| {
+            symcache
+                .lookup_function_name(name)
+                .map(|entry| (entry.address(), entry.is_inlined()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            entries(&symcache),
+            entries(&original),
+            "entries of {}",
+            name
+        );
+    }
+
+    // the checksum detects corruption of the upgraded SymCache
+    let last = upgraded.len() - 1;
+    upgraded[last] ^= 0xff;
+    let error = new::SymCache::parse(&upgraded)?.validate().unwrap_err();
+    assert_eq!(error.to_string(), new::Error::ChecksumMismatch.to_string());
+
+    Ok(())
+}
+
+/// Writes the `CfiCache` of the given fixture.
+fn write_cfi(path: &str) -> Result<Vec<u8>, Error> {
+    let buffer = ByteView::open(fixture(path))?;
+    let cfi_cache = CfiCache::from_object(&Object::parse(&buffer)?)?;
+    let mut cfi = Vec::new();
+    cfi_cache.write_to(&mut cfi)?;
+    Ok(cfi)
+}
+
+#[test]
+fn test_write_cfi() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&buffer)?;
+    let cfi = write_cfi("linux/crash")?;
+
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.set_reverse_index(true);
+    writer.set_checksum(true);
+    writer.set_cfi(Some(cfi.clone()))?;
+    writer.process_object(&object)?;
+    writer.finish()?;
+
+    let symcache = new::SymCache::parse(&buffer)?;
+    symcache.validate()?;
+    assert_eq!(symcache.cfi(), Some(&cfi[..]));
+    assert!(symcache.lookup_function_name("main").next().is_some());
+
+    // the CFI can be loaded as a `CfiCache` without copying
+    let cfi_cache = CfiCache::from_bytes(ByteView::from_slice(symcache.cfi().unwrap()))?;
+    assert!(cfi_cache.is_latest());
+    assert!(!cfi_cache.as_slice().is_empty());
+    assert_eq!(SymCache::parse(&buffer)?.cfi(), Some(&cfi[..]));
+
+    // the CFI is kept when upgrading
+    let mut upgraded = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut upgraded))?;
+    writer.process_symcache(&SymCache::parse(&buffer)?)?;
+    writer.finish()?;
+    assert_eq!(new::SymCache::parse(&upgraded)?.cfi(), Some(&cfi[..]));
+
+    // the CFI must fit into the buffer
+    let truncated = &buffer[..buffer.len() - 1];
+    let error = new::SymCache::parse(truncated).unwrap_err();
+    assert_eq!(error.to_string(), new::Error::BadFormatLength.to_string());
+
+    // the CFI must start with the preamble of a supported `CfiCache`
+    let mut corrupted = buffer.clone();
+    let cfi_start = corrupted.len() - cfi.len();
+    corrupted[cfi_start] ^= 0xff;
+    let error = new::SymCache::parse(&corrupted).unwrap_err();
+    assert_eq!(error.to_string(), new::Error::UnsupportedCfi.to_string());
+
+    let mut writer = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+    let mut unversioned = cfi.clone();
+    unversioned.drain(..8);
+    let mut future_version = cfi.clone();
+    future_version[4..8].copy_from_slice(&3u32.to_ne_bytes());
+    for invalid in [Vec::new(), unversioned, future_version] {
+        let error = writer.set_cfi(Some(invalid)).unwrap_err();
+        assert_eq!(error.kind(), SymCacheErrorKind::UnsupportedCfi);
+    }
+
+    let buffer = SymCacheWriter::write_object(&object, Cursor::new(Vec::new()))?.into_inner();
+    assert_eq!(new::SymCache::parse(&buffer)?.cfi(), None);
+
+    Ok(())
+}
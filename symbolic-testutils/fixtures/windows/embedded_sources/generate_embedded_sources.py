@@ -0,0 +1,290 @@
+#!/usr/bin/env python3
+
+# This script was used to generate `CrashWithException.pdb` in this directory, used to test reading
+# source files embedded into PDBs.
+#
+# The MSVC toolchain is not available to build a PDB with `/embed`, so this script adds embedded
+# sources to `../CrashWithException.pdb` instead. It writes the streams in the layout produced by
+# the linker:
+#
+# - The `/src/headerblock` named stream lists the embedded files. Each entry references the file
+#   name and the virtual file name in the `/names` string table.
+# - For every entry, the `/src/files/<virtual name>` named stream holds the file contents. The
+#   virtual name is the lowercase file name.
+#
+# `crashwithexception.cpp` is stored without compression, and `pch.h` is stored in the format
+# written by .NET compilers: a 32-bit uncompressed size followed by deflated data. All other
+# streams are copied unchanged, and the MSF container is rewritten without free blocks.
+#
+# Pre-requisites:
+#
+# - Python 3
+
+import os
+import struct
+import zlib
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+INPUT = os.path.join(HERE, "..", "CrashWithException.pdb")
+OUTPUT = os.path.join(HERE, "CrashWithException.pdb")
+
+PROJECT = "c:\\users\\sentry\\source\\repos\\crashwithexception\\crashwithexception\\"
+
+SOURCES = [
+    (
+        PROJECT + "crashwithexception.cpp",
+        0,
+        b'#include "pch.h"\r\n'
+        b"\r\n"
+        b"int main()\r\n"
+        b"{\r\n"
+        b'    throw std::runtime_error("crash");\r\n'
+        b"}\r\n",
+    ),
+    (
+        PROJECT + "pch.h",
+        101,
+        b"#ifndef PCH_H\r\n"
+        b"#define PCH_H\r\n"
+        b"\r\n"
+        b"#include <stdexcept>\r\n"
+        b"\r\n"
+        b"#endif // PCH_H\r\n",
+    ),
+]
+
+MAGIC = b"Microsoft C/C++ MSF 7.00\r\n\x1a\x44\x53\x00\x00\x00"
+SOURCE_HEADER_VERSION = 19980827
+
+
+def read_msf(data):
+    """Returns the block size and the contents of all streams of an MSF file."""
+    assert data[:32] == MAGIC
+    block_size, _, _, directory_size, _, block_map = struct.unpack_from("<6I", data, 32)
+
+    def read_blocks(blocks, size):
+        contents = b"".join(data[b * block_size : (b + 1) * block_size] for b in blocks)
+        return contents[:size]
+
+    directory_blocks = (directory_size + block_size - 1) // block_size
+    blocks = struct.unpack_from("<%dI" % directory_blocks, data, block_map * block_size)
+    directory = read_blocks(blocks, directory_size)
+
+    (count,) = struct.unpack_from("<I", directory, 0)
+    sizes = struct.unpack_from("<%dI" % count, directory, 4)
+    offset = 4 + 4 * count
+
+    streams = []
+    for size in sizes:
+        if size == 0xFFFFFFFF:
+            streams.append(None)
+            continue
+
+        count = (size + block_size - 1) // block_size
+        blocks = struct.unpack_from("<%dI" % count, directory, offset)
+        offset += 4 * count
+        streams.append(read_blocks(blocks, size))
+
+    return block_size, streams
+
+
+def write_msf(block_size, streams):
+    """Writes streams into a new MSF file with blocks assigned sequentially."""
+    # Block 0 holds the super block, blocks 1 and 2 the free page maps.
+    blocks = [b"", b"", b""]
+
+    def add_blocks(contents):
+        start = len(blocks)
+        for offset in range(0, len(contents), block_size):
+            blocks.append(contents[offset : offset + block_size])
+        return list(range(start, len(blocks)))
+
+    sizes = []
+    stream_blocks = []
+    for stream in streams:
+        if stream is None:
+            sizes.append(0xFFFFFFFF)
+            continue
+
+        sizes.append(len(stream))
+        stream_blocks.extend(add_blocks(stream))
+
+    directory = struct.pack("<I", len(streams))
+    directory += struct.pack("<%dI" % len(sizes), *sizes)
+    directory += struct.pack("<%dI" % len(stream_blocks), *stream_blocks)
+    directory_blocks = add_blocks(directory)
+    block_map = add_blocks(struct.pack("<%dI" % len(directory_blocks), *directory_blocks))[0]
+    assert len(blocks) <= block_size * 8
+
+    # All blocks in the file are in use, the remaining bits of the free page map are set.
+    free_map = bytearray(b"\xff" * block_size)
+    for index in range(len(blocks)):
+        free_map[index // 8] &= ~(1 << (index % 8)) & 0xFF
+    blocks[1] = bytes(free_map)
+
+    blocks[0] = MAGIC + struct.pack(
+        "<6I", block_size, 1, len(blocks), len(directory), 0, block_map
+    )
+
+    return b"".join(block.ljust(block_size, b"\0") for block in blocks)
+
+
+def hash_string_v1(name):
+    """The hash function of named stream maps, see `hashStringV1` in LLVM."""
+    result = 0
+    for offset in range(0, len(name) - len(name) % 4, 4):
+        result ^= struct.unpack_from("<I", name, offset)[0]
+
+    remainder = name[len(name) - len(name) % 4 :]
+    if len(remainder) >= 2:
+        result ^= struct.unpack_from("<H", remainder, 0)[0]
+        remainder = remainder[2:]
+    if remainder:
+        result ^= remainder[0]
+
+    result |= 0x20202020
+    result ^= result >> 11
+    return (result ^ (result >> 16)) & 0xFFFF
+
+
+def write_hash_table(capacity, entries, bucket):
+    """Serializes a hash table with linear probing into its on-disk format."""
+    buckets = [None] * capacity
+    for key, value in entries:
+        index = bucket(key) % capacity
+        while buckets[index] is not None:
+            index = (index + 1) % capacity
+        buckets[index] = (key, value)
+
+    words = (capacity + 31) // 32
+    present = [0] * words
+    for index, entry in enumerate(buckets):
+        if entry is not None:
+            present[index // 32] |= 1 << (index % 32)
+
+    data = struct.pack("<2I", len(entries), capacity)
+    data += struct.pack("<I%dI" % words, words, *present)
+    data += struct.pack("<I", 0)
+    for entry in buckets:
+        if entry is not None:
+            data += entry[1]
+
+    return data
+
+
+def parse_named_streams(info):
+    """Returns the header, named streams and trailing data of the PDB information stream."""
+    header = info[:28]
+    offset = 28
+    (names_size,) = struct.unpack_from("<I", info, offset)
+    names = info[offset + 4 : offset + 4 + names_size]
+    offset += 4 + names_size
+
+    count, _ = struct.unpack_from("<2I", info, offset)
+    offset += 8
+    for _ in range(2):
+        (words,) = struct.unpack_from("<I", info, offset)
+        offset += 4 + 4 * words
+
+    streams = {}
+    for _ in range(count):
+        name_offset, stream = struct.unpack_from("<2I", info, offset)
+        offset += 8
+        name = names[name_offset : names.index(b"\0", name_offset)]
+        streams[name] = stream
+
+    return header, streams, info[offset:]
+
+
+def write_named_streams(header, streams, trailer):
+    """Serializes the PDB information stream with the given named streams."""
+    names = b""
+    entries = []
+    for name, stream in streams.items():
+        entries.append((name, struct.pack("<2I", len(names), stream)))
+        names += name + b"\0"
+
+    capacity = 1
+    while len(entries) >= capacity * 2 // 3 + 1:
+        capacity *= 2
+
+    data = header + struct.pack("<I", len(names)) + names
+    data += write_hash_table(capacity, entries, hash_string_v1)
+    return data + trailer
+
+
+def parse_string_table(data):
+    """Returns a map of strings to their offsets in the `/names` stream."""
+    (size,) = struct.unpack_from("<I", data, 8)
+    buffer = data[12 : 12 + size]
+
+    strings = {}
+    offset = 0
+    while offset < len(buffer):
+        end = buffer.index(b"\0", offset)
+        strings.setdefault(buffer[offset:end], offset)
+        offset = end + 1
+
+    return strings
+
+
+def compress(compression, contents):
+    if compression == 0:
+        return contents
+
+    deflate = zlib.compressobj(9, zlib.DEFLATED, -15)
+    compressed = deflate.compress(contents) + deflate.flush()
+    return struct.pack("<I", len(contents)) + compressed
+
+
+def main():
+    with open(INPUT, "rb") as f:
+        block_size, streams = read_msf(f.read())
+
+    header, named, trailer = parse_named_streams(streams[1])
+    strings = parse_string_table(streams[named[b"/names"]])
+
+    entries = []
+    for path, compression, contents in SOURCES:
+        name = strings[path.encode()]
+        stored = compress(compression, contents)
+
+        stream_name = ("/src/files/" + path.lower()).encode()
+        named[stream_name] = len(streams)
+        streams.append(stored)
+
+        # SrcHeaderBlockEntry: size, version, crc, file size, file name, object name, virtual
+        # name, compression, is virtual, padding and reserved bytes.
+        entry = struct.pack(
+            "<7I2B2x8x",
+            40,
+            SOURCE_HEADER_VERSION,
+            zlib.crc32(contents),
+            len(stored),
+            name,
+            0,
+            name,
+            compression,
+            0,
+        )
+        entries.append((name, struct.pack("<I", name) + entry))
+
+    table = write_hash_table(len(entries), entries, lambda name: name)
+    size = 64 + len(table)
+    # SrcHeaderBlockHeader: version, size, file time, age and padding.
+    block = struct.pack("<2IQI44x", SOURCE_HEADER_VERSION, size, 0, 1) + table
+
+    if b"/src/headerblock" in named:
+        streams[named[b"/src/headerblock"]] = block
+    else:
+        named[b"/src/headerblock"] = len(streams)
+        streams.append(block)
+
+    streams[1] = write_named_streams(header, named, trailer)
+
+    with open(OUTPUT, "wb") as f:
+        f.write(write_msf(block_size, streams))
+
+
+if __name__ == "__main__":
+    main()
@@ -1,6 +1,8 @@
 //! Basic definitions necessary for stack unwinding.
-use std::convert::TryInto;
+use std::borrow::Cow;
+use std::convert::{TryFrom, TryInto};
 use std::fmt::Debug;
+use std::iter::FromIterator;
 use std::str::FromStr;
 
 use num_traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedRem, CheckedSub, Unsigned};
@@ -177,4 +179,168 @@ impl<'a> MemoryRegion<'a> {
         let index = (address.try_into().ok()?).checked_sub(self.base_addr as usize)?;
         A::read_bytes(self.contents.get(index..)?, endian)
     }
+
+    /// Returns true if `address` lies within this memory region.
+    pub fn contains(&self, address: u64) -> bool {
+        matches!(
+            address.checked_sub(self.base_addr),
+            Some(offset) if offset < self.len() as u64
+        )
+    }
+}
+
+/// A source of memory that can be read by address.
+///
+/// This abstracts over the memory available to an [`Evaluator`](crate::evaluator::Evaluator)
+/// for dereferencing addresses. It is implemented for a single [`MemoryRegion`], for a set of
+/// [`MemoryRegions`], and for [`LazyMemory`], which reads memory on demand.
+pub trait Memory {
+    /// Reads `len` bytes of memory starting at `address`.
+    ///
+    /// Returns `None` unless all of the requested bytes are available.
+    fn read(&self, address: u64, len: usize) -> Option<Cow<'_, [u8]>>;
+}
+
+impl<M: Memory + ?Sized> Memory for &M {
+    fn read(&self, address: u64, len: usize) -> Option<Cow<'_, [u8]>> {
+        (**self).read(address, len)
+    }
+}
+
+impl Memory for MemoryRegion<'_> {
+    fn read(&self, address: u64, len: usize) -> Option<Cow<'_, [u8]>> {
+        let start = usize::try_from(address.checked_sub(self.base_addr)?).ok()?;
+        let bytes = self.contents.get(start..start.checked_add(len)?)?;
+        Some(Cow::Borrowed(bytes))
+    }
+}
+
+/// A set of discontiguous memory regions, such as the memory list of a minidump.
+///
+/// The regions are kept sorted by their base addresses, so that the region containing an address
+/// is found with a binary search. Regions never overlap, and reads spanning multiple adjacent
+/// regions are not supported.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryRegions<'a> {
+    regions: Vec<MemoryRegion<'a>>,
+}
+
+impl<'a> MemoryRegions<'a> {
+    /// Creates an empty set of memory regions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a memory region.
+    ///
+    /// Returns `false` and leaves the set unchanged if the region is empty or overlaps a region
+    /// that was added before.
+    pub fn insert(&mut self, region: MemoryRegion<'a>) -> bool {
+        if region.is_empty() {
+            return false;
+        }
+
+        let index = self
+            .regions
+            .partition_point(|r| r.base_addr <= region.base_addr);
+        let end = region.base_addr.saturating_add(region.len() as u64);
+
+        let overlaps_previous = matches!(
+            index.checked_sub(1).map(|i| &self.regions[i]),
+            Some(previous) if previous.contains(region.base_addr)
+        );
+        let overlaps_next = matches!(
+            self.regions.get(index),
+            Some(next) if next.base_addr < end
+        );
+        if overlaps_previous || overlaps_next {
+            return false;
+        }
+
+        self.regions.insert(index, region);
+        true
+    }
+
+    /// Returns the memory region containing `address`, if any.
+    pub fn region_at(&self, address: u64) -> Option<&MemoryRegion<'a>> {
+        let index = self.regions.partition_point(|r| r.base_addr <= address);
+        let region = self.regions.get(index.checked_sub(1)?)?;
+        if region.contains(address) {
+            Some(region)
+        } else {
+            None
+        }
+    }
+
+    /// Returns an iterator over all memory regions, ordered by their base addresses.
+    pub fn iter(&self) -> impl Iterator<Item = &MemoryRegion<'a>> {
+        self.regions.iter()
+    }
+}
+
+/// Collects memory regions, skipping empty regions and those that overlap a previous region.
+impl<'a> FromIterator<MemoryRegion<'a>> for MemoryRegions<'a> {
+    fn from_iter<T: IntoIterator<Item = MemoryRegion<'a>>>(iter: T) -> Self {
+        let mut regions = Self::new();
+        for region in iter {
+            regions.insert(region);
+        }
+        regions
+    }
+}
+
+impl Memory for MemoryRegions<'_> {
+    fn read(&self, address: u64, len: usize) -> Option<Cow<'_, [u8]>> {
+        self.region_at(address)?.read(address, len)
+    }
+}
+
+/// Memory that is read on demand by a callback.
+///
+/// The callback is invoked with the address and the number of bytes for every read, and returns
+/// the bytes if they are available. This allows to evaluate expressions against memory that is
+/// expensive to load entirely, for instance memory lists of large minidumps.
+///
+/// # Example
+///
+/// ```
+/// use symbolic_unwind::{LazyMemory, Memory};
+///
+/// let memory = LazyMemory::new(|address: u64, len: usize| {
+///     if address >= 0x1000 {
+///         Some(vec![0xff; len])
+///     } else {
+///         None
+///     }
+/// });
+///
+/// assert_eq!(memory.read(0x1000, 2).as_deref(), Some(&[0xff, 0xff][..]));
+/// assert!(memory.read(0x800, 2).is_none());
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct LazyMemory<F> {
+    read: F,
+}
+
+impl<F> LazyMemory<F>
+where
+    F: Fn(u64, usize) -> Option<Vec<u8>>,
+{
+    /// Creates memory that is read with the given callback.
+    pub fn new(read: F) -> Self {
+        Self { read }
+    }
+}
+
+impl<F> Memory for LazyMemory<F>
+where
+    F: Fn(u64, usize) -> Option<Vec<u8>>,
+{
+    fn read(&self, address: u64, len: usize) -> Option<Cow<'_, [u8]>> {
+        let bytes = (self.read)(address, len)?;
+        if bytes.len() < len {
+            return None;
+        }
+        Some(Cow::Owned(bytes))
+    }
 }
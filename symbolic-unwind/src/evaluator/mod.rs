@@ -14,7 +14,7 @@
 //! <literal>    ::=  -?[0-9]+
 //! ```
 //! Most of this syntax should be familiar. The symbol `^` denotes a dereference operation,
-//! i.e. assuming that some representation `m` of a region of memory is available,
+//! i.e. assuming that some representation `m` of [memory](super::base::Memory) is available,
 //! `x ^` evaluates to `m[x]`. If no memory is available or `m` is not defined at `x`,
 //! evaluating the expression will fail. The symbol
 //! `@` denotes an align operation; it truncates its first operand to a multiple of its
@@ -32,20 +32,23 @@
 //! assignments described above. They can be be parsed with the
 //! [assignment](parsing::assignment), [assignment_complete](parsing::assignment_complete),
 //! [assignments](parsing::assignments),
-//! and [assignments_complete](parsing::assignments_complete) parsers.
+//! and [assignments_complete](parsing::assignments_complete) parsers, and evaluated with
+//! [`Evaluator::evaluate_program_string`].
 //!
 //! By contrast, Breakpad `STACK CFI` records (see [here](https://github.com/google/breakpad/blob/main/docs/symbol_files.md#stack-cfi-records)
 //! contain sequences of rules for essentially the same purpose. They can be parsed with the
 //! [rule](parsing::rule), [rule_complete](parsing::rule_complete),
 //! [rules](parsing::rules),
 //! and [rules_complete](parsing::rules_complete) parsers.
+//!
+//! A `STACK WIN` record without a program string only describes the sizes of the frame. Both
+//! kinds of records are turned into the caller's registers by [`Evaluator::evaluate_stack_win`].
 use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt;
-use std::ops::Range;
 use std::str::FromStr;
 
-use super::base::{Endianness, MemoryRegion, RegisterValue};
+use super::base::{Endianness, Memory, RegisterValue};
 use parsing::ParseExprError;
 
 pub mod parsing;
@@ -53,6 +56,57 @@ pub mod parsing;
 #[cfg(test)]
 mod strategies;
 
+/// The number of 32-bit words searched for a return address if the program of a `STACK WIN`
+/// record does not recover one.
+const RA_SEARCH_WORDS: u32 = 40;
+
+/// The type of a `STACK WIN` record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StackWinType {
+    /// Frame pointer omission (FPO) data, which only describes the sizes of the frame.
+    Fpo,
+
+    /// Frame data, which usually contains a program string.
+    FrameData,
+}
+
+/// The unwind information of a `STACK WIN` record, used on 32-bit Windows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StackWinInfo {
+    /// The type of the record.
+    pub ty: StackWinType,
+
+    /// The number of bytes of parameters the function was called with.
+    pub params_size: u32,
+
+    /// The number of bytes of callee-saved registers the function pushes onto the stack.
+    pub saved_regs_size: u32,
+
+    /// The number of bytes of local variables of the function.
+    pub locals_size: u32,
+
+    /// Whether the function uses `$ebp` as a general purpose register, saving the caller's value
+    /// among the other callee-saved registers.
+    pub uses_base_pointer: bool,
+
+    /// The program string computing the caller's registers, if any.
+    ///
+    /// Without a program string, the return address is assumed to follow the saved registers and
+    /// local variables of the function.
+    pub program_string: Option<String>,
+}
+
+/// The caller's registers recovered by [`Evaluator::evaluate_stack_win`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StackWinRegisters {
+    /// The final values of all variables assigned by the program, including `$eip` and `$esp`.
+    pub registers: BTreeMap<Variable, u32>,
+
+    /// Whether `$eip` and `$esp` were found by searching the stack for a return address, since the
+    /// program did not recover a valid one.
+    pub scanned: bool,
+}
+
 /// Structure that encapsulates the information necessary to evaluate Breakpad
 /// RPN expressions.
 ///
@@ -60,11 +114,11 @@ mod strategies;
 /// - An address type, which is used both for basic expressions and for pointers into `memory`
 /// - An [`Endianness`](super::base::Endianness) that controls how values are read from memory
 pub struct Evaluator<'memory, A, E> {
-    /// A region of memory.
+    /// The memory that expressions can dereference.
     ///
     /// If this is `None`, evaluation of expressions containing dereference
     /// operations will fail.
-    memory: Option<MemoryRegion<'memory>>,
+    memory: Option<Box<dyn Memory + Send + Sync + 'memory>>,
 
     /// A map containing the values of constants.
     ///
@@ -102,10 +156,14 @@ impl<'memory, A, E> Evaluator<'memory, A, E> {
         }
     }
 
-    /// Sets the evaluator's memory to the given `MemoryRegion`.
+    /// Sets the evaluator's memory.
+    ///
+    /// This can be a single [`MemoryRegion`](super::base::MemoryRegion), multiple
+    /// [`MemoryRegions`](super::base::MemoryRegions), or any other implementation of [`Memory`].
+    /// The memory must be `Send` and `Sync`, so that the evaluator can be shared between threads.
     #[must_use]
-    pub fn memory(mut self, memory: MemoryRegion<'memory>) -> Self {
-        self.memory = Some(memory);
+    pub fn memory<M: Memory + Send + Sync + 'memory>(mut self, memory: M) -> Self {
+        self.memory = Some(Box::new(memory));
         self
     }
 
@@ -178,13 +236,14 @@ impl<'memory, A: RegisterValue, E: Endianness> Evaluator<'memory, A, E> {
                     .memory
                     .as_ref()
                     .ok_or(EvaluationError(EvaluationErrorInner::MemoryUnavailable))?;
-                memory.get(address, self.endian).ok_or_else(|| {
-                    EvaluationError(EvaluationErrorInner::IllegalMemoryAccess {
-                        address: address.try_into().ok(),
-                        bytes: A::WIDTH as usize,
-                        address_range: memory.base_addr..memory.base_addr + memory.len() as u64,
-                    })
-                })
+                let bytes = A::WIDTH as usize;
+                memory
+                    .read(address.into(), bytes)
+                    .and_then(|contents| A::read_bytes(&contents, self.endian))
+                    .ok_or(EvaluationError(EvaluationErrorInner::IllegalMemoryAccess {
+                        address: address.into(),
+                        bytes,
+                    }))
             }
         }
     }
@@ -211,6 +270,38 @@ impl<'memory, A: RegisterValue, E: Endianness> Evaluator<'memory, A, E> {
         Ok(computed_registers)
     }
 
+    /// Evaluates an assignment and stores the result in the variable map.
+    ///
+    /// Subsequently evaluated expressions see the new value of the variable. Returns the
+    /// assigned value.
+    pub fn evaluate_assignment(
+        &mut self,
+        assignment: &Assignment<A>,
+    ) -> Result<A, EvaluationError<A>> {
+        let Assignment(variable, expr) = assignment;
+        let value = self.evaluate(expr)?;
+        self.variables.insert(variable.clone(), value);
+        Ok(value)
+    }
+
+    /// Parses and evaluates a program string of a `STACK WIN` record.
+    ///
+    /// The assignments of the program string are evaluated in order, so each one can refer to
+    /// the variables assigned before, including temporaries like `$T0`. Returns the final values
+    /// of all assigned variables.
+    pub fn evaluate_program_string(
+        &mut self,
+        program_string: &str,
+    ) -> Result<BTreeMap<Variable, A>, ExpressionError<A>> {
+        let mut assigned = BTreeMap::new();
+        for assignment in parsing::assignments_complete(program_string.trim())? {
+            let value = self.evaluate_assignment(&assignment)?;
+            assigned.insert(assignment.0, value);
+        }
+
+        Ok(assigned)
+    }
+
     /// Reads a string of CFI rules and adds them to the evaluator.
     pub fn add_cfi_rules_string(&mut self, rules_string: &str) -> Result<(), ParseExprError> {
         for Rule(lhs, rhs) in parsing::rules_complete(rules_string.trim())?.into_iter() {
@@ -221,6 +312,110 @@ impl<'memory, A: RegisterValue, E: Endianness> Evaluator<'memory, A, E> {
     }
 }
 
+impl<'memory, E: Endianness> Evaluator<'memory, u32, E> {
+    /// Recovers the caller's registers from a `STACK WIN` record.
+    ///
+    /// The variables of the evaluator must contain the callee's registers, including `$esp`.
+    /// `callee_params_size` is the size of the parameters of the function called by this frame, or
+    /// `0` for the innermost frame.
+    ///
+    /// Like the Breakpad processor, this defines the constants `.cbCalleeParams`, `.cbSavedRegs`,
+    /// `.cbLocals` and `.cbParams` from the record, and `.raSearchStart` and `.raSearch` as the
+    /// location of the return address following the parameters, locals and saved registers. It
+    /// then evaluates the program string of the record, or for records without one, a program
+    /// reading the return address from `.raSearchStart` and `$ebp` right below it if the function
+    /// [uses the base pointer](StackWinInfo::uses_base_pointer).
+    ///
+    /// If `is_return_address` rejects the recovered `$eip`, the stack is searched for a return
+    /// address starting at `.raSearchStart`. If that fails as well, the registers are returned as
+    /// the program computed them.
+    pub fn evaluate_stack_win<F>(
+        &mut self,
+        info: &StackWinInfo,
+        callee_params_size: u32,
+        is_return_address: F,
+    ) -> Result<StackWinRegisters, ExpressionError<u32>>
+    where
+        F: Fn(u32) -> bool,
+    {
+        let esp_variable = Variable("$esp".into());
+        let eip_variable = Variable("$eip".into());
+
+        let esp = self.variables.get(&esp_variable).copied().ok_or_else(|| {
+            EvaluationError(EvaluationErrorInner::UndefinedVariable(
+                esp_variable.clone(),
+            ))
+        })?;
+        let add = |left: u32, right: u32| {
+            left.checked_add(right)
+                .ok_or(EvaluationError(EvaluationErrorInner::IllegalOperation {
+                    left,
+                    right,
+                    op: BinOp::Add,
+                }))
+        };
+        let ra_search_start = add(
+            add(add(esp, callee_params_size)?, info.locals_size)?,
+            info.saved_regs_size,
+        )?;
+
+        for (name, value) in [
+            (".cbCalleeParams", callee_params_size),
+            (".cbSavedRegs", info.saved_regs_size),
+            (".cbLocals", info.locals_size),
+            (".cbParams", info.params_size),
+            (".raSearchStart", ra_search_start),
+            (".raSearch", ra_search_start),
+        ] {
+            self.constants.insert(Constant(name.into()), value);
+        }
+
+        let program_string = match info.program_string {
+            Some(ref program_string) => program_string.as_str(),
+            // The function saved `$ebp` first, right below the return address.
+            None if info.uses_base_pointer => {
+                "$eip .raSearchStart ^ = $esp .raSearchStart 4 + = $ebp .raSearchStart 4 - ^ ="
+            }
+            None => "$eip .raSearchStart ^ = $esp .raSearchStart 4 + =",
+        };
+        let mut registers = self.evaluate_program_string(program_string)?;
+
+        let mut scanned = false;
+        if !matches!(registers.get(&eip_variable), Some(&eip) if is_return_address(eip)) {
+            if let Some((address, eip)) =
+                self.search_return_address(ra_search_start, is_return_address)
+            {
+                registers.insert(eip_variable, eip);
+                registers.insert(esp_variable, address.saturating_add(4));
+                scanned = true;
+            }
+        }
+
+        Ok(StackWinRegisters { registers, scanned })
+    }
+
+    /// Searches up to [`RA_SEARCH_WORDS`] values starting at `start` for a return address.
+    ///
+    /// Returns the address at which the return address was found, along with its value.
+    fn search_return_address<F>(&self, start: u32, is_return_address: F) -> Option<(u32, u32)>
+    where
+        F: Fn(u32) -> bool,
+    {
+        let memory = self.memory.as_ref()?;
+        let mut address = start;
+        for _ in 0..RA_SEARCH_WORDS {
+            let bytes = memory.read(address.into(), 4)?;
+            let value = u32::read_bytes(&bytes, self.endian)?;
+            if is_return_address(value) {
+                return Some((address, value));
+            }
+            address = address.checked_add(4)?;
+        }
+
+        None
+    }
+}
+
 /// An error encountered while evaluating an expression.
 #[derive(Debug)]
 #[non_exhaustive]
@@ -235,14 +430,12 @@ enum EvaluationErrorInner<A> {
     /// to any memory.
     MemoryUnavailable,
 
-    /// The requested piece of memory would exceed the bounds of the memory region.
+    /// The requested piece of memory is not available.
     IllegalMemoryAccess {
         /// The number of bytes that were tried to read.
         bytes: usize,
         /// The address at which the read was attempted.
-        address: Option<usize>,
-        /// The range of available addresses.
-        address_range: Range<u64>,
+        address: u64,
     },
 
     /// An illegal arithmetical operation was attempted.
@@ -262,14 +455,14 @@ impl<A: fmt::Display> fmt::Display for EvaluationErrorInner<A> {
             Self::UndefinedConstant(c) => write!(f, "Constant {} is not defined", c),
             Self::UndefinedVariable(v) => write!(f, "Variable {} is not defined", v),
             Self::MemoryUnavailable => write!(f, "The evaluator does not have access to memory"),
-            Self::IllegalMemoryAccess {
-                bytes, address: Some(address), address_range
-            } => write!(f, "Tried to read {} bytes at memory address {}. The available address range is [{}, {})", bytes, address, address_range.start, address_range.end),
-            Self::IllegalMemoryAccess {
-                bytes, address: None, ..
-            } => write!(f, "Tried to read {} bytes at address that exceeds the maximum usize value", bytes),
-            Self::IllegalOperation {
-                left, right, op } => write!(f, "Illegal operation {} {} {}", left, op, right),
+            Self::IllegalMemoryAccess { bytes, address } => write!(
+                f,
+                "Tried to read {} bytes at memory address {}, which is not available",
+                bytes, address
+            ),
+            Self::IllegalOperation { left, right, op } => {
+                write!(f, "Illegal operation {} {} {}", left, op, right)
+            }
         }
     }
 }
@@ -522,7 +715,7 @@ impl<T: fmt::Display> fmt::Display for Rule<T> {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::base::LittleEndian;
+    use crate::base::{LazyMemory, LittleEndian, MemoryRegion, MemoryRegions};
 
     #[test]
     fn division_by_zero() {
@@ -534,4 +727,243 @@ mod test {
         let eval = Evaluator::new(LittleEndian);
         assert!(eval.evaluate(&expr).is_err());
     }
+
+    #[test]
+    fn program_string() {
+        let memory = [0x00, 0x90, 0x00, 0x00, 0x34, 0x12, 0x00, 0x00];
+        let mut variables = BTreeMap::new();
+        variables.insert("$ebp".parse().unwrap(), 0x8000u32);
+
+        let mut eval = Evaluator::new(LittleEndian)
+            .memory(MemoryRegion {
+                base_addr: 0x8000,
+                contents: &memory,
+            })
+            .variables(variables);
+
+        let assigned = eval
+            .evaluate_program_string(
+                "$T0 $ebp = $eip $T0 4 + ^ = $ebp $T0 ^ = $esp $T0 8 + = $L $ebp 4 - =",
+            )
+            .unwrap();
+
+        let get = |name: &str| assigned[&name.parse::<Variable>().unwrap()];
+        assert_eq!(get("$T0"), 0x8000);
+        assert_eq!(get("$eip"), 0x1234);
+        assert_eq!(get("$ebp"), 0x9000);
+        assert_eq!(get("$esp"), 0x8008);
+        // Later assignments see the updated value of `$ebp`.
+        assert_eq!(get("$L"), 0x8ffc);
+    }
+
+    #[test]
+    fn deref_memory_regions() {
+        let stack = 0x2000u32.to_le_bytes();
+        let heap = 0x1234u32.to_le_bytes();
+        let memory: MemoryRegions = vec![
+            MemoryRegion {
+                base_addr: 0x8000,
+                contents: &stack,
+            },
+            MemoryRegion {
+                base_addr: 0x2000,
+                contents: &heap,
+            },
+        ]
+        .into_iter()
+        .collect();
+
+        let eval = Evaluator::new(LittleEndian).memory(&memory);
+        let expr = "32768 ^ ^".parse::<Expr<u32>>().unwrap();
+        assert_eq!(eval.evaluate(&expr).unwrap(), 0x1234);
+
+        // The region at 0x2000 only contains a single value.
+        let expr = "8194 ^".parse::<Expr<u32>>().unwrap();
+        assert!(eval.evaluate(&expr).is_err());
+    }
+
+    #[test]
+    fn memory_regions_reject_overlaps() {
+        let contents = [0u8; 0x10];
+        let region = |base_addr| MemoryRegion {
+            base_addr,
+            contents: &contents,
+        };
+
+        let mut memory = MemoryRegions::new();
+        assert!(memory.insert(region(0x1000)));
+        assert!(memory.insert(region(0x1010)));
+        assert!(!memory.insert(region(0x1008)));
+        assert!(!memory.insert(region(0xff8)));
+        assert!(!memory.insert(region(0x1010)));
+        assert!(!memory.insert(MemoryRegion {
+            base_addr: 0x2000,
+            contents: &[],
+        }));
+        assert!(memory.insert(region(0xff0)));
+
+        let bases: Vec<_> = memory.iter().map(|r| r.base_addr).collect();
+        assert_eq!(bases, [0xff0, 0x1000, 0x1010]);
+
+        // Collecting keeps the first of overlapping regions.
+        let memory: MemoryRegions = vec![region(0x1008), region(0x1000), region(0x1018)]
+            .into_iter()
+            .collect();
+        let bases: Vec<_> = memory.iter().map(|r| r.base_addr).collect();
+        assert_eq!(bases, [0x1008, 0x1018]);
+    }
+
+    #[test]
+    fn evaluator_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Evaluator<'static, u64, LittleEndian>>();
+    }
+
+    #[test]
+    fn deref_lazy_memory() {
+        let memory = LazyMemory::new(|address: u64, len: usize| {
+            let value = address.checked_sub(0x8000)? as u32;
+            Some(value.to_le_bytes()[..len].to_vec())
+        });
+
+        let eval = Evaluator::new(LittleEndian).memory(memory);
+        let expr = "32772 ^".parse::<Expr<u32>>().unwrap();
+        assert_eq!(eval.evaluate(&expr).unwrap(), 4);
+
+        let expr = "4 ^".parse::<Expr<u32>>().unwrap();
+        assert!(eval.evaluate(&expr).is_err());
+    }
+
+    /// Evaluates a `STACK WIN` record with `$esp` at the start of the given stack.
+    fn evaluate_stack_win(
+        stack: &[u32],
+        info: &StackWinInfo,
+        callee_params_size: u32,
+    ) -> StackWinRegisters {
+        let contents: Vec<u8> = stack.iter().flat_map(|word| word.to_le_bytes()).collect();
+        let mut variables = BTreeMap::new();
+        variables.insert("$esp".parse().unwrap(), 0x8000);
+        variables.insert("$ebp".parse().unwrap(), 0xeb);
+
+        let mut eval = Evaluator::new(LittleEndian)
+            .memory(MemoryRegion {
+                base_addr: 0x8000,
+                contents: &contents,
+            })
+            .variables(variables);
+
+        eval.evaluate_stack_win(info, callee_params_size, |address| {
+            (0x1000..0x2000).contains(&address)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn stack_win_fpo() {
+        let info = StackWinInfo {
+            ty: StackWinType::Fpo,
+            params_size: 8,
+            saved_regs_size: 4,
+            locals_size: 4,
+            uses_base_pointer: false,
+            program_string: None,
+        };
+        // local, saved register, return address
+        let stack = [0x0, 0x77, 0x1234];
+
+        let result = evaluate_stack_win(&stack, &info, 0);
+        let get = |name: &str| {
+            result
+                .registers
+                .get(&name.parse::<Variable>().unwrap())
+                .copied()
+        };
+        assert!(!result.scanned);
+        assert_eq!(get("$eip"), Some(0x1234));
+        assert_eq!(get("$esp"), Some(0x800c));
+        // `$ebp` is not touched by the function, so it is not recovered
+        assert_eq!(get("$ebp"), None);
+
+        // the parameters of the function called by this frame precede its locals
+        let stack = [0x0, 0x0, 0x0, 0x77, 0x1234];
+        let result = evaluate_stack_win(&stack, &info, 8);
+        let get = |name: &str| {
+            result
+                .registers
+                .get(&name.parse::<Variable>().unwrap())
+                .copied()
+        };
+        assert_eq!(get("$eip"), Some(0x1234));
+        assert_eq!(get("$esp"), Some(0x8014));
+    }
+
+    #[test]
+    fn stack_win_fpo_base_pointer() {
+        let info = StackWinInfo {
+            ty: StackWinType::Fpo,
+            params_size: 8,
+            saved_regs_size: 4,
+            locals_size: 4,
+            uses_base_pointer: true,
+            program_string: None,
+        };
+        // local, saved `$ebp`, return address
+        let stack = [0x0, 0x9000, 0x1234];
+
+        let result = evaluate_stack_win(&stack, &info, 0);
+        let get = |name: &str| {
+            result
+                .registers
+                .get(&name.parse::<Variable>().unwrap())
+                .copied()
+        };
+        assert!(!result.scanned);
+        assert_eq!(get("$eip"), Some(0x1234));
+        assert_eq!(get("$esp"), Some(0x800c));
+        assert_eq!(get("$ebp"), Some(0x9000));
+    }
+
+    #[test]
+    fn stack_win_ra_search() {
+        let info = StackWinInfo {
+            ty: StackWinType::FrameData,
+            params_size: 0,
+            saved_regs_size: 0,
+            locals_size: 4,
+            uses_base_pointer: false,
+            program_string: Some(
+                "$T0 .raSearch = $eip $T0 ^ = $esp $T0 4 + = $L .cbLocals .cbSavedRegs + =".into(),
+            ),
+        };
+        // local, garbage instead of a return address, return address
+        let stack = [0x0, 0x5555, 0x1234];
+
+        let result = evaluate_stack_win(&stack, &info, 0);
+        let get = |name: &str| {
+            result
+                .registers
+                .get(&name.parse::<Variable>().unwrap())
+                .copied()
+        };
+        assert!(result.scanned);
+        assert_eq!(get("$eip"), Some(0x1234));
+        assert_eq!(get("$esp"), Some(0x800c));
+        assert_eq!(get("$T0"), Some(0x8004));
+        assert_eq!(get("$L"), Some(4));
+
+        // without a return address on the stack, the program's result is returned
+        let result = evaluate_stack_win(&stack[..2], &info, 0);
+        assert!(!result.scanned);
+        assert_eq!(
+            result.registers[&"$eip".parse::<Variable>().unwrap()],
+            0x5555
+        );
+    }
+
+    #[test]
+    fn program_string_undefined() {
+        let mut eval = Evaluator::<u32, _>::new(LittleEndian);
+        assert!(eval.evaluate_program_string("$T0 $ebp =").is_err());
+        assert!(eval.evaluate_program_string("$T0 $ebp").is_err());
+    }
 }
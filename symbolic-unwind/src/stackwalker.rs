@@ -0,0 +1,1180 @@
+//! A stackwalker that computes the call stack of a thread from its registers and memory.
+//!
+//! The [`Stackwalker`] starts at the *context frame*, given by the register values of a thread
+//! (for instance, as recorded in a minidump), and recovers the registers of each calling frame in
+//! turn. Like the Breakpad processor, it tries the following strategies in order:
+//!
+//! 1. Call frame information (CFI): On 32-bit Windows, the program string of the `STACK WIN`
+//!    record covering the instruction is evaluated, or the frame sizes of the record are used if
+//!    it has no program string. Otherwise, `STACK CFI` rules provided by the module containing
+//!    the instruction are evaluated with an [`Evaluator`].
+//! 2. Frame pointers: the caller's frame pointer and return address are read from the location
+//!    the callee's frame pointer points to.
+//! 3. Stack scanning: the stack is searched for a value that points into the code of a known
+//!    module, which is likely a return address.
+//!
+//! Each frame records the strategy that found it as [`FrameTrust`]. Walking ends when no strategy
+//! yields a plausible caller, i.e. one with an instruction pointer into a known module and a stack
+//! pointer greater than the callee's.
+//!
+//! Register names follow the conventions of Breakpad symbol files, for instance `$rip`, `$rsp`
+//! and `$rbp` on x86_64 or `pc`, `sp` and `x29` on ARM64.
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+use symbolic_common::CpuFamily;
+
+use super::base::{LittleEndian, Memory, RegisterValue};
+use super::evaluator::{Constant, Evaluator, Identifier, Variable};
+
+pub use super::evaluator::{StackWinInfo, StackWinType};
+
+/// The maximum number of frames returned by default.
+const DEFAULT_MAX_FRAMES: usize = 1024;
+
+/// The number of pointer-sized words searched for a return address when scanning the stack.
+const SCAN_WORDS: u64 = 40;
+
+/// The factor by which the scanned range is extended for the context frame.
+///
+/// The context frame may have allocated a large amount of stack space before the crash.
+const CONTEXT_SCAN_FACTOR: u64 = 4;
+
+/// How a frame was found, ordered from least to most trustworthy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FrameTrust {
+    /// The frame could not be validated.
+    None,
+
+    /// The return address was found by scanning the stack.
+    Scan,
+
+    /// The return address was found by scanning the stack, starting at the location given by a
+    /// `STACK WIN` record.
+    CfiScan,
+
+    /// The frame was found by following the frame pointer.
+    FramePointer,
+
+    /// The frame was found by evaluating call frame information.
+    Cfi,
+
+    /// The frame was given explicitly by the thread's registers.
+    Context,
+}
+
+/// The values of registers in a stack frame, by name.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Registers {
+    values: BTreeMap<String, u64>,
+}
+
+impl Registers {
+    /// Creates an empty register set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of the named register.
+    pub fn set<S: Into<String>>(&mut self, name: S, value: u64) {
+        self.values.insert(name.into(), value);
+    }
+
+    /// Returns the value of the named register, if it is known.
+    pub fn get(&self, name: &str) -> Option<u64> {
+        self.values.get(name).copied()
+    }
+
+    /// Returns an iterator over the names and values of all known registers.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u64)> + '_ {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_str(), *value))
+    }
+}
+
+impl<S: Into<String>> std::iter::FromIterator<(S, u64)> for Registers {
+    fn from_iter<T: IntoIterator<Item = (S, u64)>>(iter: T) -> Self {
+        let mut registers = Self::new();
+        for (name, value) in iter {
+            registers.set(name, value);
+        }
+        registers
+    }
+}
+
+/// A source of call frame information for the code of a module.
+///
+/// This is implemented for closures taking an address and returning the rules string, so that
+/// any CFI storage can be plugged into the [`Stackwalker`].
+pub trait CfiProvider {
+    /// Returns the `STACK CFI` rules in effect at the given address, relative to the module's
+    /// load address.
+    ///
+    /// The rules are given as a single string of `register: expression` pairs, where later rules
+    /// for a register override earlier ones. It must contain rules for the `.cfa` and `.ra`
+    /// pseudoregisters to recover the caller's frame.
+    fn cfi_rules(&self, address: u64) -> Option<String>;
+
+    /// Returns the `STACK WIN` record covering the given address, relative to the module's load
+    /// address.
+    ///
+    /// This is only used on 32-bit x86, where such records take precedence over `STACK CFI`
+    /// rules. The default implementation returns `None`.
+    fn stack_win(&self, address: u64) -> Option<StackWinInfo> {
+        let _ = address;
+        None
+    }
+}
+
+impl<F> CfiProvider for F
+where
+    F: Fn(u64) -> Option<String>,
+{
+    fn cfi_rules(&self, address: u64) -> Option<String> {
+        self(address)
+    }
+}
+
+/// A module loaded into the address space of the process.
+#[derive(Clone, Copy)]
+pub struct Module<'a> {
+    /// The address at which the module is loaded.
+    pub base_addr: u64,
+
+    /// The size of the module in memory.
+    pub size: u64,
+
+    /// The call frame information of the module, if available.
+    pub cfi: Option<&'a dyn CfiProvider>,
+}
+
+impl<'a> Module<'a> {
+    /// Creates a module without call frame information.
+    pub fn new(base_addr: u64, size: u64) -> Self {
+        Self {
+            base_addr,
+            size,
+            cfi: None,
+        }
+    }
+
+    /// Sets the call frame information of this module.
+    #[must_use]
+    pub fn cfi(mut self, cfi: &'a dyn CfiProvider) -> Self {
+        self.cfi = Some(cfi);
+        self
+    }
+
+    /// Returns true if the given address lies within this module.
+    pub fn contains(&self, address: u64) -> bool {
+        matches!(address.checked_sub(self.base_addr), Some(offset) if offset < self.size)
+    }
+
+    /// Returns the first address after this module.
+    fn end_addr(&self) -> u64 {
+        self.base_addr.saturating_add(self.size)
+    }
+}
+
+impl fmt::Debug for Module<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Module")
+            .field("base_addr", &self.base_addr)
+            .field("size", &self.size)
+            .field("has_cfi", &self.cfi.is_some())
+            .finish()
+    }
+}
+
+impl fmt::Debug for Stackwalker<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Stackwalker")
+            .field("cpu", &self.cpu)
+            .field("has_memory", &self.memory.is_some())
+            .field("modules", &self.modules)
+            .field("max_frames", &self.max_frames)
+            .finish()
+    }
+}
+
+/// A frame in a call stack.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// The value of the instruction pointer in this frame.
+    ///
+    /// For all frames but the context frame, this is the return address.
+    pub instruction: u64,
+
+    /// How this frame was found.
+    pub trust: FrameTrust,
+
+    /// The index of the module containing the instruction, if any.
+    pub module: Option<usize>,
+
+    /// The recovered register values of this frame.
+    ///
+    /// Registers whose values could not be recovered are missing.
+    pub registers: Registers,
+}
+
+impl Frame {
+    /// Returns the address to use for looking up the function and unwind information.
+    ///
+    /// The return address of a caller frame points to the instruction after the call, which may
+    /// belong to a different function or line. This returns an address within the call
+    /// instruction instead.
+    pub fn lookup_address(&self) -> u64 {
+        if self.trust == FrameTrust::Context {
+            self.instruction
+        } else {
+            self.instruction.saturating_sub(1)
+        }
+    }
+}
+
+/// An error encountered while walking the stack.
+#[derive(Debug)]
+enum StackwalkErrorInner {
+    /// Stackwalking is not supported for the CPU family.
+    UnsupportedCpu(CpuFamily),
+
+    /// The registers of the context frame lack a required register.
+    MissingRegister(&'static str),
+}
+
+impl fmt::Display for StackwalkErrorInner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnsupportedCpu(cpu) => write!(f, "Stackwalking is not supported for {:?}", cpu),
+            Self::MissingRegister(name) => {
+                write!(f, "The context frame does not contain register {}", name)
+            }
+        }
+    }
+}
+
+/// An error encountered while walking the stack.
+#[derive(Debug)]
+pub struct StackwalkError(StackwalkErrorInner);
+
+impl fmt::Display for StackwalkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Error for StackwalkError {}
+
+/// Properties of a CPU family relevant for stackwalking.
+#[derive(Clone, Copy, Debug)]
+struct ArchInfo {
+    /// The size of pointers and general purpose registers in bytes.
+    pointer_size: u64,
+
+    /// The instruction pointer.
+    ip: &'static str,
+
+    /// The stack pointer.
+    sp: &'static str,
+
+    /// The frame pointer.
+    fp: &'static str,
+
+    /// The link register holding the return address of leaf functions, if any.
+    lr: Option<&'static str>,
+
+    /// Registers that are preserved across calls.
+    ///
+    /// If the CFI does not recover one of these registers, its value is carried over from the
+    /// callee.
+    callee_saved: &'static [&'static str],
+}
+
+impl ArchInfo {
+    fn from_cpu(cpu: CpuFamily) -> Option<Self> {
+        Some(match cpu {
+            CpuFamily::Intel32 => ArchInfo {
+                pointer_size: 4,
+                ip: "$eip",
+                sp: "$esp",
+                fp: "$ebp",
+                lr: None,
+                callee_saved: &["$ebp", "$ebx", "$esi", "$edi"],
+            },
+            CpuFamily::Amd64 => ArchInfo {
+                pointer_size: 8,
+                ip: "$rip",
+                sp: "$rsp",
+                fp: "$rbp",
+                lr: None,
+                callee_saved: &["$rbp", "$rbx", "$r12", "$r13", "$r14", "$r15"],
+            },
+            CpuFamily::Arm32 => ArchInfo {
+                pointer_size: 4,
+                ip: "pc",
+                sp: "sp",
+                fp: "r11",
+                lr: Some("lr"),
+                callee_saved: &["r4", "r5", "r6", "r7", "r8", "r9", "r10", "r11"],
+            },
+            CpuFamily::Arm64 => ArchInfo {
+                pointer_size: 8,
+                ip: "pc",
+                sp: "sp",
+                fp: "x29",
+                lr: Some("x30"),
+                callee_saved: &[
+                    "x19", "x20", "x21", "x22", "x23", "x24", "x25", "x26", "x27", "x28", "x29",
+                ],
+            },
+            _ => return None,
+        })
+    }
+}
+
+/// A strategy to recover the registers of the caller of the last frame.
+type UnwindFn<'a> = fn(&Stackwalker<'a>, &ArchInfo, &[Frame]) -> Option<(FrameTrust, Registers)>;
+
+/// Computes the call stack of a thread from its registers and memory.
+///
+/// The stackwalker is configured with the memory of the process, such as the thread's stack, and
+/// the list of loaded modules along with their call frame information. Memory is read
+/// in little-endian byte order.
+///
+/// # Example
+///
+/// ```
+/// use symbolic_common::CpuFamily;
+/// use symbolic_unwind::stackwalker::{FrameTrust, Module, Registers, Stackwalker};
+/// use symbolic_unwind::MemoryRegion;
+///
+/// // A stack with a saved frame pointer and a return address into the module.
+/// let mut stack = Vec::new();
+/// stack.extend_from_slice(&0u64.to_le_bytes());
+/// stack.extend_from_slice(&0x1234u64.to_le_bytes());
+///
+/// let mut registers = Registers::new();
+/// registers.set("$rip", 0x1100);
+/// registers.set("$rsp", 0x8000);
+/// registers.set("$rbp", 0x8000);
+///
+/// let frames = Stackwalker::new(CpuFamily::Amd64)
+///     .memory(MemoryRegion { base_addr: 0x8000, contents: &stack })
+///     .module(Module::new(0x1000, 0x1000))
+///     .walk(registers)
+///     .unwrap();
+///
+/// assert_eq!(frames.len(), 2);
+/// assert_eq!(frames[1].instruction, 0x1234);
+/// assert_eq!(frames[1].trust, FrameTrust::FramePointer);
+/// ```
+pub struct Stackwalker<'a> {
+    cpu: CpuFamily,
+    memory: Option<Box<dyn Memory + Send + Sync + 'a>>,
+    /// All modules in the order they were added, see [`Frame::module`].
+    modules: Vec<Module<'a>>,
+    /// The indices of the modules used for lookups, ordered by their base addresses.
+    sorted_modules: Vec<usize>,
+    max_frames: usize,
+}
+
+impl<'a> Stackwalker<'a> {
+    /// Creates a stackwalker for the given CPU family, without memory or modules.
+    ///
+    /// Supported CPU families are `Intel32`, `Amd64`, `Arm32` and `Arm64`.
+    pub fn new(cpu: CpuFamily) -> Self {
+        Self {
+            cpu,
+            memory: None,
+            modules: Vec::new(),
+            sorted_modules: Vec::new(),
+            max_frames: DEFAULT_MAX_FRAMES,
+        }
+    }
+
+    /// Sets the process's memory.
+    ///
+    /// This can be a single [`MemoryRegion`](super::base::MemoryRegion), multiple
+    /// [`MemoryRegions`](super::base::MemoryRegions), or any other implementation of [`Memory`].
+    /// Unwind information can refer to any part of the memory, not only to the stack.
+    #[must_use]
+    pub fn memory<M: Memory + Send + Sync + 'a>(mut self, memory: M) -> Self {
+        self.memory = Some(Box::new(memory));
+        self
+    }
+
+    /// Adds a module loaded into the process.
+    ///
+    /// The index of the module in the order of calls to this method is reported in
+    /// [`Frame::module`]. Modules that are empty or overlap a previously added module are ignored,
+    /// but still count towards the indices of later modules.
+    #[must_use]
+    pub fn module(mut self, module: Module<'a>) -> Self {
+        let index = self.modules.len();
+        self.modules.push(module);
+
+        if module.size == 0 {
+            return self;
+        }
+
+        let position = self
+            .sorted_modules
+            .partition_point(|&i| self.modules[i].base_addr <= module.base_addr);
+        let overlaps_previous = matches!(
+            position.checked_sub(1).map(|p| &self.modules[self.sorted_modules[p]]),
+            Some(previous) if previous.contains(module.base_addr)
+        );
+        let overlaps_next = matches!(
+            self.sorted_modules.get(position).map(|&i| &self.modules[i]),
+            Some(next) if next.base_addr < module.end_addr()
+        );
+        if !overlaps_previous && !overlaps_next {
+            self.sorted_modules.insert(position, index);
+        }
+
+        self
+    }
+
+    /// Sets the maximum number of frames to return, including the context frame.
+    #[must_use]
+    pub fn max_frames(mut self, max_frames: usize) -> Self {
+        self.max_frames = max_frames;
+        self
+    }
+
+    /// Walks the stack starting at the context frame given by `registers`.
+    ///
+    /// The registers must contain at least the instruction and stack pointers. Returns the
+    /// frames from the innermost to the outermost.
+    pub fn walk(&self, registers: Registers) -> Result<Vec<Frame>, StackwalkError> {
+        let arch = ArchInfo::from_cpu(self.cpu).ok_or(StackwalkError(
+            StackwalkErrorInner::UnsupportedCpu(self.cpu),
+        ))?;
+
+        let instruction =
+            registers
+                .get(arch.ip)
+                .ok_or(StackwalkError(StackwalkErrorInner::MissingRegister(
+                    arch.ip,
+                )))?;
+        if registers.get(arch.sp).is_none() {
+            return Err(StackwalkError(StackwalkErrorInner::MissingRegister(
+                arch.sp,
+            )));
+        }
+
+        let mut frames = vec![self.make_frame(instruction, FrameTrust::Context, registers)];
+        while frames.len() < self.max_frames {
+            match self.unwind(&arch, &frames) {
+                Some(frame) => frames.push(frame),
+                None => break,
+            }
+        }
+
+        Ok(frames)
+    }
+
+    /// Recovers the caller of the last frame, trying all strategies in order.
+    fn unwind(&self, arch: &ArchInfo, frames: &[Frame]) -> Option<Frame> {
+        let callee = frames.last()?;
+        let first_unwind = frames.len() == 1;
+
+        let strategies: [UnwindFn<'a>; 4] = [
+            Self::unwind_stack_win,
+            Self::unwind_cfi,
+            Self::unwind_frame_pointer,
+            Self::unwind_scan,
+        ];
+
+        for unwind in &strategies {
+            let (trust, registers, instruction) = match unwind(self, arch, frames) {
+                Some((trust, registers)) => match registers.get(arch.ip) {
+                    Some(instruction) => (trust, registers, instruction),
+                    None => continue,
+                },
+                None => continue,
+            };
+
+            // All strategies may produce garbage from corrupted stacks or CFI that does not match
+            // the module, so only accept return addresses that point into a known module.
+            if self.is_valid_caller(arch, callee, &registers, first_unwind)
+                && self.module_index(instruction).is_some()
+            {
+                return Some(self.make_frame(instruction, trust, registers));
+            }
+        }
+
+        None
+    }
+
+    /// Checks whether the recovered caller registers are plausible.
+    ///
+    /// The stack grows towards lower addresses, so the caller's stack pointer must be greater
+    /// than the callee's. Only when unwinding the context frame, it may be the same, since leaf
+    /// functions on some architectures do not need to touch the stack.
+    fn is_valid_caller(
+        &self,
+        arch: &ArchInfo,
+        callee: &Frame,
+        caller: &Registers,
+        first_unwind: bool,
+    ) -> bool {
+        let (ip, sp, callee_sp) = match (
+            caller.get(arch.ip),
+            caller.get(arch.sp),
+            callee.registers.get(arch.sp),
+        ) {
+            (Some(ip), Some(sp), Some(callee_sp)) => (ip, sp, callee_sp),
+            _ => return false,
+        };
+
+        if ip == 0 {
+            return false;
+        }
+
+        if first_unwind {
+            sp >= callee_sp
+        } else {
+            sp > callee_sp
+        }
+    }
+
+    /// Recovers the caller's registers from the `STACK WIN` record of the callee's module.
+    ///
+    /// See [`Evaluator::evaluate_stack_win`]. Return addresses must point into a known module.
+    fn unwind_stack_win(
+        &self,
+        arch: &ArchInfo,
+        frames: &[Frame],
+    ) -> Option<(FrameTrust, Registers)> {
+        if self.cpu != CpuFamily::Intel32 {
+            return None;
+        }
+
+        let callee = frames.last()?;
+        let info = self.stack_win(callee)?;
+
+        // The callee may have been called with parameters that are still on the stack.
+        let callee_params_size = match frames.len().checked_sub(2) {
+            Some(index) => self
+                .stack_win(&frames[index])
+                .map_or(0, |info| info.params_size),
+            None => 0,
+        };
+
+        let recovered = self
+            .evaluator::<u32>(callee)
+            .evaluate_stack_win(&info, callee_params_size, |address| {
+                self.module_index(address.into()).is_some()
+            })
+            .ok()?;
+
+        let mut caller = self.callee_saved_registers(arch, callee);
+        for (variable, value) in recovered.registers {
+            let name = variable.to_string();
+            if name == arch.ip || name == arch.sp || arch.callee_saved.contains(&name.as_str()) {
+                caller.set(name, value.into());
+            }
+        }
+
+        let trust = if recovered.scanned {
+            FrameTrust::CfiScan
+        } else {
+            FrameTrust::Cfi
+        };
+        Some((trust, caller))
+    }
+
+    /// Returns the `STACK WIN` record for the instruction of the given frame.
+    fn stack_win(&self, frame: &Frame) -> Option<StackWinInfo> {
+        let module = &self.modules[frame.module?];
+        module
+            .cfi?
+            .stack_win(frame.lookup_address() - module.base_addr)
+    }
+
+    /// Recovers the caller's registers by evaluating the CFI of the callee's module.
+    fn unwind_cfi(&self, arch: &ArchInfo, frames: &[Frame]) -> Option<(FrameTrust, Registers)> {
+        let callee = frames.last()?;
+        let module = &self.modules[callee.module?];
+        let address = callee.lookup_address() - module.base_addr;
+        let rules = module.cfi?.cfi_rules(address)?;
+
+        let recovered = match arch.pointer_size {
+            4 => self.evaluate_cfi::<u32>(callee, &rules)?,
+            _ => self.evaluate_cfi::<u64>(callee, &rules)?,
+        };
+
+        let mut caller = self.callee_saved_registers(arch, callee);
+
+        for (ident, value) in recovered {
+            match ident {
+                Identifier::Const(c) if c.is_cfa() => caller.set(arch.sp, value),
+                Identifier::Const(c) if c.is_ra() => caller.set(arch.ip, value),
+                ident => caller.set(ident.to_string(), value),
+            }
+        }
+
+        if caller.get(arch.sp).is_none() || caller.get(arch.ip).is_none() {
+            return None;
+        }
+
+        Some((FrameTrust::Cfi, caller))
+    }
+
+    /// Returns the callee-saved registers of the callee, which are the same in the caller
+    /// unless unwind information says otherwise.
+    fn callee_saved_registers(&self, arch: &ArchInfo, callee: &Frame) -> Registers {
+        let mut registers = Registers::new();
+        for name in arch.callee_saved {
+            if let Some(value) = callee.registers.get(name) {
+                registers.set(*name, value);
+            }
+        }
+        registers
+    }
+
+    /// Evaluates CFI rules against the callee's registers with values of type `A`.
+    fn evaluate_cfi<A>(&self, callee: &Frame, rules: &str) -> Option<Vec<(Identifier, u64)>>
+    where
+        A: RegisterValue + TryFrom<u64>,
+    {
+        let mut evaluator = self.evaluator::<A>(callee);
+        evaluator.add_cfi_rules_string(rules).ok()?;
+        let recovered = evaluator.evaluate_cfi_rules().ok()?;
+
+        Some(
+            recovered
+                .into_iter()
+                .map(|(ident, value)| (ident, value.into()))
+                .collect(),
+        )
+    }
+
+    /// Creates an evaluator with the callee's registers and the process's memory.
+    ///
+    /// Registers named like variables (starting with `$`) are defined as variables, all others
+    /// as constants.
+    fn evaluator<A>(&self, callee: &Frame) -> Evaluator<'_, A, LittleEndian>
+    where
+        A: RegisterValue + TryFrom<u64>,
+    {
+        let mut constants = BTreeMap::new();
+        let mut variables = BTreeMap::new();
+        for (name, value) in callee.registers.iter() {
+            let value = match A::try_from(value) {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+
+            if name.starts_with('$') {
+                if let Ok(variable) = name.parse::<Variable>() {
+                    variables.insert(variable, value);
+                }
+            } else if let Ok(constant) = name.parse::<Constant>() {
+                constants.insert(constant, value);
+            }
+        }
+
+        let evaluator = Evaluator::new(LittleEndian)
+            .constants(constants)
+            .variables(variables);
+
+        match self.memory {
+            Some(ref memory) => evaluator.memory(&**memory),
+            None => evaluator,
+        }
+    }
+
+    /// Recovers the caller's registers from the frame record the frame pointer points to.
+    ///
+    /// The frame record consists of the caller's frame pointer followed by the return address.
+    /// On ARM, the return address of the callee is in the link register instead, and the frame
+    /// record holds the caller's link register.
+    fn unwind_frame_pointer(
+        &self,
+        arch: &ArchInfo,
+        frames: &[Frame],
+    ) -> Option<(FrameTrust, Registers)> {
+        let callee = frames.last()?;
+        let fp = callee.registers.get(arch.fp)?;
+        if fp == 0 || fp % arch.pointer_size != 0 {
+            return None;
+        }
+
+        let caller_fp = self.read_pointer(arch, fp)?;
+        let return_address = self.read_pointer(arch, fp.checked_add(arch.pointer_size)?)?;
+        let caller_sp = fp.checked_add(2 * arch.pointer_size)?;
+
+        let mut caller = Registers::new();
+        caller.set(arch.fp, caller_fp);
+        caller.set(arch.sp, caller_sp);
+        match arch.lr {
+            Some(lr) => {
+                caller.set(arch.ip, callee.registers.get(lr)?);
+                caller.set(lr, return_address);
+            }
+            None => caller.set(arch.ip, return_address),
+        }
+
+        Some((FrameTrust::FramePointer, caller))
+    }
+
+    /// Recovers the caller's registers by searching the stack for a return address.
+    ///
+    /// Any value pointing into a known module is considered a return address. If it directly
+    /// follows the location the frame pointer points to, the caller's frame pointer is restored
+    /// from there as well.
+    fn unwind_scan(&self, arch: &ArchInfo, frames: &[Frame]) -> Option<(FrameTrust, Registers)> {
+        let callee = frames.last()?;
+        let sp = callee.registers.get(arch.sp)?;
+        let words = if callee.trust == FrameTrust::Context {
+            SCAN_WORDS * CONTEXT_SCAN_FACTOR
+        } else {
+            SCAN_WORDS
+        };
+
+        let (address, instruction) = self.scan_for_return_address(arch, sp, words)?;
+
+        let mut caller = Registers::new();
+        caller.set(arch.ip, instruction);
+        caller.set(arch.sp, address + arch.pointer_size);
+
+        if let Some(fp) = callee.registers.get(arch.fp) {
+            let caller_fp = if fp.checked_add(arch.pointer_size) == Some(address) {
+                self.read_pointer(arch, fp)
+            } else {
+                Some(fp)
+            };
+            if let Some(caller_fp) = caller_fp {
+                caller.set(arch.fp, caller_fp);
+            }
+        }
+
+        Some((FrameTrust::Scan, caller))
+    }
+
+    /// Searches up to `words` pointer-sized values starting at `start` for a value pointing into
+    /// a known module.
+    ///
+    /// Returns the address at which the value was found, along with the value.
+    fn scan_for_return_address(
+        &self,
+        arch: &ArchInfo,
+        start: u64,
+        words: u64,
+    ) -> Option<(u64, u64)> {
+        for index in 0..words {
+            let address = start.checked_add(index * arch.pointer_size)?;
+            let value = self.read_pointer(arch, address)?;
+            if self.module_index(value).is_some() {
+                return Some((address, value));
+            }
+        }
+
+        None
+    }
+
+    fn make_frame(&self, instruction: u64, trust: FrameTrust, registers: Registers) -> Frame {
+        let mut frame = Frame {
+            instruction,
+            trust,
+            module: None,
+            registers,
+        };
+        frame.module = self.module_index(frame.lookup_address());
+        frame
+    }
+
+    /// Returns the index of the module containing the given address.
+    fn module_index(&self, address: u64) -> Option<usize> {
+        let position = self
+            .sorted_modules
+            .partition_point(|&i| self.modules[i].base_addr <= address);
+        let index = *self.sorted_modules.get(position.checked_sub(1)?)?;
+        if self.modules[index].contains(address) {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    /// Reads a pointer-sized value from memory.
+    fn read_pointer(&self, arch: &ArchInfo, address: u64) -> Option<u64> {
+        let memory = self.memory.as_ref()?;
+        let bytes = memory.read(address, arch.pointer_size as usize)?;
+        match arch.pointer_size {
+            4 => u32::read_bytes(&bytes, LittleEndian).map(u64::from),
+            _ => u64::read_bytes(&bytes, LittleEndian),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::base::{LazyMemory, MemoryRegion};
+    use std::iter::FromIterator;
+
+    /// Builds little-endian stack memory from pointer-sized words.
+    fn stack_words(pointer_size: u64, words: &[u64]) -> Vec<u8> {
+        let mut stack = Vec::new();
+        for word in words {
+            if pointer_size == 4 {
+                stack.extend_from_slice(&(*word as u32).to_le_bytes());
+            } else {
+                stack.extend_from_slice(&word.to_le_bytes());
+            }
+        }
+        stack
+    }
+
+    fn instructions(frames: &[Frame]) -> Vec<(u64, FrameTrust)> {
+        frames
+            .iter()
+            .map(|frame| (frame.instruction, frame.trust))
+            .collect()
+    }
+
+    #[test]
+    fn unsupported_cpu() {
+        let registers = Registers::from_iter([("$pc", 0x1000)]);
+        assert!(Stackwalker::new(CpuFamily::Mips32).walk(registers).is_err());
+    }
+
+    #[test]
+    fn missing_stack_pointer() {
+        let registers = Registers::from_iter([("$rip", 0x1000)]);
+        assert!(Stackwalker::new(CpuFamily::Amd64).walk(registers).is_err());
+    }
+
+    #[test]
+    fn amd64_cfi() {
+        // The function at 0x1000 pushes rbp and rbx, the one at 0x2000 only rbp.
+        let cfi = |address: u64| match address {
+            0x0..=0xfff => Some(
+                ".cfa: $rsp 24 + .ra: .cfa -8 + ^ $rbp: .cfa -16 + ^ $rbx: .cfa -24 + ^".into(),
+            ),
+            0x1000..=0x1fff => Some(".cfa: $rsp 16 + .ra: .cfa -8 + ^ $rbp: .cfa -16 + ^".into()),
+            _ => None,
+        };
+
+        let stack = stack_words(
+            8,
+            &[
+                0xb0,   // rbx
+                0xb9,   // rbp
+                0x2010, // return address
+                0x00,   // rbp
+                0x1020, // return address
+            ],
+        );
+
+        let registers = Registers::from_iter([("$rip", 0x1008), ("$rsp", 0x8000), ("$rbx", 1)]);
+        let frames = Stackwalker::new(CpuFamily::Amd64)
+            .memory(MemoryRegion {
+                base_addr: 0x8000,
+                contents: &stack,
+            })
+            .module(Module::new(0x1000, 0x2000).cfi(&cfi))
+            .walk(registers)
+            .unwrap();
+
+        assert_eq!(
+            instructions(&frames),
+            [
+                (0x1008, FrameTrust::Context),
+                (0x2010, FrameTrust::Cfi),
+                (0x1020, FrameTrust::Cfi),
+            ]
+        );
+
+        assert_eq!(frames[1].registers.get("$rsp"), Some(0x8018));
+        assert_eq!(frames[1].registers.get("$rbp"), Some(0xb9));
+        assert_eq!(frames[1].registers.get("$rbx"), Some(0xb0));
+        assert_eq!(frames[2].registers.get("$rsp"), Some(0x8028));
+        assert_eq!(frames[2].registers.get("$rbx"), Some(0xb0));
+        assert_eq!(frames[2].module, Some(0));
+    }
+
+    #[test]
+    fn amd64_cfi_outside_module() {
+        // The CFI does not match the code, so the return address it recovers is garbage.
+        let cfi = |_address: u64| Some(".cfa: $rsp 8 + .ra: .cfa -8 + ^".into());
+        let stack = stack_words(8, &[0x9000, 0x1050]);
+
+        let registers = Registers::from_iter([("$rip", 0x1008), ("$rsp", 0x8000)]);
+        let frames = Stackwalker::new(CpuFamily::Amd64)
+            .memory(MemoryRegion {
+                base_addr: 0x8000,
+                contents: &stack,
+            })
+            .module(Module::new(0x1000, 0x1000).cfi(&cfi))
+            .walk(registers)
+            .unwrap();
+
+        assert_eq!(
+            instructions(&frames),
+            [(0x1008, FrameTrust::Context), (0x1050, FrameTrust::Scan)]
+        );
+    }
+
+    #[test]
+    fn x86_frame_pointer() {
+        let stack = stack_words(4, &[0xdead, 0x8010, 0x1234, 0xdead, 0, 0x1456]);
+
+        let registers =
+            Registers::from_iter([("$eip", 0x1100), ("$esp", 0x8000), ("$ebp", 0x8004)]);
+        let frames = Stackwalker::new(CpuFamily::Intel32)
+            .memory(MemoryRegion {
+                base_addr: 0x8000,
+                contents: &stack,
+            })
+            .module(Module::new(0x1000, 0x1000))
+            .walk(registers)
+            .unwrap();
+
+        assert_eq!(
+            instructions(&frames),
+            [
+                (0x1100, FrameTrust::Context),
+                (0x1234, FrameTrust::FramePointer),
+                (0x1456, FrameTrust::FramePointer),
+            ]
+        );
+        assert_eq!(frames[1].registers.get("$esp"), Some(0x800c));
+        assert_eq!(frames[2].registers.get("$esp"), Some(0x8018));
+    }
+
+    #[test]
+    fn arm64_leaf_frame_pointer() {
+        // The leaf function at 0x1100 has not set up a frame record, so its return address is
+        // still in the link register.
+        let stack = stack_words(8, &[0x8010, 0x1200, 0, 0x1300]);
+
+        let registers = Registers::from_iter([
+            ("pc", 0x1100),
+            ("sp", 0x8000),
+            ("x29", 0x8000),
+            ("x30", 0x1180),
+        ]);
+        let frames = Stackwalker::new(CpuFamily::Arm64)
+            .memory(MemoryRegion {
+                base_addr: 0x8000,
+                contents: &stack,
+            })
+            .module(Module::new(0x1000, 0x1000))
+            .walk(registers)
+            .unwrap();
+
+        assert_eq!(
+            instructions(&frames),
+            [
+                (0x1100, FrameTrust::Context),
+                (0x1180, FrameTrust::FramePointer),
+                (0x1200, FrameTrust::FramePointer),
+            ]
+        );
+    }
+
+    #[test]
+    fn arm_cfi_and_scan() {
+        let cfi = |address: u64| match address {
+            0x0..=0xff => Some(".cfa: sp 8 + .ra: .cfa -4 + ^ r4: .cfa -8 + ^".into()),
+            _ => None,
+        };
+
+        // The second frame has neither CFI nor a frame pointer, so its caller is found by
+        // scanning past a value that does not point into a module.
+        let stack = stack_words(4, &[0x44, 0x1200, 0x9999, 0x1300]);
+
+        let registers = Registers::from_iter([("pc", 0x1010), ("sp", 0x8000), ("r4", 1)]);
+        let frames = Stackwalker::new(CpuFamily::Arm32)
+            .memory(MemoryRegion {
+                base_addr: 0x8000,
+                contents: &stack,
+            })
+            .module(Module::new(0x1000, 0x1000).cfi(&cfi))
+            .walk(registers)
+            .unwrap();
+
+        assert_eq!(
+            instructions(&frames),
+            [
+                (0x1010, FrameTrust::Context),
+                (0x1200, FrameTrust::Cfi),
+                (0x1300, FrameTrust::Scan),
+            ]
+        );
+        assert_eq!(frames[1].registers.get("r4"), Some(0x44));
+        assert_eq!(frames[2].registers.get("sp"), Some(0x8010));
+    }
+
+    #[test]
+    fn x86_stack_win() {
+        struct StackWin;
+
+        impl CfiProvider for StackWin {
+            fn cfi_rules(&self, _address: u64) -> Option<String> {
+                None
+            }
+
+            fn stack_win(&self, address: u64) -> Option<StackWinInfo> {
+                let fpo = StackWinInfo {
+                    ty: StackWinType::Fpo,
+                    params_size: 8,
+                    saved_regs_size: 0,
+                    locals_size: 0,
+                    uses_base_pointer: false,
+                    program_string: None,
+                };
+
+                match address {
+                    0x0..=0xff => Some(StackWinInfo {
+                        ty: StackWinType::FrameData,
+                        program_string: Some(
+                            "$T0 $ebp = $eip $T0 4 + ^ = $ebp $T0 ^ = $esp $T0 8 + =".into(),
+                        ),
+                        ..fpo
+                    }),
+                    0x100..=0x1ff => Some(StackWinInfo {
+                        saved_regs_size: 4,
+                        locals_size: 4,
+                        uses_base_pointer: true,
+                        ..fpo
+                    }),
+                    0x200..=0x2ff => Some(fpo),
+                    _ => None,
+                }
+            }
+        }
+
+        let stack = stack_words(
+            4,
+            &[
+                0x0000, // unused
+                0x0000, // unused
+                0x8020, // saved ebp
+                0x1200, // return address
+                0x0000, // parameter
+                0x0000, // parameter
+                0x0000, // local
+                0x0077, // saved ebp
+                0x1300, // return address
+                0x0000, // parameter
+                0x0000, // parameter
+                0x5555, // garbage
+                0x1400, // return address
+            ],
+        );
+
+        let registers =
+            Registers::from_iter([("$eip", 0x1010), ("$esp", 0x8000), ("$ebp", 0x8008)]);
+        let frames = Stackwalker::new(CpuFamily::Intel32)
+            .memory(MemoryRegion {
+                base_addr: 0x8000,
+                contents: &stack,
+            })
+            .module(Module::new(0x1000, 0x1000).cfi(&StackWin))
+            .walk(registers)
+            .unwrap();
+
+        assert_eq!(
+            instructions(&frames),
+            [
+                (0x1010, FrameTrust::Context),
+                (0x1200, FrameTrust::Cfi),
+                (0x1300, FrameTrust::Cfi),
+                (0x1400, FrameTrust::CfiScan),
+            ]
+        );
+
+        assert_eq!(frames[1].registers.get("$esp"), Some(0x8010));
+        assert_eq!(frames[1].registers.get("$ebp"), Some(0x8020));
+        assert_eq!(frames[2].registers.get("$esp"), Some(0x8024));
+        assert_eq!(frames[2].registers.get("$ebp"), Some(0x77));
+        assert_eq!(frames[3].registers.get("$esp"), Some(0x8034));
+    }
+
+    #[test]
+    fn module_indices() {
+        let stack = stack_words(8, &[0x3200, 0x1850]);
+
+        // The modules are not sorted by address, and the last one overlaps the second.
+        let registers = Registers::from_iter([("$rip", 0x1100), ("$rsp", 0x8000)]);
+        let frames = Stackwalker::new(CpuFamily::Amd64)
+            .memory(MemoryRegion {
+                base_addr: 0x8000,
+                contents: &stack,
+            })
+            .module(Module::new(0x3000, 0x1000))
+            .module(Module::new(0x1000, 0x1000))
+            .module(Module::new(0x1800, 0x100))
+            .walk(registers)
+            .unwrap();
+
+        let modules: Vec<_> = frames
+            .iter()
+            .map(|frame| (frame.instruction, frame.module))
+            .collect();
+        assert_eq!(
+            modules,
+            [(0x1100, Some(1)), (0x3200, Some(0)), (0x1850, Some(1))]
+        );
+    }
+
+    #[test]
+    fn lazy_memory() {
+        // Two frame records, each holding the caller's frame pointer and the return address.
+        let stack = stack_words(8, &[0x8010, 0x1200, 0x0, 0x1300]);
+        let memory = LazyMemory::new(move |address: u64, len: usize| {
+            let start = usize::try_from(address.checked_sub(0x8000)?).ok()?;
+            Some(stack.get(start..start.checked_add(len)?)?.to_vec())
+        });
+
+        let registers =
+            Registers::from_iter([("$rip", 0x1100), ("$rsp", 0x8000), ("$rbp", 0x8000)]);
+        let frames = Stackwalker::new(CpuFamily::Amd64)
+            .memory(memory)
+            .module(Module::new(0x1000, 0x1000))
+            .walk(registers)
+            .unwrap();
+
+        assert_eq!(
+            instructions(&frames),
+            [
+                (0x1100, FrameTrust::Context),
+                (0x1200, FrameTrust::FramePointer),
+                (0x1300, FrameTrust::FramePointer),
+            ]
+        );
+    }
+
+    #[test]
+    fn max_frames() {
+        let stack = stack_words(8, &[0x1200, 0x1300, 0x1400]);
+
+        let registers = Registers::from_iter([("$rip", 0x1100), ("$rsp", 0x8000)]);
+        let frames = Stackwalker::new(CpuFamily::Amd64)
+            .memory(MemoryRegion {
+                base_addr: 0x8000,
+                contents: &stack,
+            })
+            .module(Module::new(0x1000, 0x1000))
+            .max_frames(3)
+            .walk(registers)
+            .unwrap();
+
+        assert_eq!(
+            instructions(&frames),
+            [
+                (0x1100, FrameTrust::Context),
+                (0x1200, FrameTrust::Scan),
+                (0x1300, FrameTrust::Scan),
+            ]
+        );
+    }
+}